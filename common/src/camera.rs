@@ -1,6 +1,10 @@
-use std::ops::{
-    Range,
-    RangeBounds,
+use std::{
+    ops::{
+        Range,
+        RangeBounds,
+        RangeInclusive,
+    },
+    path::Path,
 };
 
 use glam::{
@@ -13,7 +17,32 @@ use serde::{
     Serialize,
 };
 
-use crate::angle::Radians;
+use crate::{
+    angle::{
+        Degree,
+        Radians,
+    },
+    error::ConfigError,
+    Camera,
+    Config,
+};
+
+/// The radius of the black hole, the same as the renderers.
+const BLACKHOLE_RADIUS: f32 = 0.6;
+/// Rays start on the sky past this radius, the same as the renderers.
+const SKYBOX_RADIUS: f32 = 3.6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// The local frame the camera sees from.
+pub enum Observer {
+    /// Held still by its engines, the rays are unchanged.
+    #[default]
+    Static,
+    /// Falling straight towards the black hole from rest far away.
+    ///
+    /// Light ahead bunches together and brightens, light behind spreads out and dims.
+    Infalling,
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// A Camera that orbits around a target.
@@ -32,6 +61,9 @@ pub struct OrbitCamera {
     phi: f32,
     /// angle on the y axis
     theta: f32,
+    /// frame of the observer
+    #[serde(default)]
+    pub observer: Observer,
 }
 
 impl OrbitCamera {
@@ -49,6 +81,7 @@ impl OrbitCamera {
             target,
             phi: std::f32::consts::FRAC_PI_2,
             theta: 0.0,
+            observer: Observer::Static,
         }
     }
 
@@ -88,6 +121,21 @@ impl OrbitCamera {
         Vec3::new(x, y, z)
     }
 
+    /// Move the target across the view by `delta`, scaled by the distance to it.
+    pub fn pan(&mut self, delta: Vec2) {
+        let eye = self.eye();
+        let forward = (self.target - eye).normalize_or_zero();
+        let right = forward.cross(Vec3::Y).normalize_or_zero();
+        let up = right.cross(forward);
+
+        self.target += (right * delta.x + up * delta.y) * eye.distance(self.target);
+    }
+
+    /// The target of the [`OrbitCamera`].
+    pub fn target(&self) -> Vec3 {
+        self.target
+    }
+
     /// Change the target of the [`OrbitCamera`].
     pub fn set_target(&mut self, target: Vec3) {
         self.target = target;
@@ -102,6 +150,250 @@ impl OrbitCamera {
     pub fn set_theta(&mut self, theta: f32) {
         self.theta = theta;
     }
+
+    /// The radius of the orbit.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Manually set the radius of the orbit, ignoring the bounds.
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+    }
+}
+
+/// The field of view and distance that frame the shadow, found by [`frame_shadow`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Framing {
+    pub fov: Radians,
+    /// The radius of the orbit around the black hole.
+    pub radius: f32,
+}
+
+impl Framing {
+    /// Move the `camera` to the framing.
+    pub fn apply(&self, camera: &mut Camera) {
+        match camera {
+            Camera::Orbit(cam) => {
+                cam.fov = self.fov;
+                cam.radius = self.radius;
+            }
+        }
+    }
+}
+
+/// Find the field of view that makes the shadow, bounded by the photon ring,
+/// fill all but `margin` of the longer side of the frame.
+///
+/// The camera keeps its distance, unless the field of view would be too narrow or wide,
+/// in which case it moves within the bounds of its orbit.
+/// Assumes a static observer orbiting the black hole.
+///
+/// Returns [`None`] if the camera is too close to see the edge of the shadow.
+pub fn frame_shadow(config: &Config, margin: f32) -> Option<Framing> {
+    const FOV: RangeInclusive<f32> = 30.0..=120.0;
+
+    let Camera::Orbit(cam) = &config.camera;
+    let fill = (1.0 - margin).clamp(0.01, 1.0);
+
+    // rays leave the edge of the frame at `atan(2 * fov / PI)` from the center
+    let fov_for = |radius: f32| Some(shadow_tan(radius)? / fill * 0.5 * std::f32::consts::PI);
+
+    let fov = Degree::from(Radians(fov_for(cam.radius)?)).0;
+    if FOV.contains(&fov) {
+        return Some(Framing {
+            fov: Radians::from(Degree(fov)),
+            radius: cam.radius,
+        });
+    }
+
+    // move the camera so the closest field of view fits
+    let fov = Radians::from(Degree(fov.clamp(*FOV.start(), *FOV.end()))).0;
+    let (mut near, mut far) = (cam.bounds.start, cam.bounds.end.min(SKYBOX_RADIUS));
+
+    // the shadow shrinks as the camera moves away
+    for _ in 0..32 {
+        let mid = 0.5 * (near + far);
+        match fov_for(mid) {
+            Some(needed) if needed < fov => far = mid,
+            _ => near = mid,
+        }
+    }
+
+    Some(Framing {
+        fov: Radians(fov),
+        radius: far,
+    })
+}
+
+/// The tangent of the angle between the black hole and the edge of its shadow,
+/// seen from a camera `radius` away.
+///
+/// The renderers pull rays with a field of `6 R^4 / r^4`, so a ray with unit speed
+/// and angular momentum `L` is captured past the peak of its effective potential,
+/// `L^6 / (216 R^8)`, when that is below its energy.
+fn shadow_tan(radius: f32) -> Option<f32> {
+    let r4 = BLACKHOLE_RADIUS.powi(4);
+    let energy = 0.5 - 2.0 * r4 / radius.powi(3);
+
+    if energy <= 0.0 {
+        // nothing escapes from here
+        return None;
+    }
+
+    let critical = (216.0 * r4 * r4 * energy).powf(1.0 / 6.0);
+    let sin = critical / radius;
+
+    // past the photon sphere the shadow covers more than half the sky
+    (sin < 1.0).then(|| sin / (1.0 - sin * sin).sqrt())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A named [`Camera`], saved in the config to come back to later.
+pub struct Bookmark {
+    pub name: String,
+    pub camera: Camera,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// The orbit of an [`OrbitCamera`] at a point in time.
+pub struct Keyframe {
+    /// time of the keyframe, in seconds
+    pub time: f32,
+    /// radius of orbit
+    pub radius: f32,
+    /// angle on the xz axis
+    pub phi: f32,
+    /// angle on the y axis
+    pub theta: f32,
+    /// how the path eases into this keyframe from the one before
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl Keyframe {
+    /// A keyframe at `time` holding the orbit of the `camera`.
+    pub fn new(camera: &OrbitCamera, time: f32) -> Self {
+        Self {
+            time,
+            radius: camera.radius,
+            phi: camera.phi,
+            theta: camera.theta,
+            easing: Easing::default(),
+        }
+    }
+
+    fn lerp(self, rhs: Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+        Self {
+            time: lerp(self.time, rhs.time),
+            radius: lerp(self.radius, rhs.radius),
+            phi: lerp(self.phi, rhs.phi),
+            theta: lerp(self.theta, rhs.theta),
+            easing: rhs.easing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// How the orbit changes between two [`Keyframes`](Keyframe).
+pub enum Easing {
+    /// At a constant speed.
+    #[default]
+    Linear,
+    /// Starting slowly, and speeding up.
+    In,
+    /// Starting quickly, and slowing down.
+    Out,
+    /// Starting and ending slowly.
+    InOut,
+}
+
+impl Easing {
+    /// Ease the fraction `t` of the way between two keyframes.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::In => t * t,
+            Easing::Out => t * (2.0 - t),
+            Easing::InOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Moves an [`OrbitCamera`] between [`Keyframes`](Keyframe) over time.
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    /// Create a new [`CameraPath`], sorting the `keyframes` by time.
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Self { keyframes }
+    }
+
+    /// Load a path from a file.
+    ///
+    /// Fails if the file cannot be read or parsed.
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)?;
+
+        let path: Self = toml::from_str(&contents)?;
+        Ok(Self::new(path.keyframes))
+    }
+
+    /// Saves a path as toml.
+    ///
+    /// Fails if the toml couldn't be generated, or the contents couldn't be written.
+    pub fn save(&self, writer: &mut impl std::io::Write) -> Result<(), ConfigError> {
+        let toml = toml::to_string_pretty(self)?;
+
+        write!(writer, "{}", toml)?;
+
+        Ok(())
+    }
+
+    /// The keyframes of the path, sorted by time.
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// The time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// The orbit at `time`, interpolated between keyframes with the easing of the next one.
+    ///
+    /// Times outside of the path hold the first or last keyframe.
+    pub fn evaluate(&self, time: f32) -> Option<Keyframe> {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+
+        match next {
+            0 => self.keyframes.first().copied(),
+            n if n == self.keyframes.len() => self.keyframes.last().copied(),
+            n => {
+                let (a, b) = (self.keyframes[n - 1], self.keyframes[n]);
+                let t = (time - a.time) / (b.time - a.time);
+                Some(a.lerp(b, b.easing.apply(t)))
+            }
+        }
+    }
+
+    /// Move the `camera` to where it is on the path at `time`.
+    ///
+    /// Leaves the camera alone if the path is empty.
+    pub fn apply(&self, camera: &mut OrbitCamera, time: f32) {
+        if let Some(key) = self.evaluate(time) {
+            camera.radius = key.radius;
+            camera.phi = key.phi;
+            camera.theta = key.theta;
+        }
+    }
 }
 
 fn range_from_range_bounds<T: RangeBounds<f32>>(range: T, min: f32, max: f32) -> Range<f32> {
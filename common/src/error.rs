@@ -8,4 +8,6 @@ pub enum ConfigError {
     Deserialise(#[from] toml::de::Error),
     #[error(transparent)]
     Serialise(#[from] toml::ser::Error),
+    #[error("no field named `{0}`")]
+    UnknownField(String),
 }
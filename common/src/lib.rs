@@ -1,6 +1,11 @@
 mod angle;
 pub mod camera;
 mod error;
+mod render;
+pub mod shadow;
+pub mod srgb;
+pub mod stokes;
+mod tile;
 
 use std::path::Path;
 
@@ -8,7 +13,11 @@ pub use angle::{
     Degree,
     Radians,
 };
-use camera::OrbitCamera;
+use camera::{
+    Bookmark,
+    Observer,
+    OrbitCamera,
+};
 use glam::{
     vec3,
     Affine3A,
@@ -18,6 +27,12 @@ use serde::{
     Deserialize,
     Serialize,
 };
+pub use render::{
+    Render,
+    Sample,
+    Samples,
+};
+pub use tile::Tile;
 
 bitflags::bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -32,9 +47,40 @@ bitflags::bitflags! {
         const RK4           = 1 << 4;
         const ADAPTIVE  = 1 << 5;
         const BLOOM         = 1 << 6;
+        /// Output linear light, instead of encoding it as sRGB.
+        const LINEAR_OUTPUT = 1 << 7;
+        /// Output the [`stokes`](crate::stokes) parameters of polarized light, instead of color.
+        const POLARIZATION  = 1 << 8;
+        /// Keep integrating rays from a camera inside the horizon, until the singularity.
+        const INTERIOR      = 1 << 9;
+        /// Ripple the field with a toy gravitational wave, see [`Ripple`].
+        const RIPPLE        = 1 << 10;
+        /// Replace the black hole with an emissive [`Star`], such as a neutron star.
+        const STAR          = 1 << 11;
     }
 }
 
+impl Features {
+    /// Returns `true` if frames are encoded as sRGB for display,
+    /// rather than holding linear light or [`stokes`] parameters.
+    pub fn encodes_srgb(self) -> bool {
+        !self.intersects(Features::LINEAR_OUTPUT | Features::POLARIZATION)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// The floating point precision used when integrating rays.
+///
+/// Only the software renderer supports [`Precision::Double`],
+/// the hardware renderer always integrates in single precision.
+pub enum Precision {
+    /// 32-bit floats, matches the hardware renderer.
+    #[default]
+    Single,
+    /// 64-bit floats, slower but useful for reference renders.
+    Double,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Disk {
     /// Radius of the disk
@@ -45,6 +91,32 @@ pub struct Disk {
     pub color: Vec3,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A toy gravitational wave spreading out from the black hole.
+///
+/// Not an exact solution of GR, but animating the `time`
+/// shows lensing distortions moving outward.
+pub struct Ripple {
+    /// Strength of the ripple, compared to the field
+    pub amplitude: f32,
+    /// Wavefronts passing a point per unit of time
+    pub frequency: f32,
+    /// Time of the frame, the wavefronts move at the speed of light
+    pub time: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A glowing star in place of the horizon, enabled by [`Features::STAR`].
+///
+/// The star bends light the same as the black hole,
+/// so a compact star can be seen from all the way around.
+pub struct Star {
+    /// Radius of the surface, the horizon of the black hole is 0.6
+    pub radius: f32,
+    /// Color temperature of the surface, in Kelvin
+    pub temperature: f32,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// The camera used to control perspective of the rays fired from it.
 pub enum Camera {
@@ -66,6 +138,20 @@ impl Camera {
         }
     }
 
+    /// The local frame the [`Camera`] sees from.
+    pub fn observer(&self) -> Observer {
+        match self {
+            Camera::Orbit(cam) => cam.observer,
+        }
+    }
+
+    /// A mutable view to the [`Camera`] observer.
+    pub fn observer_mut(&mut self) -> &mut Observer {
+        match self {
+            Camera::Orbit(cam) => &mut cam.observer,
+        }
+    }
+
     /// A mutable view to the [`Cameras`](Camera) field of view.
     /// Allows you to change the fov at runtime.
     pub fn fov_mut(&mut self) -> &mut Radians {
@@ -88,8 +174,22 @@ impl Default for Disk {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     pub features: Features,
+    #[serde(default)]
+    pub precision: Precision,
+    /// Seeds the random numbers of every sample.
+    ///
+    /// Renders with the same seed are reproducible.
+    #[serde(default)]
+    pub seed: u64,
     pub camera: Camera,
     pub disk: Disk,
+    #[serde(default)]
+    pub ripple: Ripple,
+    #[serde(default)]
+    pub star: Star,
+    /// Cameras saved by name.
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
 }
 
 impl Config {
@@ -109,6 +209,75 @@ impl Config {
         Ok(toml::from_str(s)?)
     }
 
+    /// Overrides a single field of the config.
+    ///
+    /// The `key` is a dotted path to the field, such as `disk.radius`.
+    /// The `value` is parsed as toml, falling back to a plain string,
+    /// so `features=DISK_VOL|AA` works without quoting.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), error::ConfigError> {
+        let mut root = toml::Value::try_from(&*self)?;
+
+        let (parents, field) = match key.rsplit_once('.') {
+            Some((parents, field)) => (Some(parents), field),
+            None => (None, key),
+        };
+
+        // walk down to the table that owns the field
+        let mut table = root.as_table_mut().expect("config is a table");
+        for part in parents.into_iter().flat_map(|p| p.split('.')) {
+            table = table
+                .get_mut(part)
+                .and_then(toml::Value::as_table_mut)
+                .ok_or_else(|| error::ConfigError::UnknownField(key.to_owned()))?;
+        }
+
+        let old = table
+            .get_mut(field)
+            .ok_or_else(|| error::ConfigError::UnknownField(key.to_owned()))?;
+
+        *old = toml::from_str::<toml::Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut t| t.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_owned()));
+
+        *self = root.try_into()?;
+
+        Ok(())
+    }
+
+    /// The dotted paths of every field that differs from `other`,
+    /// in the same form as the keys given to [`Config::set`].
+    pub fn diff(&self, other: &Config) -> Vec<String> {
+        fn walk(prefix: &str, a: &toml::Value, b: &toml::Value, keys: &mut Vec<String>) {
+            match (a, b) {
+                (toml::Value::Table(a), toml::Value::Table(b)) => {
+                    let fields = a.keys().chain(b.keys().filter(|k| !a.contains_key(*k)));
+                    for field in fields {
+                        let key = if prefix.is_empty() {
+                            field.to_owned()
+                        } else {
+                            format!("{prefix}.{field}")
+                        };
+
+                        match (a.get(field), b.get(field)) {
+                            (Some(a), Some(b)) => walk(&key, a, b, keys),
+                            _ => keys.push(key),
+                        }
+                    }
+                }
+                (a, b) if a != b => keys.push(prefix.to_owned()),
+                _ => (),
+            }
+        }
+
+        let mut keys = Vec::new();
+        if let (Ok(a), Ok(b)) = (toml::Value::try_from(self), toml::Value::try_from(other)) {
+            walk("", &a, &b, &mut keys);
+        }
+
+        keys
+    }
+
     /// Saves a config file to disk.
     /// 
     /// Fails if the toml couldn't be generated, or the contents couldn't be written.
@@ -121,10 +290,46 @@ impl Config {
     }
 }
 
+/// The rng seed of a single `sample` of a render using `seed`.
+///
+/// A seed of 0 leaves the sample unchanged.
+pub fn sample_seed(seed: u64, sample: u32) -> u32 {
+    // https://github.com/aappleby/smhasher/wiki/MurmurHash3 (fmix64)
+    let mut h = seed;
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^= h >> 33;
+
+    sample.wrapping_add((h ^ (h >> 32)) as u32)
+}
+
+impl Default for Ripple {
+    fn default() -> Self {
+        Self {
+            amplitude: 0.1,
+            frequency: 1.0,
+            time: 0.0,
+        }
+    }
+}
+
+impl Default for Star {
+    fn default() -> Self {
+        Self {
+            radius: 0.8,
+            temperature: 10000.0,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             features: Features::empty(),
+            precision: Precision::Single,
+            seed: 0,
             camera: Camera::Orbit(OrbitCamera::new(
                 // 90 degree FOV
                 angle::Degree(90.0),
@@ -136,6 +341,9 @@ impl Default for Config {
                 Vec3::ZERO,
             )),
             disk: Default::default(),
+            ripple: Default::default(),
+            star: Default::default(),
+            bookmarks: Vec::new(),
         }
     }
 }
@@ -0,0 +1,113 @@
+use crate::Config;
+
+/// A renderer that accumulates samples of a frame.
+///
+/// Implemented by both the hardware and software renderers,
+/// so code can be generic over where the frame is rendered.
+pub trait Render {
+    /// Update the size of the frame and the [`Config`].
+    ///
+    /// Accumulation restarts if either has changed.
+    fn update(&mut self, width: u32, height: u32, config: Config);
+
+    /// A flag to determine if the renderer needs to re-render.
+    fn must_render(&self) -> bool;
+
+    /// Compute the next sample, accumulating it into the frame.
+    fn compute_sample(&mut self);
+
+    /// The number of samples accumulated into the frame.
+    fn sample_count(&self) -> u32;
+
+    /// Copy the current frame output into `[r, g, b, a]` bytes.
+    fn frame(&self) -> Vec<u8>;
+
+    /// Copy the current frame output into floats `[r, g, b, a]`.
+    ///
+    /// Only more precise than [`Render::frame`] if the renderer accumulates in floats.
+    fn frame_hdr(&self) -> Vec<f32> {
+        self.frame().into_iter().map(|b| b as f32 / 255.0).collect()
+    }
+
+    /// Copy the current frame output into floats `[r, g, b, a]` of linear light,
+    /// decoding it if it's encoded for display.
+    ///
+    /// Used for formats that store linear light, such as EXR.
+    /// Frames of [`stokes`](crate::stokes) parameters are copied as they are.
+    fn frame_linear(&self) -> Vec<f32>;
+
+    /// Convert the renderer into `[r, g, b, a]` bytes representing the frame output.
+    fn into_frame(self) -> Vec<u8>
+    where
+        Self: Sized;
+
+    /// Compute samples one at a time, viewing the accumulation after each.
+    ///
+    /// ```ignore
+    /// let mut samples = renderer.samples();
+    /// loop {
+    ///     let sample = samples.next_sample();
+    ///     show(sample.frame());
+    ///
+    ///     if sample.index() == 63 {
+    ///         break;
+    ///     }
+    /// }
+    /// ```
+    fn samples(&mut self) -> Samples<'_, Self>
+    where
+        Self: Sized,
+    {
+        Samples {
+            renderer: self,
+            computed: 0,
+        }
+    }
+}
+
+/// A never ending stream of samples, created by [`Render::samples`].
+///
+/// Each [`Sample`] borrows the renderer, so it isn't an [`Iterator`].
+/// The stream ends when it's dropped.
+pub struct Samples<'a, R> {
+    renderer: &'a mut R,
+    computed: u32,
+}
+
+impl<'a, R: Render> Samples<'a, R> {
+    /// Compute the next sample.
+    pub fn next_sample(&mut self) -> Sample<'_, R> {
+        self.renderer.compute_sample();
+
+        let index = self.computed;
+        self.computed += 1;
+
+        Sample {
+            renderer: self.renderer,
+            index,
+        }
+    }
+}
+
+/// A view of the accumulation after a sample was computed.
+pub struct Sample<'a, R> {
+    renderer: &'a R,
+    index: u32,
+}
+
+impl<'a, R: Render> Sample<'a, R> {
+    /// The number of samples computed by the stream before this one.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Copy the accumulated frame into `[r, g, b, a]` bytes.
+    pub fn frame(&self) -> Vec<u8> {
+        self.renderer.frame()
+    }
+
+    /// The renderer, to view the frame without copying it.
+    pub fn renderer(&self) -> &'a R {
+        self.renderer
+    }
+}
@@ -0,0 +1,116 @@
+//! Measuring the shadow of the black hole in a rendered frame.
+//!
+//! The shadow is the dark region around the center of the frame where rays fall into the hole.
+//! A disk in front of the hole splits the shadow in two,
+//! so frames rendered without a disk give the most accurate measurements.
+
+use std::collections::VecDeque;
+
+use glam::{
+    IVec2,
+    UVec2,
+    Vec2,
+};
+use serde::Serialize;
+
+use crate::angle::Radians;
+
+/// The brightest a pixel can be while still counting as part of the shadow.
+const THRESHOLD: u8 = 2;
+
+/// The shadow found in a frame, with angles in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Shadow {
+    /// The diameter of a circle with the same area as the shadow.
+    pub diameter: f32,
+    /// The center of the shadow, relative to the center of the frame.
+    ///
+    /// `x` points to the right of the frame, and `y` to the bottom.
+    pub centroid: Vec2,
+    /// The number of pixels in the shadow.
+    pub pixels: u32,
+}
+
+/// Find the shadow in an rgba8 `frame`, rendered with the field of view `fov`.
+///
+/// Returns [`None`] if there are no dark pixels near the center of the frame.
+pub fn measure(frame: &[u8], width: u32, height: u32, fov: Radians) -> Option<Shadow> {
+    assert_eq!(frame.len(), (width * height * 4) as usize, "frame is the wrong size");
+
+    let res = UVec2::new(width, height);
+    let index = |p: UVec2| (p.y * width + p.x) as usize;
+    let is_dark = |p: UVec2| {
+        let i = index(p) * 4;
+        frame[i..i + 3].iter().all(|&c| c <= THRESHOLD)
+    };
+
+    let start = nearest_dark(res, res / 2, is_dark)?;
+
+    // flood fill the shadow from the dark pixel closest to the center
+    let mut visited = vec![false; (width * height) as usize];
+    let mut queue = VecDeque::from([start]);
+    visited[index(start)] = true;
+
+    let mut pixels = 0u32;
+    let mut sum = Vec2::ZERO;
+
+    while let Some(p) = queue.pop_front() {
+        pixels += 1;
+        sum += p.as_vec2();
+
+        let neighbours = [
+            p.x.checked_sub(1).map(|x| UVec2::new(x, p.y)),
+            p.y.checked_sub(1).map(|y| UVec2::new(p.x, y)),
+            Some(UVec2::new(p.x + 1, p.y)).filter(|n| n.x < width),
+            Some(UVec2::new(p.x, p.y + 1)).filter(|n| n.y < height),
+        ];
+
+        for n in neighbours.into_iter().flatten() {
+            if !visited[index(n)] && is_dark(n) {
+                visited[index(n)] = true;
+                queue.push_back(n);
+            }
+        }
+    }
+
+    // the same projection the renderers use to turn pixels into rays
+    let res = res.as_vec2();
+    let scale = 2.0 / res.max_element() * 2.0 * fov.as_f32() * std::f32::consts::FRAC_1_PI;
+
+    let center = (sum / pixels as f32 - 0.5 * res) * scale;
+    let radius = (pixels as f32 * std::f32::consts::FRAC_1_PI).sqrt() * scale;
+
+    Some(Shadow {
+        diameter: (2.0 * radius.atan()).to_degrees(),
+        centroid: Vec2::new(center.x.atan().to_degrees(), center.y.atan().to_degrees()),
+        pixels,
+    })
+}
+
+/// The dark pixel closest to `center`, searching in growing squares
+/// out to a tenth of the frame.
+fn nearest_dark(res: UVec2, center: UVec2, is_dark: impl Fn(UVec2) -> bool) -> Option<UVec2> {
+    let max = (res.min_element() / 10).max(1) as i32;
+    let center = center.as_ivec2();
+
+    for ring in 0..=max {
+        for y in -ring..=ring {
+            for x in -ring..=ring {
+                // only the edge of the square is new
+                if x.abs() != ring && y.abs() != ring {
+                    continue;
+                }
+
+                let p = center + IVec2::new(x, y);
+                if p.cmpge(IVec2::ZERO).all() && p.cmplt(res.as_ivec2()).all() {
+                    let p = p.as_uvec2();
+                    if is_dark(p) {
+                        return Some(p);
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
@@ -0,0 +1,41 @@
+//! The sRGB transfer function, encoding linear light for display.
+//!
+//! Frames are encoded unless [`Features::encodes_srgb`](crate::Features::encodes_srgb)
+//! says otherwise.
+//! <https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)>
+
+/// Encode a channel of linear light for display.
+pub fn encode(linear: f32) -> f32 {
+    if linear < 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decode a channel encoded for display back into linear light.
+pub fn decode(encoded: f32) -> f32 {
+    if encoded < 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode the colors of `[r, g, b, a]` floats of linear light for display, leaving alpha alone.
+pub fn encode_frame(frame: &mut [f32]) {
+    for pixel in frame.chunks_exact_mut(4) {
+        for channel in &mut pixel[..3] {
+            *channel = encode(*channel);
+        }
+    }
+}
+
+/// Decode the colors of `[r, g, b, a]` floats back into linear light, leaving alpha alone.
+pub fn decode_frame(frame: &mut [f32]) {
+    for pixel in frame.chunks_exact_mut(4) {
+        for channel in &mut pixel[..3] {
+            *channel = decode(*channel);
+        }
+    }
+}
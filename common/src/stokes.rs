@@ -0,0 +1,29 @@
+//! Stokes parameters, rendered instead of color with [`Features::POLARIZATION`](crate::Features).
+//!
+//! Each pixel holds `[I, Q, U, V]` of the light reaching the camera,
+//! measured against the camera's right axis.
+//!
+//! The parameters are scaled by `1 / (1 + I)`, so `I` is stored in `[0, 1)`,
+//! and `Q`, `U` and `V` in `(-1, 1)` since they can't be larger than `I`.
+//! They're then stored as `0.5 + 0.5 * x`, so every channel fits in `[0, 1]`
+//! and the 8-bit buffer of the hardware renderer holds the same frame as the software one.
+//! The scale is the same for every parameter, so the degree and angle of polarization are kept.
+
+use glam::Vec4;
+
+/// Pack Stokes parameters into the channels of a pixel.
+pub fn encode(stokes: Vec4) -> Vec4 {
+    let [i, q, u, v] = (stokes / (1.0 + stokes.x.max(0.0))).to_array();
+    Vec4::new(i, 0.5 + 0.5 * q, 0.5 + 0.5 * u, 0.5 + 0.5 * v)
+}
+
+/// Unpack the Stokes parameters of a pixel.
+///
+/// Exact for a single sample, the mean of many samples is scaled back by its own `I`.
+pub fn decode(pixel: Vec4) -> Vec4 {
+    let [i, q, u, v] = pixel.to_array();
+    let scaled = Vec4::new(i, 2.0 * q - 1.0, 2.0 * u - 1.0, 2.0 * v - 1.0);
+
+    // `1 + I` is `1 / (1 - i)`
+    scaled / (1.0 - i).max(f32::EPSILON)
+}
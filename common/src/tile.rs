@@ -0,0 +1,61 @@
+use std::str::FromStr;
+
+use glam::UVec2;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A rectangular region of a frame, in pixels.
+///
+/// `min` is inclusive and `max` is exclusive.
+pub struct Tile {
+    pub min: UVec2,
+    pub max: UVec2,
+}
+
+impl Tile {
+    /// A [`Tile`] covering the whole frame.
+    pub fn full(width: u32, height: u32) -> Self {
+        Self {
+            min: UVec2::ZERO,
+            max: UVec2::new(width, height),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> u32 {
+        self.max.y - self.min.y
+    }
+
+    /// Checks that the [`Tile`] isn't empty and fits inside a frame.
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        self.min.cmplt(self.max).all() && self.max.cmple(UVec2::new(width, height)).all()
+    }
+}
+
+impl FromStr for Tile {
+    type Err = String;
+
+    /// Parses a tile in the form `x0,y0,x1,y1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .split(',')
+            .map(|part| part.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        match parts[..] {
+            [x0, y0, x1, y1] if x0 < x1 && y0 < y1 => Ok(Tile {
+                min: UVec2::new(x0, y0),
+                max: UVec2::new(x1, y1),
+            }),
+            [_, _, _, _] => Err(format!("tile `{s}` is empty")),
+            _ => Err(format!("expected x0,y0,x1,y1, found `{s}`")),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Reading back the frame presented to the window, with everything drawn to it.
+
+use graphics::wgpu;
+
+use crate::error::CaptureError;
+
+/// A frame read back from the window.
+pub struct Capture {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed 8-bit rgba pixels, row by row from the top.
+    pub rgba: Vec<u8>,
+}
+
+/// Called with the next frame once it's read back, see [`State::capture_frame`](crate::State::capture_frame).
+pub type CaptureCallback = Box<dyn FnOnce(Result<Capture, CaptureError>)>;
+
+/// A copy of the surface texture waiting to be read back.
+pub(crate) struct Pending {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    /// The row length of the buffer, padded to [`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`].
+    padded_row: u32,
+    bgra: bool,
+}
+
+/// Record a copy of the `texture` of the surface, to read back after submitting.
+pub(crate) fn copy(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    config: &wgpu::SurfaceConfiguration,
+) -> Result<Pending, CaptureError> {
+    if !config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+        return Err(CaptureError::Unsupported);
+    }
+
+    let bgra = match config.format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        format => return Err(CaptureError::Format(format)),
+    };
+
+    let (width, height) = (config.width, config.height);
+    let row = width * 4;
+    let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("frame capture"),
+        size: (padded_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+
+    Ok(Pending {
+        buffer,
+        width,
+        height,
+        padded_row,
+        bgra,
+    })
+}
+
+impl Pending {
+    /// Wait for the copy to finish, and read it back.
+    pub(crate) fn read(self, device: &wgpu::Device) -> Result<Capture, CaptureError> {
+        let slice = self.buffer.slice(..);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait).panic_on_timeout();
+        rx.recv().expect("map callback is called by the poll")?;
+
+        let row = (self.width * 4) as usize;
+        let mut rgba = Vec::with_capacity(row * self.height as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for padded in mapped.chunks_exact(self.padded_row as usize) {
+                rgba.extend_from_slice(&padded[..row]);
+            }
+        }
+        self.buffer.unmap();
+
+        if self.bgra {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(Capture {
+            width: self.width,
+            height: self.height,
+            rgba,
+        })
+    }
+}
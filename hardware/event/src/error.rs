@@ -1,6 +1,12 @@
-use graphics::ContextBuildError;
+use graphics::{
+    wgpu,
+    ContextBuildError,
+};
 use thiserror::Error;
-use winit::error::EventLoopError;
+use winit::error::{
+    EventLoopError,
+    OsError,
+};
 
 #[derive(Debug, Error)]
 pub enum RunError {
@@ -10,3 +16,24 @@ pub enum RunError {
     #[error(transparent)]
     EvenLoop(#[from] EventLoopError),
 }
+
+#[derive(Debug, Error)]
+pub enum WindowError {
+    #[error(transparent)]
+    Os(#[from] OsError),
+
+    #[error(transparent)]
+    Surface(#[from] wgpu::CreateSurfaceError),
+}
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("the surface can't be copied from")]
+    Unsupported,
+
+    #[error("frames can't be read back from a {0:?} surface")]
+    Format(wgpu::TextureFormat),
+
+    #[error(transparent)]
+    Map(#[from] wgpu::BufferAsyncError),
+}
@@ -0,0 +1,265 @@
+//! The state of the keyboard and mouse, kept up to date by the event loop.
+//!
+//! Events consumed by the app, such as clicks on a gui, still release buttons and move the cursor,
+//! but don't press anything.
+
+use std::{
+    collections::HashSet,
+    hash::Hash,
+};
+
+use glam::{
+    vec2,
+    Vec2,
+};
+use winit::{
+    event::{
+        ElementState,
+        MouseButton,
+        MouseScrollDelta,
+        WindowEvent,
+    },
+    keyboard::{
+        KeyCode,
+        ModifiersState,
+        PhysicalKey,
+    },
+    window::Window,
+};
+
+/// Which of a set of buttons are held, and which changed this frame.
+struct Buttons<B> {
+    down: HashSet<B>,
+    pressed: HashSet<B>,
+    released: HashSet<B>,
+}
+
+impl<B> Default for Buttons<B> {
+    fn default() -> Self {
+        Self {
+            down: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+        }
+    }
+}
+
+impl<B: Copy + Eq + Hash> Buttons<B> {
+    fn update(&mut self, button: B, state: ElementState, consumed: bool) {
+        match state {
+            // repeats aren't new presses
+            ElementState::Pressed if !consumed && self.down.insert(button) => {
+                self.pressed.insert(button);
+            }
+            ElementState::Released if self.down.remove(&button) => {
+                self.released.insert(button);
+            }
+            _ => (),
+        }
+    }
+
+    /// Release every button, such as when the window loses focus.
+    fn release_all(&mut self) {
+        self.released.extend(self.down.drain());
+    }
+
+    fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
+
+pub struct Mouse {
+    pos: Vec2,
+    /// How far the mouse has moved this frame.
+    delta: Vec2,
+    scroll_delta: Vec2,
+    buttons: Buttons<MouseButton>,
+}
+
+impl Mouse {
+    pub const PIXELS_PER_LINE: f32 = 50.0;
+
+    fn new() -> Self {
+        Self {
+            pos: Vec2::ZERO,
+            delta: Vec2::ZERO,
+            scroll_delta: Vec2::ZERO,
+            buttons: Buttons::default(),
+        }
+    }
+
+    fn update(&mut self, window: &Window, event: &WindowEvent, consumed: bool) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let pos = vec2(position.x as f32, position.y as f32);
+                if !consumed {
+                    self.delta += pos - self.pos;
+                }
+                self.pos = pos;
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.buttons.update(*button, *state, consumed);
+            }
+            WindowEvent::MouseWheel { delta, .. } if !consumed => {
+                self.scroll_delta = match *delta {
+                    MouseScrollDelta::LineDelta(x, y) => vec2(x, y) * Self::PIXELS_PER_LINE,
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        vec2(delta.x as f32, delta.y as f32) / window.scale_factor() as f32
+                    }
+                }
+            }
+            WindowEvent::Focused(false) => self.buttons.release_all(),
+            _ => (),
+        }
+    }
+
+    fn end_frame(&mut self, dt: f32) {
+        const DECAY_RATE: f32 = 5.0;
+        // moving at 1/4th of a pixel
+        const CLOSE_TO_ZERO: f32 = 0.25;
+
+        self.delta = Vec2::ZERO;
+        self.buttons.end_frame();
+
+        let decay = (-DECAY_RATE * dt).exp();
+        let smoothed = self.scroll_delta * decay;
+
+        // stop moving if the velocity is close to zero
+        if smoothed.abs_diff_eq(Vec2::ZERO, CLOSE_TO_ZERO) {
+            self.scroll_delta = Vec2::ZERO
+        } else {
+            self.scroll_delta = smoothed;
+        }
+    }
+
+    /// The position of the cursor in the window, in physical pixels.
+    pub fn pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    /// How far the cursor moved this frame, in physical pixels.
+    pub fn delta(&self) -> Vec2 {
+        self.delta
+    }
+
+    /// The scroll in logical pixels, smoothed out over the frames after scrolling.
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.scroll_delta
+    }
+
+    pub fn is_down(&self, button: MouseButton) -> bool {
+        self.buttons.down.contains(&button)
+    }
+
+    /// Whether the `button` was pressed this frame.
+    pub fn just_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.pressed.contains(&button)
+    }
+
+    /// Whether the `button` was released this frame.
+    pub fn just_released(&self, button: MouseButton) -> bool {
+        self.buttons.released.contains(&button)
+    }
+
+    pub fn left_down(&self) -> bool {
+        self.is_down(MouseButton::Left)
+    }
+
+    pub fn right_down(&self) -> bool {
+        self.is_down(MouseButton::Right)
+    }
+
+    pub fn middle_down(&self) -> bool {
+        self.is_down(MouseButton::Middle)
+    }
+}
+
+pub struct Keyboard {
+    keys: Buttons<KeyCode>,
+    modifiers: ModifiersState,
+}
+
+impl Keyboard {
+    fn new() -> Self {
+        Self {
+            keys: Buttons::default(),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    fn update(&mut self, event: &WindowEvent, consumed: bool) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    self.keys.update(key, event.state, consumed);
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::Focused(false) => {
+                self.keys.release_all();
+                self.modifiers = ModifiersState::empty();
+            }
+            _ => (),
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.keys.end_frame();
+    }
+
+    pub fn is_down(&self, key: KeyCode) -> bool {
+        self.keys.down.contains(&key)
+    }
+
+    /// Whether the `key` was pressed this frame, ignoring repeats.
+    pub fn just_pressed(&self, key: KeyCode) -> bool {
+        self.keys.pressed.contains(&key)
+    }
+
+    /// Whether the `key` was released this frame.
+    pub fn just_released(&self, key: KeyCode) -> bool {
+        self.keys.released.contains(&key)
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+}
+
+/// The keyboard and mouse of the main window.
+pub struct Input {
+    mouse: Mouse,
+    keyboard: Keyboard,
+}
+
+impl Input {
+    pub(crate) fn new() -> Self {
+        Self {
+            mouse: Mouse::new(),
+            keyboard: Keyboard::new(),
+        }
+    }
+
+    /// Keep track of an `event`, which may have been `consumed` by the app.
+    pub(crate) fn update(&mut self, window: &Window, event: &WindowEvent, consumed: bool) {
+        self.mouse.update(window, event, consumed);
+        self.keyboard.update(event, consumed);
+    }
+
+    /// Forget what changed this frame, `dt` seconds long.
+    pub(crate) fn end_frame(&mut self, dt: f32) {
+        self.mouse.end_frame(dt);
+        self.keyboard.end_frame();
+    }
+
+    pub fn mouse(&self) -> &Mouse {
+        &self.mouse
+    }
+
+    pub fn keyboard(&self) -> &Keyboard {
+        &self.keyboard
+    }
+}
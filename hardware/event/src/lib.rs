@@ -1,41 +1,87 @@
+mod capture;
 mod error;
+mod input;
+mod proxy;
 mod timer;
+mod window;
 
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
 
+pub use capture::{
+    Capture,
+    CaptureCallback,
+};
 use error::RunError;
-use graphics::wgpu::{
-    self,
-    CommandEncoderDescriptor,
-    Device,
-    PresentMode,
-    Queue,
-    SurfaceConfiguration,
+pub use error::{
+    CaptureError,
+    WindowError,
+};
+use graphics::{
+    wgpu::{
+        self,
+        CommandEncoderDescriptor,
+        Device,
+        PresentMode,
+        Queue,
+        SurfaceConfiguration,
+    },
+    MultisampleTarget,
 };
-use timer::Timer;
+pub use input::{
+    Input,
+    Keyboard,
+    Mouse,
+};
+pub use proxy::Proxy;
+pub use timer::Timer;
+use window::Windows;
 pub use winit::event_loop::EventLoopBuilder;
 use winit::{
+    error::OsError,
     event::{
         Event as WEvent,
+        StartCause,
         WindowEvent,
     },
     event_loop::{
         ControlFlow,
         EventLoop,
     },
-    window::Window,
+    window::{
+        Window,
+        WindowBuilder,
+        WindowId,
+    },
 };
 
 pub struct State<'a> {
     device: &'a Arc<Device>,
     queue: &'a Arc<Queue>,
     window: &'a Window,
+    context: &'a graphics::Context,
+    create_window: &'a dyn Fn(WindowBuilder) -> Result<Window, OsError>,
+    exit: &'a dyn Fn(),
+    windows: &'a mut Windows,
+    capture: &'a mut Option<CaptureCallback>,
 
     timer: &'a mut Timer,
+    input: &'a mut Input,
 
     surface_config: &'a mut SurfaceConfiguration,
+    multisample: &'a mut Option<MultisampleTarget>,
 
     dirty: bool,
+    redraw: Redraw,
+    /// When to draw the next frame without input, when drawing [`Redraw::OnDemand`].
+    redraw_at: &'a mut Option<Instant>,
+    fixed_timestep: Duration,
+    target_fps: Option<u32>,
 }
 
 impl<'a> State<'a> {
@@ -51,6 +97,69 @@ impl<'a> State<'a> {
         self.surface_config.present_mode = present_mode(vsync);
     }
 
+    /// Whether frames are drawn back to back.
+    pub fn is_continuous(&self) -> bool {
+        self.redraw == Redraw::Continuous
+    }
+
+    /// Draw frames back to back, or only after input and every [`IDLE_REDRAW`] otherwise.
+    pub fn set_continuous(&mut self, continuous: bool) {
+        self.redraw = if continuous {
+            Redraw::Continuous
+        } else {
+            Redraw::Idle
+        };
+    }
+
+    pub fn redraw(&self) -> Redraw {
+        self.redraw
+    }
+
+    /// Choose when frames are drawn.
+    pub fn set_redraw(&mut self, redraw: Redraw) {
+        self.redraw = redraw;
+    }
+
+    /// Draw another frame, even when drawing [`Redraw::OnDemand`].
+    pub fn request_redraw(&mut self) {
+        self.request_redraw_after(Duration::ZERO);
+    }
+
+    /// Draw another frame `delay` from now, such as to check on work in the background.
+    ///
+    /// Only needed when drawing [`Redraw::OnDemand`], the earliest request is kept until it's drawn.
+    pub fn request_redraw_after(&mut self, delay: Duration) {
+        let Some(at) = Instant::now().checked_add(delay) else {
+            return;
+        };
+
+        let earliest = self.redraw_at.map_or(at, |other| other.min(at));
+        *self.redraw_at = Some(earliest);
+    }
+
+    /// The most frames drawn each second, if limited.
+    pub fn target_fps(&self) -> Option<u32> {
+        self.target_fps
+    }
+
+    /// Limit the frames drawn back to back to `fps` a second, waiting between them.
+    ///
+    /// Unlike vsync, this doesn't depend on the display, so it also saves power
+    /// on machines that don't support it.
+    pub fn set_target_fps(&mut self, fps: Option<u32>) {
+        self.target_fps = fps.filter(|&fps| fps > 0);
+    }
+
+    /// The time between calls to [`EventHandler::fixed_update`].
+    pub fn fixed_timestep(&self) -> Duration {
+        self.fixed_timestep
+    }
+
+    /// Call [`EventHandler::fixed_update`] every `timestep`, [`FIXED_TIMESTEP`] by default.
+    pub fn set_fixed_timestep(&mut self, timestep: Duration) {
+        self.fixed_timestep = timestep.max(MIN_FIXED_TIMESTEP);
+    }
+
     pub fn dimensions(&self) -> (u32, u32) {
         // both dimensions are guaranteed to be greater than 0
         (self.surface_config.width, self.surface_config.height)
@@ -68,21 +177,140 @@ impl<'a> State<'a> {
         self.window
     }
 
+    /// The graphics context the window is drawn with.
+    pub fn context(&self) -> &graphics::Context {
+        self.context
+    }
+
+    /// Open another window, its events are passed on as [`Event::Child`].
+    ///
+    /// The window is closed once it's dropped.
+    pub fn create_window(&self, builder: WindowBuilder) -> Result<Arc<Window>, OsError> {
+        (self.create_window)(builder).map(Arc::new)
+    }
+
+    /// Open another window drawn by the event loop, with [`EventHandler::draw_window`].
+    ///
+    /// Its events are passed on as [`Event::Child`], it's closed when asked to or by [`State::close_window`].
+    pub fn open_window(&mut self, builder: WindowBuilder) -> Result<WindowId, WindowError> {
+        let window = self.create_window(builder)?;
+        let surface = self.create_surface(Arc::clone(&window))?;
+
+        Ok(self.windows.insert(
+            window,
+            surface,
+            self.context.adapter(),
+            self.device,
+            self.surface_config,
+        ))
+    }
+
+    /// Close a window opened with [`State::open_window`], returns false if it was already closed.
+    pub fn close_window(&mut self, id: WindowId) -> bool {
+        self.windows.remove(id)
+    }
+
+    /// A window opened with [`State::open_window`], if it's still open.
+    pub fn child_window(&self, id: WindowId) -> Option<&Window> {
+        self.windows.window(id)
+    }
+
+    /// The size of the surface of a window opened with [`State::open_window`].
+    pub fn child_dimensions(&self, id: WindowId) -> Option<(u32, u32)> {
+        self.windows
+            .config(id)
+            .map(|config| (config.width, config.height))
+    }
+
+    /// Create a surface to draw to another `window`, with the same device as the main one.
+    pub fn create_surface(
+        &self,
+        window: Arc<Window>,
+    ) -> Result<wgpu::Surface<'static>, wgpu::CreateSurfaceError> {
+        self.context.create_surface(window)
+    }
+
+    /// Read back the next frame presented to the window, with everything drawn to it.
+    ///
+    /// The `callback` is called with the frame once it's drawn, replacing any earlier request not yet captured.
+    pub fn capture_frame(
+        &mut self,
+        callback: impl FnOnce(Result<Capture, CaptureError>) + 'static,
+    ) {
+        *self.capture = Some(Box::new(callback));
+    }
+
+    /// Exit the event loop, [`EventHandler::exit`] is called before it returns.
+    pub fn exit(&self) {
+        (self.exit)();
+    }
+
     pub fn surface_config(&self) -> &SurfaceConfiguration {
         self.surface_config
     }
 
+    /// The target to draw antialiased to, resolved into the frame, when the window has
+    /// more than one [`sample_count`](graphics::Context::sample_count) a pixel.
+    ///
+    /// It's kept the same size as the frame, to draw to with
+    /// [`Encoder::begin_multisampled_render_pass`](graphics::Encoder::begin_multisampled_render_pass).
+    pub fn multisample_target(&self) -> Option<&MultisampleTarget> {
+        self.multisample.as_ref()
+    }
+
     pub fn timer(&self) -> &Timer {
         self.timer
     }
+
+    /// The keyboard and mouse, as of the events so far.
+    pub fn input(&self) -> &Input {
+        self.input
+    }
+}
+
+/// How often a frame is drawn without input, when drawing [`Redraw::Idle`].
+pub const IDLE_REDRAW: Duration = Duration::from_millis(100);
+
+/// When the event loop draws frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Redraw {
+    /// Back to back, as fast as the [`State::target_fps`] and vsync allow.
+    #[default]
+    Continuous,
+    /// After input, and every [`IDLE_REDRAW`].
+    Idle,
+    /// Only after input, or when requested with [`State::request_redraw`].
+    ///
+    /// The loop sleeps in between, so an app with nothing to do uses no power.
+    OnDemand,
 }
 
+/// The default time between fixed updates, 60 times a second.
+pub const FIXED_TIMESTEP: Duration = Duration::from_nanos(16_666_667);
+/// Stops the loop from spinning on fixed updates with a zero timestep.
+const MIN_FIXED_TIMESTEP: Duration = Duration::from_micros(100);
+/// The most fixed updates run before a frame.
+///
+/// A slow frame drops the time it's behind by, rather than
+/// running more updates, taking longer still, and falling further behind.
+const MAX_FIXED_UPDATES: u32 = 8;
+
 pub enum Event<'a, T = ()> {
     Window(&'a WindowEvent),
+    /// An event for a window opened with [`State::create_window`] or [`State::open_window`].
+    Child(WindowId, &'a WindowEvent),
     User(T),
 }
 
 pub trait EventHandler<T = ()>: Sized {
+    /// Called every [`State::fixed_timestep`], before [`EventHandler::update`],
+    /// however long the frames take.
+    ///
+    /// Runs any number of times a frame, including none.
+    #[inline(always)]
+    #[allow(unused_variables)]
+    fn fixed_update(&mut self, state: &mut State, dt: f32) {}
+
     fn update(&mut self, state: &mut State);
     fn draw(
         &mut self,
@@ -91,15 +319,65 @@ pub trait EventHandler<T = ()>: Sized {
         target: &wgpu::TextureView,
     );
 
+    /// Returns true if the app consumed the `event`,
+    /// it then isn't counted as pressing anything in the [`Input`].
     #[inline(always)]
     #[allow(unused_variables)]
     fn event(&mut self, state: &State, event: Event<T>) -> bool {
         false
     }
 
+    /// Draw to a window opened with [`State::open_window`], after the main window is drawn.
+    #[inline(always)]
+    #[allow(unused_variables)]
+    fn draw_window(
+        &mut self,
+        state: &mut State,
+        id: WindowId,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+    }
+
+    /// Called before the surface is dropped, such as when the app is sent to the background on Android.
+    ///
+    /// Nothing is drawn until [`EventHandler::resumed`],
+    /// resources that are lost along with the surface can be dropped here.
+    #[inline(always)]
+    #[allow(unused_variables)]
+    fn suspended(&mut self, state: &mut State) {}
+
+    /// Called once the surface is recreated after being [`suspended`](EventHandler::suspended).
+    #[inline(always)]
+    #[allow(unused_variables)]
+    fn resumed(&mut self, state: &mut State) {}
+
+    /// Called once the device is recreated after being lost, such as by a driver reset.
+    ///
+    /// Everything made with the old device, such as pipelines and textures,
+    /// has to be made again with [`State::device`].
+    #[inline(always)]
+    #[allow(unused_variables)]
+    fn device_recreated(&mut self, state: &mut State) {}
+
+    /// Called once the surface is reconfigured to a new size, `width` by `height` pixels.
+    ///
+    /// Both dimensions are greater than 0.
+    #[inline(always)]
+    #[allow(unused_variables)]
+    fn resized(&mut self, state: &mut State, width: u32, height: u32) {}
+
     #[inline(always)]
     #[allow(unused_variables)]
     fn frame_end(&mut self, state: &State) {}
+
+    /// Called once as the event loop exits, before [`run`] returns.
+    ///
+    /// Whether the window was closed, [`State::exit`] was called or the device ran out of memory,
+    /// so it's the place to save anything that should outlive the app.
+    #[inline(always)]
+    #[allow(unused_variables)]
+    fn exit(&mut self, state: &State) {}
 }
 
 pub fn run<E, T>(
@@ -119,7 +397,7 @@ where
     }
 
     log::info!("building graphics context");
-    let ctx = gfx.build(Some(&event_loop))?;
+    let mut ctx = gfx.build(Some(&event_loop))?;
 
     // create the app
     log::info!("creating app");
@@ -128,17 +406,19 @@ where
     // Poll by default
     event_loop.set_control_flow(ControlFlow::Poll);
 
+    // dropped while suspended
+    let mut surface = Some(ctx.take_surface().expect("created with a window"));
     let window = ctx.window().expect("created with a window");
-    let surface = ctx.surface().expect("created with a window");
-    let device = ctx.device();
-    let queue = ctx.queue();
+    // replaced if the device is lost
+    let mut device = ctx.device();
+    let mut queue = ctx.queue();
 
     let size = window.inner_size();
 
     // create the surface configuration for the window
     let mut config = SurfaceConfiguration {
         desired_maximum_frame_latency: 2,
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        usage: surface_usage(&ctx),
         format: ctx.view_format().expect("created with a window"),
         width: size.width.max(1),
         height: size.height.max(1),
@@ -147,21 +427,43 @@ where
             .capabilities()
             .expect("created with a window")
             .alpha_modes[0],
-        view_formats: vec![],
+        view_formats: ctx.view_formats(),
     };
 
-    surface.configure(&device, &config);
+    if let Some(surface) = &surface {
+        surface.configure(&device, &config);
+    }
     log::info!("configured surface with {:?}", &config);
 
     window.set_visible(true);
 
     // create a timer used for timing deltas
     let mut timer = Timer::new();
+    let mut input = Input::new();
+    let mut windows = Windows::default();
+    let mut capture = None;
+
+    let sample_count = ctx.sample_count();
+    let multisample_target = |device: &Device, config: &SurfaceConfiguration| {
+        let size = (config.width, config.height);
+        (sample_count > 1)
+            .then(|| MultisampleTarget::new(device, config.format, sample_count, size))
+    };
+    let mut multisample = multisample_target(&device, &config);
 
     let mut dirty = false;
+    let mut redraw = Redraw::default();
+    let mut redraw_at = None;
+    let mut fixed_timestep = FIXED_TIMESTEP;
+    let mut target_fps = None;
+    // when the last frame started drawing
+    let mut last_frame = Instant::now();
+    // the time not yet taken by fixed updates
+    let mut accumulator = Duration::ZERO;
 
     // start the event loop
     let mut running = true;
+    let context = &mut ctx;
     timer.start();
 
     event_loop.run(move |event, target| {
@@ -171,28 +473,90 @@ where
             return;
         }
 
+        // recover from a driver reset, before anything else uses the device
+        let recreated = context.is_device_lost();
+        if recreated {
+            if let Err(e) = context.recreate_device() {
+                log::error!("failed to recreate device: {e}");
+
+                running = false;
+                target.exit();
+                return;
+            }
+
+            device = context.device();
+            queue = context.queue();
+
+            if let Some(surface) = &surface {
+                surface.configure(&device, &config);
+            }
+            windows.configure(&device);
+            multisample = multisample_target(&device, &config);
+        }
+
+        let create_window = |builder: WindowBuilder| builder.build(target);
+        let exit = || target.exit();
+
         // create a state for this frame
         let mut state = State {
             device: &device,
             queue: &queue,
             window: &window,
+            context: &*context,
+            create_window: &create_window,
+            exit: &exit,
+            windows: &mut windows,
+            capture: &mut capture,
+            multisample: &mut multisample,
             timer: &mut timer,
+            input: &mut input,
             surface_config: &mut config,
             dirty: false,
+            redraw,
+            redraw_at: &mut redraw_at,
+            fixed_timestep,
+            target_fps,
         };
 
+        if recreated {
+            app.device_recreated(&mut state);
+            window.request_redraw();
+        }
+
         match event {
             WEvent::UserEvent(user) => {
                 // pass on user events to the state
                 let _ = app.event(&state, Event::User(user));
+
+                // show what the event changed, even when idle
+                if redraw != Redraw::Continuous {
+                    window.request_redraw();
+                }
             }
 
             WEvent::WindowEvent { event, window_id } if window_id == window.id() => {
-                let _ = app.event(&state, Event::Window(&event));
+                let consumed = app.event(&state, Event::Window(&event));
+                state.input.update(&window, &event, consumed);
+
+                // respond to input straight away, even when idle
+                if redraw != Redraw::Continuous && !matches!(event, WindowEvent::RedrawRequested) {
+                    window.request_redraw();
+                }
 
                 match event {
                     WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
-                        reconfigure_surface(&window, surface, &mut config, &device);
+                        // reconfigured once resumed
+                        let Some(surface) = &surface else {
+                            return;
+                        };
+
+                        let old = state.dimensions();
+                        reconfigure_surface(&window, surface, state.surface_config, &device);
+
+                        let (width, height) = state.dimensions();
+                        if (width, height) != old {
+                            app.resized(&mut state, width, height);
+                        }
                         // On macos the window needs to be redrawn manually after resizing
                         window.request_redraw();
                     }
@@ -203,6 +567,14 @@ where
                     WindowEvent::RedrawRequested => {
                         profiling::scope!("event::redraw");
 
+                        // nothing to draw to while suspended
+                        let Some(surface) = &surface else {
+                            return;
+                        };
+
+                        last_frame = Instant::now();
+                        // this is the frame that was requested
+                        *state.redraw_at = None;
                         state.timer.tick();
 
                         if dirty {
@@ -252,6 +624,25 @@ where
                             }
                         };
 
+                        {
+                            profiling::scope!("app::fixed_update");
+
+                            accumulator += Duration::from_secs_f32(state.timer.dt());
+
+                            let mut updates = 0;
+                            while accumulator >= state.fixed_timestep {
+                                if updates == MAX_FIXED_UPDATES {
+                                    accumulator = Duration::ZERO;
+                                    break;
+                                }
+
+                                let step = state.fixed_timestep;
+                                app.fixed_update(&mut state, step.as_secs_f32());
+                                accumulator -= step;
+                                updates += 1;
+                            }
+                        }
+
                         {
                             profiling::scope!("app::update");
                             app.update(&mut state);
@@ -260,6 +651,12 @@ where
                         // create a view into the surface texture
                         let target = frame.texture.create_view(&Default::default());
 
+                        // keep up with the size of the surface
+                        let (width, height) = state.dimensions();
+                        if let Some(multisample) = state.multisample.as_mut() {
+                            multisample.resize(&device, width, height);
+                        }
+
                         let mut encoder =
                             device.create_command_encoder(&CommandEncoderDescriptor::default());
 
@@ -268,29 +665,146 @@ where
                             app.draw(&mut state, &mut encoder, &target);
                         }
 
+                        // copy the frame once everything is drawn to it
+                        let capture = state.capture.take().map(|callback| {
+                            let config = &*state.surface_config;
+                            let pending =
+                                capture::copy(&device, &mut encoder, &frame.texture, config);
+                            (callback, pending)
+                        });
+
                         {
                             profiling::scope!("encoder::submit");
                             queue.submit(Some(encoder.finish()));
                         }
 
+                        if let Some((callback, pending)) = capture {
+                            profiling::scope!("frame::capture");
+                            callback(pending.and_then(|pending| pending.read(&device)));
+                        }
+
                         {
                             profiling::scope!("frame::present");
                             frame.present();
                         }
 
+                        for id in state.windows.ids() {
+                            profiling::scope!("app::draw_window");
+
+                            let Some(frame) = state.windows.current_texture(id, &device) else {
+                                continue;
+                            };
+                            let target = frame.texture.create_view(&Default::default());
+
+                            let mut encoder =
+                                device.create_command_encoder(&CommandEncoderDescriptor::default());
+                            app.draw_window(&mut state, id, &mut encoder, &target);
+
+                            queue.submit(Some(encoder.finish()));
+                            frame.present();
+                        }
+
                         profiling::finish_frame!();
 
                         app.frame_end(&state);
+                        state.input.end_frame(state.timer.dt());
 
                         dirty = state.dirty;
+                        redraw = state.redraw;
+                        fixed_timestep = state.fixed_timestep;
+                        target_fps = state.target_fps;
                     }
                     _ => (),
                 }
             }
-            WEvent::AboutToWait => {
-                // constantly redraw
+            WEvent::WindowEvent { event, window_id } => {
+                let _ = app.event(&state, Event::Child(window_id, &event));
+
+                if state.windows.contains(window_id) {
+                    match event {
+                        WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                            state.windows.reconfigure(window_id, &device);
+                        }
+                        WindowEvent::CloseRequested => {
+                            state.windows.remove(window_id);
+                        }
+                        _ => (),
+                    }
+                }
+
+                // child windows are drawn along with the main one
+                if redraw != Redraw::Continuous && !matches!(event, WindowEvent::RedrawRequested) {
+                    window.request_redraw();
+                }
+            }
+            WEvent::Suspended => {
+                log::info!("suspended, dropping surface");
+
+                app.suspended(&mut state);
+                surface = None;
+            }
+            // sent once at the start too, when the surface already exists
+            WEvent::Resumed if surface.is_none() => {
+                log::info!("resumed, recreating surface");
+
+                let new = match context.create_surface(Arc::clone(&window)) {
+                    Ok(new) => new,
+                    Err(e) => {
+                        log::error!("failed to recreate surface: {e}");
+
+                        running = false;
+                        target.exit();
+                        return;
+                    }
+                };
+
+                // the window may have changed size while suspended
+                let old = state.dimensions();
+                reconfigure_surface(&window, &new, state.surface_config, &device);
+                surface = Some(new);
+
+                app.resumed(&mut state);
+
+                let (width, height) = state.dimensions();
+                if (width, height) != old {
+                    app.resized(&mut state, width, height);
+                }
+
+                window.request_redraw();
+            }
+            WEvent::NewEvents(StartCause::ResumeTimeReached { .. }) => {
                 window.request_redraw();
             }
+            WEvent::AboutToWait => {
+                let next_frame = target_fps
+                    .map(|fps| last_frame + Duration::from_secs_f64(1.0 / fps as f64))
+                    .filter(|&next| next > Instant::now());
+
+                match (redraw, *state.redraw_at) {
+                    (Redraw::Continuous, _) => {
+                        if let Some(next_frame) = next_frame {
+                            // wait out the rest of the frame, then redraw
+                            target.set_control_flow(ControlFlow::WaitUntil(next_frame));
+                        } else {
+                            // constantly redraw
+                            target.set_control_flow(ControlFlow::Poll);
+                            window.request_redraw();
+                        }
+                    }
+                    (Redraw::Idle, _) => {
+                        target.set_control_flow(ControlFlow::wait_duration(IDLE_REDRAW));
+                    }
+                    (Redraw::OnDemand, Some(at)) => {
+                        target.set_control_flow(ControlFlow::WaitUntil(at));
+                    }
+                    (Redraw::OnDemand, None) => {
+                        target.set_control_flow(ControlFlow::Wait);
+                    }
+                }
+            }
+            WEvent::LoopExiting => {
+                app.exit(&state);
+            }
             _ => (),
         }
     })?;
@@ -318,6 +832,15 @@ fn reconfigure_surface(
     surface.configure(device, config);
 }
 
+/// Frames are copied from the surface when they're captured, where it's supported.
+fn surface_usage(ctx: &graphics::Context) -> wgpu::TextureUsages {
+    let supported = ctx
+        .capabilities()
+        .map_or(wgpu::TextureUsages::empty(), |c| c.usages);
+
+    wgpu::TextureUsages::RENDER_ATTACHMENT | (supported & wgpu::TextureUsages::COPY_SRC)
+}
+
 fn present_mode(vsync: bool) -> wgpu::PresentMode {
     if vsync {
         wgpu::PresentMode::AutoVsync
@@ -0,0 +1,34 @@
+use winit::event_loop::{
+    EventLoop,
+    EventLoopProxy,
+};
+
+/// Sends [`Event::User`](crate::Event::User)s to the app from other threads,
+/// such as when work in the background finishes.
+pub struct Proxy<T: 'static> {
+    proxy: EventLoopProxy<T>,
+}
+
+impl<T: 'static> Proxy<T> {
+    /// Create a proxy for the `event_loop` given to the app by [`run`](crate::run).
+    pub fn new(event_loop: &EventLoop<T>) -> Self {
+        Self {
+            proxy: event_loop.create_proxy(),
+        }
+    }
+
+    /// Send an `event` to the app, waking the event loop if it's waiting.
+    ///
+    /// Gives the `event` back if the event loop has exited.
+    pub fn send(&self, event: T) -> Result<(), T> {
+        self.proxy.send_event(event).map_err(|e| e.0)
+    }
+}
+
+impl<T: 'static> Clone for Proxy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            proxy: self.proxy.clone(),
+        }
+    }
+}
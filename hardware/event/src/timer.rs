@@ -1,4 +1,13 @@
-use std::time::Instant;
+use std::{
+    collections::VecDeque,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// The number of frames kept for the statistics.
+const HISTORY: usize = 300;
 
 struct Times {
     start: Instant,
@@ -15,11 +24,18 @@ impl Times {
 
 pub struct Timer {
     times: Option<Times>,
+    /// The last [`HISTORY`] frame times, oldest first.
+    history: VecDeque<Duration>,
+    frames: u64,
 }
 
 impl Timer {
     pub(crate) fn new() -> Self {
-        Self { times: None }
+        Self {
+            times: None,
+            history: VecDeque::with_capacity(HISTORY),
+            frames: 0,
+        }
     }
 
     pub fn dt(&self) -> f32 {
@@ -51,6 +67,61 @@ impl Timer {
     pub(crate) fn tick(&mut self) {
         if let Some(times) = self.times.as_mut() {
             times.push(Instant::now());
+
+            if self.history.len() == HISTORY {
+                self.history.pop_front();
+            }
+            self.history.push_back(Duration::from_secs_f32(self.dt()));
+            self.frames += 1;
         }
     }
+
+    /// The number of frames drawn.
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    /// The time since the event loop started.
+    pub fn elapsed(&self) -> Duration {
+        self.times
+            .as_ref()
+            .map_or(Duration::ZERO, |times| times.start.elapsed())
+    }
+
+    /// The recent frame times, oldest first.
+    pub fn history(&self) -> impl ExactSizeIterator<Item = Duration> + '_ {
+        self.history.iter().copied()
+    }
+
+    /// The frames drawn each second, over the recent frames.
+    pub fn fps(&self) -> f32 {
+        let total: Duration = self.history.iter().sum();
+        if total.is_zero() {
+            0.0
+        } else {
+            self.history.len() as f32 / total.as_secs_f32()
+        }
+    }
+
+    /// The mean of the recent frame times.
+    pub fn average(&self) -> Duration {
+        let total: Duration = self.history.iter().sum();
+        total
+            .checked_div(self.history.len() as u32)
+            .unwrap_or_default()
+    }
+
+    /// The recent frame time that `percentile` of frames were at least as fast as,
+    /// such as 0.99 for the slowest 1%.
+    pub fn percentile(&self, percentile: f32) -> Duration {
+        if self.history.is_empty() {
+            return Duration::ZERO;
+        }
+
+        let mut sorted: Vec<_> = self.history.iter().copied().collect();
+        sorted.sort_unstable();
+
+        let i = (percentile.clamp(0.0, 1.0) * (sorted.len() - 1) as f32).round() as usize;
+        sorted[i]
+    }
 }
@@ -0,0 +1,135 @@
+//! Windows opened alongside the main one, each drawn to its own surface.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+
+use graphics::wgpu::{
+    self,
+    SurfaceConfiguration,
+};
+use winit::window::{
+    Window,
+    WindowId,
+};
+
+struct Child {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    config: SurfaceConfiguration,
+}
+
+/// The windows opened with [`State::open_window`](crate::State::open_window).
+#[derive(Default)]
+pub(crate) struct Windows {
+    windows: HashMap<WindowId, Child>,
+}
+
+impl Windows {
+    /// Draw to a new `window`, configured like the `main` surface where it can be.
+    pub(crate) fn insert(
+        &mut self,
+        window: Arc<Window>,
+        surface: wgpu::Surface<'static>,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        main: &SurfaceConfiguration,
+    ) -> WindowId {
+        let capabilities = surface.get_capabilities(adapter);
+        let size = window.inner_size();
+
+        let format = if capabilities.formats.contains(&main.format) {
+            main.format
+        } else {
+            capabilities.formats[0]
+        };
+
+        let config = SurfaceConfiguration {
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            alpha_mode: capabilities.alpha_modes[0],
+            usage: main.usage & capabilities.usages,
+            // views can only differ from the surface by being srgb or not
+            view_formats: main
+                .view_formats
+                .iter()
+                .copied()
+                .filter(|view| view.remove_srgb_suffix() == format.remove_srgb_suffix())
+                .collect(),
+            ..main.clone()
+        };
+        surface.configure(device, &config);
+
+        let id = window.id();
+        self.windows.insert(
+            id,
+            Child {
+                window,
+                surface,
+                config,
+            },
+        );
+
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: WindowId) -> bool {
+        self.windows.remove(&id).is_some()
+    }
+
+    pub(crate) fn contains(&self, id: WindowId) -> bool {
+        self.windows.contains_key(&id)
+    }
+
+    pub(crate) fn ids(&self) -> Vec<WindowId> {
+        self.windows.keys().copied().collect()
+    }
+
+    pub(crate) fn window(&self, id: WindowId) -> Option<&Window> {
+        self.windows.get(&id).map(|child| child.window.as_ref())
+    }
+
+    pub(crate) fn config(&self, id: WindowId) -> Option<&SurfaceConfiguration> {
+        self.windows.get(&id).map(|child| &child.config)
+    }
+
+    /// Match the surface of the window to its size.
+    pub(crate) fn reconfigure(&mut self, id: WindowId, device: &wgpu::Device) {
+        if let Some(child) = self.windows.get_mut(&id) {
+            let size = child.window.inner_size();
+            child.config.width = size.width.max(1);
+            child.config.height = size.height.max(1);
+            child.surface.configure(device, &child.config);
+        }
+    }
+
+    /// Configure every surface again, such as with a new `device`.
+    pub(crate) fn configure(&self, device: &wgpu::Device) {
+        for child in self.windows.values() {
+            child.surface.configure(device, &child.config);
+        }
+    }
+
+    /// The next texture to draw to the window, [`None`] if one isn't ready.
+    pub(crate) fn current_texture(
+        &mut self,
+        id: WindowId,
+        device: &wgpu::Device,
+    ) -> Option<wgpu::SurfaceTexture> {
+        let child = self.windows.get(&id)?;
+
+        match child.surface.get_current_texture() {
+            Ok(frame) => Some(frame),
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.reconfigure(id, device);
+                self.windows.get(&id)?.surface.get_current_texture().ok()
+            }
+            Err(e) => {
+                log::warn!("failed to get surface texture of {id:?}: {e}");
+                None
+            }
+        }
+    }
+}
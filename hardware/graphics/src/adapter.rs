@@ -0,0 +1,111 @@
+use std::{
+    convert::Infallible,
+    str::FromStr,
+};
+
+use wgpu::{
+    Adapter,
+    Backends,
+    Instance,
+    PowerPreference,
+    Surface,
+};
+
+/// Overrides the backends chosen by the app, a list such as `vulkan` or `dx12,gl`.
+pub const BACKEND_ENV: &str = "KERRBHY_BACKEND";
+/// Overrides the power preference chosen by the app, one of `low`, `high` or `none`.
+pub const POWER_PREFERENCE_ENV: &str = "KERRBHY_POWER_PREFERENCE";
+
+/// Chooses which [`Adapter`] a [`Context`](crate::Context) is created on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AdapterSelector {
+    /// Let wgpu pick the most suitable adapter.
+    #[default]
+    Auto,
+    /// The adapter at this index of [`enumerate_adapters`].
+    Index(usize),
+    /// The first adapter whose name contains this string, ignoring case.
+    Name(String),
+}
+
+impl FromStr for AdapterSelector {
+    type Err = Infallible;
+
+    /// Parses an index, or falls back to a name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(index) => AdapterSelector::Index(index),
+            Err(_) => AdapterSelector::Name(s.to_owned()),
+        })
+    }
+}
+
+impl AdapterSelector {
+    /// Pick an adapter from the `adapters` that can present to the `surface`.
+    ///
+    /// Returns [`None`] for [`AdapterSelector::Auto`].
+    pub(crate) fn select(
+        &self,
+        adapters: Vec<Adapter>,
+        surface: Option<&Surface>,
+    ) -> Option<Adapter> {
+        let supported = |adapter: &Adapter| match surface {
+            Some(surface) => adapter.is_surface_supported(surface),
+            None => true,
+        };
+
+        match self {
+            AdapterSelector::Auto => None,
+            AdapterSelector::Index(index) => adapters.into_iter().nth(*index).filter(supported),
+            AdapterSelector::Name(name) => {
+                let name = name.to_lowercase();
+
+                adapters
+                    .into_iter()
+                    .filter(supported)
+                    .find(|adapter| adapter.get_info().name.to_lowercase().contains(&name))
+            }
+        }
+    }
+}
+
+/// Lists every [`Adapter`] available on the `backends`, or those set by [`BACKEND_ENV`].
+///
+/// The order matches [`AdapterSelector::Index`].
+pub fn enumerate_adapters(backends: Backends) -> Vec<Adapter> {
+    let backends = backends_from_env().unwrap_or(backends);
+    let instance = Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+
+    instance.enumerate_adapters(backends)
+}
+
+/// The backends set by [`BACKEND_ENV`], if it's set to any.
+pub(crate) fn backends_from_env() -> Option<Backends> {
+    let var = std::env::var(BACKEND_ENV).ok()?;
+
+    let backends = wgpu::util::parse_backends_from_comma_list(&var.to_lowercase());
+    if backends.is_empty() {
+        log::warn!("ignoring {BACKEND_ENV}={var:?}, no backends were recognised");
+        return None;
+    }
+
+    Some(backends)
+}
+
+/// The power preference set by [`POWER_PREFERENCE_ENV`], if it's set to one.
+pub(crate) fn power_preference_from_env() -> Option<PowerPreference> {
+    let var = std::env::var(POWER_PREFERENCE_ENV).ok()?;
+
+    match var.to_lowercase().as_str() {
+        "low" => Some(PowerPreference::LowPower),
+        "high" => Some(PowerPreference::HighPerformance),
+        "none" => Some(PowerPreference::None),
+        _ => {
+            log::warn!("ignoring {POWER_PREFERENCE_ENV}={var:?}, expected low, high or none");
+            None
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Requesting a device, and noticing when it's lost.
+//!
+//! A device can be lost at any time, such as by a driver reset.
+//! Everything made with it fails from then on, until it's recreated.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use wgpu::{
+    Adapter,
+    Device,
+    DeviceLostReason,
+    Queue,
+};
+
+use crate::{
+    error::Error,
+    requirements::Requirements,
+};
+
+/// Request a device from the `adapter`, along with a flag set once it's lost.
+///
+/// The calls made with it are traced to the `trace_path` directory, if given.
+pub(crate) async fn request(
+    adapter: &Adapter,
+    requirements: &Requirements,
+    trace_path: Option<&Path>,
+) -> Result<(Device, Queue, Arc<AtomicBool>), Error> {
+    let (features, limits) = requirements.negotiate(adapter)?;
+
+    log::info!("requesting features {features:?}");
+    log::debug!("requesting limits {limits:?}");
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: features,
+                required_limits: limits,
+            },
+            trace_path,
+        )
+        .await?;
+
+    let lost = Arc::new(AtomicBool::new(false));
+    watch(&device, &lost);
+
+    Ok((device, queue, lost))
+}
+
+/// Set `lost` once the `device` is lost, instead of panicking on the errors that follow.
+fn watch(device: &Device, lost: &Arc<AtomicBool>) {
+    let flag = Arc::clone(lost);
+    device.set_device_lost_callback(move |reason, message| {
+        // the device was dropped on purpose, such as when it's replaced
+        if matches!(
+            reason,
+            DeviceLostReason::Dropped | DeviceLostReason::ReplacedCallback
+        ) {
+            return;
+        }
+
+        log::error!("device lost ({reason:?}): {message}");
+        flag.store(true, Ordering::Relaxed);
+    });
+
+    let flag = Arc::clone(lost);
+    device.on_uncaptured_error(Box::new(move |error| {
+        // everything fails once the device is lost, until it's recreated
+        if flag.load(Ordering::Relaxed) {
+            log::debug!("ignoring error on lost device: {error}");
+            return;
+        }
+
+        match error {
+            wgpu::Error::OutOfMemory { .. } => {
+                log::error!("device out of memory: {error}");
+                flag.store(true, Ordering::Relaxed);
+            }
+            // bugs, so fail loudly like wgpu does by default
+            wgpu::Error::Validation { .. } => panic!("wgpu error: {error}"),
+        }
+    }));
+}
@@ -1,17 +1,21 @@
 use wgpu::{
     Buffer,
     BufferAddress,
+    Color,
     Device,
     Extent3d,
     ImageCopyBuffer,
     ImageCopyTexture,
     ImageSubresourceRange,
+    LoadOp,
     RenderPassDescriptor,
     Texture,
+    TextureView,
 };
 
 use crate::{
     ComputePass,
+    MultisampleTarget,
     RenderPass,
 };
 
@@ -75,6 +79,32 @@ impl<'a> Encoder<'a> {
         }
     }
 
+    /// Begins recording of a render pass drawing to the `multisample` target,
+    /// which is resolved into the `target` at the end of the pass.
+    ///
+    /// `load` is what the samples start as, [`LoadOp::Load`] to draw over an earlier pass.
+    #[inline]
+    pub fn begin_multisampled_render_pass<'pass>(
+        &'pass mut self,
+        label: &str,
+        device: &Device,
+        multisample: &'pass MultisampleTarget,
+        target: &'pass TextureView,
+        load: LoadOp<Color>,
+    ) -> RenderPass<'_> {
+        self.begin_render_pass(
+            label,
+            device,
+            RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(multisample.attachment(target, load))],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            },
+        )
+    }
+
     /// Begins recording of a compute pass.
     ///
     /// This function returns a [`ComputePass`] object which records a single
@@ -82,11 +112,36 @@ impl<'a> Encoder<'a> {
     #[inline]
     pub fn begin_compute_pass(&mut self, label: &str, device: &Device) -> ComputePass<'_> {
         match self {
-            Encoder::Wgpu(enc) => ComputePass::Wgpu(enc.begin_compute_pass(&Default::default())),
+            Encoder::Wgpu(enc) => {
+                ComputePass::Wgpu(enc.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some(label),
+                    timestamp_writes: None,
+                }))
+            }
             Encoder::Profiled(enc) => ComputePass::Profiled(enc.scoped_compute_pass(label, device)),
         }
     }
 
+    /// Start a group of commands, labeled `label` in GPU captures such as RenderDoc.
+    ///
+    /// Groups nest, each has to be ended with [`Self::pop_debug_group`].
+    #[inline]
+    pub fn push_debug_group(&mut self, label: &str) {
+        self.inner().push_debug_group(label);
+    }
+
+    /// End the group started by the last [`Self::push_debug_group`].
+    #[inline]
+    pub fn pop_debug_group(&mut self) {
+        self.inner().pop_debug_group();
+    }
+
+    /// Mark this point in the commands with a `label`, shown in GPU captures.
+    #[inline]
+    pub fn insert_debug_marker(&mut self, label: &str) {
+        self.inner().insert_debug_marker(label);
+    }
+
     /// Copy data from one buffer to another.
     ///
     /// # Panics
@@ -22,9 +22,15 @@ pub enum Error {
     #[error(transparent)]
     RequestDeviceError(#[from] RequestDeviceError),
 
+    #[error("Features {0:?} aren't supported by the adapter")]
+    FeaturesUnsupported(wgpu::Features),
+
     #[error("Limits requested couldn't be fulfilled")]
     LimitsSurpassed,
 
     #[error("Failed to find an appropriate adapter")]
     AdapterCreationError,
+
+    #[error("No adapter matching {0:?} could be found")]
+    AdapterNotFound(crate::AdapterSelector),
 }
@@ -1,13 +1,47 @@
+mod adapter;
+mod device;
 mod encoder;
 mod error;
+mod memory;
+mod mipmap;
+mod multisample;
+mod offscreen;
 mod pass;
+mod requirements;
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    },
+};
 
-use std::sync::Arc;
-
+pub use adapter::{
+    enumerate_adapters,
+    AdapterSelector,
+    BACKEND_ENV,
+    POWER_PREFERENCE_ENV,
+};
 pub use encoder::Encoder;
 pub use error::Error as ContextBuildError;
 use error::Error;
+pub use memory::{
+    Allocation,
+    MemoryTracker,
+    MemoryUsage,
+};
+pub use mipmap::generate_mipmaps;
+pub use multisample::MultisampleTarget;
+pub use offscreen::{
+    read_back,
+    OffscreenTarget,
+};
 pub use pass::*;
+use requirements::Requirements;
 pub use wgpu;
 use wgpu::{
     Adapter,
@@ -27,32 +61,56 @@ use winit::{
 
 struct WindowData {
     window: Arc<Window>,
-    surface: Surface<'static>,
+    /// Taken by whatever draws to the window.
+    surface: Option<Surface<'static>>,
     capabilities: SurfaceCapabilities,
+    options: SurfaceOptions,
+}
+
+/// How the surface of the window is configured.
+struct SurfaceOptions {
     vsync: bool,
+    srgb: bool,
+    view_formats: Vec<TextureFormat>,
+    sample_count: u32,
 }
 
 pub struct ContextBuilder {
-    features: Box<dyn FnOnce(&wgpu::Adapter) -> wgpu::Features>,
-    limits: wgpu::Limits,
+    requirements: Requirements,
+    backends: wgpu::Backends,
+    power_preference: wgpu::PowerPreference,
+    adapter: AdapterSelector,
+    trace_path: Option<PathBuf>,
 
     window: Option<WindowBuilder>,
     vsync: bool,
+    srgb: bool,
+    view_formats: Vec<TextureFormat>,
+    sample_count: u32,
 }
 
 impl ContextBuilder {
     /// Create a new [`ContextBuilder`].
-    /// 
-    /// Can choose the features and limits of the [`Context`].
-    pub fn new(
-        features: impl FnOnce(&wgpu::Adapter) -> wgpu::Features + 'static,
-        limits: wgpu::Limits,
-    ) -> Self {
+    ///
+    /// The [`Context`] is only created on adapters with these `features` and `limits`,
+    /// and the device is given no more than them, unless optional ones are added.
+    pub fn new(features: wgpu::Features, limits: wgpu::Limits) -> Self {
         Self {
-            features: Box::new(features),
-            limits,
+            requirements: Requirements {
+                features,
+                optional_features: wgpu::Features::empty(),
+                optional_limits: limits.clone(),
+                limits,
+            },
+            backends: wgpu::Backends::PRIMARY,
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            adapter: AdapterSelector::Auto,
+            trace_path: None,
             window: None,
             vsync: true,
+            srgb: false,
+            view_formats: Vec::new(),
+            sample_count: 1,
         }
     }
 
@@ -64,6 +122,94 @@ impl ContextBuilder {
         }
     }
 
+    /// Whether the [`Window`] waits for vertical sync.
+    ///
+    /// Defaults to `true`.
+    pub fn with_vsync(self, vsync: bool) -> Self {
+        Self { vsync, ..self }
+    }
+
+    /// Whether the [`Window`] has an sRGB surface, where it supports one.
+    ///
+    /// Colors drawn to an sRGB surface are encoded from linear light,
+    /// otherwise they're shown as they're written.
+    /// Defaults to `false`.
+    pub fn with_srgb(self, srgb: bool) -> Self {
+        Self { srgb, ..self }
+    }
+
+    /// Other formats that views of the surface can have, such as the sRGB version of it.
+    ///
+    /// Only formats that differ from the surface by being sRGB or not are kept,
+    /// see [`Context::view_formats`].
+    pub fn with_view_formats(self, view_formats: Vec<TextureFormat>) -> Self {
+        Self {
+            view_formats,
+            ..self
+        }
+    }
+
+    /// How many samples a pixel drawing to the [`Window`] is antialiased with,
+    /// lowered to what the surface supports.
+    ///
+    /// Defaults to `1`, without antialiasing, see [`Context::sample_count`].
+    pub fn with_sample_count(self, sample_count: u32) -> Self {
+        Self {
+            sample_count,
+            ..self
+        }
+    }
+
+    /// Only look for adapters on these `backends`.
+    ///
+    /// Defaults to [`wgpu::Backends::PRIMARY`], overridden by [`BACKEND_ENV`].
+    pub fn with_backends(self, backends: wgpu::Backends) -> Self {
+        Self { backends, ..self }
+    }
+
+    /// Whether to prefer a fast or a power saving adapter, such as on a laptop with two GPUs.
+    ///
+    /// Defaults to [`wgpu::PowerPreference::HighPerformance`], overridden by [`POWER_PREFERENCE_ENV`].
+    /// Only used when the adapter is [`AdapterSelector::Auto`].
+    pub fn with_power_preference(self, power_preference: wgpu::PowerPreference) -> Self {
+        Self {
+            power_preference,
+            ..self
+        }
+    }
+
+    /// Enable these `features` too, where the adapter supports them.
+    ///
+    /// Check which were granted with [`Context::features`].
+    pub fn with_optional_features(mut self, features: wgpu::Features) -> Self {
+        self.requirements.optional_features = features;
+        self
+    }
+
+    /// Raise the limits towards these, as far as the adapter allows.
+    ///
+    /// Check how far they were raised with [`Context::limits`].
+    pub fn with_optional_limits(mut self, limits: wgpu::Limits) -> Self {
+        self.requirements.optional_limits = limits;
+        self
+    }
+
+    /// Choose which adapter the [`Context`] is created on.
+    pub fn with_adapter(self, adapter: AdapterSelector) -> Self {
+        Self { adapter, ..self }
+    }
+
+    /// Record a trace of the wgpu calls made with the device to the `path` directory,
+    /// to replay them when reporting a bug.
+    ///
+    /// Only recorded with the `trace` feature, otherwise wgpu logs that it's missing.
+    pub fn with_trace_path(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            trace_path: Some(path.into()),
+            ..self
+        }
+    }
+
     /// Returns `true` if the builder has an attached window.
     pub fn has_window(&self) -> bool {
         self.window.is_some()
@@ -75,22 +221,54 @@ impl ContextBuilder {
         event_loop: Option<&EventLoop<T>>,
     ) -> Result<Context, ContextBuildError> {
         let Self {
-            features,
-            limits,
+            requirements,
+            backends,
+            power_preference,
+            adapter,
+            trace_path,
             window,
             vsync,
+            srgb,
+            view_formats,
+            sample_count,
         } = self;
 
+        // let users force a working configuration, without the app supporting it
+        let backends = adapter::backends_from_env().unwrap_or(backends);
+        let power_preference = adapter::power_preference_from_env().unwrap_or(power_preference);
+
         let window_info = event_loop.zip(window);
 
-        Context::create(window_info, vsync, features, limits)
+        let options = SurfaceOptions {
+            vsync,
+            srgb,
+            view_formats,
+            sample_count,
+        };
+
+        Context::create(
+            window_info,
+            options,
+            requirements,
+            backends,
+            power_preference,
+            adapter,
+            trace_path,
+        )
     }
 }
 
 pub struct Context {
+    instance: wgpu::Instance,
     adapter: Adapter,
     device: Arc<Device>,
     queue: Arc<Queue>,
+    /// Set once the device is lost.
+    lost: Arc<AtomicBool>,
+    /// Kept to recreate the device.
+    requirements: Requirements,
+    trace_path: Option<PathBuf>,
+    memory: MemoryTracker,
 
     window_data: Option<WindowData>,
 }
@@ -98,12 +276,15 @@ pub struct Context {
 impl Context {
     fn create<T>(
         window_info: Option<(&EventLoop<T>, WindowBuilder)>,
-        vsync: bool,
-        features: impl FnOnce(&wgpu::Adapter) -> wgpu::Features,
-        limits: wgpu::Limits,
+        options: SurfaceOptions,
+        requirements: Requirements,
+        backends: wgpu::Backends,
+        power_preference: wgpu::PowerPreference,
+        selector: AdapterSelector,
+        trace_path: Option<PathBuf>,
     ) -> Result<Self, ContextBuildError> {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
+            backends,
             ..Default::default()
         });
 
@@ -120,46 +301,38 @@ impl Context {
             (None, None)
         };
 
-        let (adapter, device, queue) = pollster::block_on(async {
-            let adapter = instance
-                .request_adapter(&wgpu::RequestAdapterOptions {
-                    power_preference: wgpu::PowerPreference::HighPerformance,
-                    force_fallback_adapter: false,
-                    // Request an adapter which can render to our surface
-                    compatible_surface: surface.as_ref(),
-                })
-                .await
-                .ok_or_else(|| Error::AdapterCreationError)?;
-
-            let adapter_limits = adapter.limits();
-
-            if !limits.check_limits(&adapter_limits) {
-                log::error!("requested limits aren't all supported by adapter");
-
-                return Err(Error::LimitsSurpassed);
-            }
-
-            let (device, queue) = adapter
-                .request_device(
-                    &wgpu::DeviceDescriptor {
-                        label: None,
-                        required_features: features(&adapter),
-                        required_limits: adapter_limits,
-                    },
-                    None,
-                )
-                .await?;
-
-            Ok::<_, Error>((adapter, device, queue))
+        let (adapter, device, queue, lost) = pollster::block_on(async {
+            let adapter = if selector == AdapterSelector::Auto {
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference,
+                        force_fallback_adapter: false,
+                        // Request an adapter which can render to our surface
+                        compatible_surface: surface.as_ref(),
+                    })
+                    .await
+                    .ok_or_else(|| Error::AdapterCreationError)?
+            } else {
+                selector
+                    .select(instance.enumerate_adapters(backends), surface.as_ref())
+                    .ok_or_else(|| Error::AdapterNotFound(selector))?
+            };
+
+            log::info!("using adapter {:?}", adapter.get_info());
+
+            let (device, queue, lost) =
+                device::request(&adapter, &requirements, trace_path.as_deref()).await?;
+
+            Ok::<_, Error>((adapter, device, queue, lost))
         })?;
 
         let window_data = if let (Some(surface), Some(window)) = (surface.take(), window.take()) {
             let capabilities = surface.get_capabilities(&adapter);
 
             Some(WindowData {
-                vsync,
+                options,
                 window,
-                surface,
+                surface: Some(surface),
                 capabilities,
             })
         } else {
@@ -170,9 +343,14 @@ impl Context {
         let queue = Arc::new(queue);
 
         Ok(Context {
+            instance,
             adapter,
             device,
             queue,
+            lost,
+            requirements,
+            trace_path,
+            memory: MemoryTracker::new(),
             window_data,
         })
     }
@@ -186,17 +364,80 @@ impl Context {
     }
 
     pub fn is_vsync(&self) -> bool {
-        self.window_data.as_ref().map(|d| d.vsync).unwrap_or(false)
+        self.window_data
+            .as_ref()
+            .map(|d| d.options.vsync)
+            .unwrap_or(false)
     }
 
     pub fn surface(&self) -> Option<&Surface> {
-        self.window_data.as_ref().map(|d| &d.surface)
+        self.window_data.as_ref().and_then(|d| d.surface.as_ref())
+    }
+
+    /// Take ownership of the surface of the window, such as to drop it while suspended.
+    ///
+    /// [`Context::surface`] returns [`None`] afterwards.
+    pub fn take_surface(&mut self) -> Option<Surface<'static>> {
+        self.window_data.as_mut().and_then(|d| d.surface.take())
+    }
+
+    /// Create a surface to draw to another `window`, with the same device as the [`Context`].
+    pub fn create_surface(
+        &self,
+        window: Arc<Window>,
+    ) -> Result<Surface<'static>, wgpu::CreateSurfaceError> {
+        self.instance.create_surface(window)
     }
 
     pub fn adapter(&self) -> &Adapter {
         &self.adapter
     }
 
+    /// The features enabled on the device, including the optional ones that were granted.
+    pub fn features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
+    /// The limits of the device, raised towards the optional ones where they could be.
+    pub fn limits(&self) -> wgpu::Limits {
+        self.device.limits()
+    }
+
+    /// Whether the device was lost, such as by a driver reset or running out of memory.
+    ///
+    /// Nothing made with it works from then on, until [`Context::recreate_device`] is called.
+    pub fn is_device_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// Replace a lost device with a new one, on the same adapter with the same features and limits.
+    ///
+    /// Everything made with the old device has to be made again with [`Context::device`],
+    /// and the surface has to be configured again.
+    pub fn recreate_device(&mut self) -> Result<(), ContextBuildError> {
+        log::info!("recreating device");
+
+        let (device, queue, lost) = pollster::block_on(device::request(
+            &self.adapter,
+            &self.requirements,
+            self.trace_path.as_deref(),
+        ))?;
+
+        self.device = Arc::new(device);
+        self.queue = Arc::new(queue);
+        self.lost = lost;
+
+        Ok(())
+    }
+
+    /// The memory of the textures and buffers tracked by those using the [`Context`].
+    ///
+    /// It's kept when the device is recreated, the allocations of the old one are
+    /// released as they're dropped.
+    pub fn memory(&self) -> &MemoryTracker {
+        &self.memory
+    }
+
     pub fn device(&self) -> Arc<Device> {
         Arc::clone(&self.device)
     }
@@ -213,6 +454,14 @@ impl Context {
         self.capabilities().map(|cap| cap.formats.as_slice())
     }
 
+    /// Whether the surface was asked to be sRGB, with [`ContextBuilder::with_srgb`].
+    pub fn is_srgb(&self) -> bool {
+        self.window_data
+            .as_ref()
+            .map(|d| d.options.srgb)
+            .unwrap_or(false)
+    }
+
     pub fn view_format(&self) -> Option<TextureFormat> {
         #[rustfmt::skip]
         const PREFERRED: [TextureFormat; 2] = [
@@ -221,8 +470,13 @@ impl Context {
         ];
         if let Some(formats) = self.formats() {
             for tex in PREFERRED {
+                // prefer a non srgb swapchain, unless asked otherwise
+                let tex = if self.is_srgb() {
+                    tex.add_srgb_suffix()
+                } else {
+                    tex
+                };
                 if formats.contains(&tex) {
-                    // always prefer non srgb swapchain
                     return Some(tex);
                 }
             }
@@ -233,4 +487,59 @@ impl Context {
             None
         }
     }
+
+    /// How many samples a pixel drawing to the surface is antialiased with,
+    /// `1` without antialiasing.
+    ///
+    /// The most asked for with [`ContextBuilder::with_sample_count`]
+    /// that the [`Context::view_format`] supports.
+    pub fn sample_count(&self) -> u32 {
+        let (Some(data), Some(format)) = (&self.window_data, self.view_format()) else {
+            return 1;
+        };
+
+        // the adapter may support more than every adapter does
+        let features = self.features();
+        let flags = if features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+            self.adapter.get_texture_format_features(format).flags
+        } else {
+            format.guaranteed_format_features(features).flags
+        };
+
+        let requested = data.options.sample_count;
+        let supported = [16, 8, 4, 2]
+            .into_iter()
+            .filter(|&count| count <= requested)
+            .find(|&count| flags.sample_count_supported(count))
+            .unwrap_or(1);
+
+        if supported != requested.max(1) {
+            log::warn!("{format:?} surfaces can't have {requested} samples, using {supported}");
+        }
+
+        supported
+    }
+
+    /// The formats views of the surface can have, besides the [`Context::view_format`].
+    ///
+    /// Those asked for with [`ContextBuilder::with_view_formats`] that the surface allows,
+    /// the ones that only differ from it by being sRGB or not.
+    pub fn view_formats(&self) -> Vec<TextureFormat> {
+        let (Some(data), Some(format)) = (&self.window_data, self.view_format()) else {
+            return Vec::new();
+        };
+
+        data.options
+            .view_formats
+            .iter()
+            .copied()
+            .filter(|&view| {
+                let allowed = view.remove_srgb_suffix() == format.remove_srgb_suffix();
+                if !allowed {
+                    log::warn!("surface views can't be {view:?}, as the surface is {format:?}");
+                }
+                allowed && view != format
+            })
+            .collect()
+    }
 }
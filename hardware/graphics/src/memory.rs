@@ -0,0 +1,163 @@
+//! Keeping track of the gpu memory in use, by what it's used for.
+//!
+//! wgpu doesn't report how much memory the adapter has, or how much is in use,
+//! so the sizes of the textures and buffers are added up as they're created.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        Mutex,
+        Weak,
+    },
+};
+
+use wgpu::{
+    Buffer,
+    Texture,
+};
+
+/// The bytes used by the textures and buffers of a label.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub textures: u64,
+    pub buffers: u64,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.textures + self.buffers
+    }
+}
+
+#[derive(Default)]
+struct Totals {
+    labels: BTreeMap<String, MemoryUsage>,
+    /// The most in use at once.
+    peak: u64,
+}
+
+impl Totals {
+    fn total(&self) -> u64 {
+        self.labels.values().map(MemoryUsage::total).sum()
+    }
+}
+
+/// Adds up the memory of the textures and buffers tracked with it,
+/// see [`Context::memory`](crate::Context::memory).
+///
+/// Clones share the same totals.
+#[derive(Clone, Default)]
+pub struct MemoryTracker {
+    totals: Arc<Mutex<Totals>>,
+}
+
+impl MemoryTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the memory of the `texture` under `label`, until the [`Allocation`] is dropped.
+    pub fn track_texture(&self, label: &str, texture: &Texture) -> Allocation {
+        let usage = MemoryUsage {
+            textures: texture_bytes(texture),
+            buffers: 0,
+        };
+        self.track(label, usage)
+    }
+
+    /// Count the memory of the `buffer` under `label`, until the [`Allocation`] is dropped.
+    pub fn track_buffer(&self, label: &str, buffer: &Buffer) -> Allocation {
+        let usage = MemoryUsage {
+            textures: 0,
+            buffers: buffer.size(),
+        };
+        self.track(label, usage)
+    }
+
+    fn track(&self, label: &str, usage: MemoryUsage) -> Allocation {
+        let mut totals = self.totals.lock().unwrap();
+
+        let entry = totals.labels.entry(label.to_owned()).or_default();
+        entry.textures += usage.textures;
+        entry.buffers += usage.buffers;
+        totals.peak = totals.peak.max(totals.total());
+
+        Allocation {
+            totals: Arc::downgrade(&self.totals),
+            label: label.to_owned(),
+            usage,
+        }
+    }
+
+    /// The memory in use by each label, of those still in use.
+    pub fn by_label(&self) -> BTreeMap<String, MemoryUsage> {
+        self.totals.lock().unwrap().labels.clone()
+    }
+
+    /// The memory in use by every label.
+    pub fn total(&self) -> MemoryUsage {
+        let totals = self.totals.lock().unwrap();
+
+        totals
+            .labels
+            .values()
+            .fold(MemoryUsage::default(), |sum, usage| MemoryUsage {
+                textures: sum.textures + usage.textures,
+                buffers: sum.buffers + usage.buffers,
+            })
+    }
+
+    /// The most bytes that were in use at once.
+    pub fn peak(&self) -> u64 {
+        self.totals.lock().unwrap().peak
+    }
+}
+
+/// Memory counted by a [`MemoryTracker`], until it's dropped.
+///
+/// Keep it alongside the texture or buffer it was tracked for.
+#[must_use = "the memory stops being counted once the allocation is dropped"]
+pub struct Allocation {
+    totals: Weak<Mutex<Totals>>,
+    label: String,
+    usage: MemoryUsage,
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        let Some(totals) = self.totals.upgrade() else {
+            return;
+        };
+        let mut totals = totals.lock().unwrap();
+
+        if let Some(entry) = totals.labels.get_mut(&self.label) {
+            entry.textures -= self.usage.textures;
+            entry.buffers -= self.usage.buffers;
+
+            if entry.total() == 0 {
+                totals.labels.remove(&self.label);
+            }
+        }
+    }
+}
+
+/// The bytes used by every mip level and layer of a `texture`.
+fn texture_bytes(texture: &Texture) -> u64 {
+    let format = texture.format();
+    let (block_width, block_height) = format.block_dimensions();
+    // depth and stencil formats can't be copied as a whole, so guess
+    let block_size = format.block_copy_size(None).unwrap_or(4) as u64;
+
+    let size = texture.size();
+    (0..texture.mip_level_count())
+        .map(|level| {
+            let mip = size.mip_level_size(level, texture.dimension());
+            let blocks_x = mip.width.div_ceil(block_width) as u64;
+            let blocks_y = mip.height.div_ceil(block_height) as u64;
+
+            blocks_x * blocks_y * mip.depth_or_array_layers as u64 * block_size
+        })
+        .sum::<u64>()
+        * texture.sample_count() as u64
+}
@@ -0,0 +1,116 @@
+//! Filling in the mip levels of a texture from the first.
+//!
+//! wgpu doesn't generate mipmaps, so each level is drawn from the one above it.
+
+use wgpu::{
+    Device,
+    Texture,
+    TextureUsages,
+};
+
+use crate::Encoder;
+
+/// Fill in every mip level of the `texture`, after the first, by downsampling the level above.
+///
+/// The `texture` has to be 2D, with a filterable format that can be drawn to,
+/// and [`TextureUsages::RENDER_ATTACHMENT`] | [`TextureUsages::TEXTURE_BINDING`].
+/// Each layer of an array texture has its own mipmaps.
+pub fn generate_mipmaps(encoder: &mut Encoder, device: &Device, texture: &Texture) {
+    assert_eq!(texture.dimension(), wgpu::TextureDimension::D2);
+    assert!(texture
+        .usage()
+        .contains(TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING));
+
+    if texture.mip_level_count() <= 1 {
+        return;
+    }
+
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("mipmap"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("mipmap.wgsl").into()),
+    });
+    // the layout is taken from the shader
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("mipmap"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &module,
+            entry_point: "vert",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &module,
+            entry_point: "frag",
+            targets: &[Some(texture.format().into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+    let layout = pipeline.get_bind_group_layout(0);
+
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("mipmap"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let view = |layer, level| {
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("mipmap level"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_mip_level: level,
+            mip_level_count: Some(1),
+            base_array_layer: layer,
+            array_layer_count: Some(1),
+            ..Default::default()
+        })
+    };
+
+    for layer in 0..texture.depth_or_array_layers() {
+        for level in 1..texture.mip_level_count() {
+            let source = view(layer, level - 1);
+            let target = view(layer, level);
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("mipmap"),
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
+
+            let mut pass = encoder.begin_render_pass(
+                "mipmap",
+                device,
+                wgpu::RenderPassDescriptor {
+                    label: Some("mipmap"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &target,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            // every pixel is drawn over
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                },
+            );
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vert(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // a triangle covering the whole level, see the fullscreen shader
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.uv = uv;
+    // textures start at the top, so y is flipped
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@fragment
+fn frag(in: VertexOutput) -> @location(0) vec4<f32> {
+    // each pixel is between 4 texels of the level above, so they're averaged
+    return textureSample(source, source_sampler, in.uv);
+}
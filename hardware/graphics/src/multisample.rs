@@ -0,0 +1,98 @@
+//! A multisampled color target, resolved into the texture that's shown.
+
+use wgpu::{
+    Color,
+    Device,
+    LoadOp,
+    RenderPassColorAttachment,
+    Texture,
+    TextureFormat,
+    TextureView,
+};
+
+/// A texture with many samples a pixel, to draw antialiased edges to.
+///
+/// Passes drawing to it resolve it into a texture with one sample a pixel, such as the surface,
+/// see [`Encoder::begin_multisampled_render_pass`](crate::Encoder::begin_multisampled_render_pass).
+pub struct MultisampleTarget {
+    texture: Texture,
+    view: TextureView,
+}
+
+impl MultisampleTarget {
+    /// Create a `width` by `height` target, with `sample_count` samples a pixel.
+    ///
+    /// The `format` has to match the texture it's resolved into.
+    pub fn new(
+        device: &Device,
+        format: TextureFormat,
+        sample_count: u32,
+        (width, height): (u32, u32),
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("multisample target"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        Self { texture, view }
+    }
+
+    /// Match the size of the texture it's resolved into, recreating the target if it's changed.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == (self.texture.width(), self.texture.height()) {
+            return;
+        }
+
+        *self = Self::new(device, self.format(), self.sample_count(), (width, height));
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.texture.sample_count()
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.texture.format()
+    }
+
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// The multisample state of pipelines that draw to the target.
+    pub fn multisample_state(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count(),
+            ..Default::default()
+        }
+    }
+
+    /// An attachment drawing to the target, resolved into `resolve_target` at the end of the pass.
+    ///
+    /// The samples are kept, so another pass can `load` them to draw over.
+    pub fn attachment<'a>(
+        &'a self,
+        resolve_target: &'a TextureView,
+        load: LoadOp<Color>,
+    ) -> RenderPassColorAttachment<'a> {
+        RenderPassColorAttachment {
+            view: &self.view,
+            resolve_target: Some(resolve_target),
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Drawing without a window, and reading the results back.
+//!
+//! Headless contexts have no surface, so they draw to a texture instead.
+
+use wgpu::{
+    BufferAsyncError,
+    Device,
+    Queue,
+    Texture,
+    TextureFormat,
+    TextureUsages,
+    TextureView,
+};
+
+/// A texture to draw to instead of a surface, which can be read back to the cpu.
+pub struct OffscreenTarget {
+    texture: Texture,
+}
+
+impl OffscreenTarget {
+    /// Create a `width` by `height` target, with the `usage` it's drawn with.
+    ///
+    /// It can always be copied from, to be read back.
+    pub fn new(
+        device: &Device,
+        (width, height): (u32, u32),
+        format: TextureFormat,
+        usage: TextureUsages,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen target"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: usage | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        Self { texture }
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> TextureView {
+        self.texture.create_view(&Default::default())
+    }
+
+    /// Wait for the work drawing to the target, and read it back, see [`read_back`].
+    pub fn read_back(&self, device: &Device, queue: &Queue) -> Result<Vec<u8>, BufferAsyncError> {
+        read_back(device, queue, &self.texture)
+    }
+
+    /// Read back a target of 32-bit floats, such as [`TextureFormat::Rgba32Float`].
+    pub fn read_back_f32(
+        &self,
+        device: &Device,
+        queue: &Queue,
+    ) -> Result<Vec<f32>, BufferAsyncError> {
+        let bytes = self.read_back(device, queue)?;
+
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|float| f32::from_ne_bytes(float.try_into().unwrap()))
+            .collect())
+    }
+}
+
+/// Wait for the work submitted to the `queue`, and read back the first mip level of the `texture`.
+///
+/// The texels are tightly packed, row by row from the top, without the padding of the copy.
+/// The `texture` has to be 2D, with [`TextureUsages::COPY_SRC`].
+pub fn read_back(
+    device: &Device,
+    queue: &Queue,
+    texture: &Texture,
+) -> Result<Vec<u8>, BufferAsyncError> {
+    assert_eq!(texture.dimension(), wgpu::TextureDimension::D2);
+
+    let block_size = texture
+        .format()
+        .block_copy_size(None)
+        .expect("the format can be copied as a whole");
+    let (width, height) = (texture.width(), texture.height());
+    let row = width * block_size;
+    // copies have to be aligned, which is trimmed once it's read
+    let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("read back"),
+        size: padded_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = tx.send(result);
+    });
+    // there's no runtime polling the device, so wait for the copy here
+    device.poll(wgpu::Maintain::Wait).panic_on_timeout();
+    rx.recv().expect("map callback is called by the poll")?;
+
+    let mut texels = Vec::with_capacity(row as usize * height as usize);
+    {
+        let mapped = slice.get_mapped_range();
+        for padded in mapped.chunks_exact(padded_row as usize) {
+            texels.extend_from_slice(&padded[..row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    Ok(texels)
+}
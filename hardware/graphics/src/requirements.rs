@@ -0,0 +1,107 @@
+//! Agreeing on the features and limits of a device with its adapter.
+//!
+//! Only what's asked for is requested, so the device works the same on any adapter that supports it.
+
+use wgpu::{
+    Adapter,
+    Features,
+    Limits,
+};
+
+use crate::error::Error;
+
+/// What a [`Context`](crate::Context) needs from its adapter, and what it can make use of.
+pub(crate) struct Requirements {
+    /// Creating the context fails without these.
+    pub features: Features,
+    /// Enabled where the adapter supports them.
+    pub optional_features: Features,
+    /// Creating the context fails if the adapter can't meet these.
+    pub limits: Limits,
+    /// The `limits` are raised towards these, as far as the adapter allows.
+    pub optional_limits: Limits,
+}
+
+impl Requirements {
+    /// The features and limits to request from the `adapter`.
+    pub(crate) fn negotiate(&self, adapter: &Adapter) -> Result<(Features, Limits), Error> {
+        let supported = adapter.features();
+
+        let missing = self.features - supported;
+        if !missing.is_empty() {
+            return Err(Error::FeaturesUnsupported(missing));
+        }
+
+        let allowed = adapter.limits();
+
+        let mut surpassed = false;
+        self.limits
+            .check_limits_with_fail_fn(&allowed, false, |name, requested, allowed| {
+                log::error!("requested {name} of {requested}, the adapter allows {allowed}");
+                surpassed = true;
+            });
+        if surpassed {
+            return Err(Error::LimitsSurpassed);
+        }
+
+        let features = self.features | (self.optional_features & supported);
+        let limits = raise(&self.limits, &self.optional_limits, &allowed);
+
+        Ok((features, limits))
+    }
+}
+
+/// Raise each of the `limits` towards the `optional` ones, without going past those `allowed`.
+fn raise(limits: &Limits, optional: &Limits, allowed: &Limits) -> Limits {
+    let mut raised = limits.clone();
+
+    macro_rules! raise {
+        ($($name:ident),* $(,)?) => {
+            $(raised.$name = limits.$name.max(optional.$name.min(allowed.$name));)*
+        };
+    }
+
+    // alignments are better the smaller they are
+    macro_rules! lower {
+        ($($name:ident),* $(,)?) => {
+            $(raised.$name = limits.$name.min(optional.$name.max(allowed.$name));)*
+        };
+    }
+
+    raise!(
+        max_texture_dimension_1d,
+        max_texture_dimension_2d,
+        max_texture_dimension_3d,
+        max_texture_array_layers,
+        max_bind_groups,
+        max_bindings_per_bind_group,
+        max_dynamic_uniform_buffers_per_pipeline_layout,
+        max_dynamic_storage_buffers_per_pipeline_layout,
+        max_sampled_textures_per_shader_stage,
+        max_samplers_per_shader_stage,
+        max_storage_buffers_per_shader_stage,
+        max_storage_textures_per_shader_stage,
+        max_uniform_buffers_per_shader_stage,
+        max_uniform_buffer_binding_size,
+        max_storage_buffer_binding_size,
+        max_vertex_buffers,
+        max_buffer_size,
+        max_vertex_attributes,
+        max_vertex_buffer_array_stride,
+        max_inter_stage_shader_components,
+        max_compute_workgroup_storage_size,
+        max_compute_invocations_per_workgroup,
+        max_compute_workgroup_size_x,
+        max_compute_workgroup_size_y,
+        max_compute_workgroup_size_z,
+        max_compute_workgroups_per_dimension,
+        max_push_constant_size,
+        max_non_sampler_bindings,
+    );
+    lower!(
+        min_uniform_buffer_offset_alignment,
+        min_storage_buffer_offset_alignment,
+    );
+
+    raised
+}
@@ -0,0 +1,145 @@
+//! Budgets for the gpu time of frames and scopes, to notice when they're overrun.
+
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    time::Duration,
+};
+
+use wgpu_profiler::GpuTimerQueryResult;
+
+/// The budget of a frame at 60 fps.
+pub const FRAME_60FPS: Duration = Duration::from_micros(16_667);
+
+/// A frame, or a scope in it, that took longer than its budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overrun {
+    /// The label of the scope, or `None` for the whole frame.
+    pub label: Option<String>,
+    pub duration: Duration,
+    pub budget: Duration,
+}
+
+impl Overrun {
+    /// The label of the scope, or "frame".
+    pub fn name(&self) -> &str {
+        self.label.as_deref().unwrap_or("frame")
+    }
+}
+
+type Hook = Box<dyn FnMut(&Overrun) + Send>;
+
+/// Budgets for the gpu time of a frame, and of the scopes with a label.
+///
+/// Overruns are logged as they start and end, unless a hook is set with [`Self::on_overrun`].
+#[derive(Default)]
+pub struct FrameBudget {
+    frame: Option<Duration>,
+    scopes: HashMap<String, Duration>,
+    hook: Option<Hook>,
+
+    /// The names of those over budget at the last check.
+    over: HashSet<String>,
+}
+
+impl FrameBudget {
+    /// A budget without any limits, until they're added.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the gpu time of the whole frame.
+    pub fn with_frame(self, budget: Duration) -> Self {
+        Self {
+            frame: Some(budget),
+            ..self
+        }
+    }
+
+    /// Limit the gpu time of every scope labelled `label`, added up over a frame.
+    pub fn with_scope(mut self, label: impl Into<String>, budget: Duration) -> Self {
+        self.scopes.insert(label.into(), budget);
+        self
+    }
+
+    /// Call `hook` with every overrun, instead of logging them.
+    pub fn on_overrun(self, hook: impl FnMut(&Overrun) + Send + 'static) -> Self {
+        Self {
+            hook: Some(Box::new(hook)),
+            ..self
+        }
+    }
+
+    /// Whether there's anything to check.
+    pub fn is_empty(&self) -> bool {
+        self.frame.is_none() && self.scopes.is_empty()
+    }
+
+    /// Check the `timings` of a frame against the budgets, returning the overruns.
+    pub fn check(&mut self, timings: &[GpuTimerQueryResult]) -> Vec<Overrun> {
+        let mut overruns = Vec::new();
+
+        if let Some(budget) = self.frame {
+            let duration = crate::frame_duration(timings);
+            if duration > budget {
+                overruns.push(Overrun {
+                    label: None,
+                    duration,
+                    budget,
+                });
+            }
+        }
+
+        if !self.scopes.is_empty() {
+            for (label, duration) in crate::label_durations(timings) {
+                let Some(&budget) = self.scopes.get(label) else {
+                    continue;
+                };
+
+                if duration > budget {
+                    overruns.push(Overrun {
+                        label: Some(label.to_owned()),
+                        duration,
+                        budget,
+                    });
+                }
+            }
+        }
+
+        let over = overruns
+            .iter()
+            .map(|overrun| overrun.name().to_owned())
+            .collect::<HashSet<_>>();
+
+        match &mut self.hook {
+            Some(hook) => {
+                for overrun in &overruns {
+                    hook(overrun);
+                }
+            }
+            None => {
+                // only log the changes, overruns usually last many frames
+                for overrun in overruns.iter().filter(|o| !self.over.contains(o.name())) {
+                    log::warn!(
+                        "{} took {:.2}ms, over its budget of {:.2}ms",
+                        overrun.name(),
+                        ms(overrun.duration),
+                        ms(overrun.budget)
+                    );
+                }
+                for name in self.over.difference(&over) {
+                    log::info!("{name} is back within its budget");
+                }
+            }
+        }
+        self.over = over;
+
+        overruns
+    }
+}
+
+fn ms(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
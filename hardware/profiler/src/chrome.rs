@@ -0,0 +1,157 @@
+//! Exporting puffin frames as a Chrome trace, to open in `chrome://tracing` or Perfetto.
+//!
+//! The gpu timings are sent to puffin as their own thread, see [`send_timings_to_puffin`],
+//! so the trace has both the cpu and gpu scopes.
+//!
+//! [`send_timings_to_puffin`]: crate::send_timings_to_puffin
+
+use std::{
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+    sync::Mutex,
+};
+
+use puffin::{
+    FrameView,
+    GlobalFrameView,
+    Reader,
+    ScopeCollection,
+};
+use serde_json::{
+    json,
+    Value,
+};
+
+/// The frames kept for [`export_chrome_trace`], once recording.
+static FRAMES: Mutex<Option<GlobalFrameView>> = Mutex::new(None);
+
+/// Keep the frames finished by puffin from now on, to export with [`export_chrome_trace`].
+///
+/// Scopes are only recorded while [`puffin::set_scopes_on`].
+pub fn record_chrome_trace() {
+    FRAMES
+        .lock()
+        .unwrap()
+        .get_or_insert_with(GlobalFrameView::default);
+}
+
+/// Write every frame kept since [`record_chrome_trace`] to `path`, as Chrome trace JSON.
+///
+/// Frames are only kept once they're finished, with [`profiling::finish_frame!`].
+pub fn export_chrome_trace(path: impl AsRef<Path>) -> io::Result<()> {
+    let frames = FRAMES.lock().unwrap();
+    let Some(frames) = frames.as_ref() else {
+        return Err(io::Error::other("no frames were recorded"));
+    };
+
+    let trace = json!({
+        "traceEvents": trace_events(&frames.lock())?,
+        "displayTimeUnit": "ms",
+    });
+
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut file, &trace)?;
+    file.flush()
+}
+
+/// A complete event for each scope, and the name of each thread.
+fn trace_events(view: &FrameView) -> io::Result<Vec<Value>> {
+    let frames = view.all_uniq();
+
+    // timestamps are from the first frame, they lose precision from the epoch
+    let origin = frames
+        .iter()
+        .map(|frame| frame.meta().range_ns.0)
+        .min()
+        .unwrap_or(0);
+
+    let mut threads: Vec<String> = Vec::new();
+    let mut events = Vec::new();
+
+    for frame in frames {
+        // the error type depends on whether puffin packs its frames
+        let frame = frame
+            .unpacked()
+            .map_err(|_| invalid("failed to unpack a frame"))?;
+
+        for (thread, stream) in &frame.thread_streams {
+            // each thread is numbered in the order it's first seen
+            let tid = match threads.iter().position(|name| *name == thread.name) {
+                Some(tid) => tid,
+                None => {
+                    threads.push(thread.name.clone());
+                    threads.len() - 1
+                }
+            };
+
+            let scopes = Scopes {
+                collection: view.scope_collection(),
+                stream: &stream.stream,
+                origin,
+                tid,
+            };
+            scopes.add_events(&mut events, Reader::from_start(&stream.stream))?;
+        }
+    }
+
+    for (tid, name) in threads.iter().enumerate() {
+        events.push(json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": 0,
+            "tid": tid,
+            "args": { "name": name },
+        }));
+    }
+
+    Ok(events)
+}
+
+/// The scopes of a thread in a single frame.
+struct Scopes<'a> {
+    collection: &'a ScopeCollection,
+    stream: &'a puffin::Stream,
+    origin: puffin::NanoSecond,
+    tid: usize,
+}
+
+impl Scopes<'_> {
+    /// Add the scopes read by the `reader` to `events`, and their children.
+    fn add_events(&self, events: &mut Vec<Value>, reader: Reader<'_>) -> io::Result<()> {
+        for scope in reader {
+            let scope = scope.map_err(invalid)?;
+
+            let name = self.collection.fetch_by_id(&scope.id).map_or_else(
+                || "unknown".to_owned(),
+                |details| details.name().to_string(),
+            );
+
+            // chrome traces are in microseconds
+            events.push(json!({
+                "name": name,
+                "ph": "X",
+                "ts": (scope.record.start_ns - self.origin) as f64 / 1000.0,
+                "dur": scope.record.duration_ns as f64 / 1000.0,
+                "pid": 0,
+                "tid": self.tid,
+                "args": { "data": scope.record.data },
+            }));
+
+            let children =
+                Reader::with_offset(self.stream, scope.child_begin_position).map_err(invalid)?;
+            self.add_events(events, children)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Puffin's errors aren't all `Display`, so they're shown with `Debug`.
+fn invalid(err: impl std::fmt::Debug) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{err:?}"))
+}
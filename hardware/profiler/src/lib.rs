@@ -1,22 +1,62 @@
+mod budget;
+mod chrome;
+mod pipeline;
+mod stats;
+mod summary;
+#[cfg(feature = "tracy")]
+mod tracy;
+
 use std::{
-    collections::HashMap,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
     ops::Range,
     time::Duration,
 };
 
+pub use budget::{
+    FrameBudget,
+    Overrun,
+    FRAME_60FPS,
+};
+pub use chrome::{
+    export_chrome_trace,
+    record_chrome_trace,
+};
+pub use pipeline::{
+    DispatchStatistics,
+    PipelineStatistics,
+    PIPELINE_STATISTICS_FEATURES,
+};
+pub use stats::ScopeStats;
+pub use summary::{
+    LabelSummary,
+    TimingSummary,
+};
+#[cfg(feature = "tracy")]
+pub use tracy::{
+    send_timings_to_tracy,
+    TracyGpu,
+    TracyStream,
+};
 pub use wgpu_profiler as gpu;
 use wgpu_profiler::{
     GpuProfiler,
     GpuTimerQueryResult,
 };
 
+/// The features GPU timings are recorded with, where the adapter supports them.
+pub const FEATURES: wgpu::Features =
+    wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES);
+
 #[must_use = "Stream result must be checked for failure"]
 pub enum StreamResult {
     /// Stream uploaded successfully.
     Success,
     /// No frame timings to upload.
     Empty,
-    /// Puffin is disabled, can't upload.
+    /// The profiler is disabled, can't upload.
     Disabled,
     /// Failed to upload Stream.
     Failure,
@@ -78,96 +118,138 @@ impl PuffinStream for GpuProfiler {
             return StreamResult::Disabled;
         }
 
-        if let Some(timings) = self.process_finished_frame(ns_per_frame) {
-            if timings.is_empty() {
-                // no point adding scopes if there aren't any!
-                return StreamResult::Empty;
-            }
+        match self.process_finished_frame(ns_per_frame) {
+            Some(timings) => send_timings_to_puffin(&timings, start_time_ns, id_cache),
+            None => StreamResult::Failure,
+        }
+    }
+}
 
-            // create a stream to write scopes to
-            let mut stream = puffin::Stream::default();
-
-            // give puffin details of the scopes we're about to allocate
-            let ids = {
-                // lock the profiler
-                let mut profiler = puffin::GlobalProfiler::lock();
-
-                let mut scope_names = Vec::new();
-
-                // push all of the gpu timings
-                for res in &timings {
-                    add_scope_names(&mut scope_names, res)
-                }
-
-                if let Some(cache) = id_cache {
-                    let mut ids = Vec::new();
-
-                    // go through and try and get ids from the cache first
-                    for name in scope_names {
-                        let id = cache.entry(name.clone()).or_insert_with(|| {
-                            // scope with this name doesn't exist, register it
-                            let id = profiler.register_user_scopes(&[
-                                puffin::ScopeDetails::from_scope_name(name),
-                            ]);
-
-                            assert_eq!(id.len(), 1);
-
-                            // extract the id
-                            let &[id] = id.as_slice() else {
-                                unreachable!();
-                            };
-
-                            id
-                        });
-
-                        ids.push(*id);
-                    }
-
-                    ids
-                } else {
-                    // no cache, just register new scopes all at once
-                    let scopes = scope_names
-                        .into_iter()
-                        .map(puffin::ScopeDetails::from_scope_name)
-                        .collect::<Vec<_>>();
-
-                    profiler.register_user_scopes(&scopes)
-                }
-            };
-
-            // add each scope with their ids into the stream
-            {
-                let delta = Delta::new(&timings);
-
-                // write the timings to the stream
-                let mut index = 0;
-                for res in &timings {
-                    write_timings(&mut stream, start_time_ns, res, &ids, &delta, &mut index);
-                }
-            }
+/// Send `timings` already taken from a [`GpuProfiler`] to puffin.
+///
+/// Useful when the timings are needed for something else too,
+/// as [`GpuProfiler::process_finished_frame`] only returns them once.
+#[profiling::function]
+pub fn send_timings_to_puffin(
+    timings: &[GpuTimerQueryResult],
+    start_time_ns: i64,
+    id_cache: Option<&mut IdCache>,
+) -> StreamResult {
+    if !puffin::are_scopes_on() {
+        return StreamResult::Disabled;
+    }
+
+    if timings.is_empty() {
+        // no point adding scopes if there aren't any!
+        return StreamResult::Empty;
+    }
+
+    // create a stream to write scopes to
+    let mut stream = puffin::Stream::default();
+
+    // give puffin details of the scopes we're about to allocate
+    let ids = {
+        // lock the profiler
+        let mut profiler = puffin::GlobalProfiler::lock();
+
+        let mut scope_names = Vec::new();
+
+        // push all of the gpu timings
+        for res in timings {
+            add_scope_names(&mut scope_names, res)
+        }
+
+        if let Some(cache) = id_cache {
+            let mut ids = Vec::new();
 
-            {
-                // lock the profiler
-                let mut profiler = puffin::GlobalProfiler::lock();
-
-                // finally, report the scopes to puffin using the stream
-                profiler.report_user_scopes(
-                    // the "gpu" is it's own thread
-                    puffin::ThreadInfo {
-                        start_time_ns: None,
-                        name: "gpu".to_owned(),
-                    },
-                    &puffin::StreamInfo::parse(stream)
-                        .unwrap()
-                        .as_stream_into_ref(),
-                );
+            // go through and try and get ids from the cache first
+            for name in scope_names {
+                let id = cache.entry(name.clone()).or_insert_with(|| {
+                    // scope with this name doesn't exist, register it
+                    let id = profiler
+                        .register_user_scopes(&[puffin::ScopeDetails::from_scope_name(name)]);
+
+                    assert_eq!(id.len(), 1);
+
+                    // extract the id
+                    let &[id] = id.as_slice() else {
+                        unreachable!();
+                    };
+
+                    id
+                });
+
+                ids.push(*id);
             }
 
-            StreamResult::Success
+            ids
         } else {
-            StreamResult::Failure
+            // no cache, just register new scopes all at once
+            let scopes = scope_names
+                .into_iter()
+                .map(puffin::ScopeDetails::from_scope_name)
+                .collect::<Vec<_>>();
+
+            profiler.register_user_scopes(&scopes)
+        }
+    };
+
+    // add each scope with their ids into the stream
+    {
+        let delta = Delta::new(timings);
+
+        // write the timings to the stream
+        let mut index = 0;
+        for res in timings {
+            write_timings(&mut stream, start_time_ns, res, &ids, &delta, &mut index);
         }
     }
+
+    {
+        // lock the profiler
+        let mut profiler = puffin::GlobalProfiler::lock();
+
+        // finally, report the scopes to puffin using the stream
+        profiler.report_user_scopes(
+            // the "gpu" is it's own thread
+            puffin::ThreadInfo {
+                start_time_ns: None,
+                name: "gpu".to_owned(),
+            },
+            &puffin::StreamInfo::parse(stream)
+                .unwrap()
+                .as_stream_into_ref(),
+        );
+    }
+
+    StreamResult::Success
+}
+
+/// The time from the start of the first timing to the end of the last.
+pub fn frame_duration(timings: &[GpuTimerQueryResult]) -> Duration {
+    match (timings.first(), timings.last()) {
+        (Some(first), Some(last)) => {
+            Duration::from_secs_f64((last.time.end - first.time.start).max(0.0))
+        }
+        _ => Duration::ZERO,
+    }
+}
+
+/// The time spent in the scopes of each label, including nested scopes,
+/// added up over the `timings` of a frame.
+fn label_durations(timings: &[GpuTimerQueryResult]) -> BTreeMap<&str, Duration> {
+    fn add<'a>(durations: &mut BTreeMap<&'a str, Duration>, timings: &'a [GpuTimerQueryResult]) {
+        for result in timings {
+            let duration = Duration::from_secs_f64((result.time.end - result.time.start).max(0.0));
+            *durations.entry(result.label.as_str()).or_default() += duration;
+
+            add(durations, &result.nested_queries);
+        }
+    }
+
+    let mut durations = BTreeMap::new();
+    add(&mut durations, timings);
+    durations
 }
 
 fn write_timings(
@@ -0,0 +1,186 @@
+//! Counting the invocations of compute dispatches, with pipeline statistics queries.
+//!
+//! The counts are read back a frame or so later, without waiting for the gpu.
+
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use wgpu::{
+    Buffer,
+    CommandEncoder,
+    ComputePass,
+    Device,
+    QuerySet,
+};
+
+/// The features pipeline statistics are recorded with, where the adapter supports them.
+pub const PIPELINE_STATISTICS_FEATURES: wgpu::Features = wgpu::Features::PIPELINE_STATISTICS_QUERY;
+
+/// The bytes of each query, there's a single statistic.
+const QUERY_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+
+/// The counts of a dispatch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatchStatistics {
+    pub label: String,
+    /// The number of times the compute shader was invoked.
+    pub compute_invocations: u64,
+}
+
+/// Records the compute invocations of the passes it's begun in.
+///
+/// Each frame, the passes are wrapped in [`Self::begin`] and [`Self::end`],
+/// the queries [resolved](Self::resolve) once they're done, and the frame ended after submitting.
+pub struct PipelineStatistics {
+    query_set: QuerySet,
+    resolve: Buffer,
+    read: Buffer,
+    capacity: u32,
+
+    /// The labels of the queries begun this frame.
+    ///
+    /// Passes borrow the query set until they end, so they're begun through a shared reference.
+    labels: RefCell<Vec<String>>,
+    /// The labels of the queries being read back.
+    pending: Option<Vec<String>>,
+    mapping: bool,
+    /// Whether the read back succeeded, once it's finished.
+    mapped: Arc<Mutex<Option<bool>>>,
+
+    results: Vec<DispatchStatistics>,
+}
+
+impl PipelineStatistics {
+    /// Record up to `capacity` passes a frame, if the device has [`PIPELINE_STATISTICS_FEATURES`].
+    pub fn new(device: &Device, capacity: u32) -> Option<Self> {
+        if !device.features().contains(PIPELINE_STATISTICS_FEATURES) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("pipeline statistics"),
+            ty: wgpu::QueryType::PipelineStatistics(
+                wgpu::PipelineStatisticsTypes::COMPUTE_SHADER_INVOCATIONS,
+            ),
+            count: capacity,
+        });
+
+        let size = capacity as u64 * QUERY_SIZE;
+        let resolve = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pipeline statistics resolve"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let read = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pipeline statistics read"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve,
+            read,
+            capacity,
+            labels: RefCell::new(Vec::new()),
+            pending: None,
+            mapping: false,
+            mapped: Arc::new(Mutex::new(None)),
+            results: Vec::new(),
+        })
+    }
+
+    /// Start counting the dispatches in the `pass`, until [`Self::end`].
+    ///
+    /// Returns `false` without counting if the frame is full,
+    /// or the last frame is still being read back.
+    pub fn begin<'a>(&'a self, pass: &mut ComputePass<'a>, label: &str) -> bool {
+        let mut labels = self.labels.borrow_mut();
+        if self.pending.is_some() || labels.len() as u32 == self.capacity {
+            return false;
+        }
+
+        pass.begin_pipeline_statistics_query(&self.query_set, labels.len() as u32);
+        labels.push(label.to_owned());
+
+        true
+    }
+
+    /// Stop counting the dispatches in the `pass`, if [`Self::begin`] returned `true`.
+    pub fn end(&self, pass: &mut ComputePass) {
+        pass.end_pipeline_statistics_query();
+    }
+
+    /// Copy the counts of the frame to be read back, once every pass using them has ended.
+    pub fn resolve(&mut self, encoder: &mut CommandEncoder) {
+        let labels = self.labels.get_mut();
+        if labels.is_empty() || self.pending.is_some() {
+            return;
+        }
+
+        let count = labels.len() as u32;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve, 0, &self.read, 0, count as u64 * QUERY_SIZE);
+
+        self.pending = Some(std::mem::take(self.labels.get_mut()));
+    }
+
+    /// Start reading back the counts, once the commands resolving them are submitted.
+    pub fn end_frame(&mut self) {
+        let Some(labels) = &self.pending else {
+            return;
+        };
+        if self.mapping {
+            return;
+        }
+
+        let size = labels.len() as u64 * QUERY_SIZE;
+        let mapped = Arc::clone(&self.mapped);
+        self.read
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *mapped.lock().unwrap() = Some(result.is_ok());
+            });
+        self.mapping = true;
+    }
+
+    /// The counts of the last frame that's been read back.
+    ///
+    /// Updated as the device is polled, such as when submitting.
+    pub fn results(&mut self) -> &[DispatchStatistics] {
+        let Some(mapped) = self.mapped.lock().unwrap().take() else {
+            return &self.results;
+        };
+
+        // the next frame can be counted either way
+        let labels = self.pending.take().unwrap_or_default();
+        self.mapping = false;
+
+        if mapped {
+            let size = labels.len() as u64 * QUERY_SIZE;
+
+            {
+                let view = self.read.slice(..size).get_mapped_range();
+                self.results = labels
+                    .into_iter()
+                    .zip(view.chunks_exact(QUERY_SIZE as usize))
+                    .map(|(label, count)| DispatchStatistics {
+                        label,
+                        compute_invocations: u64::from_ne_bytes(count.try_into().unwrap()),
+                    })
+                    .collect();
+            }
+
+            self.read.unmap();
+        }
+
+        &self.results
+    }
+}
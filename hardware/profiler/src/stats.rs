@@ -0,0 +1,114 @@
+//! Rolling statistics of gpu scopes, to show in an app each frame.
+//!
+//! Reading puffin back means locking it, these are kept as the timings come in.
+
+use std::{
+    collections::{
+        BTreeMap,
+        VecDeque,
+    },
+    time::Duration,
+};
+
+use wgpu_profiler::GpuTimerQueryResult;
+
+/// The number of frames kept for each scope by default.
+const DEFAULT_LENGTH: usize = 300;
+
+/// The recent durations of each gpu scope, by label.
+///
+/// Scopes with the same label in a frame are added together,
+/// and scopes that haven't been seen for a full history are forgotten.
+#[derive(Debug, Clone)]
+pub struct ScopeStats {
+    length: usize,
+    frame: u64,
+    scopes: BTreeMap<String, Scope>,
+}
+
+#[derive(Debug, Clone)]
+struct Scope {
+    /// The last `length` durations, oldest first.
+    history: VecDeque<Duration>,
+    /// The frame the scope was last seen in.
+    last_seen: u64,
+}
+
+impl Default for ScopeStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_LENGTH)
+    }
+}
+
+impl ScopeStats {
+    /// Keep the last `length` frames of each scope.
+    pub fn new(length: usize) -> Self {
+        Self {
+            length: length.max(1),
+            frame: 0,
+            scopes: BTreeMap::new(),
+        }
+    }
+
+    /// Record the `timings` of a frame, and the scopes nested in them.
+    pub fn push_frame(&mut self, timings: &[GpuTimerQueryResult]) {
+        self.frame += 1;
+
+        for (label, duration) in crate::label_durations(timings) {
+            let scope = self
+                .scopes
+                .entry(label.to_owned())
+                .or_insert_with(|| Scope {
+                    history: VecDeque::with_capacity(self.length),
+                    last_seen: 0,
+                });
+
+            if scope.history.len() == self.length {
+                scope.history.pop_front();
+            }
+            scope.history.push_back(duration);
+            scope.last_seen = self.frame;
+        }
+
+        let (frame, length) = (self.frame, self.length as u64);
+        self.scopes
+            .retain(|_, scope| frame - scope.last_seen < length);
+    }
+
+    /// The labels of every scope, in order.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.scopes.keys().map(String::as_str)
+    }
+
+    /// The recent durations of the scope, oldest first.
+    pub fn history(&self, label: &str) -> impl ExactSizeIterator<Item = Duration> + '_ {
+        self.scopes
+            .get(label)
+            .map(|scope| scope.history.iter())
+            .unwrap_or_default()
+            .copied()
+    }
+
+    /// The last duration of the scope.
+    pub fn latest(&self, label: &str) -> Option<Duration> {
+        self.scopes.get(label)?.history.back().copied()
+    }
+
+    /// The mean of the recent durations of the scope.
+    pub fn average(&self, label: &str) -> Option<Duration> {
+        let history = &self.scopes.get(label)?.history;
+        let total: Duration = history.iter().sum();
+
+        total.checked_div(history.len() as u32)
+    }
+
+    /// The recent duration that `percentile` of frames were at least as fast as,
+    /// such as 0.95 for the slowest 5%.
+    pub fn percentile(&self, label: &str, percentile: f32) -> Option<Duration> {
+        let mut sorted: Vec<_> = self.scopes.get(label)?.history.iter().copied().collect();
+        sorted.sort_unstable();
+
+        let i = (percentile.clamp(0.0, 1.0) * (sorted.len() - 1) as f32).round() as usize;
+        sorted.get(i).copied()
+    }
+}
@@ -0,0 +1,150 @@
+//! Summarising gpu timings over a run, to track performance without a gui.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+};
+
+use serde_json::json;
+use wgpu_profiler::GpuTimerQueryResult;
+
+/// The durations of each gpu scope, by label, added up over many frames.
+///
+/// Nested scopes are kept under their own label.
+#[derive(Debug, Clone, Default)]
+pub struct TimingSummary {
+    /// The duration of every scope with a label, in milliseconds.
+    durations: BTreeMap<String, Vec<f64>>,
+}
+
+/// The statistics of every scope with a label, in milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelSummary {
+    pub label: String,
+    pub count: usize,
+    pub min: f64,
+    pub mean: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl TimingSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the `timings` of a frame, and the scopes nested in them.
+    pub fn add(&mut self, timings: &[GpuTimerQueryResult]) {
+        for result in timings {
+            let duration = (result.time.end - result.time.start).max(0.0) * 1000.0;
+            self.durations
+                .entry(result.label.clone())
+                .or_default()
+                .push(duration);
+
+            self.add(&result.nested_queries);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.durations.is_empty()
+    }
+
+    /// The statistics of each label, sorted by label.
+    pub fn labels(&self) -> Vec<LabelSummary> {
+        self.durations
+            .iter()
+            .map(|(label, durations)| {
+                let mut sorted = durations.clone();
+                sorted.sort_by(f64::total_cmp);
+
+                let count = sorted.len();
+                // the nearest rank
+                let p95 = sorted[(count as f64 * 0.95).ceil() as usize - 1];
+
+                LabelSummary {
+                    label: label.clone(),
+                    count,
+                    min: sorted[0],
+                    mean: sorted.iter().sum::<f64>() / count as f64,
+                    p95,
+                    max: sorted[count - 1],
+                }
+            })
+            .collect()
+    }
+
+    /// Write the statistics of each label as CSV, with a header.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "label,count,min_ms,mean_ms,p95_ms,max_ms")?;
+
+        for label in self.labels() {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                csv_field(&label.label),
+                label.count,
+                label.min,
+                label.mean,
+                label.p95,
+                label.max
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the statistics of each label as a JSON array.
+    pub fn write_json(&self, writer: impl Write) -> io::Result<()> {
+        let labels = self
+            .labels()
+            .into_iter()
+            .map(|label| {
+                json!({
+                    "label": label.label,
+                    "count": label.count,
+                    "min_ms": label.min,
+                    "mean_ms": label.mean,
+                    "p95_ms": label.p95,
+                    "max_ms": label.max,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::to_writer_pretty(writer, &labels)?;
+
+        Ok(())
+    }
+
+    /// Save the statistics to `path`, as CSV if it ends in `.csv`, otherwise as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut file = BufWriter::new(File::create(path)?);
+
+        let csv = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if csv {
+            self.write_csv(&mut file)?;
+        } else {
+            self.write_json(&mut file)?;
+        }
+
+        file.flush()
+    }
+}
+
+/// Quote a `field` if it would break the row.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
@@ -0,0 +1,118 @@
+//! Sending gpu timings to Tracy, straight through its client.
+//!
+//! `profiling` only has one backend at a time, so its cpu zones stay with puffin.
+//!
+//! Tracy wants gpu timestamps, so the timings are sent as nanoseconds,
+//! measured from the first timings sent.
+
+use tracy_client::{
+    Client,
+    GpuContext,
+    GpuContextType,
+};
+use wgpu_profiler::{
+    GpuProfiler,
+    GpuTimerQueryResult,
+};
+
+use crate::StreamResult;
+
+/// The Tracy gpu context that timings are sent to.
+///
+/// It's created with the first timings, so they start at the same time.
+pub struct TracyGpu {
+    client: Client,
+    context: Option<GpuContext>,
+}
+
+impl TracyGpu {
+    /// Start the Tracy client, if it isn't already.
+    pub fn new() -> Self {
+        Self {
+            client: Client::start(),
+            context: None,
+        }
+    }
+}
+
+impl Default for TracyGpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub trait TracyStream {
+    fn send_to_tracy(&mut self, ns_per_frame: f32, tracy: &mut TracyGpu) -> StreamResult;
+}
+
+impl TracyStream for GpuProfiler {
+    #[profiling::function]
+    fn send_to_tracy(&mut self, ns_per_frame: f32, tracy: &mut TracyGpu) -> StreamResult {
+        match self.process_finished_frame(ns_per_frame) {
+            Some(timings) => send_timings_to_tracy(&timings, tracy),
+            None => StreamResult::Failure,
+        }
+    }
+}
+
+/// Send `timings` already taken from a [`GpuProfiler`] to Tracy.
+///
+/// See [`send_timings_to_puffin`](crate::send_timings_to_puffin) to send them to puffin too.
+#[profiling::function]
+pub fn send_timings_to_tracy(
+    timings: &[GpuTimerQueryResult],
+    tracy: &mut TracyGpu,
+) -> StreamResult {
+    if !Client::is_running() {
+        return StreamResult::Disabled;
+    }
+
+    let Some(first) = timings.first() else {
+        return StreamResult::Empty;
+    };
+
+    if tracy.context.is_none() {
+        // the gpu time now, as close as we know it
+        let timestamp = nanos(first.time.start);
+
+        // the client is a cheap handle, creating a context takes one
+        let client = tracy.client.clone();
+        match client.new_gpu_context(Some("gpu"), GpuContextType::Invalid, timestamp, 1.0) {
+            Ok(context) => tracy.context = Some(context),
+            Err(_) => return StreamResult::Failure,
+        }
+    }
+    let context = tracy.context.as_ref().expect("created above");
+
+    for result in timings {
+        if send_span(context, result).is_err() {
+            return StreamResult::Failure;
+        }
+    }
+
+    StreamResult::Success
+}
+
+/// Send the span of a `result`, with its children nested inside.
+fn send_span(
+    context: &GpuContext,
+    result: &GpuTimerQueryResult,
+) -> Result<(), tracy_client::GpuSpanCreationError> {
+    // there are no function, line or file details of gpu scopes
+    let mut span = context.span_alloc(&result.label, "", "", 0)?;
+
+    // children are begun and ended inside the parent
+    for child in &result.nested_queries {
+        send_span(context, child)?;
+    }
+
+    span.end_zone();
+    span.upload_timestamp(nanos(result.time.start), nanos(result.time.end));
+
+    Ok(())
+}
+
+/// The gpu timestamp of a time in seconds, with a period of 1ns.
+fn nanos(seconds: f64) -> i64 {
+    (seconds * 1e9) as i64
+}
@@ -1,14 +1,14 @@
 use std::sync::Arc;
 
-pub use common::Config;
+pub use common::{
+    Config,
+    Render,
+    Tile,
+};
 use graphics::{
     wgpu,
     Encoder,
 };
-use rayon::{
-    iter::ParallelIterator,
-    slice::ParallelSlice,
-};
 
 /// The Hardware [`Renderer`].
 pub struct Renderer {
@@ -20,12 +20,25 @@ pub struct Renderer {
 }
 
 impl Renderer {
-    /// Create a new [`Renderer`].
+    /// The features a device needs to create a [`Renderer`].
+    pub const FEATURES: wgpu::Features = marcher::FEATURES;
+
+    /// The limits a device needs to create a [`Renderer`].
+    pub fn limits() -> wgpu::Limits {
+        marcher::limits()
+    }
+
+    /// Create a new [`Renderer`], counting its memory in [`graphics::Context::memory`].
     pub fn new(ctx: &graphics::Context) -> Self {
-        let device = ctx.device();
-        let queue = ctx.queue();
+        let mut renderer = Self::from_device(ctx.device(), ctx.queue());
+        renderer.track_memory(ctx.memory().clone());
+        renderer
+    }
 
-        let marcher = marcher::Marcher::new(device.clone(), &queue);
+    /// Create a new [`Renderer`] on an existing device,
+    /// such as a second renderer working in the background.
+    pub fn from_device(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> Self {
+        let marcher = marcher::Marcher::new(device.clone(), queue.clone());
 
         Self {
             device,
@@ -36,9 +49,19 @@ impl Renderer {
         }
     }
 
-    /// A flag to determine if the [`Renderer`] needs to re-render.
-    pub fn must_render(&self) -> bool {
-        self.dirty
+    /// The device the [`Renderer`] computes on.
+    pub fn device(&self) -> &Arc<wgpu::Device> {
+        &self.device
+    }
+
+    /// The queue the samples of [`Render::compute_sample`] are submitted to.
+    pub fn queue(&self) -> &Arc<wgpu::Queue> {
+        &self.queue
+    }
+
+    /// Count the memory of the textures with the `memory` tracker.
+    pub fn track_memory(&mut self, memory: graphics::MemoryTracker) {
+        self.marcher.track_memory(memory);
     }
 
     /// The texture view that the [`Renderer`] is rendering to.
@@ -46,10 +69,42 @@ impl Renderer {
         self.marcher.view()
     }
 
-    /// Update the state of the [`Renderer`].
+    /// Replace the equirectangular map of the sky, restarting accumulation.
+    pub fn set_stars(&mut self, image: &image::DynamicImage) {
+        self.marcher.set_stars(image);
+        self.dirty = true;
+    }
+
+    /// Rebuild the compute pipeline from new WGSL `source`, restarting accumulation.
+    pub fn reload_shader(&mut self, source: &str) -> Result<(), wgpu::Error> {
+        self.marcher.reload_shader(source)?;
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// The invocations of each dispatch, where the device supports pipeline statistics.
+    ///
+    /// End the frame with [`PipelineStatistics::end_frame`](profiler::PipelineStatistics::end_frame)
+    /// after submitting the commands from [`Self::compute`].
+    pub fn pipeline_statistics(&mut self) -> Option<&mut profiler::PipelineStatistics> {
+        self.marcher.pipeline_statistics()
+    }
+
+    /// Every texture allocated by the [`Renderer`].
+    pub fn textures(&self) -> [&wgpu::Texture; 2] {
+        self.marcher.textures()
+    }
+
+    /// Update the state of the [`Renderer`], only rendering a `tile` of the frame.
     #[profiling::function]
-    pub fn update(&mut self, width: u32, height: u32, cfg: Config) {
-        self.dirty = self.marcher.update(width, height, cfg);
+    pub fn update_tile(&mut self, width: u32, height: u32, tile: Tile, cfg: Config) {
+        self.dirty = self.marcher.update_tile(width, height, tile, cfg);
+    }
+
+    /// Seed the samples as if `first` samples had already been computed.
+    pub fn set_first_sample(&mut self, first: u32) {
+        self.marcher.set_first_sample(first);
     }
 
     /// Submit commands to compute.
@@ -58,96 +113,55 @@ impl Renderer {
         self.marcher.record(encoder);
     }
 
-    /// Convert the state of the [`Renderer`] into bytes representing the frame output.
     #[profiling::function]
-    pub fn into_frame(self, mut encoder: wgpu::CommandEncoder) -> Vec<u8> {
-        let (frame, row, aligned_row) = copy_texture_to_buffer(
-            &self.device,
-            &mut encoder,
-            self.marcher.texture(),
-            self.marcher.size(),
-        );
-
-        // submit the commands to finish the work before reading
+    fn read_frame(&self) -> Vec<u8> {
+        graphics::read_back(&self.device, &self.queue, self.marcher.texture())
+            .expect("failed to read frame from gpu")
+    }
+}
+
+impl Render for Renderer {
+    #[profiling::function]
+    fn update(&mut self, width: u32, height: u32, cfg: Config) {
+        self.dirty = self.marcher.update(width, height, cfg);
+    }
+
+    fn must_render(&self) -> bool {
+        self.dirty
+    }
+
+    /// Compute a sample and submit it straight away.
+    #[profiling::function]
+    fn compute_sample(&mut self) {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.compute(&mut Encoder::Wgpu(&mut encoder));
         self.queue.submit(Some(encoder.finish()));
 
-        let (tx, rx) = flume::bounded(1);
-
-        // we want to read the entire buffer off of the gpu
-        let slice = frame.slice(..);
-        slice.map_async(wgpu::MapMode::Read, move |cb| tx.send(cb).unwrap());
-
-        // we have to poll the device here ourselves,
-        // because we're assuming there is no runtime polling for us
-        self.device.poll(wgpu::Maintain::Wait).panic_on_timeout();
-
-        // block until we get a result
-        if let Ok(Ok(())) = rx.recv() {
-            let data = slice.get_mapped_range();
-
-            let result = {
-                profiling::scope!("Trimming image");
-                // trim the edges of the data
-                // to make sure that the resulting image is the correct size
-                let whole_rows = data.par_chunks_exact(aligned_row as usize);
-                whole_rows
-                    .flat_map(|chunk| chunk.split_at(row as usize).0.to_vec())
-                    .collect()
-            };
-
-            // get rid of the buffer from the CPU.
-            drop(data);
-            frame.unmap();
-
-            result
-        } else {
-            panic!("failed to read frame from gpu")
+        if let Some(statistics) = self.pipeline_statistics() {
+            statistics.end_frame();
         }
     }
-}
 
-/// Copies a texture to a buffer with the correct alignments.
-#[profiling::function]
-fn copy_texture_to_buffer(
-    device: &wgpu::Device,
-    encoder: &mut wgpu::CommandEncoder,
-    source_texture: &wgpu::Texture,
-    size: wgpu::Extent3d,
-) -> (wgpu::Buffer, u32, u32) {
-    assert!(source_texture.dimension() == wgpu::TextureDimension::D2);
-
-    let block_size = source_texture.format().block_copy_size(None).unwrap();
-    let row = size.width * block_size;
-    let aligned_row = pad_to(row, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
-
-    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: aligned_row as u64 * size.height as u64,
-        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-        mapped_at_creation: false,
-    });
-
-    let source = wgpu::ImageCopyTexture {
-        texture: source_texture,
-        mip_level: 0,
-        origin: wgpu::Origin3d::ZERO,
-        aspect: wgpu::TextureAspect::All,
-    };
-
-    let destination = wgpu::ImageCopyBuffer {
-        buffer: &buffer,
-        layout: wgpu::ImageDataLayout {
-            offset: 0,
-            bytes_per_row: Some(aligned_row),
-            rows_per_image: None,
-        },
-    };
-
-    encoder.copy_texture_to_buffer(source, destination, size);
-
-    (buffer, row, aligned_row)
-}
+    fn sample_count(&self) -> u32 {
+        self.marcher.sample_count()
+    }
+
+    fn frame(&self) -> Vec<u8> {
+        self.read_frame()
+    }
+
+    fn frame_linear(&self) -> Vec<f32> {
+        let mut frame = self.frame_hdr();
 
-fn pad_to(x: u32, y: u32) -> u32 {
-    ((x + y - 1) / y) * y
+        // the buffer holds the mean encoded for display
+        if self.marcher.config().features.encodes_srgb() {
+            common::srgb::decode_frame(&mut frame);
+        }
+
+        frame
+    }
+
+    fn into_frame(self) -> Vec<u8> {
+        self.read_frame()
+    }
 }
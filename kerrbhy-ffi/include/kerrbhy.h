@@ -0,0 +1,108 @@
+#ifndef KERRBHY_H
+#define KERRBHY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Where frames are rendered.
+typedef enum KerrbhyBackend {
+  // Use the GPU if one is available, otherwise fall back to the CPU.
+  KERRBHY_BACKEND_AUTO,
+  KERRBHY_BACKEND_HARDWARE,
+  KERRBHY_BACKEND_SOFTWARE,
+} KerrbhyBackend;
+
+// The result of every call.
+typedef enum KerrbhyStatus {
+  KERRBHY_STATUS_OK,
+  // A pointer was null, or a buffer was the wrong size.
+  KERRBHY_STATUS_INVALID_ARGUMENT,
+  // The config couldn't be parsed.
+  KERRBHY_STATUS_INVALID_CONFIG,
+  // The renderer couldn't be created.
+  KERRBHY_STATUS_CREATE_FAILED,
+  // Rust panicked, the renderer shouldn't be used again.
+  KERRBHY_STATUS_PANIC,
+} KerrbhyStatus;
+
+// An opaque handle to a renderer that accumulates samples.
+typedef struct KerrbhyRenderer KerrbhyRenderer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create a renderer for `width` x `height` frames, using the default config.
+//
+// On success `out` points to a renderer that must be freed with [`kerrbhy_renderer_destroy`].
+//
+// # Safety
+//
+// `out` must be valid to write a pointer to.
+enum KerrbhyStatus kerrbhy_renderer_create(enum KerrbhyBackend backend,
+                                           uint32_t width,
+                                           uint32_t height,
+                                           struct KerrbhyRenderer **out);
+
+// Replace the config with one parsed from a toml string.
+//
+// Accumulation restarts if the config has changed.
+//
+// # Safety
+//
+// `renderer` must come from [`kerrbhy_renderer_create`],
+// and `toml` must be a nul terminated string.
+enum KerrbhyStatus kerrbhy_renderer_set_config(struct KerrbhyRenderer *renderer, const char *toml);
+
+// Compute `samples` more samples, accumulating them into the frame.
+//
+// Blocks until the samples are finished.
+//
+// # Safety
+//
+// `renderer` must come from [`kerrbhy_renderer_create`].
+enum KerrbhyStatus kerrbhy_renderer_render(struct KerrbhyRenderer *renderer, uint32_t samples);
+
+// Copy the frame into `out` as `[r, g, b, a]` bytes.
+//
+// `len` must be exactly `width * height * 4`.
+//
+// # Safety
+//
+// `renderer` must come from [`kerrbhy_renderer_create`],
+// and `out` must be valid to write `len` bytes to.
+enum KerrbhyStatus kerrbhy_renderer_read_rgba8(const struct KerrbhyRenderer *renderer,
+                                               uint8_t *out,
+                                               size_t len);
+
+// Copy the frame into `out` as `[r, g, b, a]` floats.
+//
+// `len` must be exactly `width * height * 4`.
+// The hardware renderer accumulates in 8 bits, so only the software renderer gains precision.
+//
+// # Safety
+//
+// `renderer` must come from [`kerrbhy_renderer_create`],
+// and `out` must be valid to write `len` floats to.
+enum KerrbhyStatus kerrbhy_renderer_read_rgba32f(const struct KerrbhyRenderer *renderer,
+                                                 float *out,
+                                                 size_t len);
+
+// Free a renderer, does nothing if it is null.
+//
+// # Safety
+//
+// `renderer` must come from [`kerrbhy_renderer_create`] and not be used after.
+enum KerrbhyStatus kerrbhy_renderer_destroy(struct KerrbhyRenderer *renderer);
+
+// The message of the last failure on this thread,
+// or null if the last call succeeded.
+//
+// The string is valid until the next call on this thread.
+const char *kerrbhy_last_error(void);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* KERRBHY_H */
@@ -0,0 +1,306 @@
+//! A C API for embedding the renderer.
+//!
+//! The header is generated with cbindgen into `include/kerrbhy.h`.
+//! Every function returns a [`KerrbhyStatus`],
+//! with a description of the last failure from [`kerrbhy_last_error`].
+
+use std::{
+    cell::RefCell,
+    ffi::{
+        c_char,
+        CStr,
+        CString,
+    },
+    panic::AssertUnwindSafe,
+    ptr,
+};
+
+use common::{
+    Config,
+    Render,
+};
+use kerrbhy::Simulator;
+
+/// Where frames are rendered.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KerrbhyBackend {
+    /// Use the GPU if one is available, otherwise fall back to the CPU.
+    Auto,
+    Hardware,
+    Software,
+}
+
+/// The result of every call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KerrbhyStatus {
+    Ok,
+    /// A pointer was null, or a buffer was the wrong size.
+    InvalidArgument,
+    /// The config couldn't be parsed.
+    InvalidConfig,
+    /// The renderer couldn't be created.
+    CreateFailed,
+    /// Rust panicked, the renderer shouldn't be used again.
+    Panic,
+}
+
+/// An opaque handle to a renderer that accumulates samples.
+pub struct KerrbhyRenderer {
+    renderer: Simulator,
+    width: u32,
+    height: u32,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // interior nul bytes would truncate the message anyway
+    let message = CString::new(message.replace('\0', "")).expect("nul bytes were removed");
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Run `f`, turning errors and panics into a [`KerrbhyStatus`].
+///
+/// Clears the last error, so it only describes the call that just failed.
+fn guard(f: impl FnOnce() -> Result<(), (KerrbhyStatus, String)>) -> KerrbhyStatus {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => KerrbhyStatus::Ok,
+        Ok(Err((status, message))) => {
+            set_last_error(message);
+            status
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_owned());
+
+            set_last_error(message);
+            KerrbhyStatus::Panic
+        }
+    }
+}
+
+fn invalid(message: &str) -> (KerrbhyStatus, String) {
+    (KerrbhyStatus::InvalidArgument, message.to_owned())
+}
+
+/// Create a renderer for `width` x `height` frames, using the default config.
+///
+/// On success `out` points to a renderer that must be freed with [`kerrbhy_renderer_destroy`].
+///
+/// # Safety
+///
+/// `out` must be valid to write a pointer to.
+#[no_mangle]
+pub unsafe extern "C" fn kerrbhy_renderer_create(
+    backend: KerrbhyBackend,
+    width: u32,
+    height: u32,
+    out: *mut *mut KerrbhyRenderer,
+) -> KerrbhyStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(invalid("`out` is null"));
+        }
+
+        if width == 0 || height == 0 {
+            return Err(invalid("the frame is empty"));
+        }
+
+        let hardware = || anyhow::Ok(Simulator::hardware(&kerrbhy::context()?));
+
+        let mut renderer = match backend {
+            KerrbhyBackend::Hardware => {
+                hardware().map_err(|e| (KerrbhyStatus::CreateFailed, format!("{e:#}")))?
+            }
+            KerrbhyBackend::Software => Simulator::software(),
+            KerrbhyBackend::Auto => hardware().unwrap_or_else(|e| {
+                log::warn!("falling back to the software renderer: {e:#}");
+                Simulator::software()
+            }),
+        };
+        renderer.update(width, height, Config::default());
+
+        let renderer = KerrbhyRenderer {
+            renderer,
+            width,
+            height,
+        };
+
+        // SAFETY: checked for null above, the caller guarantees it's valid
+        unsafe { *out = Box::into_raw(Box::new(renderer)) };
+
+        Ok(())
+    })
+}
+
+/// Replace the config with one parsed from a toml string.
+///
+/// Accumulation restarts if the config has changed.
+///
+/// # Safety
+///
+/// `renderer` must come from [`kerrbhy_renderer_create`],
+/// and `toml` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn kerrbhy_renderer_set_config(
+    renderer: *mut KerrbhyRenderer,
+    toml: *const c_char,
+) -> KerrbhyStatus {
+    guard(|| {
+        if toml.is_null() {
+            return Err(invalid("`toml` is null"));
+        }
+
+        // SAFETY: the caller guarantees the pointers are valid
+        let renderer = unsafe { renderer.as_mut() }.ok_or_else(|| invalid("`renderer` is null"))?;
+        let toml = unsafe { CStr::from_ptr(toml) };
+
+        let config = toml
+            .to_str()
+            .map_err(|e| e.to_string())
+            .and_then(|toml| Config::load(toml).map_err(|e| e.to_string()))
+            .map_err(|e| (KerrbhyStatus::InvalidConfig, e))?;
+
+        let (width, height) = (renderer.width, renderer.height);
+        renderer.renderer.update(width, height, config);
+
+        Ok(())
+    })
+}
+
+/// Compute `samples` more samples, accumulating them into the frame.
+///
+/// Blocks until the samples are finished.
+///
+/// # Safety
+///
+/// `renderer` must come from [`kerrbhy_renderer_create`].
+#[no_mangle]
+pub unsafe extern "C" fn kerrbhy_renderer_render(
+    renderer: *mut KerrbhyRenderer,
+    samples: u32,
+) -> KerrbhyStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees the pointer is valid
+        let renderer = unsafe { renderer.as_mut() }.ok_or_else(|| invalid("`renderer` is null"))?;
+
+        for _ in 0..samples {
+            renderer.renderer.compute_sample();
+        }
+
+        Ok(())
+    })
+}
+
+/// Copy the frame into `out` as `[r, g, b, a]` bytes.
+///
+/// `len` must be exactly `width * height * 4`.
+///
+/// # Safety
+///
+/// `renderer` must come from [`kerrbhy_renderer_create`],
+/// and `out` must be valid to write `len` bytes to.
+#[no_mangle]
+pub unsafe extern "C" fn kerrbhy_renderer_read_rgba8(
+    renderer: *const KerrbhyRenderer,
+    out: *mut u8,
+    len: usize,
+) -> KerrbhyStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees the pointer is valid
+        let renderer = unsafe { renderer.as_ref() }.ok_or_else(|| invalid("`renderer` is null"))?;
+
+        let frame = renderer.renderer.frame();
+
+        // SAFETY: the caller guarantees `out` is valid for `len` elements
+        unsafe { copy_out(&frame, out, len) }
+    })
+}
+
+/// Copy the frame into `out` as `[r, g, b, a]` floats.
+///
+/// `len` must be exactly `width * height * 4`.
+/// The hardware renderer accumulates in 8 bits, so only the software renderer gains precision.
+///
+/// # Safety
+///
+/// `renderer` must come from [`kerrbhy_renderer_create`],
+/// and `out` must be valid to write `len` floats to.
+#[no_mangle]
+pub unsafe extern "C" fn kerrbhy_renderer_read_rgba32f(
+    renderer: *const KerrbhyRenderer,
+    out: *mut f32,
+    len: usize,
+) -> KerrbhyStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees the pointer is valid
+        let renderer = unsafe { renderer.as_ref() }.ok_or_else(|| invalid("`renderer` is null"))?;
+
+        let frame = renderer.renderer.frame_hdr();
+
+        // SAFETY: the caller guarantees `out` is valid for `len` elements
+        unsafe { copy_out(&frame, out, len) }
+    })
+}
+
+/// # Safety
+///
+/// `out` must be valid to write `len` elements to.
+unsafe fn copy_out<T: Copy>(
+    frame: &[T],
+    out: *mut T,
+    len: usize,
+) -> Result<(), (KerrbhyStatus, String)> {
+    if out.is_null() {
+        return Err(invalid("`out` is null"));
+    }
+
+    if len != frame.len() {
+        return Err((
+            KerrbhyStatus::InvalidArgument,
+            format!("`out` holds {len} elements, but the frame is {}", frame.len()),
+        ));
+    }
+
+    unsafe { ptr::copy_nonoverlapping(frame.as_ptr(), out, len) };
+
+    Ok(())
+}
+
+/// Free a renderer, does nothing if it is null.
+///
+/// # Safety
+///
+/// `renderer` must come from [`kerrbhy_renderer_create`] and not be used after.
+#[no_mangle]
+pub unsafe extern "C" fn kerrbhy_renderer_destroy(renderer: *mut KerrbhyRenderer) -> KerrbhyStatus {
+    guard(|| {
+        if !renderer.is_null() {
+            // SAFETY: the caller guarantees it came from `Box::into_raw`
+            drop(unsafe { Box::from_raw(renderer) });
+        }
+
+        Ok(())
+    })
+}
+
+/// The message of the last failure on this thread,
+/// or null if the last call succeeded.
+///
+/// The string is valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn kerrbhy_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
@@ -0,0 +1,193 @@
+//! Python bindings for rendering offline.
+//!
+//! Built with maturin:
+//!
+//! ```sh
+//! cd kerrbhy-py
+//! maturin develop --release
+//! ```
+//!
+//! ```python
+//! import kerrbhy
+//!
+//! config = kerrbhy.Config()
+//! config.set("features", "DISK_VOL|AA")
+//! image = kerrbhy.render(config, 640, 360, samples=64)
+//! ```
+
+use common::{
+    camera::{
+        CameraPath,
+        Keyframe,
+    },
+    Camera,
+};
+use kerrbhy::{
+    Backend,
+    RenderJob,
+};
+use numpy::{
+    ndarray::Array3,
+    IntoPyArray as _,
+    PyArray3,
+};
+use pyo3::{
+    exceptions::{
+        PyRuntimeError,
+        PyValueError,
+    },
+    prelude::*,
+};
+
+/// A render config, the same as the toml files used by `kerrbhy`.
+#[pyclass(name = "Config")]
+#[derive(Clone, Default)]
+struct PyConfig(common::Config);
+
+#[pymethods]
+impl PyConfig {
+    /// The default config.
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a config from a toml string.
+    #[staticmethod]
+    fn from_toml(s: &str) -> PyResult<Self> {
+        common::Config::load(s)
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Load a config from a toml file.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        common::Config::load_from_path(path)
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn to_toml(&self) -> PyResult<String> {
+        let mut toml = Vec::new();
+        self.0
+            .save(&mut toml)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        Ok(String::from_utf8(toml).expect("toml is valid utf-8"))
+    }
+
+    /// Override a single field, such as `disk.radius`.
+    fn set(&mut self, key: &str, value: &str) -> PyResult<()> {
+        self.0
+            .set(key, value)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+/// Moves the camera between keyframes of `(time, radius, phi, theta)`.
+#[pyclass(name = "CameraPath")]
+struct PyCameraPath(CameraPath);
+
+#[pymethods]
+impl PyCameraPath {
+    #[new]
+    fn new(keyframes: Vec<(f32, f32, f32, f32)>) -> Self {
+        let keyframes = keyframes
+            .into_iter()
+            .map(|(time, radius, phi, theta)| Keyframe {
+                time,
+                radius,
+                phi,
+                theta,
+                easing: Default::default(),
+            })
+            .collect();
+
+        Self(CameraPath::new(keyframes))
+    }
+
+    /// The time of the last keyframe.
+    #[getter]
+    fn duration(&self) -> f32 {
+        self.0.duration()
+    }
+
+    /// The `(radius, phi, theta)` of the camera at `time`.
+    fn evaluate(&self, time: f32) -> Option<(f32, f32, f32)> {
+        self.0
+            .evaluate(time)
+            .map(|key| (key.radius, key.phi, key.theta))
+    }
+
+    /// A copy of `config` with the camera moved to where it is at `time`.
+    fn apply(&self, config: &PyConfig, time: f32) -> PyConfig {
+        let mut config = config.clone();
+
+        match &mut config.0.camera {
+            Camera::Orbit(camera) => self.0.apply(camera, time),
+        }
+
+        config
+    }
+}
+
+/// Render a frame into an array of `[height, width, 4]` bytes.
+///
+/// `backend` is one of `"auto"`, `"hardware"` or `"software"`.
+#[pyfunction]
+#[pyo3(signature = (config=None, width=800, height=600, samples=1, backend="auto", threads=None))]
+fn render<'py>(
+    py: Python<'py>,
+    config: Option<PyConfig>,
+    width: u32,
+    height: u32,
+    samples: u32,
+    backend: &str,
+    threads: Option<usize>,
+) -> PyResult<Bound<'py, PyArray3<u8>>> {
+    let backend = match backend {
+        "auto" => Backend::Auto,
+        "hardware" => Backend::Hardware,
+        "software" => Backend::Software,
+        _ => return Err(PyValueError::new_err(format!("unknown backend `{backend}`"))),
+    };
+
+    let mut builder = RenderJob::builder()
+        .resolution(width, height)
+        .samples(samples)
+        .config(config.unwrap_or_default().0)
+        .backend(backend);
+
+    if let Some(threads) = threads {
+        builder = builder.threads(threads);
+    }
+
+    let job = builder
+        .build()
+        .map_err(|e| PyValueError::new_err(format!("{e:#}")))?;
+
+    // let other python threads run while rendering
+    let image = py
+        .allow_threads(|| job.render())
+        .map_err(|e| PyRuntimeError::new_err(format!("{e:#}")))?;
+
+    let array = Array3::from_shape_vec((height as usize, width as usize, 4), image.into_raw())
+        .expect("image is the correct size");
+
+    Ok(array.into_pyarray_bound(py))
+}
+
+#[pymodule]
+#[pyo3(name = "kerrbhy")]
+fn kerrbhy_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyConfig>()?;
+    m.add_class::<PyCameraPath>()?;
+    m.add_function(wrap_pyfunction!(render, m)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Rendering many configs from a single manifest.
+//!
+//! A manifest is a toml file containing a list of jobs:
+//!
+//! ```toml
+//! [[job]]
+//! config = "scenes/bloom.toml"
+//! width = 1920
+//! height = 1080
+//! samples = 64
+//! output = "renders/bloom.png"
+//! ```
+//!
+//! Relative paths are resolved from the directory of the manifest.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use anyhow::Context as _;
+use common::{
+    Config,
+    Render,
+};
+use graphics::Context;
+use serde::Deserialize;
+
+use crate::{
+    output,
+    RendererKind,
+    Simulator,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    #[serde(rename = "job", default)]
+    pub jobs: Vec<Job>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Job {
+    /// The config to render.
+    ///
+    /// Uses the default config if not specified.
+    pub config: Option<PathBuf>,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_samples")]
+    pub samples: u32,
+    /// Where the frame is saved.
+    pub output: PathBuf,
+    #[serde(default)]
+    pub renderer: RendererKind,
+}
+
+fn default_samples() -> u32 {
+    1
+}
+
+impl Manifest {
+    /// Load a manifest from a file.
+    pub fn load_from_path(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read manifest {}", path.display()))?;
+        let mut manifest: Manifest = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse manifest {}", path.display()))?;
+
+        // make the paths relative to the manifest, not the working directory
+        if let Some(root) = path.parent() {
+            for job in &mut manifest.jobs {
+                job.config = job.config.as_ref().map(|config| root.join(config));
+                job.output = root.join(&job.output);
+            }
+        }
+
+        Ok(manifest)
+    }
+}
+
+impl Job {
+    /// Render the job and save it to its output.
+    pub fn run(&self, ctx: &Context) -> anyhow::Result<()> {
+        profiling::scope!("job", self.output.display().to_string());
+
+        let config = match self.config.as_deref() {
+            Some(path) => Config::load_from_path(path)
+                .with_context(|| format!("failed to load config from {}", path.display()))?,
+            None => Config::default(),
+        };
+
+        if self.samples == 0 {
+            anyhow::bail!("job {} must have at least one sample", self.output.display());
+        }
+
+        let mut sim = Simulator::new(self.renderer, ctx, self.width, self.height, config, false)?;
+
+        for _ in 0..self.samples {
+            sim.compute_sample();
+        }
+
+        let bytes = sim.into_frame();
+
+        if let Some(parent) = self.output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        output::save_image(&bytes, self.width, self.height, &self.output)
+    }
+}
+
+/// Render every job in the manifest.
+///
+/// Jobs can be rendered in `parallel`, sharing the same graphics context.
+pub fn run(ctx: &Context, manifest: &Manifest, parallel: bool) -> anyhow::Result<()> {
+    profiling::scope!("batch");
+
+    if parallel {
+        std::thread::scope(|s| {
+            let handles = manifest
+                .jobs
+                .iter()
+                .map(|job| s.spawn(move || job.run(ctx)))
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().expect("job panicked"))
+        })
+    } else {
+        manifest.jobs.iter().try_for_each(|job| {
+            log::info!("rendering {}", job.output.display());
+            job.run(ctx)
+        })
+    }
+}
@@ -0,0 +1,174 @@
+//! Benchmarks comparing the hardware and software renderers.
+
+use std::{
+    fmt::Write as _,
+    time::Instant,
+};
+
+use common::{
+    Config,
+    Features,
+    Render,
+};
+use graphics::{
+    wgpu,
+    Context,
+};
+use serde::Serialize;
+
+use crate::{
+    RendererKind,
+    Simulator,
+};
+
+/// A single config and resolution to benchmark.
+pub struct Case {
+    pub name: String,
+    pub config: Config,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The timings of one [`Case`] on one renderer.
+#[derive(Debug, Clone, Serialize)]
+pub struct Measurement {
+    pub case: String,
+    pub renderer: String,
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+
+    /// Total wall-clock time spent computing samples.
+    pub total_secs: f64,
+    /// Average wall-clock time of a single sample.
+    pub secs_per_sample: f64,
+    /// Average wall-clock time of a single sample, per megapixel.
+    pub secs_per_megapixel: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub measurements: Vec<Measurement>,
+}
+
+impl Report {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+
+        md.push_str("| case | renderer | resolution | samples | total (s) | ms / sample | ms / sample / MP |\n");
+        md.push_str("|------|----------|------------|---------|-----------|-------------|------------------|\n");
+
+        for m in &self.measurements {
+            // writing to a string can't fail
+            let _ = writeln!(
+                md,
+                "| {} | {} | {}x{} | {} | {:.3} | {:.3} | {:.3} |",
+                m.case,
+                m.renderer,
+                m.width,
+                m.height,
+                m.samples,
+                m.total_secs,
+                m.secs_per_sample * 1000.0,
+                m.secs_per_megapixel * 1000.0,
+            );
+        }
+
+        md
+    }
+}
+
+/// The fixed set of cases, covering each of the integrators and the more
+/// expensive features at a couple of resolutions.
+pub fn default_cases() -> Vec<Case> {
+    let features = [
+        ("euler", Features::empty()),
+        ("rk4", Features::RK4),
+        ("adaptive", Features::ADAPTIVE),
+        ("disk_sdf", Features::DISK_SDF | Features::RK4),
+        ("disk_vol", Features::DISK_VOL | Features::RK4),
+        ("sky_proc", Features::SKY_PROC | Features::RK4),
+    ];
+    let resolutions = [(256, 256), (512, 512)];
+
+    let mut cases = Vec::new();
+
+    for (width, height) in resolutions {
+        for (name, features) in features {
+            cases.push(Case {
+                name: name.to_owned(),
+                config: Config {
+                    features,
+                    ..Default::default()
+                },
+                width,
+                height,
+            });
+        }
+    }
+
+    cases
+}
+
+/// Run each of the `cases` on every renderer in `kinds`.
+pub fn run(
+    ctx: &Context,
+    cases: &[Case],
+    kinds: &[RendererKind],
+    samples: u32,
+) -> anyhow::Result<Report> {
+    profiling::scope!("bench");
+
+    let mut report = Report::default();
+
+    for case in cases {
+        for &kind in kinds {
+            log::info!("benchmarking {} on {kind:?}", case.name);
+
+            let mut sim = Simulator::new(
+                kind,
+                ctx,
+                case.width,
+                case.height,
+                case.config.clone(),
+                false,
+            )?;
+
+            // warm up, so that we don't measure any lazy initialisation
+            sim.compute_sample();
+            wait(ctx);
+
+            let start = Instant::now();
+            for _ in 0..samples {
+                sim.compute_sample();
+            }
+            wait(ctx);
+            let total_secs = start.elapsed().as_secs_f64();
+
+            let secs_per_sample = total_secs / samples as f64;
+            let megapixels = (case.width * case.height) as f64 / 1_000_000.0;
+
+            report.measurements.push(Measurement {
+                case: case.name.clone(),
+                renderer: format!("{kind:?}").to_lowercase(),
+                width: case.width,
+                height: case.height,
+                samples,
+                total_secs,
+                secs_per_sample,
+                secs_per_megapixel: secs_per_sample / megapixels,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Wait for all submitted gpu work to finish.
+fn wait(ctx: &Context) {
+    ctx.device().poll(wgpu::Maintain::Wait).panic_on_timeout();
+}
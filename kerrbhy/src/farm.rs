@@ -0,0 +1,333 @@
+//! Spreading renders across several machines.
+//!
+//! Workers run [`serve`], accepting jobs over TCP.
+//! Each job is sent as a single line of JSON,
+//! and the worker replies with a line of JSON followed by the frame
+//! as little-endian `f32`s of linear light.
+//!
+//! [`distribute`] splits the samples of a render between workers
+//! and merges the partial frames they send back.
+//! Frames are merged in linear light, averaging encoded colors would darken them.
+
+use std::{
+    io::{
+        BufRead,
+        BufReader,
+        Read,
+        Write,
+    },
+    net::{
+        TcpListener,
+        TcpStream,
+        ToSocketAddrs,
+    },
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use common::{
+    Config,
+    Render,
+    Tile,
+};
+use graphics::Context;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    partial::{
+        self,
+        Encoding,
+        Partial,
+    },
+    RendererKind,
+    Simulator,
+};
+
+/// The longest line of JSON accepted, jobs and responses are much smaller.
+const MAX_LINE: u64 = 1024 * 1024;
+
+/// How long to wait for a peer to send or receive anything before giving up.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for a worker to finish rendering a job.
+const RENDER_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// The most pixels a worker renders in one job, so a job can't exhaust its memory.
+const MAX_PIXELS: u64 = 8192 * 8192;
+
+/// The most samples a worker renders in one job.
+const MAX_SAMPLES: u32 = 1 << 16;
+
+/// A piece of work sent to a worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub config: Config,
+    pub renderer: RendererKind,
+    pub partial: Partial,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Response {
+    /// The frame follows, `len` bytes long.
+    Frame { len: usize },
+    Error { message: String },
+}
+
+/// Render a [`Job`] into `[r, g, b, a]` floats of linear light the size of its tile.
+pub fn render(ctx: &Context, job: &Job) -> anyhow::Result<Vec<f32>> {
+    profiling::scope!("farm::render");
+
+    let Partial {
+        width,
+        height,
+        tile,
+        ref samples,
+        ..
+    } = job.partial;
+
+    if !tile.fits(width, height) {
+        anyhow::bail!("tile {tile:?} doesn't fit in a {width}x{height} frame");
+    }
+
+    // jobs come from the network, so check them before anything is allocated
+    if width as u64 * height as u64 > MAX_PIXELS {
+        anyhow::bail!("a {width}x{height} frame is larger than {MAX_PIXELS} pixels");
+    }
+
+    let max_size = ctx.limits().max_texture_dimension_2d;
+    if job.renderer == RendererKind::Hardware && width.max(height) > max_size {
+        anyhow::bail!("a {width}x{height} frame is larger than the GPU's limit of {max_size}");
+    }
+
+    if samples.len() > MAX_SAMPLES as usize {
+        anyhow::bail!("{} samples are more than the {MAX_SAMPLES} allowed", samples.len());
+    }
+
+    let mut sim = Simulator::with_tile(
+        job.renderer,
+        ctx,
+        width,
+        height,
+        tile,
+        job.config.clone(),
+        false,
+    )?;
+    sim.set_first_sample(samples.start);
+
+    for _ in samples.clone() {
+        sim.compute_sample();
+    }
+
+    Ok(sim.into_frame_linear())
+}
+
+/// Accept jobs on `addr` until the process is stopped.
+///
+/// Connections are handled one at a time, sharing the graphics context.
+pub fn serve(ctx: &Context, addr: impl ToSocketAddrs) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+
+    log::info!("listening on {local_addr}");
+
+    if !local_addr.ip().is_loopback() {
+        log::warn!("anyone who can reach {local_addr} can send jobs, there's no authentication");
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::error!("failed to accept connection: {e}");
+                continue;
+            }
+        };
+
+        // a bad client shouldn't bring down the worker
+        if let Err(e) = handle(ctx, stream) {
+            log::error!("failed to handle job: {e:#}");
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(ctx: &Context, stream: TcpStream) -> anyhow::Result<()> {
+    let peer = stream.peer_addr()?;
+
+    // a client that stalls would hold up every other job
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let line = read_line(&mut reader).context("failed to read job")?;
+
+    let result = serde_json::from_str::<Job>(&line)
+        .context("failed to parse job")
+        .and_then(|job| {
+            log::info!(
+                "rendering {:?} of samples {:?} for {peer}",
+                job.partial.tile,
+                job.partial.samples
+            );
+            render(ctx, &job)
+        });
+
+    match result {
+        Ok(floats) => {
+            let bytes = floats
+                .into_iter()
+                .flat_map(f32::to_le_bytes)
+                .collect::<Vec<_>>();
+
+            write_response(&mut writer, &Response::Frame { len: bytes.len() })?;
+            writer.write_all(&bytes)?;
+        }
+        Err(e) => {
+            let message = format!("{e:#}");
+            write_response(&mut writer, &Response::Error { message })?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Read a line, failing if it's longer than [`MAX_LINE`].
+fn read_line(reader: &mut impl BufRead) -> anyhow::Result<String> {
+    let mut line = String::new();
+    reader.take(MAX_LINE).read_line(&mut line)?;
+
+    if !line.ends_with('\n') {
+        anyhow::bail!("line is too long, or the connection was closed");
+    }
+
+    Ok(line)
+}
+
+fn write_response(writer: &mut impl Write, response: &Response) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut *writer, response)?;
+    writeln!(writer)?;
+
+    Ok(())
+}
+
+/// Send a [`Job`] to the worker at `addr` and wait for the frame.
+pub fn request(addr: impl ToSocketAddrs, job: &Job) -> anyhow::Result<image::Rgba32FImage> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    // nothing is sent back until the job is rendered
+    stream.set_read_timeout(Some(RENDER_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    serde_json::to_writer(&mut writer, job)?;
+    writeln!(writer)?;
+    writer.flush()?;
+
+    let line = read_line(&mut reader).context("failed to read response")?;
+
+    match serde_json::from_str(&line).context("failed to parse response")? {
+        Response::Frame { len } => {
+            let tile = job.partial.tile;
+
+            // check the size before allocating it, the worker can't be trusted
+            let channels = tile.width() as u64 * tile.height() as u64 * 4;
+            let expected = channels * std::mem::size_of::<f32>() as u64;
+            if len as u64 != expected {
+                anyhow::bail!("frame is {len} bytes, expected {expected} bytes for its tile");
+            }
+
+            // the reader is a clone of the same socket
+            writer.set_read_timeout(Some(IO_TIMEOUT))?;
+
+            let mut bytes = vec![0; len];
+            reader.read_exact(&mut bytes)?;
+
+            let floats = bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+
+            image::Rgba32FImage::from_raw(tile.width(), tile.height(), floats)
+                .context("frame is the wrong size for its tile")
+        }
+        Response::Error { message } => anyhow::bail!("worker failed: {message}"),
+    }
+}
+
+/// Split `samples` of a whole frame evenly between `workers` and merge the results.
+///
+/// Returns the size of the frame along with its floats of linear light.
+pub fn distribute(
+    workers: &[String],
+    config: &Config,
+    renderer: RendererKind,
+    width: u32,
+    height: u32,
+    samples: u32,
+) -> anyhow::Result<(u32, u32, Vec<f32>)> {
+    profiling::scope!("farm::distribute");
+
+    if workers.is_empty() {
+        anyhow::bail!("no workers to distribute to");
+    }
+
+    // the workers send linear light, see `render`
+    let encoding = if config.features.encodes_srgb() {
+        Encoding::Linear
+    } else {
+        Encoding::Raw
+    };
+
+    // don't give workers empty ranges
+    let count = workers.len().min(samples as usize) as u32;
+
+    let jobs = (0..count).map(|i| {
+        let split = |i: u32| (samples as u64 * i as u64 / count as u64) as u32;
+        let (start, end) = (split(i), split(i + 1));
+
+        Job {
+            config: config.clone(),
+            renderer,
+            partial: Partial {
+                width,
+                height,
+                tile: Tile::full(width, height),
+                samples: start..end,
+                encoding,
+            },
+        }
+    });
+
+    let parts = std::thread::scope(|s| {
+        let handles = workers
+            .iter()
+            .zip(jobs)
+            .map(|(worker, job)| {
+                s.spawn(move || {
+                    log::info!("sending samples {:?} to {worker}", job.partial.samples);
+
+                    let image = request(worker.as_str(), &job)
+                        .with_context(|| format!("job on {worker} failed"))?;
+
+                    Ok((job.partial, image))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("request panicked"))
+            .collect::<anyhow::Result<Vec<_>>>()
+    })?;
+
+    partial::merge(&parts)
+}
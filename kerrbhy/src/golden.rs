@@ -0,0 +1,181 @@
+//! Golden image regression testing.
+//!
+//! A set of configs is rendered deterministically and compared against
+//! reference images stored on disk.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use anyhow::Context as _;
+use common::{
+    Config,
+    Render,
+};
+use graphics::Context;
+
+use crate::{
+    metrics,
+    RendererKind,
+    Simulator,
+};
+
+/// A named config to render.
+pub struct Case {
+    pub name: String,
+    pub config: Config,
+}
+
+/// How each [`Case`] is rendered and compared.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub renderer: RendererKind,
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    /// Minimum structural similarity to the reference image.
+    pub ssim: f64,
+}
+
+pub enum Outcome {
+    /// The render matched the reference image.
+    Passed { ssim: f64 },
+    /// The render didn't match the reference image.
+    ///
+    /// An image of the difference is written next to the reference.
+    Failed { ssim: f64, diff: PathBuf },
+    /// There was no reference image to compare against.
+    Missing,
+    /// The reference image was (re)written.
+    Updated,
+}
+
+impl Outcome {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Outcome::Failed { .. } | Outcome::Missing)
+    }
+}
+
+/// Loads every config in `dir` as a [`Case`], named after the file.
+pub fn load_cases(dir: &Path) -> anyhow::Result<Vec<Case>> {
+    let mut paths = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read configs from {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // keep the order deterministic
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .expect("path is a file")
+                .to_string_lossy()
+                .into_owned();
+
+            let config = Config::load_from_path(&path)
+                .with_context(|| format!("failed to load config from {}", path.display()))?;
+
+            Ok(Case { name, config })
+        })
+        .collect()
+}
+
+/// Render each case and compare it against `golden/<name>.png`.
+///
+/// When `update` is set, the references are overwritten instead.
+pub fn run(
+    ctx: &Context,
+    cases: &[Case],
+    golden: &Path,
+    options: Options,
+    update: bool,
+) -> anyhow::Result<Vec<(String, Outcome)>> {
+    profiling::scope!("golden");
+
+    let Options {
+        width,
+        height,
+        samples,
+        ..
+    } = options;
+
+    if update {
+        std::fs::create_dir_all(golden)?;
+    }
+
+    let mut outcomes = Vec::with_capacity(cases.len());
+
+    for case in cases {
+        log::info!("rendering {}", case.name);
+
+        let mut sim = Simulator::new(
+            options.renderer,
+            ctx,
+            width,
+            height,
+            case.config.clone(),
+            false,
+        )?;
+
+        for _ in 0..samples {
+            sim.compute_sample();
+        }
+
+        let frame = sim.into_frame();
+
+        let reference_path = golden.join(&case.name).with_extension("png");
+
+        let outcome = if update {
+            image::save_buffer(
+                &reference_path,
+                &frame,
+                width,
+                height,
+                image::ColorType::Rgba8,
+            )?;
+
+            Outcome::Updated
+        } else if reference_path.is_file() {
+            let reference = image::open(&reference_path)
+                .with_context(|| format!("failed to open {}", reference_path.display()))?
+                .into_rgba8();
+
+            if reference.dimensions() != (width, height) {
+                anyhow::bail!(
+                    "reference {} is {:?}, expected {:?}",
+                    reference_path.display(),
+                    reference.dimensions(),
+                    (width, height)
+                );
+            }
+
+            let ssim = metrics::ssim(&frame, &reference, width, height);
+
+            if ssim >= options.ssim {
+                Outcome::Passed { ssim }
+            } else {
+                let diff = golden.join(format!("{}.diff.png", case.name));
+                image::save_buffer(
+                    &diff,
+                    &metrics::diff(&frame, &reference),
+                    width,
+                    height,
+                    image::ColorType::Rgba8,
+                )?;
+
+                Outcome::Failed { ssim, diff }
+            }
+        } else {
+            Outcome::Missing
+        };
+
+        outcomes.push((case.name.clone(), outcome));
+    }
+
+    Ok(outcomes)
+}
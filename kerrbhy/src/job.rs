@@ -0,0 +1,174 @@
+//! A single entry point for rendering a frame from other programs.
+//!
+//! ```no_run
+//! use kerrbhy::{Backend, RenderJob};
+//!
+//! let image = RenderJob::builder()
+//!     .resolution(1920, 1080)
+//!     .samples(512)
+//!     .backend(Backend::Auto)
+//!     .build()?
+//!     .render()?;
+//!
+//! image.save("render.png")?;
+//! # anyhow::Ok(())
+//! ```
+
+use common::{
+    Config,
+    Render,
+};
+
+use crate::Simulator;
+
+/// Where a [`RenderJob`] is rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Use the GPU if one is available, otherwise fall back to the CPU.
+    #[default]
+    Auto,
+    /// Always use the GPU.
+    Hardware,
+    /// Always use the CPU.
+    Software,
+}
+
+/// A frame to render, created with [`RenderJob::builder`].
+#[derive(Debug, Clone)]
+pub struct RenderJob {
+    width: u32,
+    height: u32,
+    samples: u32,
+    config: Config,
+    backend: Backend,
+    threads: Option<usize>,
+}
+
+/// Builds a [`RenderJob`].
+#[derive(Debug, Clone)]
+pub struct RenderJobBuilder {
+    width: u32,
+    height: u32,
+    samples: u32,
+    config: Config,
+    backend: Backend,
+    threads: Option<usize>,
+}
+
+impl Default for RenderJobBuilder {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            samples: 1,
+            config: Config::default(),
+            backend: Backend::default(),
+            threads: None,
+        }
+    }
+}
+
+impl RenderJobBuilder {
+    /// The size of the frame in pixels.
+    pub fn resolution(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// The number of samples to accumulate.
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// The number of threads used by the software renderer.
+    ///
+    /// Uses as many threads as there are cores by default.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Check the options and create the [`RenderJob`].
+    pub fn build(self) -> anyhow::Result<RenderJob> {
+        if self.width == 0 || self.height == 0 {
+            anyhow::bail!("resolution {}x{} is empty", self.width, self.height);
+        }
+
+        if self.samples == 0 {
+            anyhow::bail!("a render needs at least one sample");
+        }
+
+        Ok(RenderJob {
+            width: self.width,
+            height: self.height,
+            samples: self.samples,
+            config: self.config,
+            backend: self.backend,
+            threads: self.threads,
+        })
+    }
+}
+
+impl RenderJob {
+    /// Start building a [`RenderJob`].
+    pub fn builder() -> RenderJobBuilder {
+        RenderJobBuilder::default()
+    }
+
+    /// Render the frame, creating a graphics context if the GPU is used.
+    pub fn render(&self) -> anyhow::Result<image::RgbaImage> {
+        profiling::scope!("RenderJob::render");
+
+        let bytes = match self.backend {
+            Backend::Hardware => self.render_hardware()?,
+            Backend::Software => self.render_software()?,
+            Backend::Auto => match self.render_hardware() {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    log::warn!("falling back to the software renderer: {e:#}");
+                    self.render_software()?
+                }
+            },
+        };
+
+        let image = image::RgbaImage::from_raw(self.width, self.height, bytes)
+            .expect("frame is the correct size");
+
+        Ok(image)
+    }
+
+    fn render_hardware(&self) -> anyhow::Result<Vec<u8>> {
+        let ctx = crate::context()?;
+
+        Ok(self.accumulate(Simulator::hardware(&ctx)))
+    }
+
+    fn render_software(&self) -> anyhow::Result<Vec<u8>> {
+        let mut sim = Simulator::software();
+        sim.set_threads(self.threads, false)?;
+
+        Ok(self.accumulate(sim))
+    }
+
+    fn accumulate(&self, mut renderer: Simulator) -> Vec<u8> {
+        renderer.update(self.width, self.height, self.config.clone());
+
+        for _ in 0..self.samples {
+            renderer.compute_sample();
+        }
+
+        renderer.into_frame()
+    }
+}
@@ -0,0 +1,24 @@
+pub mod batch;
+pub mod bench;
+pub mod farm;
+pub mod golden;
+mod job;
+pub mod metrics;
+pub mod output;
+pub mod partial;
+mod simulator;
+pub mod stats;
+pub mod verify;
+
+pub use job::{
+    Backend,
+    RenderJob,
+    RenderJobBuilder,
+};
+pub use simulator::{
+    context,
+    context_on,
+    RendererKind,
+    Simulator,
+};
+pub use verify::verify;
@@ -1,36 +1,165 @@
-use std::path::{Path, PathBuf};
+use std::{
+    ops::Range,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Instant,
+};
 
 use anyhow::Context as _;
-use clap::Parser;
-use common::Config;
+use clap::{
+    Parser,
+    Subcommand,
+};
+use common::{
+    Config,
+    Render,
+    Tile,
+};
 use graphics::{
     wgpu,
-    Context,
+    AdapterSelector,
 };
-use hardware_renderer::Renderer as HardwareRenderer;
-use profiler::{
-    gpu::GpuProfiler,
-    PuffinStream as _,
+use kerrbhy::{
+    output::{
+        self,
+        Format,
+        Frame,
+    },
+    partial::{
+        Encoding,
+        Partial,
+    },
+    stats::{
+        timed,
+        ImageStats,
+        MemoryStats,
+        Stats,
+        Timings,
+    },
+    verify::Tolerance,
+    RendererKind,
+    Simulator,
 };
-use software_renderer::Renderer as SoftwareRenderer;
 use time::format_description::well_known::Rfc3339;
 
+/// The quality of JPEG output when it isn't chosen.
+const DEFAULT_QUALITY: u8 = 90;
+
+// without a subcommand, the arguments of `render` are taken instead,
+// so `kerrbhy hardware 1920 1080` still works
+#[derive(Parser, Debug, Clone)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    arg_required_else_help = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    render: Option<RenderArgs>,
+
+    #[command(flatten)]
+    gpu: GpuArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct GpuArgs {
+    /// The index or name of the GPU to use, see `kerrbhy adapters`.
+    #[clap(long, global = true)]
+    adapter: Option<AdapterSelector>,
+
+    /// The graphics backend to use.
+    #[clap(long, global = true)]
+    backend: Option<Backend>,
+}
+
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
-enum RendererKind {
-    Hardware,
-    Software,
+enum Backend {
+    Vulkan,
+    Dx12,
+    Metal,
+    Gl,
 }
 
-enum Renderer {
-    Hardware {
-        renderer: HardwareRenderer,
-        profiler: Option<GpuProfiler>,
+impl From<Backend> for wgpu::Backends {
+    fn from(backend: Backend) -> Self {
+        match backend {
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+impl GpuArgs {
+    fn backends(&self) -> wgpu::Backends {
+        self.backend.map_or(wgpu::Backends::PRIMARY, Into::into)
+    }
+
+    fn context(&self) -> anyhow::Result<graphics::Context> {
+        kerrbhy::context_on(self.backends(), self.adapter.clone().unwrap_or_default())
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Render an image of a config.
+    Render(RenderArgs),
+    /// Render a config on both renderers and check that they agree.
+    Verify(VerifyArgs),
+    /// Time a fixed set of configs on the renderers.
+    Bench(BenchArgs),
+    /// Compare renders against a set of reference images.
+    Test(TestArgs),
+    /// Render every job listed in a manifest.
+    Batch(BatchArgs),
+    /// Create, check and upgrade config files.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Combine partial renders into a whole frame.
+    Merge(MergeArgs),
+    /// Accept render jobs from other machines.
+    Serve(ServeArgs),
+    /// Split a render between machines running `kerrbhy serve`.
+    Farm(FarmArgs),
+    /// List the GPUs that can be used with `--adapter`.
+    Adapters,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ConfigCommand {
+    /// Print the default config.
+    Default {
+        /// Writes the config to this path instead.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check that a config file can be loaded.
+    Validate {
+        /// The config files to check.
+        #[clap(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Rewrite a config file using the current schema.
+    ///
+    /// Fields missing from older configs are filled in with their defaults.
+    Upgrade {
+        /// The config file to upgrade.
+        path: PathBuf,
+
+        /// Overwrite the file rather than printing the upgraded config.
+        #[clap(long)]
+        in_place: bool,
     },
-    Software(SoftwareRenderer),
 }
 
-#[derive(Parser, Debug, Clone)]
-struct Args {
+#[derive(clap::Args, Debug, Clone)]
+struct RenderArgs {
     /// The kind of renderer to use.
     renderer: RendererKind,
 
@@ -40,201 +169,745 @@ struct Args {
     height: u32,
 
     /// The number of samples to compute.
-    /// 
+    ///
     /// Must be greater than 0.
-    /// 
+    ///
     /// The higher the number, the more frames are produced and a higher quality image will be produced.
     #[clap(short, long, default_value = "1", value_parser=clap::value_parser!(u32).range(1..),)]
     samples: u32,
 
+    /// Only compute a range of samples, e.g. `--sample-range 64..128`.
+    ///
+    /// The output is a partial render, see `kerrbhy merge`.
+    #[clap(long, value_name = "START..END", value_parser = parse_sample_range, conflicts_with = "samples")]
+    sample_range: Option<Range<u32>>,
+
+    /// Only render a tile of the frame, given as `x0,y0,x1,y1`.
+    ///
+    /// The output is a partial render, see `kerrbhy merge`.
+    #[clap(long, value_name = "X0,Y0,X1,Y1")]
+    tile: Option<Tile>,
+
     /// The config file to load.
-    /// 
+    ///
     /// For more interesting configs, save them in the simulator and load them here.
     #[clap(short, long)]
     config: Option<PathBuf>,
 
+    /// Overrides a field of the config, e.g. `--set disk.radius=5.0`.
+    ///
+    /// Can be given multiple times.
+    #[clap(long = "set", value_name = "KEY=VALUE", value_parser = parse_override)]
+    overrides: Vec<(String, String)>,
+
     /// Saves the frame output to disk.
     #[clap(long)]
     save: bool,
 
     /// Configures the output path of the frame on disk.
-    /// 
-    /// Defaults to `out.png`.
+    ///
+    /// Defaults to `out.png`. Use `-` to write the frame to stdout,
+    /// as a png unless another format is chosen.
     #[clap(long)]
     output: Option<PathBuf>,
 
+    /// The image format of the frame on disk.
+    ///
+    /// Inferred from the extension of the output path, falling back to `png`.
+    #[clap(long)]
+    format: Option<Format>,
+
+    /// The quality of JPEG output, from 1 to 100.
+    #[clap(
+        long,
+        default_value_t = DEFAULT_QUALITY,
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    quality: u8,
+
+    /// Saves the frame so far to `preview.png` every N samples.
+    ///
+    /// Useful for stopping bad renders early.
+    #[clap(long, value_name = "N", value_parser=clap::value_parser!(u32).range(1..))]
+    preview_every: Option<u32>,
+
+    /// Sets the fov so the black hole's shadow fills the frame,
+    /// leaving a fraction of the frame as a margin around it.
+    ///
+    /// The camera moves closer or further away if the fov would be too extreme.
+    #[clap(long, value_name = "MARGIN", num_args = 0..=1, default_missing_value = "0.1")]
+    auto_frame: Option<f32>,
+
+    /// Seeds the random numbers used by the render, overriding the config.
+    ///
+    /// Without it, the seed of the config is logged, so noisy renders can be reproduced exactly.
+    #[clap(long)]
+    seed: Option<u64>,
+
     /// Creates and shows trace information.
     #[clap(long)]
     flamegraph: bool,
+
+    /// Writes the cpu and gpu scopes of the render to this path, as a Chrome trace.
+    ///
+    /// Open it in `chrome://tracing` or Perfetto, no puffin_viewer needed.
+    #[clap(long, value_name = "PATH")]
+    chrome_trace: Option<PathBuf>,
+
+    /// Writes the min, mean, p95 and max GPU time of each scope to this path.
+    ///
+    /// Written as CSV if the path ends in `.csv`, otherwise as JSON.
+    #[clap(long, value_name = "PATH")]
+    gpu_summary: Option<PathBuf>,
+
+    /// Measures the angular diameter and centroid of the black hole's shadow.
+    ///
+    /// Renders without a disk measure best, as a disk in front splits the shadow.
+    #[clap(long, conflicts_with = "tile")]
+    shadow: bool,
+
+    /// Writes timings and other statistics of the render as JSON to this path.
+    #[clap(long)]
+    stats: Option<PathBuf>,
+
+    /// The number of threads the software renderer uses.
+    ///
+    /// Defaults to the number of cores.
+    #[clap(long, value_parser=clap::value_parser!(u32).range(1..))]
+    threads: Option<u32>,
+
+    /// Runs the software renderer at a low priority,
+    /// so the machine stays usable during long renders.
+    #[clap(long)]
+    low_priority: bool,
 }
 
-fn context() -> anyhow::Result<Context> {
-    profiling::scope!("Creating context");
+#[derive(clap::Args, Debug, Clone)]
+struct VerifyArgs {
+    /// The width of the images to compare.
+    width: u32,
+    /// The height of the images to compare.
+    height: u32,
+
+    /// The number of samples to compute on each renderer.
+    #[clap(short, long, default_value = "1", value_parser=clap::value_parser!(u32).range(1..),)]
+    samples: u32,
+
+    /// The config file to load.
+    #[clap(short, long)]
+    config: Option<PathBuf>,
+
+    /// Overrides a field of the config, e.g. `--set disk.radius=5.0`.
+    #[clap(long = "set", value_name = "KEY=VALUE", value_parser = parse_override)]
+    overrides: Vec<(String, String)>,
+
+    /// The minimum PSNR (in decibels) for the renderers to agree.
+    #[clap(long, default_value_t = Tolerance::default().psnr)]
+    psnr: f64,
 
-    // create graphics context without a window
-    let cb = graphics::ContextBuilder::new(
-        |adapter| adapter.features(),
-        wgpu::Limits::downlevel_defaults(),
-    );
+    /// The minimum SSIM for the renderers to agree.
+    #[clap(long, default_value_t = Tolerance::default().ssim)]
+    ssim: f64,
 
-    Ok(cb.build::<()>(None)?)
+    /// Saves an image of the per-pixel difference to this path.
+    #[clap(long)]
+    diff: Option<PathBuf>,
 }
 
-fn renderer(ctx: &Context, config: Config, args: &Args) -> anyhow::Result<Renderer> {
-    profiling::scope!("renderer::new");
+#[derive(clap::Args, Debug, Clone)]
+struct BenchArgs {
+    /// The number of samples to time for each config.
+    #[clap(short, long, default_value = "16", value_parser=clap::value_parser!(u32).range(1..),)]
+    samples: u32,
 
-    let renderer = match args.renderer {
-        RendererKind::Hardware => {
-            let mut renderer = HardwareRenderer::new(ctx);
-            // need to update the state with the correct config before computing
-            renderer.update(args.width, args.height, config);
+    /// The renderers to benchmark.
+    ///
+    /// Defaults to both.
+    #[clap(short, long)]
+    renderer: Vec<RendererKind>,
 
-            let profiler = if args.flamegraph {
-                Some(GpuProfiler::new(Default::default())?)
-            } else {
-                None
-            };
+    /// Writes the report as JSON to this path.
+    #[clap(long)]
+    json: Option<PathBuf>,
 
-            Renderer::Hardware { renderer, profiler }
-        }
-        RendererKind::Software => {
-            Renderer::Software(SoftwareRenderer::new(args.width, args.height, config))
-        }
-    };
+    /// Writes the report as a markdown table to this path.
+    ///
+    /// If no outputs are given, the markdown is printed instead.
+    #[clap(long)]
+    markdown: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct TestArgs {
+    /// The directory of reference images.
+    #[clap(long)]
+    golden: PathBuf,
+
+    /// The directory of configs to render.
+    #[clap(long, default_value = "scenes")]
+    configs: PathBuf,
+
+    /// The kind of renderer to use.
+    #[clap(short, long, default_value = "hardware")]
+    renderer: RendererKind,
 
-    Ok(renderer)
+    /// The width of each image.
+    #[clap(long, default_value = "256")]
+    width: u32,
+    /// The height of each image.
+    #[clap(long, default_value = "256")]
+    height: u32,
+
+    /// The number of samples to compute for each image.
+    #[clap(short, long, default_value = "8", value_parser=clap::value_parser!(u32).range(1..),)]
+    samples: u32,
+
+    /// The minimum SSIM for an image to match its reference.
+    #[clap(long, default_value = "0.98")]
+    ssim: f64,
+
+    /// Overwrite the reference images with new renders.
+    #[clap(long)]
+    update: bool,
 }
 
-fn hardware_frame(
-    renderer: &mut HardwareRenderer,
-    mut profiler: Option<&mut GpuProfiler>,
-    ctx: &Context,
-    sample: u32,
-) -> anyhow::Result<()> {
-    let device = ctx.device();
+#[derive(clap::Args, Debug, Clone)]
+struct BatchArgs {
+    /// The manifest of jobs to render.
+    manifest: PathBuf,
 
-    let mut encoder = device.create_command_encoder(&Default::default());
+    /// Render the jobs at the same time.
+    #[clap(long)]
+    parallel: bool,
+}
 
-    {
-        let mut encoder = if let Some(ref profiler) = profiler {
-            graphics::Encoder::profiled(
-                profiler,
-                &mut encoder,
-                format!("sample #{sample}"),
-                &device,
-            )
-        } else {
-            graphics::Encoder::Wgpu(&mut encoder)
-        };
+#[derive(clap::Args, Debug, Clone)]
+struct MergeArgs {
+    /// The partial renders to combine.
+    #[clap(required = true)]
+    inputs: Vec<PathBuf>,
 
-        renderer.compute(&mut encoder);
-    }
+    /// The output path of the merged frame.
+    #[clap(short, long, default_value = "out.png")]
+    output: PathBuf,
+}
 
-    if let Some(ref mut profiler) = profiler {
-        profiler.resolve_queries(&mut encoder);
-    }
+#[derive(clap::Args, Debug, Clone)]
+struct ServeArgs {
+    /// The address to listen on.
+    ///
+    /// Only this machine can connect by default.
+    /// Jobs aren't authenticated, so only listen on a trusted network, e.g. `0.0.0.0:7878`.
+    #[clap(default_value = "127.0.0.1:7878")]
+    addr: String,
+}
 
-    let queue = ctx.queue();
-    let gpu_start = puffin::now_ns();
+#[derive(clap::Args, Debug, Clone)]
+struct FarmArgs {
+    /// The kind of renderer the workers use.
+    renderer: RendererKind,
 
-    // submit the commands to finish the work
-    queue.submit(Some(encoder.finish()));
+    /// The width of the image to create.
+    width: u32,
+    /// The height of the image to create.
+    height: u32,
+
+    /// The total number of samples to compute.
+    #[clap(short, long, default_value = "1", value_parser=clap::value_parser!(u32).range(1..),)]
+    samples: u32,
+
+    /// The address of a worker, can be given multiple times.
+    #[clap(short, long = "worker", required = true)]
+    workers: Vec<String>,
 
-    if let Some(ref mut profiler) = profiler {
-        // record the GPU debug info for the flamegraph
+    /// The config file to load.
+    #[clap(short, long)]
+    config: Option<PathBuf>,
+
+    /// Overrides a field of the config, e.g. `--set disk.radius=5.0`.
+    #[clap(long = "set", value_name = "KEY=VALUE", value_parser = parse_override)]
+    overrides: Vec<(String, String)>,
 
-        profiler.end_frame()?;
+    /// The output path of the frame.
+    #[clap(short, long, default_value = "out.png")]
+    output: PathBuf,
+}
 
-        // wait for the wgpu to be finished to get debug data
-        device.poll(wgpu::Maintain::Wait).panic_on_timeout();
+fn parse_sample_range(s: &str) -> Result<Range<u32>, String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| format!("expected START..END, found `{s}`"))?;
 
-        match profiler.send_to_puffin(gpu_start, queue.get_timestamp_period(), None) {
-            profiler::StreamResult::Success => (),
-            profiler::StreamResult::Empty => (),
-            profiler::StreamResult::Disabled => log::warn!("puffin is disabled"),
-            profiler::StreamResult::Failure => log::error!("failed to send puffin data"),
-        }
+    let start = start.trim().parse::<u32>().map_err(|e| e.to_string())?;
+    let end = end.trim().parse::<u32>().map_err(|e| e.to_string())?;
+
+    if start >= end {
+        return Err(format!("sample range `{s}` is empty"));
     }
 
-    profiling::finish_frame!();
+    Ok(start..end)
+}
 
-    Ok(())
+fn parse_override(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, found `{s}`"))?;
+
+    Ok((key.trim().to_owned(), value.trim().to_owned()))
 }
 
-fn software_frame(renderer: &mut SoftwareRenderer, sample: u32) {
-    profiling::scope!("sample", format!("#{sample}"));
+fn load_config(path: Option<&Path>, overrides: &[(String, String)]) -> anyhow::Result<Config> {
+    // load the supplied config
+    let mut config = if let Some(path) = path {
+        Config::load_from_path(path)
+            .with_context(|| format!("failed to load config from {}", path.display()))?
+    } else {
+        log::warn!("using default config");
 
-    renderer.compute(sample);
+        Config::default()
+    };
 
-    profiling::finish_frame!();
+    for (key, value) in overrides {
+        config
+            .set(key, value)
+            .with_context(|| format!("failed to set {key} to {value}"))?;
+    }
+
+    Ok(config)
 }
 
-fn compute(args: &Args) -> anyhow::Result<()> {
-    let Args {
+fn compute(args: &RenderArgs, gpu: &GpuArgs) -> anyhow::Result<()> {
+    let RenderArgs {
         width,
         height,
         samples,
         ..
     } = *args;
 
-    // load the supplied config
-    let config = if let Some(path) = args.config.as_ref() {
-        Config::load_from_path(path)?
-    } else {
-        log::warn!("using default config");
+    let mut config = load_config(args.config.as_deref(), &args.overrides)?;
 
-        Config::default()
-    };
+    match args.seed {
+        Some(seed) => config.seed = seed,
+        // so the render can be reproduced with `--seed`
+        None => log::info!("seed: {}", config.seed),
+    }
+
+    if let Some(margin) = args.auto_frame {
+        match common::camera::frame_shadow(&config, margin) {
+            Some(framing) => framing.apply(&mut config.camera),
+            None => log::warn!("the camera is too close to frame the shadow"),
+        }
+    }
+
+    let tile = args.tile.unwrap_or(Tile::full(width, height));
+    if !tile.fits(width, height) {
+        anyhow::bail!("tile {tile:?} doesn't fit in a {width}x{height} frame");
+    }
+
+    let samples = args.sample_range.clone().unwrap_or(0..samples);
+    let partial = args.tile.is_some() || args.sample_range.is_some();
+
+    if partial && args.output.as_deref().is_some_and(output::is_stdout) {
+        anyhow::bail!("partial renders can't be written to stdout");
+    }
+
+    let start = Instant::now();
+    let fov = config.camera.fov();
+    let features = config.features;
 
     // create our context
-    let ctx = context()?;
+    let (ctx, context_secs) = timed(|| gpu.context());
+    let ctx = ctx?;
 
     // create the renderer
-    let mut renderer = renderer(&ctx, config, args)?;
+    let (sim, setup_secs) = timed(|| {
+        Simulator::with_tile(
+            args.renderer,
+            &ctx,
+            width,
+            height,
+            tile,
+            config,
+            args.flamegraph || args.chrome_trace.is_some() || args.gpu_summary.is_some(),
+        )
+    });
+    let mut sim = sim?;
+    sim.set_first_sample(samples.start);
+    sim.set_threads(args.threads.map(|n| n as usize), args.low_priority)?;
+
+    let mut stats = Stats {
+        renderer: args.renderer,
+        adapter: ctx.adapter().get_info().into(),
+        samples: samples.len() as u32,
+        timings: Timings {
+            context: context_secs,
+            setup: setup_secs,
+            ..Default::default()
+        },
+        memory: MemoryStats::default(),
+        image: None,
+        shadow: None,
+    };
 
     // compute the image
-    match &mut renderer {
-        Renderer::Hardware { renderer, profiler } => {
-            for sample in 0..samples {
-                hardware_frame(renderer, profiler.as_mut(), &ctx, sample)?;
+    for sample in 0..samples.len() as u32 {
+        let ((), secs) = timed(|| {
+            sim.compute_sample();
+
+            // wait for the gpu so each sample is timed on its own
+            if args.stats.is_some() {
+                ctx.device().poll(wgpu::Maintain::Wait).panic_on_timeout();
             }
+        });
+
+        stats.timings.samples.push(secs);
+
+        if args.preview_every.is_some_and(|n| (sample + 1) % n == 0) {
+            log::info!("saving preview after {} samples", sample + 1);
+
+            let bytes = sim.frame();
+            save_image(&bytes, tile.width(), tile.height(), Some(Path::new("preview.png")))?;
         }
-        Renderer::Software(renderer) => {
-            for sample in 0..samples {
-                software_frame(renderer, sample);
+    }
+
+    stats.memory = ctx.memory().into();
+
+    if let Some(path) = args.gpu_summary.as_deref() {
+        match sim.gpu_timings() {
+            Some(timings) => timings
+                .save(path)
+                .with_context(|| format!("failed to write gpu summary to {}", path.display()))?,
+            None => log::warn!("only the hardware renderer records gpu timings"),
+        }
+    }
+
+    if args.shadow {
+        let frame = sim.frame();
+
+        match common::shadow::measure(&frame, width, height, fov) {
+            Some(shadow) => {
+                eprintln!(
+                    "shadow: {:.3} degrees across, centered at ({:.3}, {:.3}) degrees",
+                    shadow.diameter, shadow.centroid.x, shadow.centroid.y
+                );
+                stats.shadow = Some(shadow);
             }
+            None => eprintln!("shadow: not found"),
         }
     }
 
     // save the frame if they requested it
     if args.save {
-        match renderer {
-            Renderer::Hardware { renderer, .. } => {
-                let frame_encoder = ctx.device().create_command_encoder(&Default::default());
-                let bytes = renderer.into_frame(frame_encoder);
-                save_image(&bytes, width, height, args.output.as_deref())?;
-            }
-            Renderer::Software(renderer) => {
-                let bytes = renderer.into_frame();
-                save_image(&bytes, width, height, args.output.as_deref())?;
+        let path = args.output.as_deref().unwrap_or(Path::new("out.png"));
+
+        let format = args
+            .format
+            .or_else(|| Format::from_path(path))
+            .unwrap_or(Format::Png);
+
+        let (frame, readback_secs) = timed(|| {
+            if format.is_linear() {
+                Frame::Hdr(sim.into_frame_linear())
+            } else if format.is_hdr() {
+                Frame::Hdr(sim.into_frame_hdr())
+            } else {
+                Frame::Ldr(sim.into_frame())
             }
+        });
+        let (result, save_secs) = timed(|| {
+            output::save_frame(
+                &frame,
+                tile.width(),
+                tile.height(),
+                path,
+                format,
+                args.quality,
+            )
+        });
+        result?;
+
+        stats.timings.readback = Some(readback_secs);
+        stats.timings.save = Some(save_secs);
+        stats.image = Some(ImageStats {
+            path: path.to_owned(),
+            width: tile.width(),
+            height: tile.height(),
+            tile: partial.then_some(tile),
+            bytes: frame.size_in_bytes(),
+        });
+
+        if partial {
+            let partial = Partial {
+                width,
+                height,
+                tile,
+                samples,
+                encoding: Encoding::new(features, format),
+            };
+            partial.save(path)?;
         }
     }
 
+    stats.timings.total = start.elapsed().as_secs_f64();
+
+    if let Some(path) = args.stats.as_deref() {
+        stats.save(path)?;
+    }
+
     profiling::finish_frame!();
 
     Ok(())
 }
 
-fn save_image(bytes: &[u8], width: u32, height: u32, path: Option<&Path>) -> anyhow::Result<()> {
-    profiling::scope!("Saving image");
+fn verify(args: &VerifyArgs, gpu: &GpuArgs) -> anyhow::Result<()> {
+    let config = load_config(args.config.as_deref(), &args.overrides)?;
 
-    let path = path.unwrap_or_else(|| Path::new("out.png"));
-    image::save_buffer(path, bytes, width, height, image::ColorType::Rgba8)?;
+    let ctx = gpu.context()?;
+
+    let tolerance = Tolerance {
+        psnr: args.psnr,
+        ssim: args.ssim,
+    };
+
+    let result = kerrbhy::verify(
+        &ctx,
+        &config,
+        args.width,
+        args.height,
+        args.samples,
+        tolerance,
+    )?;
+
+    println!("PSNR: {:.2} dB (minimum {:.2} dB)", result.psnr, tolerance.psnr);
+    println!("SSIM: {:.4} (minimum {:.4})", result.ssim, tolerance.ssim);
+
+    if let Some(path) = args.diff.as_deref() {
+        save_image(&result.diff, result.width, result.height, Some(path))?;
+    }
+
+    if result.agrees {
+        println!("renderers agree");
+
+        Ok(())
+    } else {
+        anyhow::bail!("renderers disagree")
+    }
+}
+
+fn bench(args: &BenchArgs, gpu: &GpuArgs) -> anyhow::Result<()> {
+    let ctx = gpu.context()?;
+
+    let kinds = if args.renderer.is_empty() {
+        vec![RendererKind::Hardware, RendererKind::Software]
+    } else {
+        args.renderer.clone()
+    };
+
+    let cases = kerrbhy::bench::default_cases();
+    let report = kerrbhy::bench::run(&ctx, &cases, &kinds, args.samples)?;
+
+    if let Some(path) = args.json.as_deref() {
+        std::fs::write(path, report.to_json()?)
+            .with_context(|| format!("failed to write report to {}", path.display()))?;
+    }
+
+    if let Some(path) = args.markdown.as_deref() {
+        std::fs::write(path, report.to_markdown())
+            .with_context(|| format!("failed to write report to {}", path.display()))?;
+    }
+
+    if args.json.is_none() && args.markdown.is_none() {
+        print!("{}", report.to_markdown());
+    }
+
+    Ok(())
+}
+
+fn test(args: &TestArgs, gpu: &GpuArgs) -> anyhow::Result<()> {
+    use kerrbhy::golden::{
+        Options,
+        Outcome,
+    };
+
+    let ctx = gpu.context()?;
+
+    let cases = kerrbhy::golden::load_cases(&args.configs)?;
+
+    let options = Options {
+        renderer: args.renderer,
+        width: args.width,
+        height: args.height,
+        samples: args.samples,
+        ssim: args.ssim,
+    };
+
+    let outcomes = kerrbhy::golden::run(&ctx, &cases, &args.golden, options, args.update)?;
+
+    let mut failures = 0;
+    for (name, outcome) in &outcomes {
+        match outcome {
+            Outcome::Passed { ssim } => println!("pass    {name} (ssim {ssim:.4})"),
+            Outcome::Failed { ssim, diff } => {
+                println!("FAIL    {name} (ssim {ssim:.4}, diff at {})", diff.display())
+            }
+            Outcome::Missing => println!("MISSING {name}"),
+            Outcome::Updated => println!("updated {name}"),
+        }
+
+        if outcome.is_failure() {
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{failures} of {} golden images failed", outcomes.len())
+    }
+
+    Ok(())
+}
+
+fn batch(args: &BatchArgs, gpu: &GpuArgs) -> anyhow::Result<()> {
+    let manifest = kerrbhy::batch::Manifest::load_from_path(&args.manifest)?;
+
+    let ctx = gpu.context()?;
+
+    kerrbhy::batch::run(&ctx, &manifest, args.parallel)
+}
+
+fn config(command: &ConfigCommand) -> anyhow::Result<()> {
+    let write = |config: &Config, path: Option<&Path>| -> anyhow::Result<()> {
+        match path {
+            Some(path) => {
+                let mut file = std::fs::File::create(path)
+                    .with_context(|| format!("failed to create {}", path.display()))?;
+                config.save(&mut file)?;
+            }
+            None => config.save(&mut std::io::stdout())?,
+        }
+
+        Ok(())
+    };
+
+    match command {
+        ConfigCommand::Default { output } => write(&Config::default(), output.as_deref()),
+        ConfigCommand::Validate { paths } => {
+            let mut failures = 0;
+            for path in paths {
+                // toml errors include the line and column of the problem
+                match Config::load_from_path(path) {
+                    Ok(_) => println!("ok      {}", path.display()),
+                    Err(e) => {
+                        println!("INVALID {}\n{e}", path.display());
+                        failures += 1;
+                    }
+                }
+            }
+
+            if failures > 0 {
+                anyhow::bail!("{failures} of {} configs are invalid", paths.len())
+            }
+
+            Ok(())
+        }
+        ConfigCommand::Upgrade { path, in_place } => {
+            let config = Config::load_from_path(path)
+                .with_context(|| format!("failed to load config from {}", path.display()))?;
+
+            write(&config, in_place.then_some(path.as_path()))
+        }
+    }
+}
+
+fn merge(args: &MergeArgs) -> anyhow::Result<()> {
+    let parts = args
+        .inputs
+        .iter()
+        .map(|path| {
+            let partial = Partial::load(path)?;
+            let image = image::open(path)
+                .with_context(|| format!("failed to open {}", path.display()))?
+                .into_rgba32f();
+
+            Ok((partial, image))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let (width, height, floats) = kerrbhy::partial::merge(&parts)?;
+
+    // every partial is raw, or none of them are
+    let encoded = parts[0].0.encoding != Encoding::Raw;
+
+    save_merged(floats, width, height, &args.output, encoded)
+}
+
+fn serve(args: &ServeArgs, gpu: &GpuArgs) -> anyhow::Result<()> {
+    let ctx = gpu.context()?;
+
+    kerrbhy::farm::serve(&ctx, args.addr.as_str())
+}
+
+fn farm(args: &FarmArgs) -> anyhow::Result<()> {
+    let config = load_config(args.config.as_deref(), &args.overrides)?;
+
+    let (width, height, floats) = kerrbhy::farm::distribute(
+        &args.workers,
+        &config,
+        args.renderer,
+        args.width,
+        args.height,
+        args.samples,
+    )?;
+
+    save_merged(floats, width, height, &args.output, config.features.encodes_srgb())
+}
+
+/// Save a merged frame of linear light, encoding it for display if it's `encoded`
+/// and the format of `path` doesn't hold linear light.
+fn save_merged(
+    mut floats: Vec<f32>,
+    width: u32,
+    height: u32,
+    path: &Path,
+    encoded: bool,
+) -> anyhow::Result<()> {
+    let format = Format::from_path(path).unwrap_or(Format::Png);
+
+    // merged in linear light, so it's encoded here like a single render would be
+    if encoded && !format.is_linear() {
+        common::srgb::encode_frame(&mut floats);
+    }
+
+    output::save_frame(&Frame::Hdr(floats), width, height, path, format, DEFAULT_QUALITY)
+}
+
+fn adapters(gpu: &GpuArgs) -> anyhow::Result<()> {
+    let adapters = graphics::enumerate_adapters(gpu.backends());
+
+    if adapters.is_empty() {
+        anyhow::bail!("no adapters found");
+    }
+
+    for (index, adapter) in adapters.iter().enumerate() {
+        let info = adapter.get_info();
+        let limits = adapter.limits();
+
+        println!("[{index}] {} ({:?}, {:?})", info.name, info.backend, info.device_type);
+        println!("    driver: {} {}", info.driver, info.driver_info);
+        println!(
+            "    max texture size: {}, max push constants: {} bytes",
+            limits.max_texture_dimension_2d, limits.max_push_constant_size
+        );
+    }
 
     Ok(())
 }
 
+fn save_image(bytes: &[u8], width: u32, height: u32, path: Option<&Path>) -> anyhow::Result<()> {
+    let path = path.unwrap_or_else(|| Path::new("out.png"));
+
+    kerrbhy::output::save_image(bytes, width, height, path)
+}
+
 fn init_logger() -> Result<(), fern::InitError> {
     const LOG_LEVEL_ENV: &str = "KERRBHY_LOG";
 
@@ -272,11 +945,7 @@ fn init_logger() -> Result<(), fern::InitError> {
     Ok(())
 }
 
-fn main() -> anyhow::Result<()> {
-    init_logger()?;
-
-    let args = Args::parse();
-
+fn render(args: &RenderArgs, gpu: &GpuArgs) -> anyhow::Result<()> {
     let bundle = if args.flamegraph {
         // if we're creating a flamegraph,
         // we need to enable puffin and
@@ -298,8 +967,18 @@ fn main() -> anyhow::Result<()> {
         None
     };
 
+    if args.chrome_trace.is_some() {
+        puffin::set_scopes_on(true);
+        profiler::record_chrome_trace();
+    }
+
     // start the computation
-    compute(&args)?;
+    compute(args, gpu)?;
+
+    if let Some(path) = args.chrome_trace.as_deref() {
+        profiler::export_chrome_trace(path)
+            .with_context(|| format!("failed to write chrome trace to {}", path.display()))?;
+    }
 
     if let Some((mut viewer, server)) = bundle {
         // wait for the viewer to close after we've finished computation
@@ -310,3 +989,28 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn main() -> anyhow::Result<()> {
+    init_logger()?;
+
+    let cli = Cli::parse();
+
+    // render is the default subcommand
+    let command = match cli.command {
+        Some(command) => command,
+        None => Command::Render(cli.render.context("expected a subcommand or a renderer")?),
+    };
+
+    match &command {
+        Command::Render(args) => render(args, &cli.gpu),
+        Command::Verify(args) => verify(args, &cli.gpu),
+        Command::Bench(args) => bench(args, &cli.gpu),
+        Command::Test(args) => test(args, &cli.gpu),
+        Command::Batch(args) => batch(args, &cli.gpu),
+        Command::Config(command) => config(command),
+        Command::Merge(args) => merge(args),
+        Command::Serve(args) => serve(args, &cli.gpu),
+        Command::Farm(args) => farm(args),
+        Command::Adapters => adapters(&cli.gpu),
+    }
+}
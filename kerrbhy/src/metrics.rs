@@ -0,0 +1,123 @@
+//! Image comparison metrics.
+//!
+//! All images are expected to be tightly packed `[r, g, b, a]` bytes,
+//! the alpha channel is ignored.
+
+/// Size of the square window used when computing [`ssim`].
+const SSIM_WINDOW: u32 = 8;
+
+/// Mean squared error over the color channels of two images.
+pub fn mse(a: &[u8], b: &[u8]) -> f64 {
+    assert_eq!(a.len(), b.len(), "images must be the same size");
+
+    let (sum, count) = a
+        .chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .flat_map(|(a, b)| a[..3].iter().zip(&b[..3]))
+        .fold((0.0, 0_u64), |(sum, count), (&a, &b)| {
+            let d = a as f64 - b as f64;
+            (sum + d * d, count + 1)
+        });
+
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+/// Peak signal-to-noise ratio in decibels.
+///
+/// Identical images have an infinite PSNR.
+pub fn psnr(a: &[u8], b: &[u8]) -> f64 {
+    let mse = mse(a, b);
+
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+}
+
+/// Mean structural similarity of the luminance of two images.
+///
+/// Returns a value in `[-1, 1]`, where `1` means the images are identical.
+pub fn ssim(a: &[u8], b: &[u8], width: u32, height: u32) -> f64 {
+    assert_eq!(a.len(), b.len(), "images must be the same size");
+    assert_eq!(a.len(), (width * height * 4) as usize, "incorrect dimensions");
+
+    // https://en.wikipedia.org/wiki/Structural_similarity
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let a = luminance(a);
+    let b = luminance(b);
+
+    let mut total = 0.0;
+    let mut windows = 0;
+
+    for wy in (0..height).step_by(SSIM_WINDOW as usize) {
+        for wx in (0..width).step_by(SSIM_WINDOW as usize) {
+            // the windows at the edges might be smaller
+            let w = SSIM_WINDOW.min(width - wx);
+            let h = SSIM_WINDOW.min(height - wy);
+            let n = (w * h) as f64;
+
+            let pixels = (wy..wy + h)
+                .flat_map(|y| (wx..wx + w).map(move |x| (y * width + x) as usize))
+                .map(|i| (a[i], b[i]));
+
+            let (mut mean_a, mut mean_b) = (0.0, 0.0);
+            for (a, b) in pixels.clone() {
+                mean_a += a;
+                mean_b += b;
+            }
+            mean_a /= n;
+            mean_b /= n;
+
+            let (mut var_a, mut var_b, mut covar) = (0.0, 0.0, 0.0);
+            for (a, b) in pixels {
+                var_a += (a - mean_a) * (a - mean_a);
+                var_b += (b - mean_b) * (b - mean_b);
+                covar += (a - mean_a) * (b - mean_b);
+            }
+            var_a /= n;
+            var_b /= n;
+            covar /= n;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covar + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+
+    if windows == 0 {
+        1.0
+    } else {
+        total / windows as f64
+    }
+}
+
+/// Creates an image of the absolute per-pixel difference between two images.
+pub fn diff(a: &[u8], b: &[u8]) -> Vec<u8> {
+    assert_eq!(a.len(), b.len(), "images must be the same size");
+
+    a.chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .flat_map(|(a, b)| {
+            [
+                a[0].abs_diff(b[0]),
+                a[1].abs_diff(b[1]),
+                a[2].abs_diff(b[2]),
+                u8::MAX,
+            ]
+        })
+        .collect()
+}
+
+fn luminance(image: &[u8]) -> Vec<f64> {
+    image
+        .chunks_exact(4)
+        .map(|p| 0.2126 * p[0] as f64 + 0.7152 * p[1] as f64 + 0.0722 * p[2] as f64)
+        .collect()
+}
@@ -0,0 +1,223 @@
+//! Writing rendered frames to disk.
+
+use std::{
+    io::{
+        Cursor,
+        Write,
+    },
+    path::Path,
+};
+
+use anyhow::Context as _;
+
+/// The image formats a frame can be saved as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// 8-bit PNG.
+    Png,
+    /// 16-bit PNG.
+    Png16,
+    /// 32-bit float TIFF, in linear light.
+    Tiff,
+    /// 8-bit JPEG, without alpha.
+    Jpeg,
+    /// 8-bit binary PPM, without alpha.
+    Ppm,
+    /// 32-bit float OpenEXR, in linear light.
+    Exr,
+}
+
+impl Format {
+    /// Infers the format from the extension of `path`.
+    ///
+    /// `.png` is always 8-bit, [`Format::Png16`] has to be chosen explicitly.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+
+        match ext.as_str() {
+            "png" => Some(Format::Png),
+            "tif" | "tiff" => Some(Format::Tiff),
+            "jpg" | "jpeg" => Some(Format::Jpeg),
+            "ppm" => Some(Format::Ppm),
+            "exr" => Some(Format::Exr),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the format stores more than 8 bits per channel.
+    pub fn is_hdr(self) -> bool {
+        matches!(self, Format::Png16 | Format::Tiff | Format::Exr)
+    }
+
+    /// Returns `true` if the format stores linear light, rather than colors encoded for display.
+    pub fn is_linear(self) -> bool {
+        matches!(self, Format::Tiff | Format::Exr)
+    }
+}
+
+/// A frame of `[r, g, b, a]` pixels.
+pub enum Frame {
+    Ldr(Vec<u8>),
+    Hdr(Vec<f32>),
+}
+
+impl Frame {
+    fn to_f32(&self) -> Vec<f32> {
+        match self {
+            Frame::Ldr(bytes) => bytes.iter().map(|&b| b as f32 / 255.0).collect(),
+            Frame::Hdr(floats) => floats.clone(),
+        }
+    }
+
+    fn to_u8(&self) -> Vec<u8> {
+        match self {
+            Frame::Ldr(bytes) => bytes.clone(),
+            Frame::Hdr(floats) => floats
+                .iter()
+                .map(|&f| (f.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect(),
+        }
+    }
+
+    fn to_u16(&self) -> Vec<u16> {
+        match self {
+            Frame::Ldr(bytes) => bytes.iter().map(|&b| b as u16 * 257).collect(),
+            Frame::Hdr(floats) => floats
+                .iter()
+                .map(|&f| (f.clamp(0.0, 1.0) * 65535.0).round() as u16)
+                .collect(),
+        }
+    }
+
+    /// The number of channels in the frame.
+    pub fn channels(&self) -> usize {
+        match self {
+            Frame::Ldr(bytes) => bytes.len(),
+            Frame::Hdr(floats) => floats.len(),
+        }
+    }
+
+    /// The number of bytes the frame takes in memory.
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            Frame::Ldr(bytes) => bytes.len(),
+            Frame::Hdr(floats) => std::mem::size_of_val(floats.as_slice()),
+        }
+    }
+}
+
+/// Saves a frame of `[r, g, b, a]` bytes to `path`.
+///
+/// The format is inferred from the extension of the path.
+pub fn save_image(bytes: &[u8], width: u32, height: u32, path: &Path) -> anyhow::Result<()> {
+    profiling::scope!("Saving image");
+
+    image::save_buffer(path, bytes, width, height, image::ColorType::Rgba8)?;
+
+    Ok(())
+}
+
+/// Saves a [`Frame`] to `path` as a specific [`Format`].
+///
+/// A path of `-` writes to stdout instead.
+/// `quality` is only used by [`Format::Jpeg`], from 1 to 100.
+pub fn save_frame(
+    frame: &Frame,
+    width: u32,
+    height: u32,
+    path: &Path,
+    format: Format,
+    quality: u8,
+) -> anyhow::Result<()> {
+    profiling::scope!("Saving image");
+
+    let encoded = encode_frame(frame, width, height, format, quality)?;
+
+    if is_stdout(path) {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&encoded)?;
+        stdout.flush()?;
+    } else {
+        std::fs::write(path, encoded)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the `path` means stdout.
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Encodes a [`Frame`] as a [`Format`] in memory.
+pub fn encode_frame(
+    frame: &Frame,
+    width: u32,
+    height: u32,
+    format: Format,
+    quality: u8,
+) -> anyhow::Result<Vec<u8>> {
+    use image::ImageEncoder as _;
+
+    if frame.channels() != (width * height * 4) as usize {
+        anyhow::bail!("frame doesn't match the size {width}x{height}");
+    }
+
+    // some encoders need to seek
+    let mut out = Cursor::new(Vec::new());
+
+    match format {
+        Format::Png => {
+            image::codecs::png::PngEncoder::new(&mut out).write_image(
+                &frame.to_u8(),
+                width,
+                height,
+                image::ExtendedColorType::Rgba8,
+            )?;
+        }
+        Format::Png16 => {
+            // the encoder expects native endian bytes
+            image::codecs::png::PngEncoder::new(&mut out).write_image(
+                bytemuck::cast_slice(&frame.to_u16()),
+                width,
+                height,
+                image::ExtendedColorType::Rgba16,
+            )?;
+        }
+        Format::Tiff => {
+            tiff::encoder::TiffEncoder::new(&mut out)?
+                .write_image::<tiff::encoder::colortype::RGBA32Float>(
+                    width,
+                    height,
+                    &frame.to_f32(),
+                )?;
+        }
+        Format::Jpeg => {
+            let image = image::RgbaImage::from_raw(width, height, frame.to_u8())
+                .expect("frame is the correct size");
+            // jpeg has no alpha channel
+            let image = image::DynamicImage::ImageRgba8(image).into_rgb8();
+
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality)
+                .encode_image(&image)?;
+        }
+        Format::Ppm => {
+            // binary ppm has no alpha channel either
+            write!(out, "P6\n{width} {height}\n255\n")?;
+            for pixel in frame.to_u8().chunks_exact(4) {
+                out.write_all(&pixel[..3])?;
+            }
+        }
+        Format::Exr => {
+            image::codecs::openexr::OpenExrEncoder::new(&mut out).write_image(
+                bytemuck::cast_slice(&frame.to_f32()),
+                width,
+                height,
+                image::ExtendedColorType::Rgba32F,
+            )?;
+        }
+    }
+
+    Ok(out.into_inner())
+}
@@ -0,0 +1,169 @@
+//! Partial renders that are merged into a whole frame.
+//!
+//! A partial render covers a [`Tile`] of the frame and a range of samples,
+//! so a large render can be split between several machines.
+//! Each partial image is saved with a `<image>.toml` file describing it.
+
+use std::{
+    ops::Range,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use anyhow::Context as _;
+use common::{
+    Features,
+    Tile,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::output::Format;
+
+/// How the colors of a partial image are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// Encoded as sRGB for display.
+    Srgb,
+    /// Linear light, encoded for display once it's merged.
+    Linear,
+    /// Never encoded, such as [`Features::LINEAR_OUTPUT`] or [`Features::POLARIZATION`].
+    Raw,
+}
+
+impl Encoding {
+    /// How a frame rendered with `features` is stored as a `format`.
+    pub fn new(features: Features, format: Format) -> Self {
+        if !features.encodes_srgb() {
+            Encoding::Raw
+        } else if format.is_linear() {
+            Encoding::Linear
+        } else {
+            Encoding::Srgb
+        }
+    }
+}
+
+/// Describes which part of a frame a partial image holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Partial {
+    /// The width of the whole frame.
+    pub width: u32,
+    /// The height of the whole frame.
+    pub height: u32,
+    pub tile: Tile,
+    pub samples: Range<u32>,
+    pub encoding: Encoding,
+}
+
+impl Partial {
+    /// The path of the description for a partial image.
+    pub fn metadata_path(image: &Path) -> PathBuf {
+        let mut path = image.as_os_str().to_owned();
+        path.push(".toml");
+        path.into()
+    }
+
+    /// Loads the description of a partial image.
+    pub fn load(image: &Path) -> anyhow::Result<Self> {
+        let path = Self::metadata_path(image);
+
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Saves the description next to a partial image.
+    pub fn save(&self, image: &Path) -> anyhow::Result<()> {
+        let path = Self::metadata_path(image);
+
+        std::fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// The weight of the partial image when merging,
+    /// the number of samples it accumulated.
+    pub fn weight(&self) -> u32 {
+        self.samples.len() as u32
+    }
+}
+
+/// Merges partial images into a whole frame of `[r, g, b, a]` floats.
+///
+/// Overlapping partials are averaged, weighted by their sample counts.
+/// [`Encoding::Srgb`] partials are decoded first, so they're averaged in linear light.
+/// Returns the size of the frame along with the floats,
+/// which are linear light unless the partials are [`Encoding::Raw`].
+pub fn merge(parts: &[(Partial, image::Rgba32FImage)]) -> anyhow::Result<(u32, u32, Vec<f32>)> {
+    profiling::scope!("merge");
+
+    let Some((first, _)) = parts.first() else {
+        anyhow::bail!("nothing to merge");
+    };
+    let (width, height) = (first.width, first.height);
+
+    let mut sum = vec![[0.0_f64; 4]; (width * height) as usize];
+    let mut weights = vec![0_u64; (width * height) as usize];
+
+    for (partial, image) in parts {
+        let Partial { tile, .. } = partial;
+
+        if (partial.width, partial.height) != (width, height) {
+            anyhow::bail!(
+                "partial is for a {}x{} frame, expected {width}x{height}",
+                partial.width,
+                partial.height
+            );
+        }
+
+        if (partial.encoding == Encoding::Raw) != (first.encoding == Encoding::Raw) {
+            anyhow::bail!("partials of raw frames can't be merged with partials of colors");
+        }
+
+        if !tile.fits(width, height) || image.dimensions() != (tile.width(), tile.height()) {
+            anyhow::bail!("partial image doesn't match its tile {tile:?}");
+        }
+
+        let weight = partial.weight();
+
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let index = ((y + tile.min.y) * width + x + tile.min.x) as usize;
+
+            let mut pixel = pixel.0;
+            if partial.encoding == Encoding::Srgb {
+                for channel in &mut pixel[..3] {
+                    *channel = common::srgb::decode(*channel);
+                }
+            }
+
+            for (sum, channel) in sum[index].iter_mut().zip(pixel) {
+                *sum += channel as f64 * weight as f64;
+            }
+            weights[index] += weight as u64;
+        }
+    }
+
+    let uncovered = weights.iter().filter(|&&w| w == 0).count();
+    if uncovered > 0 {
+        log::warn!("{uncovered} pixels aren't covered by any partial");
+    }
+
+    let floats = sum
+        .into_iter()
+        .zip(weights)
+        .flat_map(|(sum, weight)| {
+            // pixels without any samples are left transparent
+            sum.map(|channel| (channel / weight.max(1) as f64) as f32)
+        })
+        .collect();
+
+    Ok((width, height, floats))
+}
@@ -0,0 +1,382 @@
+use common::{
+    Config,
+    Render,
+    Tile,
+};
+use graphics::{
+    wgpu,
+    AdapterSelector,
+    Context,
+};
+use hardware_renderer::Renderer as HardwareRenderer;
+use profiler::{
+    gpu::GpuProfiler,
+    TimingSummary,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use software_renderer::Renderer as SoftwareRenderer;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RendererKind {
+    #[default]
+    Hardware,
+    Software,
+}
+
+/// A renderer of either kind, ready to compute samples.
+///
+/// Samples are computed through [`Render`], the same as either renderer on its own.
+pub enum Simulator {
+    Hardware {
+        renderer: Box<HardwareRenderer>,
+        profiler: Option<GpuProfiler>,
+        /// The GPU timings of every sample, when profiled.
+        timings: TimingSummary,
+    },
+    Software(Box<SoftwareRenderer>),
+}
+
+/// Create a graphics [`Context`] without a window.
+pub fn context() -> anyhow::Result<Context> {
+    context_on(wgpu::Backends::PRIMARY, AdapterSelector::Auto)
+}
+
+/// Create a graphics [`Context`] without a window, on a chosen adapter.
+pub fn context_on(backends: wgpu::Backends, adapter: AdapterSelector) -> anyhow::Result<Context> {
+    profiling::scope!("Creating context");
+
+    // create graphics context without a window
+    let cb = graphics::ContextBuilder::new(HardwareRenderer::FEATURES, HardwareRenderer::limits())
+        .with_optional_features(profiler::FEATURES)
+        // larger skies and renders, as far as the adapter allows
+        .with_optional_limits(wgpu::Limits {
+            max_texture_dimension_2d: u32::MAX,
+            max_buffer_size: u64::MAX,
+            ..HardwareRenderer::limits()
+        })
+        .with_backends(backends)
+        .with_adapter(adapter);
+
+    Ok(cb.build::<()>(None)?)
+}
+
+impl Simulator {
+    /// Create a hardware [`Simulator`], sized with [`Render::update`] before computing.
+    pub fn hardware(ctx: &Context) -> Self {
+        Simulator::Hardware {
+            renderer: Box::new(HardwareRenderer::new(ctx)),
+            profiler: None,
+            timings: TimingSummary::new(),
+        }
+    }
+
+    /// Create a software [`Simulator`], sized with [`Render::update`] before computing.
+    ///
+    /// Unlike [`Self::hardware`], no graphics [`Context`] is needed.
+    pub fn software() -> Self {
+        Simulator::Software(Box::new(SoftwareRenderer::new(1, 1, Config::default())))
+    }
+
+    /// Create a new [`Simulator`] of the given `kind`.
+    ///
+    /// When `profiled` is set, hardware samples record GPU timings,
+    /// for puffin and [`Self::gpu_timings`].
+    pub fn new(
+        kind: RendererKind,
+        ctx: &Context,
+        width: u32,
+        height: u32,
+        config: Config,
+        profiled: bool,
+    ) -> anyhow::Result<Self> {
+        let tile = Tile::full(width, height);
+
+        Self::with_tile(kind, ctx, width, height, tile, config, profiled)
+    }
+
+    /// Create a new [`Simulator`] that only renders a `tile` of the frame.
+    pub fn with_tile(
+        kind: RendererKind,
+        ctx: &Context,
+        width: u32,
+        height: u32,
+        tile: Tile,
+        config: Config,
+        profiled: bool,
+    ) -> anyhow::Result<Self> {
+        profiling::scope!("renderer::new");
+
+        let simulator = match kind {
+            RendererKind::Hardware => {
+                let mut renderer = HardwareRenderer::new(ctx);
+                // need to update the state with the correct config before computing
+                renderer.update_tile(width, height, tile, config);
+
+                let profiler = if profiled {
+                    Some(GpuProfiler::new(Default::default())?)
+                } else {
+                    None
+                };
+
+                Simulator::Hardware {
+                    renderer: Box::new(renderer),
+                    profiler,
+                    timings: TimingSummary::new(),
+                }
+            }
+            RendererKind::Software => Simulator::Software(Box::new(SoftwareRenderer::with_tile(
+                width, height, tile, config,
+            ))),
+        };
+
+        Ok(simulator)
+    }
+
+    /// Limit the number of threads used by the software renderer,
+    /// optionally running them at a low priority.
+    ///
+    /// Has no effect on the hardware renderer.
+    pub fn set_threads(&mut self, count: Option<usize>, low_priority: bool) -> anyhow::Result<()> {
+        match self {
+            Simulator::Hardware { .. } => {
+                if count.is_some() || low_priority {
+                    log::warn!("thread options are ignored by the hardware renderer");
+                }
+            }
+            Simulator::Software(renderer) => renderer.set_threads(count, low_priority)?,
+        }
+
+        Ok(())
+    }
+
+    /// Seed the samples as if `first` samples had already been computed.
+    pub fn set_first_sample(&mut self, first: u32) {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.set_first_sample(first),
+            Simulator::Software(renderer) => renderer.set_first_sample(first),
+        }
+    }
+
+    /// The kind of renderer being used.
+    pub fn kind(&self) -> RendererKind {
+        match self {
+            Simulator::Hardware { .. } => RendererKind::Hardware,
+            Simulator::Software(_) => RendererKind::Software,
+        }
+    }
+
+    /// The hardware renderer, such as to draw the texture it renders to.
+    pub fn as_hardware(&self) -> Option<&HardwareRenderer> {
+        match self {
+            Simulator::Hardware { renderer, .. } => Some(renderer),
+            Simulator::Software(_) => None,
+        }
+    }
+
+    /// The hardware renderer, to record samples into an existing encoder.
+    pub fn as_hardware_mut(&mut self) -> Option<&mut HardwareRenderer> {
+        match self {
+            Simulator::Hardware { renderer, .. } => Some(renderer),
+            Simulator::Software(_) => None,
+        }
+    }
+
+    /// The software renderer, for the options only it has.
+    pub fn as_software_mut(&mut self) -> Option<&mut SoftwareRenderer> {
+        match self {
+            Simulator::Hardware { .. } => None,
+            Simulator::Software(renderer) => Some(renderer),
+        }
+    }
+
+    /// Replace the equirectangular map of the sky, restarting accumulation.
+    pub fn set_stars(&mut self, image: &image::DynamicImage) {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.set_stars(image),
+            Simulator::Software(renderer) => {
+                let texture = software_renderer::Texture2D::from_image(image.to_rgba32f());
+                renderer.set_sky(software_renderer::Sky::Equirectangular(texture));
+            }
+        }
+    }
+
+    /// The GPU timings of the samples computed so far, if they're profiled.
+    pub fn gpu_timings(&self) -> Option<&TimingSummary> {
+        match self {
+            Simulator::Hardware {
+                profiler: Some(_),
+                timings,
+                ..
+            } => Some(timings),
+            _ => None,
+        }
+    }
+
+    /// Convert the [`Simulator`] into floats of linear light, see [`Render::frame_linear`].
+    pub fn into_frame_linear(self) -> Vec<f32> {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.frame_linear(),
+            Simulator::Software(renderer) => renderer.into_frame_linear(),
+        }
+    }
+
+    /// Convert the [`Simulator`] into floats representing the frame output.
+    pub fn into_frame_hdr(self) -> Vec<f32> {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.frame_hdr(),
+            Simulator::Software(renderer) => renderer.into_frame_hdr(),
+        }
+    }
+}
+
+impl Render for Simulator {
+    fn update(&mut self, width: u32, height: u32, config: Config) {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.update(width, height, config),
+            Simulator::Software(renderer) => renderer.update(width, height, config),
+        }
+    }
+
+    fn must_render(&self) -> bool {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.must_render(),
+            Simulator::Software(renderer) => renderer.must_render(),
+        }
+    }
+
+    /// Compute a sample, recording its GPU timings when profiled.
+    fn compute_sample(&mut self) {
+        let sample = self.sample_count();
+
+        match self {
+            Simulator::Hardware {
+                renderer,
+                profiler,
+                timings,
+            } => hardware_frame(renderer, profiler.as_mut(), timings, sample),
+            Simulator::Software(renderer) => software_frame(renderer, sample),
+        }
+    }
+
+    fn sample_count(&self) -> u32 {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.sample_count(),
+            Simulator::Software(renderer) => renderer.sample_count(),
+        }
+    }
+
+    fn frame(&self) -> Vec<u8> {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.frame(),
+            Simulator::Software(renderer) => renderer.frame(),
+        }
+    }
+
+    /// The hardware renderer accumulates in 8 bits,
+    /// so only the software renderer gains any precision.
+    fn frame_hdr(&self) -> Vec<f32> {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.frame_hdr(),
+            Simulator::Software(renderer) => renderer.frame_hdr(),
+        }
+    }
+
+    fn frame_linear(&self) -> Vec<f32> {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.frame_linear(),
+            Simulator::Software(renderer) => renderer.frame_linear(),
+        }
+    }
+
+    fn into_frame(self) -> Vec<u8> {
+        match self {
+            Simulator::Hardware { renderer, .. } => renderer.into_frame(),
+            Simulator::Software(renderer) => renderer.into_frame(),
+        }
+    }
+}
+
+fn hardware_frame(
+    renderer: &mut HardwareRenderer,
+    mut profiler: Option<&mut GpuProfiler>,
+    summary: &mut TimingSummary,
+    sample: u32,
+) {
+    profiling::scope!("sample", format!("#{sample}"));
+
+    let device = renderer.device().clone();
+    let queue = renderer.queue().clone();
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+
+    {
+        let mut encoder = if let Some(ref profiler) = profiler {
+            graphics::Encoder::profiled(
+                profiler,
+                &mut encoder,
+                // the same label each sample, so the timings can be summarised
+                "sample",
+                &device,
+            )
+        } else {
+            graphics::Encoder::Wgpu(&mut encoder)
+        };
+
+        renderer.compute(&mut encoder);
+    }
+
+    if let Some(ref mut profiler) = profiler {
+        profiler.resolve_queries(&mut encoder);
+    }
+
+    let gpu_start = puffin::now_ns();
+
+    // submit the commands to finish the work
+    queue.submit(Some(encoder.finish()));
+
+    if let Some(ref mut profiler) = profiler {
+        // record the GPU debug info for the flamegraph
+
+        if let Err(e) = profiler.end_frame() {
+            log::error!("failed to end the GPU profiler frame: {e}");
+        }
+
+        // wait for the wgpu to be finished to get debug data
+        device.poll(wgpu::Maintain::Wait).panic_on_timeout();
+
+        match profiler.process_finished_frame(queue.get_timestamp_period()) {
+            Some(timings) => {
+                summary.add(&timings);
+
+                // the timings might only be summarised
+                if puffin::are_scopes_on() {
+                    send_to_puffin(&timings, gpu_start);
+                }
+            }
+            None => log::error!("failed to read the GPU timings"),
+        }
+    }
+
+    profiling::finish_frame!();
+}
+
+fn send_to_puffin(timings: &[profiler::gpu::GpuTimerQueryResult], gpu_start: i64) {
+    match profiler::send_timings_to_puffin(timings, gpu_start, None) {
+        profiler::StreamResult::Success => (),
+        profiler::StreamResult::Empty => (),
+        profiler::StreamResult::Disabled => log::warn!("puffin is disabled"),
+        profiler::StreamResult::Failure => log::error!("failed to send puffin data"),
+    }
+}
+
+fn software_frame(renderer: &mut SoftwareRenderer, sample: u32) {
+    profiling::scope!("sample", format!("#{sample}"));
+
+    renderer.compute_sample();
+
+    profiling::finish_frame!();
+}
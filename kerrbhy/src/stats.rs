@@ -0,0 +1,129 @@
+//! Machine-readable statistics of a render.
+
+use std::{
+    collections::BTreeMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Instant,
+};
+
+use anyhow::Context as _;
+use common::{
+    shadow::Shadow,
+    Tile,
+};
+use graphics::wgpu;
+use serde::Serialize;
+
+use crate::RendererKind;
+
+/// Everything recorded about a single render.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub renderer: RendererKind,
+    pub adapter: AdapterStats,
+    pub samples: u32,
+    pub timings: Timings,
+    pub memory: MemoryStats,
+    /// Set once the image is saved.
+    pub image: Option<ImageStats>,
+    /// The shadow measured with `--shadow`.
+    pub shadow: Option<Shadow>,
+}
+
+/// Wall-clock time spent in each stage of the render, in seconds.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Timings {
+    pub context: f64,
+    pub setup: f64,
+    /// Time of each sample, waiting for the GPU to finish.
+    pub samples: Vec<f64>,
+    pub readback: Option<f64>,
+    pub save: Option<f64>,
+    pub total: f64,
+}
+
+/// GPU memory allocated by the renderer, in bytes.
+///
+/// An estimate, added up from the sizes of the textures and buffers.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryStats {
+    /// In use once every sample is computed.
+    pub total: u64,
+    /// The most in use at once.
+    pub peak: u64,
+    pub by_label: BTreeMap<String, u64>,
+}
+
+/// The GPU used to create the context.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdapterStats {
+    pub name: String,
+    pub vendor: u32,
+    pub device: u32,
+    pub device_type: String,
+    pub driver: String,
+    pub driver_info: String,
+    pub backend: String,
+}
+
+/// The image written to disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageStats {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// The tile of the frame the image holds, if it's a partial render.
+    pub tile: Option<Tile>,
+    pub bytes: usize,
+}
+
+impl From<wgpu::AdapterInfo> for AdapterStats {
+    fn from(info: wgpu::AdapterInfo) -> Self {
+        Self {
+            name: info.name,
+            vendor: info.vendor,
+            device: info.device,
+            device_type: format!("{:?}", info.device_type),
+            driver: info.driver,
+            driver_info: info.driver_info,
+            backend: format!("{:?}", info.backend),
+        }
+    }
+}
+
+impl From<&graphics::MemoryTracker> for MemoryStats {
+    fn from(memory: &graphics::MemoryTracker) -> Self {
+        Self {
+            total: memory.total().total(),
+            peak: memory.peak(),
+            by_label: memory
+                .by_label()
+                .into_iter()
+                .map(|(label, usage)| (label, usage.total()))
+                .collect(),
+        }
+    }
+}
+
+impl Stats {
+    /// Writes the stats as pretty JSON to `path`.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+
+        std::fs::write(path, json)
+            .with_context(|| format!("failed to write stats to {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Runs `f`, returning its result and how long it took in seconds.
+pub fn timed<T>(f: impl FnOnce() -> T) -> (T, f64) {
+    let start = Instant::now();
+    let result = f();
+
+    (result, start.elapsed().as_secs_f64())
+}
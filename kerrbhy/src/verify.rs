@@ -0,0 +1,86 @@
+//! Checks that the hardware and software renderers agree.
+
+use common::{
+    Config,
+    Render,
+};
+use graphics::Context;
+
+use crate::{
+    metrics,
+    RendererKind,
+    Simulator,
+};
+
+/// The minimum similarity two frames need to be considered in agreement.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    /// Minimum peak signal-to-noise ratio, in decibels.
+    pub psnr: f64,
+    /// Minimum structural similarity.
+    pub ssim: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            psnr: 25.0,
+            ssim: 0.9,
+        }
+    }
+}
+
+/// The result of comparing the output of both renderers.
+pub struct Verification {
+    pub width: u32,
+    pub height: u32,
+
+    pub psnr: f64,
+    pub ssim: f64,
+    /// Absolute per-pixel difference between the frames, as `[r, g, b, a]` bytes.
+    pub diff: Vec<u8>,
+
+    /// `true` if the frames are within the [`Tolerance`].
+    pub agrees: bool,
+}
+
+/// Render `config` on both the hardware and software renderers and compare
+/// the results.
+pub fn verify(
+    ctx: &Context,
+    config: &Config,
+    width: u32,
+    height: u32,
+    samples: u32,
+    tolerance: Tolerance,
+) -> anyhow::Result<Verification> {
+    profiling::scope!("verify");
+
+    let render = |kind| -> anyhow::Result<Vec<u8>> {
+        let mut sim = Simulator::new(kind, ctx, width, height, config.clone(), false)?;
+
+        for _ in 0..samples {
+            sim.compute_sample();
+        }
+
+        Ok(sim.into_frame())
+    };
+
+    let hardware = render(RendererKind::Hardware)?;
+    let software = render(RendererKind::Software)?;
+
+    let psnr = metrics::psnr(&hardware, &software);
+    let ssim = metrics::ssim(&hardware, &software, width, height);
+    let diff = metrics::diff(&hardware, &software);
+
+    let agrees = psnr >= tolerance.psnr && ssim >= tolerance.ssim;
+
+    Ok(Verification {
+        width,
+        height,
+        psnr,
+        ssim,
+        diff,
+        agrees,
+    })
+}
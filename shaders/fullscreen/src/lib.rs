@@ -5,17 +5,23 @@ use std::sync::Arc;
 use graphics::{
     wgpu,
     Encoder,
+    RenderPass,
 };
 
 pub struct Fullscreen {
     device: Arc<wgpu::Device>,
     pipeline: wgpu::RenderPipeline,
+    /// Draws in false color, showing the exposure of each pixel.
+    exposure: wgpu::RenderPipeline,
     sampler: wgpu::Sampler,
+
+    false_color: bool,
 }
 
 impl Fullscreen {
     pub fn new(ctx: &graphics::Context) -> Self {
         let device = ctx.device();
+        let format = ctx.view_format().unwrap();
 
         let module = shader::create_shader_module(&device);
         let layout = shader::create_pipeline_layout(&device);
@@ -24,32 +30,61 @@ impl Fullscreen {
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&layout),
-            vertex,
-            fragment: Some(wgpu::FragmentState {
-                module: &module,
-                entry_point: shader::ENTRY_FRAG,
-                targets: &[Some(wgpu::ColorTargetState::from(
-                    ctx.view_format().unwrap(),
-                ))],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
+        let create_pipeline = |entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&layout),
+                vertex: vertex.clone(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState::from(format))],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        // the colors are already srgb, so they're decoded for an srgb target to encode again
+        let (pipeline, exposure) = if format.is_srgb() {
+            (
+                create_pipeline(shader::ENTRY_FRAG_SRGB),
+                create_pipeline(shader::ENTRY_EXPOSURE_SRGB),
+            )
+        } else {
+            (
+                create_pipeline(shader::ENTRY_FRAG),
+                create_pipeline(shader::ENTRY_EXPOSURE),
+            )
+        };
 
         Fullscreen {
             device,
             pipeline,
+            exposure,
             sampler,
+
+            false_color: false,
+        }
+    }
+
+    /// Draw in false color from now on, to judge the exposure of the frame.
+    pub fn set_false_color(&mut self, false_color: bool) {
+        self.false_color = false_color;
+    }
+
+    fn pipeline(&self) -> &wgpu::RenderPipeline {
+        if self.false_color {
+            &self.exposure
+        } else {
+            &self.pipeline
         }
     }
 
@@ -60,15 +95,64 @@ impl Fullscreen {
         source: &wgpu::TextureView,
         target: &wgpu::TextureView,
     ) {
-        let binding = shader::bind_groups::BindGroup0::from_bindings(
+        let binding = self.bind_group(source);
+
+        let mut pass = self.begin_pass(encoder, target);
+        pass.set_pipeline(self.pipeline());
+        shader::set_bind_groups(&mut pass, &binding);
+        // only need to draw 3 vertices
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Draw `left` on the left of the `target`, and `right` on the right,
+    /// split `split` pixels from the left.
+    ///
+    /// `size` is the size of the `target`.
+    #[profiling::function]
+    pub fn draw_split(
+        &mut self,
+        encoder: &mut Encoder,
+        (left, right): (&wgpu::TextureView, &wgpu::TextureView),
+        target: &wgpu::TextureView,
+        split: u32,
+        size: (u32, u32),
+    ) {
+        let (width, height) = size;
+        let split = split.min(width);
+
+        let left = self.bind_group(left);
+        let right = self.bind_group(right);
+
+        let mut pass = self.begin_pass(encoder, target);
+        pass.set_pipeline(self.pipeline());
+
+        shader::set_bind_groups(&mut pass, &left);
+        pass.draw(0..3, 0..1);
+
+        // draw over the right of the split, an empty scissor rect isn't allowed
+        if split < width {
+            pass.set_scissor_rect(split, 0, width - split, height);
+            shader::set_bind_groups(&mut pass, &right);
+            pass.draw(0..3, 0..1);
+        }
+    }
+
+    fn bind_group(&self, source: &wgpu::TextureView) -> shader::bind_groups::BindGroup0 {
+        shader::bind_groups::BindGroup0::from_bindings(
             &self.device,
             shader::bind_groups::BindGroupLayout0 {
                 color_texture: source,
                 color_sampler: &self.sampler,
             },
-        );
+        )
+    }
 
-        let mut pass = encoder.begin_render_pass(
+    fn begin_pass<'a>(
+        &self,
+        encoder: &'a mut Encoder,
+        target: &'a wgpu::TextureView,
+    ) -> RenderPass<'a> {
+        encoder.begin_render_pass(
             "fullscreen",
             &self.device,
             wgpu::RenderPassDescriptor {
@@ -85,10 +169,6 @@ impl Fullscreen {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             },
-        );
-        pass.set_pipeline(&self.pipeline);
-        shader::set_bind_groups(&mut pass, &binding);
-        // only need to draw 3 vertices
-        pass.draw(0..3, 0..1);
+        )
     }
 }
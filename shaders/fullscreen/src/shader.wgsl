@@ -22,16 +22,81 @@ var color_texture: texture_2d<f32>;
 @group(0) @binding(1)
 var color_sampler: sampler;
 
-@fragment
-fn frag(in: VertexOutput) -> @location(0) vec4<f32> {
-    // for the fragment shader:
+fn sample_color(in: VertexOutput) -> vec3<f32> {
     // sample the input texture at the uv coordinate
-    // output the color with full alpha
     var uv = vec2<f32>(
         in.uv.x,
         1.0 - in.uv.y
     );
-    let color = textureSample(color_texture, color_sampler, uv).rgb;
-    return vec4<f32>(color, 1.0);
+    return textureSample(color_texture, color_sampler, uv).rgb;
+}
+
+@fragment
+fn frag(in: VertexOutput) -> @location(0) vec4<f32> {
+    // output the color with full alpha
+    return vec4<f32>(sample_color(in), 1.0);
+}
+
+@fragment
+fn frag_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the texture is already srgb, which the target would encode again
+    return vec4<f32>(srgbToLinear(sample_color(in)), 1.0);
+}
+
+
+// the luminance of middle grey, exposed correctly
+const MIDDLE_GREY: f32 = 0.18;
+// the darkest stop shown, relative to middle grey
+const MIN_STOPS: f32 = -6.0;
+
+fn srgbToLinear(color: vec3<f32>) -> vec3<f32> {
+    let lower = color / 12.92;
+    let higher = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(higher, lower, color < vec3<f32>(0.04045));
 }
 
+// blue for the darkest stops, through green at middle grey, to red at clipping
+fn heat(t: f32) -> vec3<f32> {
+    let r = clamp(2.0 * t - 1.0, 0.0, 1.0);
+    let g = clamp(2.0 - abs(4.0 * t - 2.0), 0.0, 1.0);
+    let b = clamp(1.0 - 2.0 * t, 0.0, 1.0);
+    return vec3<f32>(r, g, b);
+}
+
+fn false_color(in: VertexOutput) -> vec3<f32> {
+    // color each pixel by how many stops it is from middle grey,
+    // to judge the exposure of the frame
+    let color = sample_color(in);
+
+    if (max(color.r, max(color.g, color.b)) >= 1.0) {
+        // clipped
+        return vec3<f32>(1.0, 0.0, 1.0);
+    }
+
+    let luminance = dot(srgbToLinear(color), vec3<f32>(0.2126, 0.7152, 0.0722));
+    if (luminance <= 0.0) {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    // middle grey sits half way between the darkest stop and clipping
+    let stops = log2(luminance / MIDDLE_GREY);
+    let max_stops = log2(1.0 / MIDDLE_GREY);
+    let t = select(
+        0.5 + 0.5 * stops / max_stops,
+        0.5 - 0.5 * stops / MIN_STOPS,
+        stops < 0.0
+    );
+
+    return heat(clamp(t, 0.0, 1.0));
+}
+
+@fragment
+fn exposure(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(false_color(in), 1.0);
+}
+
+@fragment
+fn exposure_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    // shown the same as on a non srgb target
+    return vec4<f32>(srgbToLinear(false_color(in)), 1.0);
+}
@@ -3,7 +3,11 @@ mod shader;
 
 use std::sync::Arc;
 
-use common::Config;
+use common::{
+    camera::Observer,
+    Config,
+    Tile,
+};
 use graphics::{
     wgpu::{
         self,
@@ -14,27 +18,62 @@ use graphics::{
         TextureDescriptor,
         TextureView,
     },
+    Allocation,
     Encoder,
+    MemoryTracker,
 };
+use profiler::PipelineStatistics;
 use shader::bind_groups::*;
 
+/// The features a device needs to run the [`Marcher`].
+///
+/// The buffer is a `read_write` storage texture, which isn't supported by `rgba8unorm` everywhere.
+pub const FEATURES: wgpu::Features =
+    wgpu::Features::PUSH_CONSTANTS.union(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+
+/// The limits a device needs to run the [`Marcher`].
+pub fn limits() -> wgpu::Limits {
+    wgpu::Limits {
+        max_push_constant_size: std::mem::size_of::<shader::PushConstants>() as u32,
+        // fits the default sky
+        max_texture_dimension_2d: 4096,
+        ..wgpu::Limits::downlevel_defaults()
+    }
+}
+
 pub struct Marcher {
     device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
 
     pipeline: ComputePipeline,
 
     stars: Texture,
     star_sampler: Sampler,
 
+    /// The [`Ripple`](common::Ripple) of the config, written when it changes.
+    ripple: wgpu::Buffer,
+    /// The [`Star`](common::Star) of the config, written when it changes.
+    star: wgpu::Buffer,
+
     config: Config,
     sample_no: u32,
+    first_sample: u32,
 
+    resolution: [u32; 2],
+    tile: Tile,
     texture: Texture,
+
+    /// Counts the memory of the textures, once it's tracked.
+    memory: Option<MemoryTracker>,
+    allocations: Vec<Allocation>,
+
+    /// Counts the invocations of each dispatch, where the device supports it.
+    statistics: Option<PipelineStatistics>,
 }
 
 impl Marcher {
     #[profiling::function]
-    pub fn new(device: Arc<wgpu::Device>, queue: &wgpu::Queue) -> Self {
+    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> Self {
         let pipeline = shader::compute::create_comp_pipeline(&device);
 
         let stars = {
@@ -42,47 +81,78 @@ impl Marcher {
 
             let star_data = include_bytes!("../../../textures/starmap_2020_4k.exr");
             let star_image = image::load_from_memory(star_data).unwrap();
-            let star_bytes = star_image.to_rgba8();
-
-            device.create_texture_with_data(
-                queue,
-                &wgpu::TextureDescriptor {
-                    label: None,
-                    size: wgpu::Extent3d {
-                        width: star_image.width(),
-                        height: star_image.height(),
-                        depth_or_array_layers: 1,
-                    },
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: wgpu::TextureDimension::D2,
-                    format: wgpu::TextureFormat::Rgba8Unorm,
-                    usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::TEXTURE_BINDING,
-                    view_formats: &[],
-                },
-                wgpu::util::TextureDataOrder::MipMajor,
-                &star_bytes,
-            )
+
+            create_stars(&device, &queue, &star_image)
         };
         let star_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             mag_filter: wgpu::FilterMode::Linear,
             min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
             ..Default::default()
         });
 
+        let config = Config::default();
+
+        let ripple = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("ripple"),
+            contents: bytemuck::bytes_of(&ripple_uniform(&config)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let star = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("star"),
+            contents: bytemuck::bytes_of(&star_uniform(&config)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
         let texture = device.create_texture(&buffer_texture_descriptor());
 
+        // a few samples can be recorded each frame
+        let statistics = PipelineStatistics::new(&device, 16);
+
         Self {
             device,
+            queue,
             pipeline,
             texture,
             stars,
-            config: Config::default(),
+            ripple,
+            star,
+            config,
             sample_no: 0,
+            first_sample: 0,
+            resolution: [1, 1],
+            tile: Tile::full(1, 1),
             star_sampler,
+            memory: None,
+            allocations: Vec::new(),
+            statistics,
         }
     }
 
+    /// Count the memory of the textures with the `memory` tracker, as they're recreated.
+    pub fn track_memory(&mut self, memory: MemoryTracker) {
+        self.memory = Some(memory);
+        self.track_textures();
+    }
+
+    fn track_textures(&mut self) {
+        // released first, the old textures are already gone
+        self.allocations.clear();
+
+        if let Some(memory) = &self.memory {
+            self.allocations = vec![
+                memory.track_texture("marcher buffer", &self.texture),
+                memory.track_texture("stars", &self.stars),
+            ];
+        }
+    }
+
+    /// The invocations of each dispatch, if the device has
+    /// [`PIPELINE_STATISTICS_FEATURES`](profiler::PIPELINE_STATISTICS_FEATURES).
+    pub fn pipeline_statistics(&mut self) -> Option<&mut PipelineStatistics> {
+        self.statistics.as_mut()
+    }
+
     pub fn texture(&self) -> &wgpu::Texture {
         &self.texture
     }
@@ -91,27 +161,108 @@ impl Marcher {
         self.texture.create_view(&Default::default())
     }
 
+    /// Replace the equirectangular map of the sky, restarting accumulation.
+    pub fn set_stars(&mut self, image: &image::DynamicImage) {
+        self.stars = create_stars(&self.device, &self.queue, image);
+        self.track_textures();
+        self.sample_no = 0;
+    }
+
+    /// Rebuild the pipeline from new WGSL `source`, restarting accumulation.
+    ///
+    /// The bindings must match the ones generated at build time.
+    /// The old pipeline is kept if the new one fails to validate.
+    pub fn reload_shader(&mut self, source: &str) -> Result<(), wgpu::Error> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("reloaded shader"),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+        let layout = shader::create_pipeline_layout(&self.device);
+        let pipeline = self
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("reloaded pipeline"),
+                layout: Some(&layout),
+                module: &module,
+                entry_point: "comp",
+            });
+
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(error);
+        }
+
+        self.pipeline = pipeline;
+        self.sample_no = 0;
+
+        Ok(())
+    }
+
+    /// Every texture allocated by the [`Marcher`].
+    pub fn textures(&self) -> [&wgpu::Texture; 2] {
+        [&self.texture, &self.stars]
+    }
+
     pub fn size(&self) -> wgpu::Extent3d {
         self.texture().size()
     }
 
-    #[profiling::function]
+    /// The config being rendered.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The number of samples recorded since the buffer was cleared.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_no
+    }
+
     pub fn update(&mut self, width: u32, height: u32, cfg: Config) -> bool {
-        let dimensions_changed = width != self.texture.width() || height != self.texture.height();
+        self.update_tile(width, height, Tile::full(width, height), cfg)
+    }
+
+    /// Only render the `tile` of a `width` x `height` frame.
+    ///
+    /// The buffer is the size of the tile.
+    #[profiling::function]
+    pub fn update_tile(&mut self, width: u32, height: u32, tile: Tile, cfg: Config) -> bool {
+        let dimensions_changed = [width, height] != self.resolution || tile != self.tile;
         let config_changed = self.config != cfg;
 
+        if cfg.ripple != self.config.ripple {
+            self.queue
+                .write_buffer(&self.ripple, 0, bytemuck::bytes_of(&ripple_uniform(&cfg)));
+        }
+        if cfg.star != self.config.star {
+            self.queue
+                .write_buffer(&self.star, 0, bytemuck::bytes_of(&star_uniform(&cfg)));
+        }
+
         self.config = cfg;
+        self.resolution = [width, height];
+        self.tile = tile;
 
         let dirty = dimensions_changed || config_changed;
 
         if dirty {
-            self.recreate_buffer(width, height);
+            self.recreate_buffer(tile.width(), tile.height());
             self.sample_no = 0;
         }
 
         dirty
     }
 
+    /// Start the rng from `first` instead of the first sample.
+    ///
+    /// Accumulation still starts from an empty buffer,
+    /// so a range of samples can be rendered independently.
+    pub fn set_first_sample(&mut self, first: u32) {
+        self.first_sample = first;
+    }
+
     #[profiling::function]
     pub fn record(&mut self, encoder: &mut Encoder) {
         let [width, height] = [self.texture.width(), self.texture.height()];
@@ -120,6 +271,8 @@ impl Marcher {
             &self.device,
             BindGroupLayout0 {
                 buffer: &self.view(),
+                ripple: self.ripple.as_entire_buffer_binding(),
+                star: self.star.as_entire_buffer_binding(),
             },
         );
 
@@ -133,8 +286,14 @@ impl Marcher {
 
         let view = self.config.camera.view();
 
+        // the observer is passed in the last bit of the features
+        let observer = match self.config.camera.observer() {
+            Observer::Static => 0,
+            Observer::Infalling => 1 << 31,
+        };
+
         let push = shader::PushConstants {
-            features: self.config.features.bits(),
+            features: self.config.features.bits() | observer,
             origin: view.translation.into(),
             fov: self.config.camera.fov().as_f32(),
             transform: view.into(),
@@ -142,19 +301,38 @@ impl Marcher {
             disk_color: self.config.disk.color,
             disk_radius: self.config.disk.radius,
             disk_thickness: self.config.disk.thickness,
-            pad: 0,
+            seed: common::sample_seed(self.config.seed, self.first_sample + self.sample_no),
+            offset: self.tile.min,
+            resolution: self.resolution.into(),
         };
 
-        let mut pass = encoder.begin_compute_pass("marcher", &self.device);
-        pass.set_pipeline(&self.pipeline);
-        pass.set_push_constants(0, bytemuck::bytes_of(&push));
-        shader::set_bind_groups(&mut pass, &bind_group0, &bind_group1);
+        {
+            let mut pass = encoder.begin_compute_pass("marcher", &self.device);
+            pass.set_pipeline(&self.pipeline);
+            pass.set_push_constants(0, bytemuck::bytes_of(&push));
+            shader::set_bind_groups(&mut pass, &bind_group0, &bind_group1);
 
-        let [x, y, _z] = shader::compute::COMP_WORKGROUP_SIZE;
-        let x = (width as f32 / x as f32).ceil() as u32;
-        let y = (height as f32 / y as f32).ceil() as u32;
+            let [x, y, _z] = shader::compute::COMP_WORKGROUP_SIZE;
+            let x = (width as f32 / x as f32).ceil() as u32;
+            let y = (height as f32 / y as f32).ceil() as u32;
+
+            let counting = self
+                .statistics
+                .as_ref()
+                .is_some_and(|statistics| statistics.begin(&mut pass, "marcher"));
+
+            // tell the samples apart in captures
+            pass.insert_debug_marker(&format!("sample {}", self.sample_no));
+            pass.dispatch_workgroups(x, y, 1);
+
+            if let Some(statistics) = self.statistics.as_ref().filter(|_| counting) {
+                statistics.end(&mut pass);
+            }
+        }
 
-        pass.dispatch_workgroups(x, y, 1);
+        if let Some(statistics) = &mut self.statistics {
+            statistics.resolve(encoder.inner());
+        }
 
         self.sample_no += 1;
     }
@@ -169,9 +347,68 @@ impl Marcher {
             },
             ..buffer_texture_descriptor()
         });
+        self.track_textures();
+    }
+}
+
+fn ripple_uniform(config: &Config) -> shader::Ripple {
+    shader::Ripple {
+        amplitude: config.ripple.amplitude,
+        frequency: config.ripple.frequency,
+        time: config.ripple.time,
     }
 }
 
+fn star_uniform(config: &Config) -> shader::Star {
+    shader::Star {
+        radius: config.star.radius,
+        temperature: config.star.temperature,
+    }
+}
+
+/// Upload the sky `image`, with mipmaps to filter it from afar.
+fn create_stars(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &image::DynamicImage,
+) -> Texture {
+    let size = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("stars"),
+        size,
+        mip_level_count: size.max_mips(wgpu::TextureDimension::D2),
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+
+    queue.write_texture(
+        texture.as_image_copy(),
+        &image.to_rgba8(),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size.width),
+            rows_per_image: None,
+        },
+        size,
+    );
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    graphics::generate_mipmaps(&mut Encoder::from(&mut encoder), device, &texture);
+    queue.submit(Some(encoder.finish()));
+
+    texture
+}
+
 fn buffer_texture_descriptor() -> wgpu::TextureDescriptor<'static> {
     wgpu::TextureDescriptor {
         label: None,
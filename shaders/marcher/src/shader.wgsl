@@ -6,6 +6,8 @@ const MAX_STEPS: u32 = 128u;
 const MAX_BOUNCES: u32 = 4u;
 const DELTA: f32 = 0.05;
 const BLACKHOLE_RADIUS: f32 = 0.6;
+const SINGULARITY_RADIUS: f32 = 0.25 * BLACKHOLE_RADIUS;
+const STAR_LIMB_DARKENING: f32 = 0.6;
 const SKYBOX_RADIUS: f32 = 3.6;
 
 // Features
@@ -16,6 +18,13 @@ const AA            = 1u << 3;
 const RK4           = 1u << 4;
 const ADAPTIVE      = 1u << 5;
 const BLOOM         = 1u << 6;
+const LINEAR_OUTPUT = 1u << 7;
+const POLARIZATION  = 1u << 8;
+const INTERIOR      = 1u << 9;
+const RIPPLE        = 1u << 10;
+const STAR          = 1u << 11;
+// set by the camera's observer, rather than being a feature
+const INFALLING_OBSERVER = 1u << 31;
 
 struct PushConstants {
     origin: vec3<f32>,
@@ -25,12 +34,32 @@ struct PushConstants {
     disk_thickness: f32,
     sample: u32,
     features: u32,
-    pad: u32,
+    // the sample used to seed the rng
+    seed: u32,
     transform: mat4x4<f32>,
+    // where the buffer is placed in the whole frame
+    offset: vec2<u32>,
+    resolution: vec2<u32>,
+}
+
+// the push constants are full, so the ripple and star are passed separately
+struct Ripple {
+    amplitude: f32,
+    frequency: f32,
+    time: f32,
+}
+
+struct Star {
+    radius: f32,
+    temperature: f32,
 }
 
 @group(0) @binding(0)
 var buffer: texture_storage_2d<rgba8unorm, read_write>;
+@group(0) @binding(1)
+var<uniform> ripple: Ripple;
+@group(0) @binding(2)
+var<uniform> star: Star;
 
 @group(1) @binding(1)
 var star_sampler: sampler;
@@ -62,6 +91,23 @@ fn xyz2rgb(color_xyz: vec3<f32>) -> vec3<f32> {
     return color_xyz * XYZ2sRGB;
 }
 
+// Encode linear light with the sRGB transfer function
+// https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let cutoff = color < vec3<f32>(0.0031308);
+    let lower = color * 12.92;
+    let higher = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(higher, lower, cutoff);
+}
+
+// Decode the sRGB transfer function back into linear light
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let cutoff = color < vec3<f32>(0.04045);
+    let lower = color / 12.92;
+    let higher = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
+
 fn blackbodyXYZ(t: f32) -> vec3<f32> {
     // https://en.wikipedia.org/wiki/Planckian_locus
     let u = (0.860117757 + 1.54118254E-4 * t + 1.28641212E-7 * t * t) / (1.0 + 8.42420235E-4 * t + 7.08145163E-7 * t * t);
@@ -83,6 +129,35 @@ fn gravitational_field(p: vec3<f32>) -> vec3<f32> {
     return -6.0 * r / (R * R * R * R * R);
 }
 
+// The light leaving the surface of the star at p, seen by a ray moving with velocity v.
+fn starEmission(p: vec3<f32>, v: vec3<f32>) -> vec3<f32> {
+    // the linear limb darkening law, with a coefficient close to the sun's
+    // https://en.wikipedia.org/wiki/Limb_darkening
+    let mu = max(dot(normalize(p), -normalize(v)), 0.0);
+    let darkening = 1.0 - STAR_LIMB_DARKENING * (1.0 - mu);
+
+    // the planckian locus is only accurate within this range
+    let color = xyz2rgb(blackbodyXYZ(clamp(star.temperature, 1000.0, 15000.0)));
+    let brightest = max(max(color.r, max(color.g, color.b)), 0.01);
+
+    return darkening * max(color, vec3<f32>(0.0)) / brightest;
+}
+
+// A toy gravitational wave, a rotating quadrupole spreading out from the black hole.
+// Not a solution of GR, but it lenses the sky in waves that move outward.
+fn rippleField(p: vec3<f32>) -> vec3<f32> {
+    let r = length(p);
+
+    // an outgoing spiral with two arms, like the waves from a binary
+    let phase = TAU * ripple.frequency * (r - ripple.time) - 2.0 * atan2(p.z, p.x);
+
+    // strongest above the poles, half as strong in the plane of the disk
+    let cos_theta = p.y / r;
+    let pattern = 0.5 * (1.0 + cos_theta * cos_theta);
+
+    return ripple.amplitude * pattern * sin(phase) * p / (r * r);
+}
+
 // ODE Integration methods
 // https://stackoverflow.com/questions/53645649/cannot-get-rk4-to-solve-for-position-of-orbiting-body-in-python/53650879#53650879
 
@@ -256,6 +331,94 @@ fn proceduralSky(rd: vec3<f32>) -> vec3<f32> {
     return intensity * color;
 }
 
+// turn the ray direction of an observer falling towards the black hole
+// into the direction seen by a static observer,
+// returning the direction and how much brighter the light looks (in w)
+fn infallingObserver(ro: vec3<f32>, rd: vec3<f32>) -> vec4<f32> {
+    // falling from rest far away, as a fraction of the speed of light
+    let beta = min(sqrt(BLACKHOLE_RADIUS / length(ro)), 0.999);
+    let gamma = 1.0 / sqrt(1.0 - beta * beta);
+    let n = -normalize(ro);
+
+    // https://en.wikipedia.org/wiki/Relativistic_aberration
+    let cos_moving = dot(rd, n);
+    let cos_static = (cos_moving - beta) / (1.0 - beta * cos_moving);
+    let sin_static = sqrt(max(1.0 - cos_static * cos_static, 0.0));
+
+    var side = rd - cos_moving * n;
+    if dot(side, side) < 1e-12 {
+        // looking straight ahead or behind, any side will do
+        side = cross(n, select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(n.x) > 0.9));
+    }
+
+    // https://en.wikipedia.org/wiki/Relativistic_Doppler_effect
+    // the total intensity scales with the 4th power of the doppler factor
+    let doppler = 1.0 / (gamma * (1.0 - beta * cos_moving));
+
+    return vec4<f32>(normalize(cos_static * n + sin_static * normalize(side)), pow(doppler, 4.0));
+}
+
+// the fraction of the disk's light that is polarized,
+// close to the limit for synchrotron radiation
+const DISK_POLARIZATION: f32 = 0.7;
+// https://en.wikipedia.org/wiki/Relative_luminance
+const LUMINANCE = vec3<f32>(0.2126, 0.7152, 0.0722);
+
+// the camera's right axis, carried along the ray
+var<private> pol_frame: vec3<f32>;
+// [I, Q, U] of the light gathered by the ray, there is no circular polarization
+var<private> stokes: vec3<f32>;
+
+// rays are integrated in flat space, bent by a force,
+// so parallel transport keeps the frame perpendicular to the ray,
+// rotating it as little as possible
+fn transportPolarization(v: vec3<f32>) {
+    let n = normalize(v);
+    let frame = pol_frame - dot(pol_frame, n) * n;
+
+    // keep the old frame if the ray has turned right around
+    if dot(frame, frame) > 1e-12 {
+        pol_frame = normalize(frame);
+    }
+}
+
+// light from the sky or scattering isn't polarized
+fn gatherUnpolarized(color: vec3<f32>) {
+    stokes.x += dot(color, LUMINANCE);
+}
+
+fn gatherDisk(p: vec3<f32>, v: vec3<f32>, color: vec3<f32>) {
+    let i = dot(color, LUMINANCE);
+    stokes.x += i;
+
+    // assume the magnetic field of the disk circles around the black hole
+    let field = vec3<f32>(-p.z, 0.0, p.x);
+
+    // synchrotron light is polarized perpendicular to the field and the ray
+    let e = cross(v, field);
+    if dot(e, e) < 1e-12 {
+        return;
+    }
+
+    let n = normalize(v);
+    let c = dot(normalize(e), pol_frame);
+    let s = dot(normalize(e), cross(n, pol_frame));
+
+    // the angle is doubled, as polarization is the same after half a turn
+    stokes.y += DISK_POLARIZATION * i * (c * c - s * s);
+    stokes.z += DISK_POLARIZATION * i * (2.0 * c * s);
+}
+
+// the speed of light at a distance r, after falling from far away
+// rays start inside the horizon at this speed, so that they can climb back out
+fn infallSpeed(r: f32) -> f32 {
+    // the work done by the field from r out to infinity
+    let rn = r / BLACKHOLE_RADIUS;
+    let work = 2.0 * BLACKHOLE_RADIUS / (rn * rn * rn);
+
+    return sqrt(1.0 + 2.0 * work);
+}
+
 fn render(ro: vec3<f32>, rd: vec3<f32>) -> vec3<f32> {
     // our timestep, start at a low value
     var h = DELTA;
@@ -267,10 +430,19 @@ fn render(ro: vec3<f32>, rd: vec3<f32>) -> vec3<f32> {
     var attenuation = vec3<f32>(1.0);
     var r = vec3<f32>(0.0);
 
+    // rays from a camera inside the horizon carry on until the singularity
+    // the field only diverges at the center, so nothing special happens at the horizon
+    var inside = has_feature(INTERIOR) && !has_feature(STAR)
+        && dot(ro, ro) < BLACKHOLE_RADIUS * BLACKHOLE_RADIUS;
+    var speed = 1.0;
+    if inside {
+        speed = infallSpeed(length(ro));
+    }
+
     // add variation to our start point along the direction
     var p = ro + (rand() * h * rd);
     // our inital velocity is just ray direction
-    var v = rd;
+    var v = speed * rd;
 
     // keep track of the number of bounces the light takes
     // this is useful when integrating volumes
@@ -282,7 +454,26 @@ fn render(ro: vec3<f32>, rd: vec3<f32>) -> vec3<f32> {
             return vec3<f32>(-1.0);
         }
 
-        if dot(p, p) < BLACKHOLE_RADIUS * BLACKHOLE_RADIUS {
+        if inside {
+            if dot(p, p) < SINGULARITY_RADIUS * SINGULARITY_RADIUS {
+                // light has hit the singularity
+                return r;
+            }
+
+            // the ray can leave through the horizon, but not enter it again
+            inside = dot(p, p) < BLACKHOLE_RADIUS * BLACKHOLE_RADIUS;
+        } else if has_feature(STAR) {
+            if dot(p, p) < star.radius * star.radius {
+                // light has come from the surface of the star
+                let emission = starEmission(p, v);
+
+                if has_feature(POLARIZATION) {
+                    gatherUnpolarized(attenuation * emission);
+                }
+
+                return r + attenuation * emission;
+            }
+        } else if dot(p, p) < BLACKHOLE_RADIUS * BLACKHOLE_RADIUS {
             // light has entered the black hole...
             // dont just return black, we might have gone through a volume to get here
             return r;
@@ -298,6 +489,10 @@ fn render(ro: vec3<f32>, rd: vec3<f32>) -> vec3<f32> {
             let sample = diskVolume(p);
             r += attenuation * sample.emission * h;
 
+            if has_feature(POLARIZATION) {
+                gatherDisk(p, v, attenuation * sample.emission * h);
+            }
+
             if sample.distance > 0.0 {
                 // hit the disc
 
@@ -321,36 +516,66 @@ fn render(ro: vec3<f32>, rd: vec3<f32>) -> vec3<f32> {
 
             if dist <= 0.0 {
                 // hit the disk
+                if has_feature(POLARIZATION) {
+                    gatherDisk(p, v, pc.disk_color);
+                }
+
                 return pc.disk_color;
             }
         }
 
+        if has_feature(RIPPLE) {
+            // the ripple kicks the ray each step, on top of the field
+            v += h * rippleField(p);
+        }
+
         // create state
         let s = mat2x3(p, v);
 
         // integrate
         var step = mat2x3f();
 
+        // the field grows quickly towards the singularity, so take smaller steps
+        var hs = h;
+        if inside {
+            hs *= max(dot(p, p) / (BLACKHOLE_RADIUS * BLACKHOLE_RADIUS), 0.05);
+        }
+
         // choose the method of integration
         if has_feature(ADAPTIVE) {
             step = bogacki_shampine(s, &h);
         } else if has_feature(RK4) {
-            step = rk4(s, h);
+            step = rk4(s, hs);
         } else {
-            step = euler(s, h);
+            step = euler(s, hs);
         }
 
         // update system
         p += step.x;
         v += step.y;
+
+        if has_feature(POLARIZATION) {
+            transportPolarization(v);
+        }
     }
 
+    if inside {
+        // never made it out of the horizon
+        return r;
+    }
+
+    var sky: vec3<f32>;
     if has_feature(SKY_PROC) {
         // procedurally create the skybox
-        r += attenuation * proceduralSky(normalize(v));
+        sky = proceduralSky(normalize(v));
     } else {
         // sample the sky from a texture
-        r += attenuation * sampleSky(normalize(v));
+        sky = sampleSky(normalize(v));
+    }
+    r += attenuation * sky;
+
+    if has_feature(POLARIZATION) {
+        gatherUnpolarized(attenuation * sky);
     }
 
     return r;
@@ -365,11 +590,14 @@ fn comp(@builtin(global_invocation_id) id: vec3<u32>) {
         return;
     }
 
+    // the position of the pixel in the whole frame
+    let pixel = id.xy + pc.offset;
+
     // seed the rng
-    seed_rng(id.xy, dim.xy, pc.sample);
+    seed_rng(pixel, pc.resolution, pc.seed);
 
-    let res = vec2<f32>(dim.xy);
-    var coord = vec2<f32>(id.xy);
+    let res = vec2<f32>(pc.resolution);
+    var coord = vec2<f32>(pixel);
 
     if has_feature(AA) {
         coord = aa_filter(coord);
@@ -397,24 +625,52 @@ fn comp(@builtin(global_invocation_id) id: vec3<u32>) {
     // the ray origin
     let ro = (vec4<f32>(pc.origin, 0.0) * pc.transform).xyz;
     // the ray direction (multiplied by the fov factor 2 * FOV * 1/PI, which gives us 90 degrees = 1.0 factor)
-    let rd = normalize((vec4<f32>(uv * 2.0 * pc.fov * FRAC_1_PI, -1.0, 0.0) * pc.transform).xyz);
+    var rd = normalize((vec4<f32>(uv * 2.0 * pc.fov * FRAC_1_PI, -1.0, 0.0) * pc.transform).xyz);
+
+    // move into the frame of a static observer
+    var brightness = 1.0;
+    if has_feature(INFALLING_OBSERVER) {
+        let observer = infallingObserver(ro, rd);
+        rd = observer.xyz;
+        brightness = observer.w;
+    }
+
+    // track polarization against the camera's right axis
+    let right = (vec4<f32>(1.0, 0.0, 0.0, 0.0) * pc.transform).xyz;
+    pol_frame = normalize(right - dot(right, rd) * rd);
+    stokes = vec3<f32>(0.0);
 
     // render using the ray information
-    var color = render(ro, rd);
+    var color = render(ro, rd) * brightness;
 
     // remove unused samples
-    color = select(
-        color,
-        vec3<f32>(0.0),
-        any(color < vec3<f32>(0.0)) || any(isInf(color)) || any(isNan(color))
-    );
+    let unused = any(color < vec3<f32>(0.0)) || any(isInf(color)) || any(isNan(color));
+    color = select(color, vec3<f32>(0.0), unused);
+
+    var out: vec4<f32>;
+    if has_feature(POLARIZATION) {
+        // output the stokes parameters instead of color
+        // scaled by 1 / (1 + I) so they fit in the buffer, see common::stokes
+        let s = select(stokes * brightness, vec3<f32>(0.0), unused);
+        let scaled = s / (1.0 + max(s.x, 0.0));
+        out = vec4<f32>(scaled.x, 0.5 + 0.5 * scaled.yz, 0.5);
+    } else {
+        out = vec4<f32>(color, 1.0);
+    }
 
-    // gamma correction
-    color = pow(color, vec3<f32>(0.45));
+    // the buffer only has 8 bits, so it holds the mean encoded for display,
+    // but the samples are still averaged in linear light
+    let encoded = !has_feature(POLARIZATION) && !has_feature(LINEAR_OUTPUT);
 
     // accumulate the color in the buffer
-    let old_color = textureLoad(buffer, id.xy);
-    let acc = mix(old_color, vec4<f32>(color, 1.0), 1.0 / f32(pc.sample + 1));
+    var old_color = textureLoad(buffer, id.xy);
+    if encoded {
+        old_color = vec4<f32>(srgb_to_linear(old_color.rgb), old_color.a);
+    }
+    var acc = mix(old_color, out, 1.0 / f32(pc.sample + 1));
+    if encoded {
+        acc = vec4<f32>(linear_to_srgb(acc.rgb), acc.a);
+    }
 
     textureStore(buffer, id.xy, acc);
 }
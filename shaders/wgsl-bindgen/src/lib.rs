@@ -18,6 +18,10 @@ use wgsl_to_wgpu::{
     WriteOptions,
 };
 
+pub use crate::preprocess::{
+    IncludeGraph,
+    ProcessedShader,
+};
 use crate::preprocess::ShaderBuilder;
 
 #[derive(Debug, Error)]
@@ -26,16 +30,23 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error(transparent)]
     Fmt(#[from] std::fmt::Error),
-    #[error("preprocess error")]
+    #[error("preprocess error: {0}")]
     Preprocessing(#[from] preprocess::Error),
     #[error("shader failed to parse")]
     ShaderParse,
+    #[error("{0}")]
+    Invalid(String),
     #[error("failed to create shader module")]
     CreateModuleError(#[from] wgsl_to_wgpu::CreateModuleError),
 }
 
 /// Create WGPU bindings and preprocess a shader
 pub fn build_shader(file: impl AsRef<Path>) -> Result<(), Error> {
+    build_shader_with_flags(file, &[])
+}
+
+/// Create WGPU bindings and preprocess a shader, with the `flags` checked by `//!if`.
+pub fn build_shader_with_flags(file: impl AsRef<Path>, flags: &[&str]) -> Result<(), Error> {
     let path = file.as_ref();
     assert!(
         path.is_file(),
@@ -44,12 +55,16 @@ pub fn build_shader(file: impl AsRef<Path>) -> Result<(), Error> {
 
     println!("cargo:rerun-if-changed={}", path.display());
 
-    let builder = ShaderBuilder::new(path).build()?;
+    let builder = ShaderBuilder::new(path)
+        .with_flags(flags.iter().copied())
+        .build()?;
 
     // make sure we re-reun for every included file too
     for included in builder.includes() {
         println!("cargo:rerun-if-changed={}", included.display());
     }
+    // kept in the build output, to see what the shader was made of
+    print!("{}", builder.include_graph());
 
     let wgsl_source = builder.wgsl();
 
@@ -125,6 +140,35 @@ pub fn build_shader(file: impl AsRef<Path>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Preprocess and parse a shader at runtime, so it can be reloaded without a rebuild.
+///
+/// Fails with the formatted parse errors if the shader is invalid.
+pub fn load_shader(file: impl AsRef<Path>) -> Result<ProcessedShader, Error> {
+    load_shader_with_flags(file, &[])
+}
+
+/// Preprocess and parse a shader at runtime, with the `flags` checked by `//!if`.
+///
+/// The flags should match the ones it was built with, to keep the same bindings.
+pub fn load_shader_with_flags(
+    file: impl AsRef<Path>,
+    flags: &[&str],
+) -> Result<ProcessedShader, Error> {
+    let path = file.as_ref();
+
+    let shader = ShaderBuilder::new(path)
+        .with_flags(flags.iter().copied())
+        .build()?;
+
+    if let Err(e) = naga::front::wgsl::parse_str(shader.source()) {
+        return Err(Error::Invalid(
+            e.emit_to_string_with_path(shader.source(), path),
+        ));
+    }
+
+    Ok(shader)
+}
+
 fn replace_all(re: &Regex, haystack: &str, replacement: impl Fn(&Captures) -> String) -> String {
     let mut new = String::with_capacity(haystack.len());
     let mut last_match = 0;
@@ -1,5 +1,9 @@
 use std::{
-    io,
+    collections::{
+        BTreeMap,
+        HashSet,
+    },
+    fmt,
     path::{
         Path,
         PathBuf,
@@ -10,20 +14,116 @@ use thiserror::Error;
 
 const INSTRUCTION_PREFIX: &str = "//!";
 const INCLUDE_INSTRUCTION: &str = "include";
+const IF_INSTRUCTION: &str = "if";
+const ELSE_INSTRUCTION: &str = "else";
+const ENDIF_INSTRUCTION: &str = "endif";
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    #[error("{0}: `//!if` needs a flag")]
+    MissingFlag(Location),
+    #[error("{1}: `//!{0}` without a matching `//!if`")]
+    Unmatched(&'static str, Location),
+    #[error("{0}: `//!if` is never closed with `//!endif`")]
+    Unclosed(Location),
+    #[error("{at}: circular include, {}", chain_string(.chain))]
+    Cycle { chain: Vec<PathBuf>, at: Location },
+}
+
+fn chain_string(chain: &[PathBuf]) -> String {
+    chain
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// A line of a shader, for errors.
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.line)
+    }
 }
 
+/// Preprocesses a shader, following its directives:
+///
+/// - `//!include FILE...` pastes in the processed files, relative to the shader.
+///   Each file is only included once, and circular includes are an error.
+/// - `//!if FLAG`, `//!else` and `//!endif` only keep the lines where `FLAG` is set,
+///   or where it isn't with `//!if !FLAG`. They nest, but can't span files.
 pub struct ShaderBuilder {
     src: PathBuf,
+    flags: HashSet<String>,
 }
 
 pub struct ProcessedShader {
     code: String,
     includes: Vec<PathBuf>,
+    graph: IncludeGraph,
+}
+
+/// The files included by each file of a shader.
+///
+/// Shown as a tree, with the files that were already included marked.
+#[derive(Debug, Clone)]
+pub struct IncludeGraph {
+    root: PathBuf,
+    /// The canonical path of each file, to the files it includes, in order.
+    edges: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl IncludeGraph {
+    /// The shader that was processed.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The files included by `file`, in order, even if they were already included.
+    pub fn includes_of(&self, file: &Path) -> &[PathBuf] {
+        file.canonicalize()
+            .ok()
+            .and_then(|file| self.edges.get(&file))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn write_tree(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        file: &Path,
+        depth: usize,
+        seen: &mut HashSet<PathBuf>,
+    ) -> fmt::Result {
+        // relative to the shader, where they can be
+        let dir = self.root.parent().unwrap_or(Path::new(""));
+        let name = file.strip_prefix(dir).unwrap_or(file);
+
+        let repeat = !seen.insert(file.to_owned());
+        let note = if repeat { " (already included)" } else { "" };
+        let indent = depth * 2;
+        writeln!(f, "{:indent$}{}{note}", "", name.display())?;
+
+        if !repeat {
+            for include in self.edges.get(file).into_iter().flatten() {
+                self.write_tree(f, include, depth + 1, seen)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for IncludeGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_tree(f, &self.root, 0, &mut HashSet::new())
+    }
 }
 
 impl ProcessedShader {
@@ -31,64 +131,363 @@ impl ProcessedShader {
         self.code
     }
 
+    pub fn source(&self) -> &str {
+        &self.code
+    }
+
+    /// Every file included, once each.
     pub fn includes(&self) -> impl Iterator<Item = &Path> {
         self.includes.iter().map(|p| p.as_path())
     }
+
+    pub fn include_graph(&self) -> &IncludeGraph {
+        &self.graph
+    }
 }
 
 impl ShaderBuilder {
     pub fn new(src: &Path) -> Self {
         Self {
             src: src.to_owned(),
+            flags: HashSet::new(),
         }
     }
 
-    pub fn build(self) -> Result<ProcessedShader, Error> {
-        let (entire_module, includes) = process(self.src)?;
+    /// Set the `flags` checked by `//!if`.
+    pub fn with_flags<S: Into<String>>(self, flags: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            flags: flags.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
 
-        Ok(ProcessedShader {
-            code: entire_module,
-            includes,
-        })
+    pub fn build(self) -> Result<ProcessedShader, Error> {
+        process(&self.src, &self.flags)
     }
 }
 
-fn process(src: impl AsRef<Path>) -> Result<(String, Vec<PathBuf>), io::Error> {
-    return inner(src.as_ref());
+/// Processes the files of a shader, following their includes.
+struct Processor<'a> {
+    flags: &'a HashSet<String>,
+    /// The files being processed, each included by the one before.
+    stack: Vec<PathBuf>,
+    /// The files included, the first time they're included.
+    includes: Vec<PathBuf>,
+    graph: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
 
-    fn inner(src: &Path) -> Result<(String, Vec<PathBuf>), io::Error> {
+fn process(src: &Path, flags: &HashSet<String>) -> Result<ProcessedShader, Error> {
+    let mut processor = Processor {
+        flags,
+        stack: Vec::new(),
+        includes: Vec::new(),
+        graph: BTreeMap::new(),
+    };
+    let code = processor.file(src)?;
+
+    Ok(ProcessedShader {
+        code,
+        includes: processor.includes,
+        graph: IncludeGraph {
+            root: src.canonicalize()?,
+            edges: processor.graph,
+        },
+    })
+}
+
+impl Processor<'_> {
+    fn file(&mut self, src: &Path) -> Result<String, Error> {
         let parent = src.parent();
         let module_source = std::fs::read_to_string(src)?;
 
+        // the same file can be included by different paths
+        let file = src.canonicalize()?;
+        self.stack.push(file.clone());
+        self.graph.entry(file.clone()).or_default();
+
         let mut module_string = String::new();
-        let mut includes = Vec::new();
 
-        'next_line: for line in module_source.lines() {
-            if let Some(rest) = line.strip_prefix(INSTRUCTION_PREFIX) {
-                if rest.starts_with(INCLUDE_INSTRUCTION) {
-                    for include in rest.split_whitespace().skip(1) {
+        // the `//!if` blocks the line is in
+        let mut branches: Vec<Branch> = Vec::new();
+
+        for (number, line) in module_source.lines().enumerate() {
+            let active = !matches!(branches.last(), Some(branch) if !branch.active());
+            let at = || Location {
+                path: src.to_owned(),
+                line: number + 1,
+            };
+
+            let Some(rest) = line.strip_prefix(INSTRUCTION_PREFIX) else {
+                if active {
+                    module_string.push_str(line);
+                    module_string.push('\n');
+                }
+                continue;
+            };
+
+            let mut words = rest.split_whitespace();
+            match words.next() {
+                Some(INCLUDE_INSTRUCTION) => {
+                    if !active {
+                        continue;
+                    }
+
+                    for include in words {
                         let mut include_path = PathBuf::new();
                         if let Some(parent) = parent {
                             include_path.push(parent);
                         }
                         include_path.push(include);
 
-                        let (included_module_string, mut other_includes) = process(&include_path)?;
+                        let included = include_path.canonicalize()?;
+
+                        if let Some(start) = self.stack.iter().position(|f| *f == included) {
+                            let mut chain = self.stack[start..].to_vec();
+                            chain.push(included);
 
-                        includes.push(include_path);
+                            return Err(Error::Cycle { chain, at: at() });
+                        }
 
+                        self.graph
+                            .get_mut(&file)
+                            .expect("added above")
+                            .push(included.clone());
+
+                        // only include each file once
+                        if self.graph.contains_key(&included) {
+                            continue;
+                        }
+
+                        self.includes.push(include_path.clone());
+                        let included_module_string = self.file(&include_path)?;
                         module_string.push_str(&included_module_string);
-                        includes.append(&mut other_includes);
                     }
+                }
+                Some(IF_INSTRUCTION) => {
+                    let flag = words.next().ok_or_else(|| Error::MissingFlag(at()))?;
+                    // `!FLAG` is set when the flag isn't
+                    let condition = match flag.strip_prefix('!') {
+                        Some(flag) => !self.flags.contains(flag),
+                        None => self.flags.contains(flag),
+                    };
 
-                    continue 'next_line;
+                    branches.push(Branch {
+                        parent_active: active,
+                        condition,
+                        in_else: false,
+                        start: at(),
+                    });
+                }
+                Some(ELSE_INSTRUCTION) => match branches.last_mut() {
+                    Some(branch) if !branch.in_else => branch.in_else = true,
+                    _ => return Err(Error::Unmatched(ELSE_INSTRUCTION, at())),
+                },
+                Some(ENDIF_INSTRUCTION) => {
+                    if branches.pop().is_none() {
+                        return Err(Error::Unmatched(ENDIF_INSTRUCTION, at()));
+                    }
+                }
+                // any other comment is kept
+                _ => {
+                    if active {
+                        module_string.push_str(line);
+                        module_string.push('\n');
+                    }
                 }
             }
+        }
 
-            module_string.push_str(line);
-            module_string.push('\n');
+        // blocks can't span files
+        if let Some(branch) = branches.pop() {
+            return Err(Error::Unclosed(branch.start));
         }
 
-        Ok((module_string, includes))
+        self.stack.pop();
+
+        Ok(module_string)
+    }
+}
+
+/// A `//!if` block, and its `//!else`.
+struct Branch {
+    /// Whether the block is in an active one itself.
+    parent_active: bool,
+    /// Whether the flag of the `//!if` matched.
+    condition: bool,
+    in_else: bool,
+    start: Location,
+}
+
+impl Branch {
+    /// Whether the lines in the block are kept, so far.
+    fn active(&self) -> bool {
+        self.parent_active && self.condition != self.in_else
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write the `files` of a shader to their own directory, with `main.wgsl` as the shader.
+    fn write_shader(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wgsl-bindgen-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (file, source) in files {
+            std::fs::write(dir.join(file), source).unwrap();
+        }
+
+        dir.join("main.wgsl")
+    }
+
+    fn build(shader: &Path, flags: &[&str]) -> Result<ProcessedShader, Error> {
+        ShaderBuilder::new(shader)
+            .with_flags(flags.iter().copied())
+            .build()
+    }
+
+    #[test]
+    fn nested_ifs() {
+        let shader = write_shader(
+            "nested-ifs",
+            &[(
+                "main.wgsl",
+                "a\n//!if A\nb\n//!if B\nc\n//!else\nd\n//!endif\n//!else\ne\n//!endif\nf\n",
+            )],
+        );
+
+        assert_eq!(build(&shader, &["A", "B"]).unwrap().source(), "a\nb\nc\nf\n");
+        assert_eq!(build(&shader, &["A"]).unwrap().source(), "a\nb\nd\nf\n");
+        // the inner block is skipped, whatever its flag
+        assert_eq!(build(&shader, &["B"]).unwrap().source(), "a\ne\nf\n");
+    }
+
+    #[test]
+    fn negated_if() {
+        let shader = write_shader("negated-if", &[("main.wgsl", "//!if !A\na\n//!endif\n")]);
+
+        assert_eq!(build(&shader, &[]).unwrap().source(), "a\n");
+        assert_eq!(build(&shader, &["A"]).unwrap().source(), "");
+    }
+
+    #[test]
+    fn missing_endif() {
+        let shader = write_shader("missing-endif", &[("main.wgsl", "a\n//!if A\nb\n")]);
+
+        match build(&shader, &[]) {
+            Err(Error::Unclosed(at)) => assert_eq!(at.line, 2),
+            _ => panic!("expected an unclosed `//!if`"),
+        }
+    }
+
+    #[test]
+    fn stray_else() {
+        let shader = write_shader("stray-else", &[("main.wgsl", "a\n//!else\nb\n")]);
+
+        match build(&shader, &[]) {
+            Err(Error::Unmatched(ELSE_INSTRUCTION, at)) => assert_eq!(at.line, 2),
+            _ => panic!("expected an unmatched `//!else`"),
+        }
+
+        // a second `//!else` doesn't match the `//!if` either
+        let shader = write_shader(
+            "second-else",
+            &[("main.wgsl", "//!if A\n//!else\n//!else\n//!endif\n")],
+        );
+
+        match build(&shader, &[]) {
+            Err(Error::Unmatched(ELSE_INSTRUCTION, at)) => assert_eq!(at.line, 3),
+            _ => panic!("expected an unmatched `//!else`"),
+        }
+    }
+
+    #[test]
+    fn stray_endif() {
+        let shader = write_shader(
+            "stray-endif",
+            &[("main.wgsl", "//!if A\n//!endif\n//!endif\n")],
+        );
+
+        match build(&shader, &[]) {
+            Err(Error::Unmatched(ENDIF_INSTRUCTION, at)) => assert_eq!(at.line, 3),
+            _ => panic!("expected an unmatched `//!endif`"),
+        }
+    }
+
+    #[test]
+    fn include_in_false_branch() {
+        // the missing file is never read
+        let shader = write_shader(
+            "false-include",
+            &[("main.wgsl", "a\n//!if A\n//!include missing.wgsl\n//!endif\nb\n")],
+        );
+
+        let processed = build(&shader, &[]).unwrap();
+        assert_eq!(processed.source(), "a\nb\n");
+        assert_eq!(processed.includes().count(), 0);
+
+        assert!(matches!(build(&shader, &["A"]), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn diamond_include() {
+        let shader = write_shader(
+            "diamond",
+            &[
+                ("main.wgsl", "//!include a.wgsl b.wgsl\nmain\n"),
+                ("a.wgsl", "//!include shared.wgsl\na\n"),
+                ("b.wgsl", "//!include shared.wgsl\nb\n"),
+                ("shared.wgsl", "shared\n"),
+            ],
+        );
+
+        let processed = build(&shader, &[]).unwrap();
+        assert_eq!(processed.source(), "shared\na\nb\nmain\n");
+
+        let includes: Vec<_> = processed
+            .includes()
+            .map(|path| path.file_name().unwrap().to_owned())
+            .collect();
+        assert_eq!(includes, ["a.wgsl", "shared.wgsl", "b.wgsl"]);
+
+        // both still include it in the graph
+        let graph = processed.include_graph();
+        let dir = shader.parent().unwrap();
+        assert_eq!(graph.includes_of(&dir.join("a.wgsl")).len(), 1);
+        assert_eq!(graph.includes_of(&dir.join("b.wgsl")).len(), 1);
+        assert_eq!(
+            graph.to_string(),
+            "main.wgsl\n  a.wgsl\n    shared.wgsl\n  b.wgsl\n    shared.wgsl (already included)\n"
+        );
+    }
+
+    #[test]
+    fn include_cycle() {
+        let shader = write_shader(
+            "cycle",
+            &[
+                ("main.wgsl", "//!include a.wgsl\n"),
+                ("a.wgsl", "//!include b.wgsl\n"),
+                ("b.wgsl", "b\n//!include a.wgsl\n"),
+            ],
+        );
+
+        let error = build(&shader, &[]).err().expect("expected a cycle");
+        let Error::Cycle { chain, at } = &error else {
+            panic!("expected a cycle, not {error}");
+        };
+
+        let names: Vec<_> = chain
+            .iter()
+            .map(|path| path.file_name().unwrap().to_owned())
+            .collect();
+        assert_eq!(names, ["a.wgsl", "b.wgsl", "a.wgsl"]);
+        assert_eq!(at.path.file_name().unwrap(), "b.wgsl");
+        assert_eq!(at.line, 2);
+
+        // the error names every file in the chain
+        assert!(error.to_string().ends_with(&chain_string(chain)));
     }
 }
@@ -1,5 +1,24 @@
-use std::sync::mpsc;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        mpsc,
+        Arc,
+    },
+};
 
+use common::{
+    shadow::Shadow,
+    Features,
+};
+use compare::{
+    Compare,
+    Mode,
+};
+use convergence::Convergence;
 use egui_file::FileDialog;
 use egui_toast::{
     Toast,
@@ -8,85 +27,247 @@ use egui_toast::{
     Toasts,
 };
 use event::EventHandler;
+use frame_times::FrameTimes;
 use fullscreen::Fullscreen;
 use glam::vec2;
 use graphics::{
     wgpu,
     Encoder,
 };
-use gui::GuiState;
+use gui::{
+    Dock,
+    GuiEvent,
+    GuiState,
+    ImageLoader,
+    TabViewer,
+};
 use hardware_renderer::*;
-use profiler::PuffinStream;
+use histogram::Histogram;
+use history::History;
+use inspector::Inspector;
+use logs::Logs;
+use presets::Gallery;
+use recent::Recent;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use settings::Settings;
+use sky::Sky;
+use software_renderer::Heatmap;
+use timeline::Timeline;
+use view::View;
 use winit::{
+    event::WindowEvent,
     event_loop::EventLoop,
     keyboard::KeyCode,
+    window::{
+        WindowBuilder,
+        WindowId,
+    },
 };
 
 use crate::{
+    compare,
+    convergence,
+    frame_times,
     gui,
-    input, ui,
+    histogram,
+    history,
+    input,
+    inspector,
+    logs,
+    offline,
+    presets,
+    recent,
+    screenshot,
+    settings,
+    sky,
+    timeline,
+    ui,
+    view,
 };
 
+/// The windows that can be docked together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tab {
+    Settings,
+    Profiler,
+    Logs,
+    Compare,
+}
+
 pub(crate) struct App {
-    renderer: Renderer,
+    view: View,
+    /// Shown next to the view when set.
+    compare: Option<Compare>,
     fullscreen: Fullscreen,
     gui: GuiState,
+    dock: Dock<Tab>,
 
-    mouse: input::Mouse,
-    keyboard: input::Keyboard,
+    gamepad: input::Gamepad,
+    sensitivity: input::Sensitivity,
 
     file_dialog: Option<FileDialog>,
+    image_dialog: Option<FileDialog>,
+    /// Picks where to save the window, gui included.
+    capture_dialog: Option<FileDialog>,
+    path_dialog: Option<FileDialog>,
+    sky_dialog: Option<FileDialog>,
+    /// A config to open next frame, dropped on the window or picked from the recent files.
+    to_open: Option<PathBuf>,
+    recent: Recent,
+    presets: Gallery,
 
     gpu_start: i64,
     profiler_id_cache: profiler::IdCache,
     profiler: profiler::gpu::GpuProfiler,
-    show_profiler: bool,
+    #[cfg(feature = "tracy")]
+    tracy: profiler::TracyGpu,
+    /// Warns when frames or scopes take too long on the gpu.
+    budget: profiler::FrameBudget,
+    /// Set while the profiler is popped out into its own window.
+    profiler_window: Arc<AtomicBool>,
+    /// A second window showing only the render, such as on a projector.
+    projector: Option<WindowId>,
+    frame_times: FrameTimes,
+    show_frame_times: bool,
+
+    show_render: bool,
+    render_settings: offline::Settings,
+    offline: Option<offline::OfflineRender>,
 
     accumulate: bool,
+    /// Stop computing samples, and only draw after input.
+    paused: bool,
+    /// Only draw continuously while accumulating.
+    on_demand: bool,
+    /// The most frames drawn each second, if limited.
+    target_fps: Option<u32>,
+    heatmap: Option<Heatmap>,
+    convergence: Convergence,
     config: Config,
+    history: History,
+    show_history: bool,
+    /// The name of the next bookmark.
+    bookmark_name: String,
+    timeline: Option<Timeline>,
+    shadow: Option<Shadow>,
+    /// The starmap used instead of the built in one.
+    sky: Option<Sky>,
+    /// A preview of the starmap, shown next to its name.
+    sky_thumbnail: Option<egui::TextureId>,
+    thumbnails: ImageLoader,
+    inspector: Option<Inspector>,
+    /// Shown in the exposure window when set.
+    histogram: Option<Histogram>,
+    false_color: bool,
+
+    /// Saved when the sim exits.
+    settings: Settings,
+
+    logs: Logs,
+    #[cfg(debug_assertions)]
+    hot_reload: crate::hot_reload::HotReload,
 
-    error_logs: mpsc::Receiver<String>,
+    adapter: wgpu::AdapterInfo,
+    /// Every adapter that `--adapter` can choose from.
+    adapters: Vec<wgpu::AdapterInfo>,
+    show_diagnostics: bool,
 }
 
 impl App {
-    pub(crate) fn new<T>(
-        _event_loop: &EventLoop<T>,
+    pub(crate) fn new(
+        event_loop: &EventLoop<GuiEvent>,
         ctx: &graphics::Context,
-        errors: mpsc::Receiver<String>,
+        logs: mpsc::Receiver<logs::Line>,
+        config: Config,
+        settings: Settings,
+        budget: profiler::FrameBudget,
     ) -> Self {
-        let renderer = Renderer::new(ctx);
+        let view = View::new(ctx, false);
         let fullscreen = Fullscreen::new(ctx);
-        let gui = GuiState::new(ctx);
-
-        gui.context().style_mut(|style| {
-            style.visuals.window_shadow = egui::epaint::Shadow::NONE;
-            style.visuals.window_rounding = egui::Rounding::ZERO;
-            style.visuals.widgets.active.rounding = egui::Rounding::ZERO;
-            style.visuals.widgets.open.rounding = egui::Rounding::ZERO;
-            style.visuals.widgets.inactive.rounding = egui::Rounding::ZERO;
-            style.visuals.widgets.hovered.rounding = egui::Rounding::ZERO;
-            style.visuals.widgets.noninteractive.rounding = egui::Rounding::ZERO;
-        });
+        let mut gui = GuiState::new(ctx, event_loop);
+        gui.set_theme(settings.theme);
+
+        let recent = Recent::load();
+
+        // reopen the windows from the last session
+        let panels = settings.panels;
+        let dock = settings
+            .dock
+            .clone()
+            .unwrap_or_else(|| Dock::new(vec![Tab::Settings]));
+        let compare = dock
+            .is_open(&Tab::Compare)
+            .then(|| Compare::new(ctx, config.clone()));
+        puffin::set_scopes_on(dock.is_open(&Tab::Profiler));
 
         Self {
-            renderer,
+            view,
+            compare,
             fullscreen,
             gui,
+            dock,
 
-            mouse: input::Mouse::new(),
-            keyboard: input::Keyboard::new(),
+            gamepad: input::Gamepad::new(),
+            sensitivity: input::Sensitivity::default(),
 
             file_dialog: None,
+            image_dialog: None,
+            capture_dialog: None,
+            path_dialog: None,
+            sky_dialog: None,
+            to_open: None,
+            recent,
+            presets: Gallery::new(event::Proxy::new(event_loop)),
 
             gpu_start: puffin::now_ns(),
             profiler_id_cache: profiler::IdCache::new(),
             profiler: profiler::gpu::GpuProfiler::new(Default::default()).unwrap(),
-            show_profiler: false,
+            #[cfg(feature = "tracy")]
+            tracy: profiler::TracyGpu::new(),
+            budget,
+            profiler_window: Arc::new(AtomicBool::new(false)),
+            projector: None,
+            frame_times: FrameTimes::default(),
+            show_frame_times: panels.frame_times,
+
+            show_render: panels.render,
+            render_settings: Default::default(),
+            offline: None,
+
+            accumulate: settings.accumulate,
+            paused: false,
+            on_demand: settings.on_demand,
+            target_fps: settings.target_fps,
+            heatmap: None,
+            convergence: Convergence::default(),
+            history: History::new(config.clone()),
+            config,
+            show_history: panels.history,
+            bookmark_name: String::new(),
+            timeline: panels.timeline.then(Timeline::default),
+            shadow: None,
+            sky: None,
+            sky_thumbnail: None,
+            thumbnails: ImageLoader::new(event::Proxy::new(event_loop)),
+            inspector: panels.inspector.then(Inspector::new),
+            histogram: panels.exposure.then(Histogram::default),
+            false_color: false,
 
-            accumulate: true,
-            config: Config::default(),
+            settings,
 
-            error_logs: errors,
+            logs: Logs::new(logs),
+            #[cfg(debug_assertions)]
+            hot_reload: crate::hot_reload::HotReload::new(),
+
+            adapter: ctx.adapter().get_info(),
+            adapters: graphics::enumerate_adapters(wgpu::Backends::PRIMARY)
+                .iter()
+                .map(wgpu::Adapter::get_info)
+                .collect(),
+            show_diagnostics: panels.diagnostics,
         }
     }
 
@@ -101,6 +282,12 @@ impl App {
 
         let toast_options = ToastOptions::default().duration_in_seconds(4.0);
 
+        let save_image = ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::S));
+        let undo = ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z));
+        let redo = ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Y));
+
+        self.presets.update(&ctx);
+
         egui::TopBottomPanel::top("Top Bar").show(&ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.style_mut().visuals.button_frame = false;
@@ -124,40 +311,173 @@ impl App {
                     self.file_dialog = Some(dialog);
                 }
 
+                ui.menu_button("Recent", |ui| {
+                    if self.recent.paths.is_empty() {
+                        ui.label("No recent files");
+                    }
+
+                    for path in &self.recent.paths {
+                        let name = path.file_name().unwrap_or(path.as_os_str());
+                        let button = ui
+                            .button(name.to_string_lossy())
+                            .on_hover_text(path.display().to_string());
+                        if button.clicked() {
+                            self.to_open = Some(path.clone());
+                            ui.close_menu();
+                        }
+                    }
+                });
+
+                if let Some(config) = ui::presets::show(ui, &self.presets) {
+                    self.config = config;
+                }
+
+                let pause = if self.paused { "Resume" } else { "Pause" };
+                if ui.button(pause).clicked() {
+                    self.paused = !self.paused;
+                }
+
+                if ui.button("Render").clicked() {
+                    self.show_render = true;
+                }
+
+                if ui.button("Compare").clicked() {
+                    if self.compare.is_none() {
+                        let config = self.config.clone();
+                        let mut compare = Compare::new(state.context(), config);
+                        if let Some(sky) = &self.sky {
+                            compare.view.set_stars(&sky.image);
+                        }
+                        self.compare = Some(compare);
+                    }
+                    self.dock.open(Tab::Compare);
+                }
+
+                if ui.button("Inspect").clicked() && self.inspector.is_none() {
+                    self.inspector = Some(Inspector::new());
+                }
+
+                if ui.button("Timeline").clicked() && self.timeline.is_none() {
+                    self.timeline = Some(Timeline::default());
+                }
+
+                if ui.button("Exposure").clicked() && self.histogram.is_none() {
+                    self.histogram = Some(Histogram::default());
+                }
+
+                if ui.button("History").clicked() {
+                    self.show_history = true;
+                }
+
+                let projector = ui
+                    .selectable_label(self.projector.is_some(), "Projector")
+                    .on_hover_text("Show the render in another window, without the gui");
+                if projector.clicked() {
+                    match self.projector.take() {
+                        Some(id) => {
+                            state.close_window(id);
+                        }
+                        None => {
+                            let builder = WindowBuilder::new().with_title("kerrbhy projector");
+                            match state.open_window(builder) {
+                                Ok(id) => self.projector = Some(id),
+                                Err(e) => {
+                                    log::error!(target: "projector", "failed to open window: {e}");
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if ui.button("Save image").clicked() || save_image {
+                    let mut dialog = FileDialog::save_file(dir.clone()).default_filename("out.png");
+                    dialog.open();
+                    self.image_dialog = Some(dialog);
+                }
+
+                let screenshot = ui
+                    .button("Screenshot")
+                    .on_hover_text("Save the window, with the gui");
+                if screenshot.clicked() {
+                    let mut dialog =
+                        FileDialog::save_file(dir.clone()).default_filename("screenshot.png");
+                    dialog.open();
+                    self.capture_dialog = Some(dialog);
+                }
+
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     ui.add_space(10.0);
 
+                    if ui.button("Diagnostics").clicked() {
+                        self.show_diagnostics = true;
+                    }
+
+                    let mut theme = self.gui.theme();
+                    if ui::theme::show(ui, &mut theme) {
+                        self.gui.set_theme(theme);
+                    }
+
+                    if ui.button("Logs").clicked() {
+                        self.dock.toggle(Tab::Logs);
+                    }
+
                     if ui.button("Profiler").clicked() {
-                        self.show_profiler = true;
-                        puffin::set_scopes_on(true);
+                        self.profiler_window.store(false, Ordering::Relaxed);
+                        self.dock.open(Tab::Profiler);
+                    }
+
+                    if ui.button("Settings").clicked() {
+                        self.dock.open(Tab::Settings);
                     }
                 });
             });
         });
 
-        egui::Area::new("Settings Area")
-            .anchor(egui::Align2::LEFT_TOP, [0.0, 0.0])
-            .show(&ctx, |ui| {
-                ui.collapsing("Settings", |ui| {
-                    ui.group(|ui| {
-                        ui.strong("Renderer");
-                        ui.checkbox(&mut vsync, "vsync");
-                        ui.checkbox(&mut self.accumulate, "accumulate");
-                    });
+        let mut dock = std::mem::replace(&mut self.dock, Dock::new(Vec::new()));
+        dock.show(
+            &ctx,
+            &mut Tabs {
+                app: self,
+                state,
+                toasts: &mut toasts,
+                toast_options,
+                vsync: &mut vsync,
+            },
+        );
+        self.dock = dock;
 
-                    ui::config::show(ui, &mut self.config);
-                });
+        let profiler_window = self.profiler_window.load(Ordering::Relaxed);
+        if profiler_window {
+            self.dock.close(&Tab::Profiler);
+        }
+        ui::profiler::window(&ctx, &self.profiler_window);
+
+        // closing a tab stops what it was showing
+        if !self.dock.is_open(&Tab::Compare) {
+            self.compare = None;
+        }
+        puffin::set_scopes_on(self.dock.is_open(&Tab::Profiler) || profiler_window);
+
+        if self.accumulate && self.convergence.measure(self.view.renderer()) {
+            self.accumulate = false;
+            toasts.add(Toast {
+                kind: ToastKind::Info,
+                text: "Converged, stopped accumulating".into(),
+                options: toast_options,
             });
+        }
 
         match ui::file_dialog::show(&ctx, self.file_dialog.as_mut(), &mut self.config) {
-            Ok(Some(ui::file_dialog::Action::Opened)) => {
+            Ok(Some(ui::file_dialog::Action::Opened(path))) => {
+                self.recent.push(path);
                 toasts.add(Toast {
                     kind: ToastKind::Success,
                     text: "Opened file".into(),
                     options: toast_options,
                 });
             }
-            Ok(Some(ui::file_dialog::Action::Saved)) => {
+            Ok(Some(ui::file_dialog::Action::Saved(path))) => {
+                self.recent.push(path);
                 toasts.add(Toast {
                     kind: ToastKind::Success,
                     text: "Saved file".into(),
@@ -170,20 +490,322 @@ impl App {
             }
         }
 
-        let profiler_open = egui::Window::new("Profiler")
-            .open(&mut self.show_profiler)
-            .show(&ctx, |ui| {
-                profiling::scope!("profiler");
-                puffin_egui::profiler_ui(ui);
-            })
-            .is_some();
+        if let Some(path) = self.to_open.take() {
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                match ui::file_dialog::open(&path, &mut self.config) {
+                    Ok(true) => {
+                        self.recent.push(path);
+                        toasts.add(Toast {
+                            kind: ToastKind::Success,
+                            text: "Opened file".into(),
+                            options: toast_options,
+                        });
+                    }
+                    Ok(false) => (),
+                    Err(e) => {
+                        log::error!(target: "file dialog", "{e}");
+                    }
+                }
+            } else {
+                toasts.add(Toast {
+                    kind: ToastKind::Warning,
+                    text: "Only .toml configs can be opened".into(),
+                    options: toast_options,
+                });
+            }
+        }
+
+        let sky_path = self.sky_dialog.as_mut().and_then(|dialog| {
+            let selected = dialog.show(&ctx).selected();
+            selected
+                .then(|| dialog.path().map(|path| path.to_owned()))
+                .flatten()
+        });
+
+        if let Some(path) = sky_path {
+            let max_size = state.device().limits().max_texture_dimension_2d;
+
+            match Sky::open(path, max_size) {
+                Ok(sky) => {
+                    self.thumbnails.load_thumbnail(sky.path.clone());
+                    self.sky = Some(sky);
+                    self.set_stars();
+                }
+                Err(e) => {
+                    log::error!(target: "sky", "{e:#}");
+                }
+            }
+        }
+
+        // pick up any edits to the shader
+        #[cfg(debug_assertions)]
+        match self.hot_reload.poll() {
+            Some(Ok(source)) => {
+                let mut reloaded = self.view.reload_shader(&source);
+                if let Some(compare) = &mut self.compare {
+                    reloaded = reloaded.and(compare.view.reload_shader(&source));
+                }
+
+                match reloaded {
+                    Ok(()) => {
+                        toasts.add(Toast {
+                            kind: ToastKind::Info,
+                            text: "Reloaded shader".into(),
+                            options: toast_options,
+                        });
+                    }
+                    Err(e) => {
+                        log::error!(target: "shader", "{e}");
+                    }
+                }
+            }
+            Some(Err(e)) => {
+                log::error!(target: "shader", "{e}");
+            }
+            None => (),
+        }
+
+        // pick up any edits to the starmap
+        match self.sky.as_mut().map(Sky::reload) {
+            Some(Ok(true)) => {
+                self.set_stars();
+                if let Some(sky) = &self.sky {
+                    self.thumbnails.load_thumbnail(sky.path.clone());
+                }
+                toasts.add(Toast {
+                    kind: ToastKind::Info,
+                    text: "Reloaded starmap".into(),
+                    options: toast_options,
+                });
+            }
+            Some(Err(e)) => {
+                log::error!(target: "sky", "{e:#}");
+            }
+            _ => (),
+        }
+
+        for (path, image) in self.thumbnails.finished() {
+            // the starmap changed again while this was decoding
+            if self.sky.as_ref().map(|sky| &sky.path) != Some(&path) {
+                continue;
+            }
+
+            match image {
+                Ok(image) => {
+                    let id = self
+                        .gui
+                        .register_image(&state.device(), &state.queue(), &image);
+                    if let Some(old) = self.sky_thumbnail.replace(id) {
+                        self.gui.free_image(old);
+                    }
+                }
+                Err(e) => {
+                    let path = path.display();
+                    log::error!(target: "sky", "failed to load thumbnail of {path}: {e}");
+                }
+            }
+        }
+
+        let image_path = self.image_dialog.as_mut().and_then(|dialog| {
+            let selected = dialog.show(&ctx).selected();
+            selected
+                .then(|| dialog.path().map(|path| path.to_owned()))
+                .flatten()
+        });
+
+        if let Some(path) = image_path {
+            let (width, height) = state.dimensions();
+
+            match screenshot::save(self.view.renderer(), width, height, &path) {
+                Ok(()) => {
+                    toasts.add(Toast {
+                        kind: ToastKind::Success,
+                        text: "Saved image".into(),
+                        options: toast_options,
+                    });
+                }
+                Err(e) => {
+                    log::error!(target: "screenshot", "{e:#}");
+                }
+            }
+        }
+
+        let capture_path = self.capture_dialog.as_mut().and_then(|dialog| {
+            let selected = dialog.show(&ctx).selected();
+            selected
+                .then(|| dialog.path().map(|path| path.to_owned()))
+                .flatten()
+        });
+
+        if let Some(path) = capture_path {
+            state.capture_frame(move |capture| {
+                let saved = capture
+                    .map_err(anyhow::Error::from)
+                    .and_then(|capture| screenshot::save_capture(capture, &path));
+
+                match saved {
+                    Ok(()) => log::info!(target: "screenshot", "saved to {}", path.display()),
+                    Err(e) => log::error!(target: "screenshot", "{e:#}"),
+                }
+            });
+        }
+
+        if let Some(compare) = &mut self.compare {
+            if compare.mode == Mode::Split {
+                ui::compare::split(&ctx, &mut compare.split);
+            }
+        }
+
+        if let Some(inspector) = &mut self.inspector {
+            let dimensions = state.dimensions();
+            if let Some(pixel) = ui::inspector::pick(&ctx, dimensions) {
+                inspector.inspect(self.view.renderer(), &self.config, dimensions, pixel);
+            }
+
+            let mut open = true;
+            ui::inspector::show(&ctx, &mut open, inspector.pixel.as_ref());
+
+            if !open {
+                self.inspector = None;
+            }
+        }
+
+        if let Some(timeline) = &mut self.timeline {
+            let mut open = true;
+            let action = ui::timeline::show(&ctx, &mut open, timeline, &self.config.bookmarks);
+
+            if let Some(ui::timeline::Action::Export) = action {
+                let dir = self
+                    .file_dialog
+                    .as_ref()
+                    .map(|fd| fd.directory().to_owned());
+                let mut dialog = FileDialog::save_file(dir).default_filename("path.toml");
+                dialog.open();
+                self.path_dialog = Some(dialog);
+            }
+
+            if timeline.preview {
+                match self.config.camera {
+                    common::Camera::Orbit(ref mut cam) => {
+                        timeline.path().apply(cam, timeline.time);
+                    }
+                }
+            }
+
+            if !open {
+                self.timeline = None;
+            }
+        }
+
+        let export_path = self.path_dialog.as_mut().and_then(|dialog| {
+            let selected = dialog.show(&ctx).selected();
+            selected
+                .then(|| dialog.path().map(|path| path.to_owned()))
+                .flatten()
+        });
+
+        if let (Some(path), Some(timeline)) = (export_path, &self.timeline) {
+            let saved = std::fs::File::create(&path)
+                .map_err(Into::into)
+                .and_then(|mut file| timeline.path().save(&mut file));
+
+            match saved {
+                Ok(()) => {
+                    toasts.add(Toast {
+                        kind: ToastKind::Success,
+                        text: "Exported camera path".into(),
+                        options: toast_options,
+                    });
+                }
+                Err(e) => {
+                    log::error!(target: "timeline", "failed to export {}: {e}", path.display());
+                }
+            }
+        }
+
+        if let Some(histogram) = &mut self.histogram {
+            histogram.update(self.view.renderer());
 
-        if puffin::are_scopes_on() && !profiler_open {
-            puffin::set_scopes_on(false);
+            let mut open = true;
+            ui::exposure::show(&ctx, &mut open, histogram, &mut self.false_color);
+
+            if !open {
+                self.histogram = None;
+                self.false_color = false;
+            }
+        }
+
+        let action = if self.show_history {
+            ui::history::show(&ctx, &mut self.show_history, &self.history)
+        } else {
+            None
+        };
+
+        match action {
+            Some(ui::history::Action::Undo(n)) => {
+                for _ in 0..n {
+                    self.history.undo(&mut self.config);
+                }
+            }
+            Some(ui::history::Action::Redo(n)) => {
+                for _ in 0..n {
+                    self.history.redo(&mut self.config);
+                }
+            }
+            None if undo => self.history.undo(&mut self.config),
+            None if redo => self.history.redo(&mut self.config),
+            None => (),
+        }
+
+        // wait until a drag is over, so it's recorded as one edit
+        if !ctx.input(|i| i.pointer.any_down()) {
+            self.history.record(&self.config);
+        }
+
+        let max_size = state.device().limits().max_texture_dimension_2d;
+        let progress = self.offline.as_ref().map(|render| render.progress());
+        match ui::render_dialog::show(
+            &ctx,
+            &mut self.show_render,
+            &mut self.render_settings,
+            max_size,
+            progress,
+        ) {
+            Some(ui::render_dialog::Action::Start) => {
+                self.offline = Some(offline::OfflineRender::start(
+                    state.device(),
+                    state.queue(),
+                    self.config.clone(),
+                    self.render_settings.clone(),
+                ));
+            }
+            Some(ui::render_dialog::Action::Cancel) => {
+                if let Some(render) = &self.offline {
+                    render.cancel();
+                }
+            }
+            None => (),
+        }
+
+        if let Some(result) = self.offline.as_mut().and_then(|render| render.finished()) {
+            self.offline = None;
+
+            match result {
+                Ok(path) => {
+                    toasts.add(Toast {
+                        kind: ToastKind::Success,
+                        text: format!("Rendered {}", path.display()).into(),
+                        options: toast_options,
+                    });
+                }
+                Err(e) => {
+                    log::error!(target: "render", "{e:#}");
+                }
+            }
         }
 
-        // read error notifications from channel
-        if let Ok(msg) = self.error_logs.try_recv() {
+        // notify of any errors logged
+        for msg in self.logs.update() {
             toasts.add(Toast {
                 kind: ToastKind::Error,
                 text: msg.into(),
@@ -191,54 +813,324 @@ impl App {
             });
         }
 
+        if self.show_frame_times {
+            ui::frame_times::show(&ctx, &self.frame_times, state.timer());
+        }
+
+        if self.show_diagnostics {
+            ui::diagnostics::show(
+                &ctx,
+                &mut self.show_diagnostics,
+                &self.adapter,
+                &self.adapters,
+                &state.device(),
+                state.surface_config(),
+                state.context().memory(),
+            );
+        }
+
         // show all the toasts at the end
         toasts.show(&ctx);
 
         state.set_vsync(vsync);
+        state.set_target_fps(self.target_fps);
+
+        // only draw on demand once there's nothing left to accumulate,
+        // though an offline render needs the progress bar to keep moving
+        let continuous = if self.paused {
+            self.offline.is_some()
+        } else {
+            !self.on_demand || self.accumulate || self.offline.is_some()
+        };
+
+        if continuous {
+            state.set_redraw(event::Redraw::Continuous);
+        } else {
+            state.set_redraw(event::Redraw::OnDemand);
+
+            // keep checking on what isn't seen as input
+            if self.sky.is_some() {
+                state.request_redraw_after(sky::POLL_INTERVAL);
+            }
+            #[cfg(debug_assertions)]
+            state.request_redraw_after(crate::hot_reload::POLL_INTERVAL);
+            if self.gamepad.is_connected() {
+                state.request_redraw_after(event::IDLE_REDRAW);
+            }
+        }
+    }
+
+    /// Show the settings of the renderer, the controls and the config.
+    fn settings_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &event::State,
+        toasts: &mut Toasts,
+        toast_options: ToastOptions,
+        vsync: &mut bool,
+    ) {
+        ui.group(|ui| {
+            ui.strong("Renderer");
+            ui.checkbox(vsync, "vsync");
+            ui.checkbox(&mut self.accumulate, "accumulate");
+            ui.checkbox(&mut self.on_demand, "render on demand")
+                .on_hover_text("Only draw after input once accumulation stops");
+
+            ui.horizontal(|ui| {
+                let mut limit = self.target_fps.is_some();
+                ui.checkbox(&mut limit, "limit fps")
+                    .on_hover_text("Save power by waiting between frames");
+
+                let fps = self.target_fps.get_or_insert(60);
+                ui.add_enabled(limit, egui::DragValue::new(fps).clamp_range(1..=240));
+                if !limit {
+                    self.target_fps = None;
+                }
+            });
+            ui.checkbox(&mut self.show_frame_times, "frame times");
+
+            ui.horizontal(|ui| {
+                let mut software = self.view.is_software();
+                ui.selectable_value(&mut software, false, "Hardware");
+                ui.selectable_value(&mut software, true, "Software");
+
+                if software != self.view.is_software() {
+                    self.view = View::new(state.context(), software);
+                    if let Some(sky) = &self.sky {
+                        self.view.set_stars(&sky.image);
+                    }
+                }
+            });
+
+            // only the software renderer keeps track of each ray
+            ui.add_enabled_ui(self.view.is_software(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Heatmap: ");
+                    ui.selectable_value(&mut self.heatmap, None, "None");
+                    ui.selectable_value(&mut self.heatmap, Some(Heatmap::Steps), "Steps");
+                    ui.selectable_value(&mut self.heatmap, Some(Heatmap::Bounces), "Bounces");
+                });
+            });
+        });
+
+        ui::convergence::show(ui, &mut self.convergence);
+
+        ui.group(|ui| {
+            ui.strong("Mouse");
+            let sensitivity = &mut self.sensitivity;
+            ui.add(
+                egui::Slider::new(&mut sensitivity.orbit, 0.0005..=0.05)
+                    .logarithmic(true)
+                    .text("Orbit"),
+            );
+            ui.add(
+                egui::Slider::new(&mut sensitivity.fov, 0.0002..=0.02)
+                    .logarithmic(true)
+                    .text("Fov"),
+            );
+            ui.add(
+                egui::Slider::new(&mut sensitivity.pan, 0.0001..=0.01)
+                    .logarithmic(true)
+                    .text("Pan"),
+            );
+        });
+
+        ui::config::show(ui, &mut self.config);
+        ui::bookmarks::show(ui, &mut self.config, &mut self.bookmark_name);
+
+        match ui::sky::show(ui, self.sky.as_ref(), self.sky_thumbnail) {
+            Some(ui::sky::Action::Open) => {
+                let mut dialog = FileDialog::open_file(None);
+                dialog.open();
+                self.sky_dialog = Some(dialog);
+            }
+            Some(ui::sky::Action::Reset) => {
+                // new views start with the built in starmap
+                self.sky = None;
+                if let Some(thumbnail) = self.sky_thumbnail.take() {
+                    self.gui.free_image(thumbnail);
+                }
+                let software = self.view.is_software();
+                self.view = View::new(state.context(), software);
+                if let Some(compare) = &mut self.compare {
+                    let software = compare.view.is_software();
+                    compare.view = View::new(state.context(), software);
+                }
+            }
+            None => (),
+        }
+
+        if ui::shadow::show(ui, self.shadow.as_ref()) {
+            let (width, height) = state.dimensions();
+            let frame = self.view.renderer().frame();
+            let fov = self.config.camera.fov();
+
+            self.shadow = common::shadow::measure(&frame, width, height, fov);
+            if self.shadow.is_none() {
+                toasts.add(Toast {
+                    kind: ToastKind::Warning,
+                    text: "No shadow found".into(),
+                    options: toast_options,
+                });
+            }
+        }
+    }
+
+    /// Use the chosen starmap in every view.
+    fn set_stars(&mut self) {
+        let Some(sky) = &self.sky else {
+            return;
+        };
+
+        self.view.set_stars(&sky.image);
+        if let Some(compare) = &mut self.compare {
+            compare.view.set_stars(&sky.image);
+        }
+    }
+}
+
+/// Fills in the docked tabs from the [`App`].
+struct Tabs<'a, 's> {
+    app: &'a mut App,
+    state: &'a event::State<'s>,
+    toasts: &'a mut Toasts,
+    toast_options: ToastOptions,
+    vsync: &'a mut bool,
+}
+
+impl TabViewer for Tabs<'_, '_> {
+    type Tab = Tab;
+
+    fn title(&mut self, tab: &mut Tab) -> egui::WidgetText {
+        format!("{tab:?}").into()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Tab) {
+        match tab {
+            Tab::Settings => {
+                self.app
+                    .settings_ui(ui, self.state, self.toasts, self.toast_options, self.vsync);
+            }
+            Tab::Profiler => {
+                if ui.button("Pop out").clicked() {
+                    self.app.profiler_window.store(true, Ordering::Relaxed);
+                }
+
+                profiling::scope!("profiler");
+                puffin_egui::profiler_ui(ui);
+            }
+            Tab::Logs => ui::logs::show(ui, &mut self.app.logs),
+            Tab::Compare => {
+                if let Some(compare) = &mut self.app.compare {
+                    ui::compare::show(ui, compare, &self.app.config, self.state.context());
+                }
+            }
+        }
+    }
+
+    fn scroll_bars(&self, tab: &Tab) -> [bool; 2] {
+        // these scroll themselves
+        match tab {
+            Tab::Profiler | Tab::Logs => [false, false],
+            Tab::Settings | Tab::Compare => [true, true],
+        }
     }
 }
 
-impl EventHandler for App {
+impl EventHandler<GuiEvent> for App {
     fn update(&mut self, state: &mut event::State) {
         let (width, height) = state.dimensions();
 
         let dt = state.timer().dt();
-        if self.keyboard.is_down(KeyCode::Space) {
+        let samples = self.view.renderer().sample_count();
+        self.frame_times.push_frame(samples);
+        let keyboard = state.input().keyboard();
+        let mouse = state.input().mouse();
+
+        if keyboard.modifiers().control_key() && keyboard.just_pressed(KeyCode::KeyQ) {
+            state.exit();
+        }
+
+        if keyboard.just_pressed(KeyCode::Space) {
             eprintln!("cleared!");
             self.profiler_id_cache.clear();
         }
 
+        self.gamepad.update();
+
+        // the face buttons toggle features
+        for button in self.gamepad.take_pressed() {
+            let feature = match button {
+                gilrs::Button::South => Features::DISK_VOL,
+                gilrs::Button::East => Features::DISK_SDF,
+                gilrs::Button::West => Features::SKY_PROC,
+                gilrs::Button::North => Features::BLOOM,
+                gilrs::Button::Start => {
+                    self.accumulate = !self.accumulate;
+                    continue;
+                }
+                _ => continue,
+            };
+
+            self.config.features.toggle(feature);
+        }
+
         // update the camera controls
         match self.config.camera {
             common::Camera::Orbit(ref mut cam) => {
                 let mut v = vec2(0.0, 0.0);
 
-                if self.keyboard.is_down(KeyCode::KeyW) {
+                if keyboard.is_down(KeyCode::KeyW) {
                     v.y += -1.0 * dt;
                 }
-                if self.keyboard.is_down(KeyCode::KeyS) {
+                if keyboard.is_down(KeyCode::KeyS) {
                     v.y += 1.0 * dt;
                 }
-                if self.keyboard.is_down(KeyCode::KeyA) {
+                if keyboard.is_down(KeyCode::KeyA) {
                     v.x += 1.0 * dt;
                 }
-                if self.keyboard.is_down(KeyCode::KeyD) {
+                if keyboard.is_down(KeyCode::KeyD) {
                     v.x += -1.0 * dt;
                 }
+                // the left stick orbits, like the keys
+                let stick = self.gamepad.left_stick();
+                v += vec2(-stick.x, -stick.y) * dt;
                 cam.orbit(v);
 
-                let zoom = -self.mouse.scroll_delta().y / input::Mouse::PIXELS_PER_LINE;
+                let zoom = -mouse.scroll_delta().y / event::Mouse::PIXELS_PER_LINE;
                 cam.zoom(zoom * dt);
+
+                // the right trigger zooms in, the left out
+                cam.zoom(-self.gamepad.triggers() * dt);
+
+                // drag with the mouse, unless the drag started on the gui
+                let drag = mouse.delta();
+                if mouse.left_down() {
+                    cam.orbit(vec2(drag.x, -drag.y) * self.sensitivity.orbit);
+                }
+                if mouse.right_down() {
+                    let fov = cam.fov.0 + drag.y * self.sensitivity.fov;
+                    cam.fov.0 = fov.clamp(30f32.to_radians(), 180f32.to_radians());
+                }
+                if mouse.middle_down() {
+                    cam.pan(vec2(-drag.x, drag.y) * self.sensitivity.pan);
+                }
             }
         };
 
-        self.mouse.smooth(dt);
-
-        self.renderer.update(width, height, self.config.clone());
+        self.view.update(width, height, self.config.clone());
+        self.view.set_heatmap(self.heatmap);
+        if let Some(compare) = &mut self.compare {
+            // only the settings are compared, so both follow the same camera
+            compare.config.camera = self.config.camera.clone();
+            compare.view.update(width, height, compare.config.clone());
+        }
 
         let ctx = self.gui.begin();
         self.ui(ctx, state);
         self.gui.end();
+
+        state.request_redraw_after(self.gui.repaint_delay());
     }
 
     fn draw(
@@ -252,12 +1144,43 @@ impl EventHandler for App {
             let encoder =
                 &mut Encoder::profiled(&self.profiler, encoder, "render", &state.device());
 
-            // only compute more work when it's needed
-            if self.accumulate || self.renderer.must_render() {
-                self.renderer.compute(encoder);
-            }
+            // only compute more work when it's needed,
+            // leaving the gpu to an offline render while one is running
+            let accumulate = self.accumulate && !self.paused && self.offline.is_none();
+            self.view.compute(encoder, accumulate);
+            self.fullscreen.set_false_color(self.false_color);
 
-            self.fullscreen.draw(encoder, &self.renderer.view(), target);
+            match &mut self.compare {
+                Some(compare) => {
+                    compare.view.compute(encoder, accumulate);
+
+                    match compare.mode {
+                        Mode::Split => {
+                            let (width, height) = state.dimensions();
+                            let split = (compare.split * width as f32).round() as u32;
+
+                            self.fullscreen.draw_split(
+                                encoder,
+                                (&self.view.view(), &compare.view.view()),
+                                target,
+                                split,
+                                (width, height),
+                            );
+                        }
+                        Mode::Flip => {
+                            let view = if compare.flipped {
+                                &compare.view
+                            } else {
+                                &self.view
+                            };
+                            self.fullscreen.draw(encoder, &view.view(), target);
+                        }
+                    }
+                }
+                None => {
+                    self.fullscreen.draw(encoder, &self.view.view(), target);
+                }
+            }
 
             self.gui.draw(state, encoder.inner(), target);
         }
@@ -267,24 +1190,127 @@ impl EventHandler for App {
         self.gpu_start = puffin::now_ns();
     }
 
-    fn event(&mut self, state: &event::State, event: event::Event<()>) -> bool {
-        let consumed = self.gui.handle_event(&event);
+    fn event(&mut self, _state: &event::State, event: event::Event<GuiEvent>) -> bool {
+        match &event {
+            event::Event::Window(WindowEvent::DroppedFile(path)) => {
+                self.to_open = Some(path.clone());
+            }
+            // closed by the event loop
+            event::Event::Child(id, WindowEvent::CloseRequested) if self.projector == Some(*id) => {
+                self.projector = None;
+            }
+            _ => (),
+        }
+
+        self.gui.handle_event(&event)
+    }
+
+    fn draw_window(
+        &mut self,
+        _state: &mut event::State,
+        id: WindowId,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        if self.projector == Some(id) {
+            let encoder = &mut Encoder::from(encoder);
+            self.fullscreen.draw(encoder, &self.view.view(), target);
+        }
+    }
 
-        if !consumed {
-            self.mouse.update_state(state.window(), &event);
-            self.keyboard.update_state(&event);
+    fn device_recreated(&mut self, state: &mut event::State) {
+        self.view = View::new(state.context(), self.view.is_software());
+        if let Some(compare) = &mut self.compare {
+            let software = compare.view.is_software();
+            compare.view = View::new(state.context(), software);
         }
+        self.set_stars();
+
+        self.fullscreen = Fullscreen::new(state.context());
+        self.gui.recreate(state.context());
+        self.profiler = profiler::gpu::GpuProfiler::new(Default::default()).unwrap();
 
-        consumed
+        // the thumbnail went with the old renderer
+        self.sky_thumbnail = None;
+        if let Some(sky) = &self.sky {
+            self.thumbnails.load_thumbnail(sky.path.clone());
+        }
+
+        // the render was on the old device too
+        if let Some(render) = self.offline.take() {
+            render.cancel();
+            log::warn!(target: "render", "the device was lost, cancelled the render");
+        }
+    }
+
+    fn exit(&mut self, state: &event::State) {
+        let window = state.window();
+
+        // keep the size from before going fullscreen
+        if window.fullscreen().is_none() {
+            let size = window.inner_size();
+            if let Ok(position) = window.outer_position() {
+                self.settings.window = Some(settings::Window {
+                    width: size.width,
+                    height: size.height,
+                    x: position.x,
+                    y: position.y,
+                });
+            }
+        }
+
+        self.settings.vsync = state.is_vsync();
+        self.settings.accumulate = self.accumulate;
+        self.settings.on_demand = self.on_demand;
+        self.settings.target_fps = self.target_fps;
+        self.settings.camera = Some(self.config.camera.clone());
+        self.settings.theme = self.gui.theme();
+        self.settings.dock = Some(self.dock.clone());
+        self.settings.panels = settings::Panels {
+            render: self.show_render,
+            history: self.show_history,
+            inspector: self.inspector.is_some(),
+            timeline: self.timeline.is_some(),
+            exposure: self.histogram.is_some(),
+            diagnostics: self.show_diagnostics,
+            frame_times: self.show_frame_times,
+        };
+
+        if let Err(e) = self.settings.save() {
+            log::error!(target: "settings", "failed to save settings: {e:#}");
+        }
     }
 
     fn frame_end(&mut self, state: &event::State) {
-        if self.profiler.end_frame().is_ok() {
-            let _ = self.profiler.send_to_puffin(
+        self.gui.draw_viewports(state);
+
+        match self.view.pipeline_statistics() {
+            Some(statistics) => {
+                statistics.end_frame();
+                self.frame_times.dispatches = statistics.results().to_vec();
+            }
+            None => self.frame_times.dispatches.clear(),
+        }
+
+        if self.profiler.end_frame().is_err() {
+            return;
+        }
+
+        let period = state.queue().get_timestamp_period();
+        if let Some(timings) = self.profiler.process_finished_frame(period) {
+            if !timings.is_empty() {
+                self.frame_times.push_gpu(&timings);
+                self.budget.check(&timings);
+            }
+
+            let _ = profiler::send_timings_to_puffin(
+                &timings,
                 self.gpu_start,
-                state.queue().get_timestamp_period(),
                 Some(&mut self.profiler_id_cache),
             );
+
+            #[cfg(feature = "tracy")]
+            let _ = profiler::send_timings_to_tracy(&timings, &mut self.tracy);
         }
     }
 }
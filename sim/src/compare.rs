@@ -0,0 +1,39 @@
+//! Comparing two configs side by side.
+//!
+//! The second config has its own [`View`], but follows the camera of the first,
+//! so the only differences on screen are the settings being compared.
+
+use common::Config;
+
+use crate::view::View;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Show `A` on the left of the split, and `B` on the right.
+    Split,
+    /// Show either `A` or `B`, flipping between them.
+    Flip,
+}
+
+pub struct Compare {
+    pub config: Config,
+    pub view: View,
+    pub mode: Mode,
+    /// Where the screen is split, as a fraction of its width.
+    pub split: f32,
+    /// Showing `B` in [`Mode::Flip`].
+    pub flipped: bool,
+}
+
+impl Compare {
+    /// Start comparing against a copy of `config`.
+    pub fn new(ctx: &graphics::Context, config: Config) -> Self {
+        Self {
+            config,
+            view: View::new(ctx, false),
+            mode: Mode::Split,
+            split: 0.5,
+            flipped: false,
+        }
+    }
+}
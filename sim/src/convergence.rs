@@ -0,0 +1,126 @@
+//! Measuring how close the accumulated frame is to converging.
+//!
+//! The frame is read back every few samples and compared against the last reading,
+//! so the cost of the read back is spread out.
+
+use common::Render;
+use glam::{
+    Vec3,
+    Vec4,
+    Vec4Swizzles as _,
+};
+
+/// The weights of each channel to find the luminance of a color.
+/// https://en.wikipedia.org/wiki/Relative_luminance
+const LUMINANCE: Vec3 = Vec3::new(0.2126, 0.7152, 0.0722);
+
+/// The change between two readings of the frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    /// The number of samples accumulated at the latest reading.
+    pub samples: u32,
+    /// The mean change in luminance of each pixel.
+    pub mean: f32,
+    /// The root mean square change in luminance, an estimate of the noise left.
+    pub rms: f32,
+    /// The fraction of pixels that changed by less than the tolerance.
+    pub converged: f32,
+}
+
+pub struct Convergence {
+    pub enabled: bool,
+    /// The number of samples between each reading.
+    pub interval: u32,
+    /// The largest change in luminance of a converged pixel.
+    pub tolerance: f32,
+    /// Stop accumulating once this fraction of the pixels has converged.
+    pub auto_stop: Option<f32>,
+
+    last: Option<(u32, Vec<f32>)>,
+    pub metrics: Option<Metrics>,
+}
+
+impl Default for Convergence {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 16,
+            tolerance: 1.0 / 255.0,
+            auto_stop: None,
+
+            last: None,
+            metrics: None,
+        }
+    }
+}
+
+impl Convergence {
+    /// Read the frame of `renderer` if enough samples have passed since the last reading.
+    ///
+    /// Returns true if the frame has converged enough to stop accumulating.
+    #[profiling::function]
+    pub fn measure(&mut self, renderer: &dyn Render) -> bool {
+        if !self.enabled {
+            self.last = None;
+            self.metrics = None;
+            return false;
+        }
+
+        let samples = renderer.sample_count();
+
+        match &self.last {
+            // accumulation has restarted
+            Some((last, _)) if samples < *last => {
+                self.last = None;
+                self.metrics = None;
+            }
+            Some((last, _)) if samples < last + self.interval.max(1) => return false,
+            _ => (),
+        }
+
+        let luminance: Vec<f32> = renderer
+            .frame_hdr()
+            .chunks_exact(4)
+            .map(|texel| Vec4::from_slice(texel).xyz().dot(LUMINANCE))
+            .collect();
+
+        if let Some((_, last)) = &self.last {
+            if last.len() == luminance.len() && !luminance.is_empty() {
+                self.metrics = Some(self.compare(samples, last, &luminance));
+            }
+        }
+
+        self.last = Some((samples, luminance));
+
+        match (self.auto_stop, self.metrics) {
+            (Some(target), Some(metrics)) => metrics.converged >= target,
+            _ => false,
+        }
+    }
+
+    fn compare(&self, samples: u32, last: &[f32], luminance: &[f32]) -> Metrics {
+        // sum in doubles, there can be millions of pixels
+        let mut sum = 0.0f64;
+        let mut sum_sq = 0.0f64;
+        let mut converged = 0u32;
+
+        for (a, b) in last.iter().zip(luminance) {
+            let delta = (b - a).abs();
+            sum += delta as f64;
+            sum_sq += (delta * delta) as f64;
+
+            if delta <= self.tolerance {
+                converged += 1;
+            }
+        }
+
+        let n = luminance.len() as f64;
+
+        Metrics {
+            samples,
+            mean: (sum / n) as f32,
+            rms: (sum_sq / n).sqrt() as f32,
+            converged: (converged as f64 / n) as f32,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! The recent history of how long each frame took on the gpu.
+//!
+//! The cpu times are kept by the [`event::Timer`].
+
+use std::collections::VecDeque;
+
+use profiler::{
+    gpu::GpuTimerQueryResult,
+    DispatchStatistics,
+    ScopeStats,
+};
+
+/// The number of frames kept.
+const LENGTH: usize = 300;
+
+#[derive(Default)]
+pub struct FrameTimes {
+    /// The time taken to render each frame on the gpu, in milliseconds.
+    ///
+    /// Empty if the adapter can't time queries.
+    pub gpu: VecDeque<f32>,
+    /// The time taken by each gpu scope, such as the passes of a frame.
+    pub scopes: ScopeStats,
+    /// The invocations of each dispatch in a recent frame.
+    ///
+    /// Empty if the adapter can't count them.
+    pub dispatches: Vec<DispatchStatistics>,
+    /// The samples accumulated at each frame.
+    pub samples: VecDeque<u32>,
+}
+
+impl FrameTimes {
+    /// Record a frame with `samples` accumulated.
+    pub fn push_frame(&mut self, samples: u32) {
+        push(&mut self.samples, samples);
+    }
+
+    /// Record the time the gpu took for a frame, and each of its scopes.
+    pub fn push_gpu(&mut self, timings: &[GpuTimerQueryResult]) {
+        let duration = profiler::frame_duration(timings);
+        push(&mut self.gpu, duration.as_secs_f32() * 1000.0);
+
+        self.scopes.push_frame(timings);
+    }
+}
+
+fn push<T>(values: &mut VecDeque<T>, value: T) {
+    if values.len() == LENGTH {
+        values.pop_front();
+    }
+    values.push_back(value);
+}
@@ -0,0 +1,70 @@
+pub use egui_dock::TabViewer;
+use egui_dock::{
+    DockArea,
+    DockState,
+    Style,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Windows docked together as tabs, in a panel along the right of the screen.
+///
+/// The tabs can be split, rearranged and torn off into their own windows,
+/// and the layout saved between sessions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dock<Tab> {
+    state: DockState<Tab>,
+}
+
+impl<Tab: PartialEq> Dock<Tab> {
+    pub fn new(tabs: Vec<Tab>) -> Self {
+        Self {
+            state: DockState::new(tabs),
+        }
+    }
+
+    pub fn is_open(&self, tab: &Tab) -> bool {
+        self.state.find_tab(tab).is_some()
+    }
+
+    /// Open the `tab`, or bring it to the front if it's already open.
+    pub fn open(&mut self, tab: Tab) {
+        match self.state.find_tab(&tab) {
+            Some(index) => self.state.set_active_tab(index),
+            None => self.state.push_to_focused_leaf(tab),
+        }
+    }
+
+    pub fn close(&mut self, tab: &Tab) {
+        if let Some(index) = self.state.find_tab(tab) {
+            self.state.remove_tab(index);
+        }
+    }
+
+    pub fn toggle(&mut self, tab: Tab) {
+        if self.is_open(&tab) {
+            self.close(&tab);
+        } else {
+            self.open(tab);
+        }
+    }
+
+    /// Show the open tabs, using the `viewer` to fill them in.
+    pub fn show(&mut self, ctx: &egui::Context, viewer: &mut impl TabViewer<Tab = Tab>) {
+        if self.state.iter_all_tabs().next().is_none() {
+            return;
+        }
+
+        egui::SidePanel::right("Dock")
+            .resizable(true)
+            .default_width(320.0)
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| {
+                DockArea::new(&mut self.state)
+                    .style(Style::from_egui(ctx.style().as_ref()))
+                    .show_inside(ui, viewer);
+            });
+    }
+}
@@ -0,0 +1,53 @@
+//! Loading images into textures the gui can show, such as thumbnails.
+
+use std::{
+    path::PathBuf,
+    sync::mpsc,
+};
+
+use image::DynamicImage;
+
+use super::GuiEvent;
+
+/// The longest side of a thumbnail, in pixels.
+pub const THUMBNAIL_SIZE: u32 = 128;
+
+/// Decodes images on other threads, so large files don't hold up the gui.
+pub struct ImageLoader {
+    /// Wakes the event loop once an image is decoded.
+    proxy: event::Proxy<GuiEvent>,
+    sender: mpsc::Sender<(PathBuf, image::ImageResult<DynamicImage>)>,
+    receiver: mpsc::Receiver<(PathBuf, image::ImageResult<DynamicImage>)>,
+}
+
+impl ImageLoader {
+    pub fn new(proxy: event::Proxy<GuiEvent>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            proxy,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Start decoding a thumbnail of the PNG, EXR or other image at `path`.
+    pub fn load_thumbnail(&self, path: PathBuf) {
+        let sender = self.sender.clone();
+        let proxy = self.proxy.clone();
+
+        std::thread::spawn(move || {
+            let image = image::open(&path).map(|i| i.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE));
+            if sender.send((path, image)).is_ok() {
+                let _ = proxy.send(GuiEvent::Repaint);
+            }
+        });
+    }
+
+    /// The images that have finished decoding since the last call.
+    pub fn finished(
+        &self,
+    ) -> impl Iterator<Item = (PathBuf, image::ImageResult<DynamicImage>)> + '_ {
+        self.receiver.try_iter()
+    }
+}
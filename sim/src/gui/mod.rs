@@ -1,10 +1,21 @@
 #![allow(unused)]
 
+mod dock;
+mod image;
 mod renderer;
 mod state;
+mod theme;
+mod viewport;
 
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::Duration,
+};
 
+pub use dock::{
+    Dock,
+    TabViewer,
+};
 use egui::{
     epaint::{
         self,
@@ -14,55 +25,136 @@ use egui::{
     TexturesDelta,
 };
 use graphics::wgpu;
+pub use image::{
+    ImageLoader,
+    THUMBNAIL_SIZE,
+};
+pub use renderer::{
+    Callback,
+    CallbackResources,
+    CallbackTrait,
+};
+pub use theme::{
+    Mode,
+    Theme,
+};
 use wgpu::RenderPassDescriptor;
-use winit::window::Window;
+use winit::{
+    event_loop::EventLoop,
+    window::Window,
+};
 
 struct PartialOutput {
     textures_delta: TexturesDelta,
     shapes: Vec<ClippedShape>,
 }
 
+/// Events sent to the gui through the event loop.
+#[derive(Debug)]
+pub enum GuiEvent {
+    /// Work on another thread finished, such as a thumbnail, so the gui needs drawing again.
+    Repaint,
+    /// A screen reader wants to interact with the gui.
+    #[cfg(feature = "accesskit")]
+    AccessKit(accesskit_winit::ActionRequestEvent),
+}
+
+#[cfg(feature = "accesskit")]
+impl From<accesskit_winit::ActionRequestEvent> for GuiEvent {
+    fn from(event: accesskit_winit::ActionRequestEvent) -> Self {
+        Self::AccessKit(event)
+    }
+}
+
 pub struct GuiState {
     window: Arc<Window>,
     renderer: renderer::Renderer,
     state: state::State,
+    theme: Theme,
+    /// The viewports shown in their own windows.
+    viewports: viewport::Viewports,
 
     // keep state over update/draw calls
     pixels_per_point: f32,
     partial: Option<PartialOutput>,
+    repaint_delay: Duration,
 }
 
 impl GuiState {
-    pub fn new(ctx: &graphics::Context) -> Self {
+    pub fn new(ctx: &graphics::Context, event_loop: &EventLoop<GuiEvent>) -> Self {
         let window = ctx.window().unwrap();
         let pixels_per_point = window.scale_factor() as f32;
 
         let context = egui::Context::default();
+        context.set_embed_viewports(false);
         let viewport = context.viewport_id();
 
-        let state = state::State::new(
-            context,
+        let mut state = state::State::new(
+            context.clone(),
             viewport,
             &window,
             Some(pixels_per_point),
             Some(ctx.device().limits().max_texture_dimension_2d as usize),
         );
 
+        #[cfg(feature = "accesskit")]
+        state.init_accesskit(&window, event_loop.create_proxy(), move || {
+            // only build the tree once a screen reader asks for it
+            context.enable_accesskit();
+            context.request_repaint();
+            context.accesskit_placeholder_tree_update()
+        });
+
         let renderer = renderer::Renderer::new(&ctx.device(), ctx.view_format().unwrap(), None, 1);
 
         Self {
             window,
             renderer,
             state,
+            theme: Theme::default(),
+            viewports: viewport::Viewports::default(),
             pixels_per_point,
             partial: None,
+            repaint_delay: Duration::MAX,
         }
     }
 
+    /// Make the renderer again with the device of the `ctx`, such as after the old one was lost.
+    ///
+    /// Images registered with the old renderer are gone, and have to be registered again.
+    pub fn recreate(&mut self, ctx: &graphics::Context) {
+        self.renderer = renderer::Renderer::new(&ctx.device(), ctx.view_format().unwrap(), None, 1);
+        self.viewports.close();
+
+        // egui only uploads the font atlas when it changes, so send it again
+        let context = self.context();
+        let fonts = context.fonts(|fonts| fonts.image());
+        context.tex_manager().write().set(
+            epaint::TextureId::default(),
+            epaint::ImageDelta::full(fonts, epaint::textures::TextureOptions::LINEAR),
+        );
+    }
+
     pub fn context(&self) -> egui::Context {
         self.state.egui_ctx().clone()
     }
 
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Restyle the gui with a new `theme`.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.context().set_visuals(theme.visuals());
+    }
+
+    /// How long until the gui needs drawing again, such as to animate,
+    /// [`Duration::MAX`] if only after input.
+    pub fn repaint_delay(&self) -> Duration {
+        self.repaint_delay
+    }
+
     pub fn begin(&mut self) -> egui::Context {
         // update state
         // state::update_viewport_info(viewport_info, &self.context(), &self.window);
@@ -79,10 +171,17 @@ impl GuiState {
             textures_delta,
             shapes,
             pixels_per_point,
-            // Ignore viewports for the time being, they're not used!
-            viewport_output: _,
+            viewport_output,
         } = self.context().end_frame();
 
+        // the other viewports are drawn along with the main window
+        self.repaint_delay = viewport_output
+            .values()
+            .map(|output| output.repaint_delay)
+            .min()
+            .unwrap_or(Duration::MAX);
+        self.viewports.update(viewport_output);
+
         self.pixels_per_point = pixels_per_point;
 
         self.state
@@ -123,8 +222,18 @@ impl GuiState {
             pixels_per_point: self.pixels_per_point,
         };
 
-        self.renderer
-            .update_buffers(device, queue, paint_jobs.as_slice(), screen_descriptor);
+        let user_cmd_bufs = self.renderer.update_buffers(
+            device,
+            queue,
+            encoder,
+            paint_jobs.as_slice(),
+            screen_descriptor,
+        );
+
+        // work for the paint callbacks has to happen before they're drawn
+        if !user_cmd_bufs.is_empty() {
+            queue.submit(user_cmd_bufs);
+        }
 
         {
             let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
@@ -151,15 +260,44 @@ impl GuiState {
         }
     }
 
-    pub fn handle_event<T>(&mut self, event: &event::Event<T>) -> bool {
-        if let event::Event::Window(window_event) = event {
-            let response = self.state.on_window_event(&self.window, window_event);
-            response.consumed
-        } else {
-            false
+    pub fn handle_event(&mut self, event: &event::Event<GuiEvent>) -> bool {
+        match event {
+            event::Event::Window(window_event) => {
+                let response = self.state.on_window_event(&self.window, window_event);
+                response.consumed
+            }
+            event::Event::Child(id, window_event) => {
+                self.viewports.on_window_event(*id, window_event)
+            }
+            event::Event::User(event) => match *event {
+                GuiEvent::Repaint => {
+                    self.context().request_repaint();
+                    true
+                }
+                #[cfg(feature = "accesskit")]
+                GuiEvent::AccessKit(ref event) => {
+                    self.state
+                        .on_accesskit_action_request(event.request.clone());
+                    self.window.request_redraw();
+                    true
+                }
+            },
         }
     }
 
+    /// Draw the viewports shown in their own windows.
+    ///
+    /// Call after the main window has been drawn, they share the same buffers.
+    pub fn draw_viewports(&mut self, state: &event::State) {
+        let ctx = self.context();
+        self.viewports.draw(&ctx, &mut self.renderer, state);
+    }
+
+    /// Storage for the resources used by [`CallbackTrait`]s, such as pipelines and bind groups.
+    pub fn callback_resources_mut(&mut self) -> &mut CallbackResources {
+        &mut self.renderer.callback_resources
+    }
+
     /// Fetches text from the clipboard and returns it.
     pub fn clipboard_text(&mut self) -> Option<String> {
         self.state.clipboard_text()
@@ -182,6 +320,55 @@ impl GuiState {
         self.renderer.texture(id)
     }
 
+    /// Uploads an `image` to a texture the gui can show.
+    ///
+    /// HDR images, such as EXRs, are kept in linear light.
+    /// Free the texture with [`GuiState::free_image`] once it's no longer shown.
+    pub fn register_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &::image::DynamicImage,
+    ) -> epaint::TextureId {
+        use wgpu::util::DeviceExt as _;
+
+        // the linear values come back unchanged, the same as decoding srgb
+        let format = match image {
+            ::image::DynamicImage::ImageRgb32F(_) | ::image::DynamicImage::ImageRgba32F(_) => {
+                wgpu::TextureFormat::Rgba8Unorm
+            }
+            _ => wgpu::TextureFormat::Rgba8UnormSrgb,
+        };
+
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("gui image"),
+                size: wgpu::Extent3d {
+                    width: image.width(),
+                    height: image.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &image.to_rgba8(),
+        );
+
+        let view = texture.create_view(&Default::default());
+        self.register_native_texture(device, &view, wgpu::FilterMode::Linear)
+    }
+
+    /// Frees a texture made by [`GuiState::register_image`].
+    pub fn free_image(&mut self, id: epaint::TextureId) {
+        self.renderer.free_texture(&id);
+    }
+
     /// Registers a `wgpu::Texture` with a `epaint::TextureId`.
     ///
     /// This enables the application to reference the texture inside an image ui
@@ -9,6 +9,8 @@ use egui::{
 
 pub mod clipboard;
 
+#[cfg(feature = "accesskit")]
+use egui::accesskit;
 use raw_window_handle::HasDisplayHandle;
 use winit::window::Window;
 
@@ -140,7 +142,6 @@ impl State {
         event_loop_proxy: winit::event_loop::EventLoopProxy<T>,
         initial_tree_update_factory: impl 'static + FnOnce() -> accesskit::TreeUpdate + Send,
     ) {
-        crate::profile_function!();
         self.accesskit = Some(accesskit_winit::Adapter::new(
             window,
             initial_tree_update_factory,
@@ -768,6 +769,8 @@ impl State {
             events: _,                    // handled elsewhere
             mutable_text_under_cursor: _, // only used in eframe web
             ime,
+            #[cfg(feature = "accesskit")]
+            accesskit_update,
         } = platform_output;
 
         self.set_cursor_icon(window, cursor_icon);
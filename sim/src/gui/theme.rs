@@ -0,0 +1,55 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The base colors of the gui.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    #[default]
+    Dark,
+    Light,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub mode: Mode,
+    /// The color of selections and links, instead of the one from the `mode`.
+    pub accent: Option<[u8; 3]>,
+}
+
+impl Theme {
+    /// The egui visuals of the theme, flattened to suit the sim.
+    pub fn visuals(&self) -> egui::Visuals {
+        let mut visuals = match self.mode {
+            Mode::Dark => egui::Visuals::dark(),
+            Mode::Light => egui::Visuals::light(),
+        };
+
+        visuals.window_shadow = egui::epaint::Shadow::NONE;
+        visuals.window_rounding = egui::Rounding::ZERO;
+        visuals.widgets.active.rounding = egui::Rounding::ZERO;
+        visuals.widgets.open.rounding = egui::Rounding::ZERO;
+        visuals.widgets.inactive.rounding = egui::Rounding::ZERO;
+        visuals.widgets.hovered.rounding = egui::Rounding::ZERO;
+        visuals.widgets.noninteractive.rounding = egui::Rounding::ZERO;
+
+        if let Some([r, g, b]) = self.accent {
+            let accent = egui::Color32::from_rgb(r, g, b);
+            visuals.selection.bg_fill = accent;
+            visuals.selection.stroke.color = visuals.strong_text_color();
+            visuals.hyperlink_color = accent;
+        }
+
+        visuals
+    }
+
+    /// The accent color shown by the theme, even without a custom one.
+    pub fn accent_color(&self) -> [u8; 3] {
+        self.accent.unwrap_or_else(|| {
+            let [r, g, b, _] = self.visuals().selection.bg_fill.to_array();
+            [r, g, b]
+        })
+    }
+}
@@ -0,0 +1,365 @@
+//! Viewports shown in their own native windows, see [`egui::viewport`].
+//!
+//! Only deferred viewports get a window, immediate ones are embedded in their parent.
+
+use std::sync::Arc;
+
+use egui::{
+    DeferredViewportUiCallback,
+    FullOutput,
+    ViewportBuilder,
+    ViewportCommand,
+    ViewportId,
+    ViewportIdMap,
+    ViewportOutput,
+};
+use graphics::wgpu;
+use winit::{
+    dpi::{
+        LogicalPosition,
+        LogicalSize,
+    },
+    event::WindowEvent,
+    window::{
+        Window,
+        WindowBuilder,
+        WindowId,
+    },
+};
+
+use super::{
+    renderer,
+    state,
+};
+
+struct Viewport {
+    builder: ViewportBuilder,
+    ui: Arc<DeferredViewportUiCallback>,
+    commands: Vec<ViewportCommand>,
+    /// Opened the next time the viewports are drawn.
+    window: Option<ViewportWindow>,
+    /// Don't keep trying to open a window that failed.
+    failed: bool,
+}
+
+struct ViewportWindow {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+    state: state::State,
+}
+
+#[derive(Default)]
+pub struct Viewports {
+    viewports: ViewportIdMap<Viewport>,
+}
+
+impl Viewports {
+    /// Close the windows of the viewports, they're opened again the next time they're drawn.
+    pub fn close(&mut self) {
+        for viewport in self.viewports.values_mut() {
+            viewport.window = None;
+        }
+    }
+
+    /// Keep track of the viewports in the `output` of a frame, closing any that are gone.
+    pub fn update(&mut self, output: ViewportIdMap<ViewportOutput>) {
+        self.viewports.retain(|id, _| output.contains_key(id));
+
+        for (id, output) in output {
+            // the root, which is drawn to the main window
+            let Some(ui) = output.viewport_ui_cb else {
+                continue;
+            };
+
+            match self.viewports.get_mut(&id) {
+                Some(viewport) => {
+                    let (commands, recreate) = viewport.builder.patch(output.builder);
+                    if recreate {
+                        viewport.window = None;
+                    }
+
+                    viewport.ui = ui;
+                    viewport.commands.extend(commands);
+                    viewport.commands.extend(output.commands);
+                }
+                None => {
+                    self.viewports.insert(
+                        id,
+                        Viewport {
+                            builder: output.builder,
+                            ui,
+                            commands: output.commands,
+                            window: None,
+                            failed: false,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Pass on an `event` for the window `id` to its viewport, returning true if it was consumed.
+    pub fn on_window_event(&mut self, id: WindowId, event: &WindowEvent) -> bool {
+        let found = self
+            .viewports
+            .iter_mut()
+            .find_map(|(&viewport_id, viewport)| {
+                viewport
+                    .window
+                    .as_mut()
+                    .filter(|window| window.window.id() == id)
+                    .map(|window| (viewport_id, window))
+            });
+
+        let Some((viewport_id, window)) = found else {
+            return false;
+        };
+
+        // let the viewport decide whether to close
+        if let WindowEvent::CloseRequested = event {
+            request_close(&mut window.state, viewport_id);
+        }
+
+        window.state.on_window_event(&window.window, event).consumed
+    }
+
+    /// Run the gui of each viewport and draw it to its window, opening windows as needed.
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        renderer: &mut renderer::Renderer,
+        state: &event::State,
+    ) {
+        let mut outputs = Vec::new();
+
+        for (&id, viewport) in &mut self.viewports {
+            if viewport.window.is_none() && !viewport.failed {
+                match open_window(ctx, id, &viewport.builder, state) {
+                    Ok(window) => viewport.window = Some(window),
+                    Err(e) => {
+                        log::error!(target: "gui", "failed to open a window for a viewport: {e:#}");
+                        viewport.failed = true;
+                    }
+                }
+            }
+
+            let Some(window) = &mut viewport.window else {
+                continue;
+            };
+
+            for command in viewport.commands.drain(..) {
+                apply_command(window, id, command);
+            }
+
+            let input = window.state.take_egui_input(&window.window);
+            let ui = Arc::clone(&viewport.ui);
+            let mut output = ctx.run(input, |ctx| ui(ctx));
+
+            let platform_output = std::mem::take(&mut output.platform_output);
+            window
+                .state
+                .handle_platform_output(&window.window, platform_output);
+
+            outputs.push(draw_window(window, ctx, renderer, state, output));
+        }
+
+        // the viewports opened by other viewports
+        if let Some(output) = outputs.pop() {
+            self.update(output);
+        }
+    }
+}
+
+fn open_window(
+    ctx: &egui::Context,
+    id: ViewportId,
+    builder: &ViewportBuilder,
+    state: &event::State,
+) -> anyhow::Result<ViewportWindow> {
+    let window = state.create_window(window_builder(builder))?;
+    let surface = state.create_surface(Arc::clone(&window))?;
+
+    let size = window.inner_size();
+    let config = wgpu::SurfaceConfiguration {
+        width: size.width.max(1),
+        height: size.height.max(1),
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        ..state.surface_config().clone()
+    };
+    surface.configure(&state.device(), &config);
+
+    let state = state::State::new(
+        ctx.clone(),
+        id,
+        &*window,
+        Some(window.scale_factor() as f32),
+        Some(state.device().limits().max_texture_dimension_2d as usize),
+    );
+
+    Ok(ViewportWindow {
+        window,
+        surface,
+        config,
+        state,
+    })
+}
+
+/// Draw the `output` of a viewport, returning the viewports in it.
+fn draw_window(
+    window: &mut ViewportWindow,
+    ctx: &egui::Context,
+    renderer: &mut renderer::Renderer,
+    state: &event::State,
+    output: FullOutput,
+) -> ViewportIdMap<ViewportOutput> {
+    let device = &state.device();
+    let queue = &state.queue();
+
+    let FullOutput {
+        textures_delta,
+        shapes,
+        pixels_per_point,
+        viewport_output,
+        ..
+    } = output;
+
+    // textures are shared with the other viewports
+    for (id, delta) in textures_delta.set {
+        renderer.update_texture(device, queue, id, &delta);
+    }
+
+    let size = window.window.inner_size();
+    if size.width > 0 && size.height > 0 {
+        if (size.width, size.height) != (window.config.width, window.config.height) {
+            window.config.width = size.width;
+            window.config.height = size.height;
+            window.surface.configure(device, &window.config);
+        }
+
+        match window.surface.get_current_texture() {
+            Ok(frame) => {
+                let paint_jobs = ctx.tessellate(shapes, pixels_per_point);
+                let screen_descriptor = &renderer::ScreenDescriptor {
+                    size_in_pixels: [window.config.width, window.config.height],
+                    pixels_per_point,
+                };
+
+                let target = frame.texture.create_view(&Default::default());
+                let mut encoder = device.create_command_encoder(&Default::default());
+
+                let mut command_buffers = renderer.update_buffers(
+                    device,
+                    queue,
+                    &mut encoder,
+                    &paint_jobs,
+                    screen_descriptor,
+                );
+
+                {
+                    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("viewport pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &target,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                store: wgpu::StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+
+                    renderer.render(&mut pass, &paint_jobs, screen_descriptor);
+                }
+
+                command_buffers.push(encoder.finish());
+                queue.submit(command_buffers);
+                frame.present();
+            }
+            // try again next frame
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                window.surface.configure(device, &window.config);
+            }
+            Err(e) => {
+                log::error!(target: "gui", "failed to get viewport texture: {e}");
+            }
+        }
+    }
+
+    for id in textures_delta.free {
+        renderer.free_texture(&id);
+    }
+
+    viewport_output
+}
+
+fn window_builder(builder: &ViewportBuilder) -> WindowBuilder {
+    let title = builder
+        .title
+        .clone()
+        .unwrap_or_else(|| "kerrbhy".to_owned());
+
+    let mut window = WindowBuilder::new()
+        .with_title(title)
+        .with_resizable(builder.resizable.unwrap_or(true))
+        .with_decorations(builder.decorations.unwrap_or(true))
+        .with_transparent(builder.transparent.unwrap_or(false))
+        .with_visible(builder.visible.unwrap_or(true));
+
+    if let Some(size) = builder.inner_size {
+        window = window.with_inner_size(LogicalSize::new(size.x, size.y));
+    }
+    if let Some(size) = builder.min_inner_size {
+        window = window.with_min_inner_size(LogicalSize::new(size.x, size.y));
+    }
+    if let Some(size) = builder.max_inner_size {
+        window = window.with_max_inner_size(LogicalSize::new(size.x, size.y));
+    }
+    if let Some(position) = builder.position {
+        window = window.with_position(LogicalPosition::new(position.x, position.y));
+    }
+
+    window
+}
+
+fn apply_command(window: &mut ViewportWindow, id: ViewportId, command: ViewportCommand) {
+    let w = &window.window;
+
+    match command {
+        ViewportCommand::Close => request_close(&mut window.state, id),
+        ViewportCommand::Title(title) => w.set_title(&title),
+        ViewportCommand::Visible(visible) => w.set_visible(visible),
+        ViewportCommand::OuterPosition(position) => {
+            w.set_outer_position(LogicalPosition::new(position.x, position.y));
+        }
+        ViewportCommand::InnerSize(size) => {
+            let _ = w.request_inner_size(LogicalSize::new(size.x, size.y));
+        }
+        ViewportCommand::MinInnerSize(size) => {
+            w.set_min_inner_size(Some(LogicalSize::new(size.x, size.y)));
+        }
+        ViewportCommand::MaxInnerSize(size) => {
+            w.set_max_inner_size(Some(LogicalSize::new(size.x, size.y)));
+        }
+        ViewportCommand::Resizable(resizable) => w.set_resizable(resizable),
+        ViewportCommand::Decorations(decorations) => w.set_decorations(decorations),
+        ViewportCommand::Minimized(minimized) => w.set_minimized(minimized),
+        ViewportCommand::Maximized(maximized) => w.set_maximized(maximized),
+        ViewportCommand::Focus => w.focus_window(),
+        command => log::debug!(target: "gui", "unsupported viewport command {command:?}"),
+    }
+}
+
+/// Tell the gui of the viewport `id` that it was asked to close.
+fn request_close(state: &mut state::State, id: ViewportId) {
+    state
+        .egui_input_mut()
+        .viewports
+        .entry(id)
+        .or_default()
+        .events
+        .push(egui::ViewportEvent::Close);
+}
@@ -0,0 +1,75 @@
+//! A histogram of the luminance of the frame, to judge its exposure.
+
+use common::Render;
+use glam::{
+    Vec3,
+    Vec4,
+    Vec4Swizzles as _,
+};
+
+/// The weights of each channel to find the luminance of a color.
+/// https://en.wikipedia.org/wiki/Relative_luminance
+const LUMINANCE: Vec3 = Vec3::new(0.2126, 0.7152, 0.0722);
+
+/// The number of bins between black and white.
+pub const BINS: usize = 64;
+
+/// The number of samples between each reading of the frame.
+const INTERVAL: u32 = 8;
+
+pub struct Histogram {
+    /// The number of pixels in each bin, from black to white.
+    pub bins: [u32; BINS],
+    /// The number of pixels with a channel at or above white.
+    pub clipped: u32,
+    pub pixels: u32,
+
+    /// The number of samples at the last reading.
+    samples: Option<u32>,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            bins: [0; BINS],
+            clipped: 0,
+            pixels: 0,
+
+            samples: None,
+        }
+    }
+}
+
+impl Histogram {
+    /// Read the frame of `renderer` again, if it has changed enough since the last reading.
+    #[profiling::function]
+    pub fn update(&mut self, renderer: &dyn Render) {
+        let samples = renderer.sample_count();
+
+        let stale = match self.samples {
+            Some(last) => samples < last || samples >= last + INTERVAL,
+            None => true,
+        };
+        if !stale {
+            return;
+        }
+
+        self.bins = [0; BINS];
+        self.clipped = 0;
+        self.pixels = 0;
+
+        for texel in renderer.frame_hdr().chunks_exact(4) {
+            let color = Vec4::from_slice(texel).xyz();
+
+            if color.max_element() >= 1.0 {
+                self.clipped += 1;
+            }
+
+            let bin = (color.dot(LUMINANCE) * BINS as f32) as usize;
+            self.bins[bin.min(BINS - 1)] += 1;
+            self.pixels += 1;
+        }
+
+        self.samples = Some(samples);
+    }
+}
@@ -0,0 +1,91 @@
+//! Undoing and redoing edits to the config.
+//!
+//! Snapshots of the config are taken once an edit settles,
+//! so dragging a slider is a single edit rather than one per frame.
+//! Moving the camera isn't recorded, it would fill the history in seconds.
+
+use common::Config;
+
+/// The most edits that can be undone.
+const LIMIT: usize = 100;
+
+/// The config before an edit, and the fields the edit changed.
+pub struct Entry {
+    pub config: Config,
+    pub label: String,
+}
+
+pub struct History {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+    /// The config as of the last edit.
+    current: Config,
+}
+
+impl History {
+    pub fn new(config: Config) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current: config,
+        }
+    }
+
+    /// Record the `config` as an edit, if it changed anything but the camera.
+    pub fn record(&mut self, config: &Config) {
+        let mut changed = config.clone();
+        changed.camera = self.current.camera.clone();
+
+        let keys = self.current.diff(&changed);
+        if keys.is_empty() {
+            return;
+        }
+
+        let config = std::mem::replace(&mut self.current, config.clone());
+        self.undo.push(Entry {
+            config,
+            label: keys.join(", "),
+        });
+        if self.undo.len() > LIMIT {
+            self.undo.remove(0);
+        }
+
+        self.redo.clear();
+    }
+
+    /// Undo the last edit to the `config`.
+    pub fn undo(&mut self, config: &mut Config) {
+        Self::step(&mut self.undo, &mut self.redo, &mut self.current, config);
+    }
+
+    /// Redo the last undone edit to the `config`.
+    pub fn redo(&mut self, config: &mut Config) {
+        Self::step(&mut self.redo, &mut self.undo, &mut self.current, config);
+    }
+
+    /// The edits that can be undone, the most recent last.
+    pub fn undo_stack(&self) -> &[Entry] {
+        &self.undo
+    }
+
+    /// The edits that can be redone, the next last.
+    pub fn redo_stack(&self) -> &[Entry] {
+        &self.redo
+    }
+
+    fn step(from: &mut Vec<Entry>, to: &mut Vec<Entry>, current: &mut Config, config: &mut Config) {
+        let Some(entry) = from.pop() else {
+            return;
+        };
+
+        // keep looking from the same place
+        let mut restored = entry.config;
+        restored.camera = config.camera.clone();
+
+        to.push(Entry {
+            config: std::mem::replace(current, restored.clone()),
+            label: entry.label,
+        });
+        *config = restored;
+    }
+}
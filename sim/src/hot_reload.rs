@@ -0,0 +1,88 @@
+//! Rebuilding the compute shader as its source is edited, without a full rebuild.
+//!
+//! Only in debug builds, as it reads the shaders from the source tree.
+
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+    },
+};
+
+/// The shader of the hardware renderer.
+const SHADER: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../shaders/marcher/src/shader.wgsl"
+);
+
+/// How often the files are checked for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct HotReload {
+    /// The shader and every file it includes, with when they were last modified.
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    checked: Instant,
+}
+
+impl HotReload {
+    pub fn new() -> Self {
+        let mut reload = Self {
+            files: Vec::new(),
+            checked: Instant::now(),
+        };
+
+        // the shader was built with what's on disk now
+        if let Ok(shader) = wgsl_bindgen::load_shader(SHADER) {
+            reload.watch(&shader);
+        } else {
+            reload.files = vec![(PathBuf::from(SHADER), modified(Path::new(SHADER)))];
+        }
+
+        reload
+    }
+
+    /// The new source of the shader, if any of its files have changed.
+    pub fn poll(&mut self) -> Option<Result<String, wgsl_bindgen::Error>> {
+        if self.checked.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.checked = Instant::now();
+
+        let changed = self
+            .files
+            .iter()
+            .any(|(path, time)| modified(path) != *time);
+        if !changed {
+            return None;
+        }
+
+        // only try once for each change, even if it fails to load
+        for (path, time) in &mut self.files {
+            *time = modified(path);
+        }
+
+        let result = wgsl_bindgen::load_shader(SHADER).map(|shader| {
+            // an edit could include more files
+            self.watch(&shader);
+            shader.wgsl()
+        });
+
+        Some(result)
+    }
+
+    fn watch(&mut self, shader: &wgsl_bindgen::ProcessedShader) {
+        self.files = std::iter::once(Path::new(SHADER))
+            .chain(shader.includes())
+            .map(|path| (path.to_owned(), modified(path)))
+            .collect();
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
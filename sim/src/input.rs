@@ -1,147 +1,115 @@
-use std::collections::HashMap;
-
-use event::Event;
 use glam::{
     vec2,
     Vec2,
 };
-use winit::{
-    event::{
-        MouseButton,
-        WindowEvent,
-    },
-    keyboard::{
-        KeyCode,
-        ModifiersState,
-        PhysicalKey,
-    },
-    window::Window,
-};
 
-pub struct Mouse {
-    pos: Vec2,
-    scroll_delta: Vec2,
-    button_states: HashMap<MouseButton, bool>,
+/// How far the camera moves for each pixel the mouse is dragged.
+pub struct Sensitivity {
+    /// Radians orbited, while dragging with the left button.
+    pub orbit: f32,
+    /// Radians the fov changes by, while dragging with the right button.
+    pub fov: f32,
+    /// The fraction of the distance to the target moved,
+    /// while dragging with the middle button.
+    pub pan: f32,
 }
 
-impl Default for Mouse {
+impl Default for Sensitivity {
     fn default() -> Self {
         Self {
-            pos: Vec2::ZERO,
-            scroll_delta: Vec2::ZERO,
-            button_states: Default::default(),
+            orbit: 0.005,
+            fov: 0.002,
+            pan: 0.001,
         }
     }
 }
 
-#[allow(dead_code)]
-impl Mouse {
-    pub const PIXELS_PER_LINE: f32 = 50.0;
+/// The gamepads connected to the machine.
+///
+/// Only the first connected gamepad moves the camera.
+pub struct Gamepad {
+    /// [`None`] if gamepads aren't supported on this platform.
+    gilrs: Option<gilrs::Gilrs>,
+    /// The buttons pressed since they were last taken.
+    pressed: Vec<gilrs::Button>,
+}
+
+impl Gamepad {
+    /// Sticks resting closer than this to the center don't move.
+    const DEAD_ZONE: f32 = 0.1;
 
     pub fn new() -> Self {
-        Self::default()
+        let gilrs = gilrs::Gilrs::new()
+            .map_err(|e| log::warn!(target: "gamepad", "gamepads are unavailable: {e}"))
+            .ok();
+
+        Self {
+            gilrs,
+            pressed: Vec::new(),
+        }
     }
 
-    pub fn update_state(&mut self, window: &Window, event: &Event<()>) {
-        if let Event::Window(e) = event {
-            match e {
-                WindowEvent::CursorMoved { position, .. } => {
-                    self.pos.x = position.x as f32;
-                    self.pos.y = position.y as f32;
-                }
-                WindowEvent::MouseInput { state, button, .. } => {
-                    let down = state.is_pressed();
-                    self.button_states
-                        .entry(*button)
-                        .and_modify(|e| *e = down)
-                        .or_insert(down);
-                }
-                WindowEvent::MouseWheel { delta, .. } => {
-                    self.scroll_delta = match *delta {
-                        winit::event::MouseScrollDelta::LineDelta(x, y) => {
-                            vec2(x, y) * Self::PIXELS_PER_LINE
-                        }
-                        winit::event::MouseScrollDelta::PixelDelta(delta) => {
-                            vec2(delta.x as f32, delta.y as f32) / window.scale_factor() as f32
-                        }
-                    }
+    /// Read the events sent by the gamepads since the last update.
+    pub fn update(&mut self) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                gilrs::EventType::ButtonPressed(button, _) => self.pressed.push(button),
+                gilrs::EventType::Connected => {
+                    log::info!(target: "gamepad", "{} connected", gilrs.gamepad(event.id).name());
                 }
                 _ => (),
             }
         }
     }
 
-    pub fn smooth(&mut self, dt: f32) {
-        const DECAY_RATE: f32 = 5.0;
-        // moving at 1/4th of a pixel
-        const CLOSE_TO_ZERO: f32 = 0.25;
-
-        let decay = (-DECAY_RATE * dt).exp();
-        let smoothed = self.scroll_delta * decay;
-
-        // stop moving if the velocity is close to zero
-        if smoothed.abs_diff_eq(Vec2::ZERO, CLOSE_TO_ZERO) {
-            self.scroll_delta = Vec2::ZERO
-        } else {
-            self.scroll_delta = smoothed;
-        }
-    }
-
-    pub fn pos(&self) -> Vec2 {
-        self.pos
+    /// Whether a gamepad is connected, its sticks aren't seen as events by the window.
+    pub fn is_connected(&self) -> bool {
+        self.active().is_some()
     }
 
-    pub fn scroll_delta(&self) -> Vec2 {
-        self.scroll_delta
+    fn active(&self) -> Option<gilrs::Gamepad<'_>> {
+        self.gilrs
+            .as_ref()?
+            .gamepads()
+            .next()
+            .map(|(_, gamepad)| gamepad)
     }
 
-    pub fn clicked(&self, button: MouseButton) -> bool {
-        self.button_states
-            .get(&button)
-            .is_some_and(|&down| down)
-    }
+    /// The position of the left stick, with `y` pointing up.
+    pub fn left_stick(&self) -> Vec2 {
+        let Some(gamepad) = self.active() else {
+            return Vec2::ZERO;
+        };
 
-    pub fn left_clicked(&self) -> bool {
-        self.clicked(MouseButton::Left)
-    }
+        let stick = vec2(
+            gamepad.value(gilrs::Axis::LeftStickX),
+            gamepad.value(gilrs::Axis::LeftStickY),
+        );
 
-    pub fn right_clicked(&self) -> bool {
-        self.clicked(MouseButton::Right)
+        if stick.length() < Self::DEAD_ZONE {
+            Vec2::ZERO
+        } else {
+            stick
+        }
     }
-}
 
-#[derive(Default)]
-pub struct Keyboard {
-    key_states: HashMap<KeyCode, bool>,
-    modifiers: ModifiersState,
-}
+    /// How far the right trigger is pulled, minus the left trigger.
+    pub fn triggers(&self) -> f32 {
+        let Some(gamepad) = self.active() else {
+            return 0.0;
+        };
 
-impl Keyboard {
-    pub fn new() -> Self {
-        Self::default()
-    }
+        let trigger = |button| gamepad.button_data(button).map_or(0.0, |data| data.value());
 
-    pub fn update_state(&mut self, event: &Event<()>) {
-        if let Event::Window(e) = event {
-            match e {
-                WindowEvent::KeyboardInput { event, .. } => {
-                    if let PhysicalKey::Code(key) = event.physical_key {
-                        let down = event.state.is_pressed();
-                        self.key_states
-                            .entry(key)
-                            .and_modify(|e| *e = down)
-                            .or_insert(down);
-                    }
-                }
-                WindowEvent::ModifiersChanged(modifiers) => {
-                    self.modifiers = modifiers.state();
-                }
-                _ => (),
-            }
-        }
+        trigger(gilrs::Button::RightTrigger2) - trigger(gilrs::Button::LeftTrigger2)
     }
 
-    pub fn is_down(&self, key: KeyCode) -> bool {
-        self.key_states.get(&key).is_some_and(|&down| down)
+    /// The buttons pressed since the last call.
+    pub fn take_pressed(&mut self) -> Vec<gilrs::Button> {
+        std::mem::take(&mut self.pressed)
     }
 }
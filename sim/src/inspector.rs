@@ -0,0 +1,81 @@
+//! Inspecting single pixels of the frame.
+//!
+//! Neither renderer keeps how each ray ended,
+//! so the ray through an inspected pixel is traced again by the software renderer.
+
+use common::{
+    Config,
+    Features,
+    Render,
+};
+use glam::{
+    UVec2,
+    Vec3,
+    Vec4,
+};
+use software_renderer::Trace;
+
+/// Everything known about an inspected pixel.
+#[derive(Debug, Clone, Copy)]
+pub struct Pixel {
+    pub position: UVec2,
+    /// The value stored in the frame.
+    pub value: Vec4,
+    /// The value as linear light, if the frame holds a color.
+    pub linear: Option<Vec3>,
+    /// The number of samples accumulated into the value.
+    pub samples: u32,
+    pub trace: Trace,
+}
+
+pub struct Inspector {
+    tracer: software_renderer::Renderer,
+    pub pixel: Option<Pixel>,
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        Self {
+            tracer: software_renderer::Renderer::new(1, 1, Config::default()),
+            pixel: None,
+        }
+    }
+
+    /// Inspect the pixel at `position` of the frame being accumulated by `renderer`.
+    #[profiling::function]
+    pub fn inspect(
+        &mut self,
+        renderer: &dyn Render,
+        config: &Config,
+        (width, height): (u32, u32),
+        position: UVec2,
+    ) {
+        let i = (position.y * width + position.x) as usize * 4;
+        let value = Vec4::from_slice(&renderer.frame_hdr()[i..i + 4]);
+
+        // frames are encoded for display, unless asked for linear light or polarization
+        let linear = if config.features.contains(Features::POLARIZATION) {
+            None
+        } else if config.features.contains(Features::LINEAR_OUTPUT) {
+            Some(value.truncate())
+        } else {
+            Some(srgb_to_linear(value.truncate()))
+        };
+
+        self.tracer.update(width, height, config.clone());
+
+        self.pixel = Some(Pixel {
+            position,
+            value,
+            linear,
+            samples: renderer.sample_count(),
+            trace: self.tracer.trace(position),
+        });
+    }
+}
+
+fn srgb_to_linear(color: Vec3) -> Vec3 {
+    let lower = color / 12.92;
+    let higher = ((color + 0.055) / 1.055).powf(2.4);
+    Vec3::select(color.cmplt(Vec3::splat(0.04045)), lower, higher)
+}
@@ -0,0 +1,63 @@
+//! Keeping the recent log lines, so they can be read after the fact.
+
+use std::{
+    collections::VecDeque,
+    sync::mpsc,
+};
+
+/// The most lines that are kept.
+const LIMIT: usize = 1000;
+
+/// A single logged message.
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub level: log::Level,
+    pub target: String,
+    pub message: String,
+    pub time: time::OffsetDateTime,
+}
+
+pub struct Logs {
+    receiver: mpsc::Receiver<Line>,
+    pub lines: VecDeque<Line>,
+    /// Only show lines at or above this level.
+    pub level: log::LevelFilter,
+    /// Only show lines with a target containing this.
+    pub target: String,
+}
+
+impl Logs {
+    pub fn new(receiver: mpsc::Receiver<Line>) -> Self {
+        Self {
+            receiver,
+            lines: VecDeque::new(),
+            level: log::LevelFilter::Info,
+            target: String::new(),
+        }
+    }
+
+    /// Keep the lines logged since the last update, returning the errors among them.
+    pub fn update(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for line in self.receiver.try_iter() {
+            if line.level == log::Level::Error {
+                errors.push(line.message.clone());
+            }
+
+            self.lines.push_back(line);
+            if self.lines.len() > LIMIT {
+                self.lines.pop_front();
+            }
+        }
+
+        errors
+    }
+
+    /// The lines that pass the filters.
+    pub fn filtered(&self) -> impl Iterator<Item = &Line> {
+        self.lines
+            .iter()
+            .filter(|line| line.level <= self.level && line.target.contains(&self.target))
+    }
+}
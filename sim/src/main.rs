@@ -1,39 +1,189 @@
 mod app;
+mod compare;
+mod convergence;
+mod frame_times;
 mod gui;
+mod histogram;
+mod history;
+#[cfg(debug_assertions)]
+mod hot_reload;
 mod input;
+mod inspector;
+mod logs;
+mod offline;
+mod presets;
+mod recent;
+mod screenshot;
+mod settings;
+mod sky;
+mod software;
+mod timeline;
 mod ui;
+mod view;
 
-use std::sync::mpsc;
+use std::{
+    path::PathBuf,
+    sync::mpsc,
+    time::Duration,
+};
 
+use anyhow::Context as _;
+use clap::Parser;
+use common::Config;
+use directories::ProjectDirs;
 use graphics::wgpu;
+use hardware_renderer::Renderer as HardwareRenderer;
+use settings::Settings;
 use time::format_description::well_known::Rfc3339;
 use winit::{
-    dpi::PhysicalSize,
-    window::WindowBuilder,
+    dpi::{
+        PhysicalPosition,
+        PhysicalSize,
+    },
+    window::{
+        Fullscreen,
+        WindowBuilder,
+    },
 };
 
+#[derive(Parser, Debug, Clone)]
+struct Cli {
+    /// A config file to start with.
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// Start in borderless fullscreen.
+    #[clap(long)]
+    fullscreen: bool,
+
+    /// The index or name of the GPU to use, listed in the diagnostics window.
+    #[clap(long)]
+    adapter: Option<graphics::AdapterSelector>,
+
+    /// Draw to an sRGB surface, where the window supports one.
+    #[clap(long)]
+    srgb: bool,
+
+    /// Record a trace of the wgpu calls to this directory, with the `trace` feature.
+    #[clap(long)]
+    trace: Option<PathBuf>,
+
+    /// Warn when a frame takes longer than this on the gpu, in milliseconds.
+    #[clap(long, value_name = "MS", value_parser = parse_budget_ms)]
+    gpu_budget: Option<f32>,
+
+    /// Warn when the gpu scopes with a label take longer than this in a frame,
+    /// e.g. `--scope-budget marcher=8`.
+    ///
+    /// Can be given multiple times.
+    #[clap(long, value_name = "LABEL=MS", value_parser = parse_scope_budget)]
+    scope_budget: Vec<(String, f32)>,
+}
+
+fn parse_scope_budget(s: &str) -> Result<(String, f32), String> {
+    let (label, ms) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected LABEL=MS, found `{s}`"))?;
+    let ms = parse_budget_ms(ms.trim())?;
+
+    Ok((label.trim().to_owned(), ms))
+}
+
+/// A budget in milliseconds, which has to fit in a [`Duration`].
+fn parse_budget_ms(s: &str) -> Result<f32, String> {
+    let ms: f32 = s
+        .parse()
+        .map_err(|e| format!("invalid budget `{s}`: {e}"))?;
+
+    if !ms.is_finite() || ms < 0.0 {
+        return Err(format!("invalid budget `{s}`: expected 0ms or more"));
+    }
+
+    Ok(ms)
+}
+
+/// The budgets given on the command line.
+fn budget(cli: &Cli) -> profiler::FrameBudget {
+    let ms = |ms: f32| Duration::from_secs_f32(ms / 1000.0);
+
+    let mut budget = profiler::FrameBudget::new();
+    if let Some(frame) = cli.gpu_budget {
+        budget = budget.with_frame(ms(frame));
+    }
+    for (label, scope) in &cli.scope_budget {
+        budget = budget.with_scope(label, ms(*scope));
+    }
+
+    budget
+}
+
 fn main() -> anyhow::Result<()> {
-    let error_logs = init_logger()?;
+    let cli = Cli::parse();
+
+    let logs = init_logger()?;
+
+    let settings = Settings::load();
+
+    let config = match &cli.config {
+        Some(path) => Config::load_from_path(path)
+            .with_context(|| format!("failed to load config from {}", path.display()))?,
+        // carry on from where the last session left off
+        None => {
+            let mut config = Config::default();
+            if let Some(camera) = &settings.camera {
+                config.camera = camera.clone();
+            }
+            config
+        }
+    };
+
+    let budget = budget(&cli);
 
     let event_loop = event::EventLoopBuilder::with_user_event().build()?;
     let window = WindowBuilder::new().with_title("Kerrbhy");
 
+    let window = match settings.window {
+        Some(w) => window
+            .with_inner_size(PhysicalSize::new(w.width, w.height))
+            .with_position(PhysicalPosition::new(w.x, w.y)),
+        None => window.with_inner_size(PhysicalSize::new(600, 600)),
+    };
+
     let window = window
-        .with_inner_size(PhysicalSize::new(600, 600))
-        .with_min_inner_size(PhysicalSize::new(400, 400));
+        .with_min_inner_size(PhysicalSize::new(400, 400))
+        .with_fullscreen(cli.fullscreen.then_some(Fullscreen::Borderless(None)));
 
-    let cb = graphics::ContextBuilder::new(
-        |adapter| adapter.features(),
-        wgpu::Limits::downlevel_defaults(),
-    )
-    .with_window(window);
+    let cb = graphics::ContextBuilder::new(HardwareRenderer::FEATURES, HardwareRenderer::limits())
+        .with_optional_features(profiler::FEATURES | profiler::PIPELINE_STATISTICS_FEATURES)
+        // larger skies and renders, as far as the adapter allows
+        .with_optional_limits(wgpu::Limits {
+            max_texture_dimension_2d: u32::MAX,
+            max_buffer_size: u64::MAX,
+            ..HardwareRenderer::limits()
+        })
+        .with_window(window)
+        .with_vsync(settings.vsync)
+        .with_srgb(cli.srgb)
+        .with_adapter(cli.adapter.unwrap_or_default());
 
-    event::run(event_loop, cb, |el, ctx| app::App::new(el, ctx, error_logs))?;
+    let cb = match cli.trace {
+        Some(path) => cb.with_trace_path(path),
+        None => cb,
+    };
+
+    event::run(event_loop, cb, |el, ctx| {
+        app::App::new(el, ctx, logs, config, settings, budget)
+    })?;
 
     Ok(())
 }
 
-fn init_logger() -> Result<mpsc::Receiver<String>, fern::InitError> {
+/// The directory the sim keeps its files in.
+fn config_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "kerrbhy").map(|dirs| dirs.config_dir().to_owned())
+}
+
+fn init_logger() -> Result<mpsc::Receiver<logs::Line>, fern::InitError> {
     const LOG_LEVEL_ENV: &str = "KERRBHY_LOG";
 
     // try and get the log level and parse it from ENV
@@ -53,10 +203,12 @@ fn init_logger() -> Result<mpsc::Receiver<String>, fern::InitError> {
     let (tx, rx) = mpsc::channel();
 
     fern::Dispatch::new()
-        .level(level)
+        // the log panel always keeps info and above
+        .level(level.max(log::LevelFilter::Info))
         // output to std-error with as much info as possible
         .chain(
             fern::Dispatch::new()
+                .level(level)
                 .format(|out, message, record| {
                     out.finish(format_args!(
                         "[{} {} {}] {}",
@@ -68,13 +220,15 @@ fn init_logger() -> Result<mpsc::Receiver<String>, fern::InitError> {
                 })
                 .chain(std::io::stderr()),
         )
-        // output simple errors to the channel
-        .chain(
-            fern::Dispatch::new()
-                .format(|out, message, _| out.finish(format_args!("{}", message)))
-                .level(log::LevelFilter::Error)
-                .chain(fern::Output::sender(tx, "")),
-        )
+        // output every line to the channel, for the log panel
+        .chain(fern::Output::call(move |record| {
+            let _ = tx.send(logs::Line {
+                level: record.level(),
+                target: record.target().to_owned(),
+                message: record.args().to_string(),
+                time: time::OffsetDateTime::now_utc(),
+            });
+        }))
         .apply()?;
 
     Ok(rx)
@@ -0,0 +1,172 @@
+//! Rendering a config at a high resolution, in the background.
+//!
+//! The render has its own [`Renderer`], so the interactive view keeps running.
+//! A turntable orbits the camera a full turn, saving an image for each frame.
+
+use std::{
+    f32::consts::TAU,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicU32,
+            Ordering,
+        },
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use common::Config;
+use glam::vec2;
+use graphics::wgpu;
+use hardware_renderer::{
+    Render,
+    Renderer,
+};
+
+use crate::screenshot;
+
+/// What to render, chosen in the render dialog.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub path: PathBuf,
+    /// Render this many frames of a turntable, instead of a single image.
+    pub turntable: Option<u32>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            samples: 256,
+            path: PathBuf::from("render.png"),
+            turntable: None,
+        }
+    }
+}
+
+/// A render running on a worker thread.
+pub struct OfflineRender {
+    samples: u32,
+    computed: Arc<AtomicU32>,
+    cancelled: Arc<AtomicBool>,
+    worker: Option<JoinHandle<anyhow::Result<PathBuf>>>,
+}
+
+impl OfflineRender {
+    /// Start rendering `config` with the `settings` on a worker thread.
+    pub fn start(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        mut config: Config,
+        settings: Settings,
+    ) -> Self {
+        let frames = settings.turntable.unwrap_or(1);
+        // every sample of every frame
+        let total = settings.samples * frames;
+        let computed = Arc::new(AtomicU32::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let worker = {
+            let computed = Arc::clone(&computed);
+            let cancelled = Arc::clone(&cancelled);
+
+            std::thread::spawn(move || {
+                let Settings {
+                    width,
+                    height,
+                    samples,
+                    path,
+                    turntable,
+                } = settings;
+
+                let mut renderer = Renderer::from_device(Arc::clone(&device), queue);
+
+                for frame in 0..frames {
+                    renderer.update(width, height, config.clone());
+
+                    for sample in 0..samples {
+                        if cancelled.load(Ordering::Relaxed) {
+                            anyhow::bail!("render was cancelled");
+                        }
+
+                        renderer.compute_sample();
+
+                        // wait for each sample, so the interactive view can share the gpu
+                        device.poll(wgpu::Maintain::Wait).panic_on_timeout();
+
+                        computed.store(frame * samples + sample + 1, Ordering::Relaxed);
+                    }
+
+                    let output = match turntable {
+                        Some(_) => frame_path(&path, &frame.to_string()),
+                        None => path.clone(),
+                    };
+                    screenshot::save(&renderer, width, height, &output)?;
+
+                    match config.camera {
+                        common::Camera::Orbit(ref mut cam) => {
+                            cam.orbit(vec2(TAU / frames as f32, 0.0));
+                        }
+                    }
+                }
+
+                match turntable {
+                    Some(_) => Ok(frame_path(&path, "####")),
+                    None => Ok(path),
+                }
+            })
+        };
+
+        Self {
+            samples: total,
+            computed,
+            cancelled,
+            worker: Some(worker),
+        }
+    }
+
+    /// The fraction of the samples that have been computed.
+    pub fn progress(&self) -> f32 {
+        self.computed.load(Ordering::Relaxed) as f32 / self.samples.max(1) as f32
+    }
+
+    /// Stop the render early, without saving it.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// The path of the saved image, once the render has finished.
+    pub fn finished(&mut self) -> Option<anyhow::Result<PathBuf>> {
+        if !self.worker.as_ref()?.is_finished() {
+            return None;
+        }
+
+        let result = self
+            .worker
+            .take()?
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("render panicked")));
+
+        Some(result)
+    }
+}
+
+/// The `path` of a turntable frame, with the `frame` added to the end of the name.
+fn frame_path(path: &Path, frame: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let frame = format!("{frame:0>4}");
+
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{stem}_{frame}.{}", ext.to_string_lossy())),
+        None => path.with_file_name(format!("{stem}_{frame}")),
+    }
+}
@@ -0,0 +1,96 @@
+//! Configs built into the sim, to start exploring from.
+//!
+//! A thumbnail of each preset is rendered on another thread by the software renderer,
+//! so opening the sim isn't held up.
+
+use std::sync::mpsc;
+
+use common::{
+    Config,
+    Render,
+};
+
+use crate::gui::GuiEvent;
+
+/// The size of each thumbnail, in pixels.
+const THUMBNAIL_SIZE: u32 = 64;
+/// The samples accumulated into each thumbnail.
+const THUMBNAIL_SAMPLES: u32 = 4;
+
+const PRESETS: [(&str, &str); 4] = [
+    ("Thin disk", include_str!("../presets/thin_disk.toml")),
+    (
+        "Volumetric disk",
+        include_str!("../presets/volumetric_disk.toml"),
+    ),
+    (
+        "Procedural sky",
+        include_str!("../presets/procedural_sky.toml"),
+    ),
+    (
+        "Adaptive integrator",
+        include_str!("../presets/adaptive.toml"),
+    ),
+];
+
+pub struct Preset {
+    pub name: &'static str,
+    pub config: Config,
+    pub thumbnail: Option<egui::TextureHandle>,
+}
+
+pub struct Gallery {
+    pub presets: Vec<Preset>,
+    thumbnails: mpsc::Receiver<(usize, Vec<u8>)>,
+}
+
+impl Gallery {
+    /// Load the presets, and start rendering their thumbnails.
+    ///
+    /// The `proxy` wakes the event loop as each one finishes.
+    pub fn new(proxy: event::Proxy<GuiEvent>) -> Self {
+        let presets: Vec<_> = PRESETS
+            .iter()
+            .map(|&(name, toml)| Preset {
+                name,
+                config: Config::load(toml).expect("presets are valid configs"),
+                thumbnail: None,
+            })
+            .collect();
+
+        let (tx, rx) = mpsc::channel();
+        let configs: Vec<_> = presets.iter().map(|p| p.config.clone()).collect();
+
+        std::thread::spawn(move || {
+            for (i, config) in configs.into_iter().enumerate() {
+                let mut renderer =
+                    software_renderer::Renderer::new(THUMBNAIL_SIZE, THUMBNAIL_SIZE, config);
+                for _ in 0..THUMBNAIL_SAMPLES {
+                    renderer.compute_sample();
+                }
+
+                // the sim has closed
+                if tx.send((i, renderer.into_frame())).is_err() {
+                    return;
+                }
+                let _ = proxy.send(GuiEvent::Repaint);
+            }
+        });
+
+        Self {
+            presets,
+            thumbnails: rx,
+        }
+    }
+
+    /// Upload any thumbnails that have finished rendering.
+    pub fn update(&mut self, ctx: &egui::Context) {
+        for (i, frame) in self.thumbnails.try_iter() {
+            let size = [THUMBNAIL_SIZE as usize; 2];
+            let image = egui::ColorImage::from_rgba_unmultiplied(size, &frame);
+
+            let preset = &mut self.presets[i];
+            preset.thumbnail = Some(ctx.load_texture(preset.name, image, Default::default()));
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! The config files opened or saved most recently.
+//!
+//! The list is kept in the platform's config directory, one path per line.
+
+use std::path::PathBuf;
+
+/// The most files that are remembered.
+const LIMIT: usize = 10;
+
+#[derive(Default)]
+pub struct Recent {
+    /// The most recent file first.
+    pub paths: Vec<PathBuf>,
+}
+
+impl Recent {
+    /// Load the list saved by the last run, or an empty list if there isn't one.
+    pub fn load() -> Self {
+        let paths = file()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|contents| contents.lines().map(PathBuf::from).collect())
+            .unwrap_or_default();
+
+        Self { paths }
+    }
+
+    /// Move the `path` to the front of the list, and save it.
+    pub fn push(&mut self, path: PathBuf) {
+        // store absolute paths, so they still work from another directory
+        let path = path.canonicalize().unwrap_or(path);
+
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(LIMIT);
+
+        if let Err(e) = self.save() {
+            log::warn!(target: "recent", "failed to save recent files: {e}");
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(file) = file() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let contents = self
+            .paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        std::fs::write(file, contents)
+    }
+}
+
+/// The file the list is kept in.
+fn file() -> Option<PathBuf> {
+    crate::config_dir().map(|dir| dir.join("recent.txt"))
+}
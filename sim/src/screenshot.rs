@@ -0,0 +1,36 @@
+//! Saving the frame being accumulated, without restarting it.
+
+use std::path::Path;
+
+use anyhow::Context as _;
+use common::Render;
+
+/// Save the current frame of the `renderer` to `path`.
+///
+/// `.exr` files are saved as floats of linear light,
+/// every other format is inferred from the extension.
+pub fn save(renderer: &dyn Render, width: u32, height: u32, path: &Path) -> anyhow::Result<()> {
+    let is_exr = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exr"));
+
+    let result = if is_exr {
+        image::Rgba32FImage::from_raw(width, height, renderer.frame_linear())
+            .context("frame is the wrong size")?
+            .save(path)
+    } else {
+        image::RgbaImage::from_raw(width, height, renderer.frame())
+            .context("frame is the wrong size")?
+            .save(path)
+    };
+
+    result.with_context(|| format!("failed to save image to {}", path.display()))
+}
+
+/// Save a `capture` of the window to `path`, the format is inferred from the extension.
+pub fn save_capture(capture: event::Capture, path: &Path) -> anyhow::Result<()> {
+    image::RgbaImage::from_raw(capture.width, capture.height, capture.rgba)
+        .context("capture is the wrong size")?
+        .save(path)
+        .with_context(|| format!("failed to save screenshot to {}", path.display()))
+}
@@ -0,0 +1,108 @@
+//! The state of the sim, kept between sessions.
+//!
+//! Saved to the platform's config directory when the sim exits,
+//! and restored when it's next launched.
+
+use std::path::PathBuf;
+
+use common::Camera;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    app::Tab,
+    gui::{
+        Dock,
+        Theme,
+    },
+};
+
+/// The size and position of the window, in physical pixels.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Window {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Which of the windows were open.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Panels {
+    pub render: bool,
+    pub history: bool,
+    pub inspector: bool,
+    pub timeline: bool,
+    pub exposure: bool,
+    pub diagnostics: bool,
+    pub frame_times: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window: Option<Window>,
+    pub vsync: bool,
+    pub accumulate: bool,
+    pub on_demand: bool,
+    /// Limit the frame rate, even without vsync.
+    pub target_fps: Option<u32>,
+    /// The camera of the config when the sim exited.
+    pub camera: Option<Camera>,
+    pub panels: Panels,
+    pub theme: Theme,
+    /// The layout of the docked tabs.
+    pub dock: Option<Dock<Tab>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            window: None,
+            vsync: true,
+            accumulate: true,
+            on_demand: false,
+            target_fps: None,
+            camera: None,
+            panels: Panels::default(),
+            theme: Theme::default(),
+            dock: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Load the settings saved by the last session, or the defaults if there aren't any.
+    pub fn load() -> Self {
+        let Some(contents) = file().and_then(|file| std::fs::read_to_string(file).ok()) else {
+            return Self::default();
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|e| {
+            log::warn!(target: "settings", "failed to read settings: {e}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(file) = file() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(file, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// The file the settings are kept in.
+fn file() -> Option<PathBuf> {
+    crate::config_dir().map(|dir| dir.join("settings.toml"))
+}
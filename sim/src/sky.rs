@@ -0,0 +1,82 @@
+//! A starmap chosen in place of the built in one.
+//!
+//! The file is watched while it's in use, so an edited starmap is reloaded straight away.
+
+use std::{
+    path::PathBuf,
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+    },
+};
+
+use anyhow::Context as _;
+
+/// How often the file is checked for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Sky {
+    pub path: PathBuf,
+    pub image: image::DynamicImage,
+    /// The largest width or height of the image, so it fits in a texture.
+    max_size: u32,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl Sky {
+    /// Load the equirectangular starmap at `path`.
+    ///
+    /// Starmaps wider or taller than `max_size` are scaled down to fit,
+    /// such as `max_texture_dimension_2d` of the device.
+    pub fn open(path: PathBuf, max_size: u32) -> anyhow::Result<Self> {
+        let modified = modified(&path);
+        let mut image = image::open(&path)
+            .with_context(|| format!("failed to load starmap from {}", path.display()))?;
+
+        if image.width() > max_size || image.height() > max_size {
+            log::warn!(
+                target: "sky",
+                "scaling the {}x{} starmap down to fit in {max_size}x{max_size}",
+                image.width(),
+                image.height()
+            );
+
+            image = image.resize(max_size, max_size, image::imageops::FilterType::Triangle);
+        }
+
+        Ok(Self {
+            path,
+            image,
+            max_size,
+            modified,
+            checked: Instant::now(),
+        })
+    }
+
+    /// Load the starmap again if the file has changed since it was last loaded.
+    ///
+    /// Returns `true` if the image was reloaded.
+    pub fn reload(&mut self) -> anyhow::Result<bool> {
+        if self.checked.elapsed() < POLL_INTERVAL {
+            return Ok(false);
+        }
+        self.checked = Instant::now();
+
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return Ok(false);
+        }
+
+        // only try once for each change, even if it fails to load
+        self.modified = modified;
+        *self = Self::open(self.path.clone(), self.max_size)?;
+
+        Ok(true)
+    }
+}
+
+fn modified(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,87 @@
+//! The texture the software renderer is shown through.
+//!
+//! Each sample is computed on the cpu, then uploaded to a texture to be drawn.
+
+use std::sync::Arc;
+
+use graphics::{
+    wgpu,
+    Allocation,
+    MemoryTracker,
+};
+
+pub struct SoftwareView {
+    queue: Arc<wgpu::Queue>,
+    device: Arc<wgpu::Device>,
+
+    texture: wgpu::Texture,
+
+    memory: MemoryTracker,
+    allocation: Allocation,
+}
+
+impl SoftwareView {
+    pub fn new(ctx: &graphics::Context) -> Self {
+        let device = ctx.device();
+        let texture = create_texture(&device, 1, 1);
+
+        let memory = ctx.memory().clone();
+        let allocation = memory.track_texture("software buffer", &texture);
+
+        Self {
+            queue: ctx.queue(),
+            device,
+            texture,
+            memory,
+            allocation,
+        }
+    }
+
+    /// The texture holding the last sample.
+    pub fn view(&self) -> wgpu::TextureView {
+        self.texture.create_view(&Default::default())
+    }
+
+    /// Recreate the texture if the size of the frame has changed.
+    #[profiling::function]
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if [width, height] != [self.texture.width(), self.texture.height()] {
+            self.texture = create_texture(&self.device, width, height);
+            self.allocation = self.memory.track_texture("software buffer", &self.texture);
+        }
+    }
+
+    /// Upload a `frame` of `[r, g, b, a]` bytes, the size of the texture.
+    #[profiling::function]
+    pub fn write(&self, frame: &[u8]) {
+        let size = self.texture.size();
+        self.queue.write_texture(
+            self.texture.as_image_copy(),
+            frame,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+    }
+}
+
+fn create_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("software"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        // the same format as the hardware renderer, so it's drawn the same
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
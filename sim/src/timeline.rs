@@ -0,0 +1,46 @@
+//! Animating the camera between bookmarks.
+//!
+//! Bookmarks are added to the timeline as keyframes,
+//! and scrubbing through the timeline moves the camera along the [`CameraPath`].
+
+use common::camera::{
+    Bookmark,
+    CameraPath,
+    Keyframe,
+};
+
+/// A keyframe, and the bookmark it came from.
+pub struct Entry {
+    pub name: String,
+    pub keyframe: Keyframe,
+}
+
+#[derive(Default)]
+pub struct Timeline {
+    pub entries: Vec<Entry>,
+    /// The time shown, in seconds.
+    pub time: f32,
+    /// Move the camera along the path as the time changes.
+    pub preview: bool,
+}
+
+impl Timeline {
+    /// Add a keyframe from the `bookmark`, a second after the last one.
+    pub fn push(&mut self, bookmark: &Bookmark) {
+        let time = self.path().duration() + if self.entries.is_empty() { 0.0 } else { 1.0 };
+
+        let keyframe = match &bookmark.camera {
+            common::Camera::Orbit(cam) => Keyframe::new(cam, time),
+        };
+
+        self.entries.push(Entry {
+            name: bookmark.name.clone(),
+            keyframe,
+        });
+    }
+
+    /// The path through every keyframe.
+    pub fn path(&self) -> CameraPath {
+        CameraPath::new(self.entries.iter().map(|entry| entry.keyframe).collect())
+    }
+}
@@ -0,0 +1,44 @@
+use common::{
+    camera::Bookmark,
+    Config,
+};
+
+/// Show the bookmarked cameras of the `cfg`, to jump between them.
+///
+/// `name` is the name of the next bookmark, kept between frames.
+pub fn show(ui: &mut egui::Ui, cfg: &mut Config, name: &mut String) {
+    ui.group(|ui| {
+        ui.strong("Bookmarks");
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(name).desired_width(120.0));
+
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Add"))
+                .clicked()
+            {
+                cfg.bookmarks.push(Bookmark {
+                    name: std::mem::take(name),
+                    camera: cfg.camera.clone(),
+                });
+            }
+        });
+
+        let mut removed = None;
+
+        for (i, bookmark) in cfg.bookmarks.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.button(&bookmark.name).clicked() {
+                    cfg.camera = bookmark.camera.clone();
+                }
+                if ui.small_button("x").clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+
+        if let Some(i) = removed {
+            cfg.bookmarks.remove(i);
+        }
+    });
+}
@@ -0,0 +1,82 @@
+use common::Config;
+
+use crate::{
+    compare::{
+        Compare,
+        Mode,
+    },
+    view::View,
+};
+
+/// Show the settings of the comparison, and config `B`.
+///
+/// `config` is config `A`, which `B` can be reset to.
+pub fn show(ui: &mut egui::Ui, compare: &mut Compare, config: &Config, ctx: &graphics::Context) {
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut compare.mode, Mode::Split, "Split");
+        ui.selectable_value(&mut compare.mode, Mode::Flip, "Flip");
+    });
+
+    match compare.mode {
+        Mode::Split => {
+            ui.add(egui::Slider::new(&mut compare.split, 0.0..=1.0).text("Split"));
+        }
+        Mode::Flip => {
+            let label = if compare.flipped {
+                "Showing B"
+            } else {
+                "Showing A"
+            };
+            if ui.button(label).clicked() {
+                compare.flipped = !compare.flipped;
+            }
+        }
+    }
+
+    ui.group(|ui| {
+        ui.strong("Renderer B");
+        ui.horizontal(|ui| {
+            let mut software = compare.view.is_software();
+            ui.selectable_value(&mut software, false, "Hardware");
+            ui.selectable_value(&mut software, true, "Software");
+
+            if software != compare.view.is_software() {
+                compare.view = View::new(ctx, software);
+            }
+        });
+    });
+
+    if ui.button("Copy A").clicked() {
+        compare.config = config.clone();
+    }
+
+    super::config::show(ui, &mut compare.config);
+}
+
+/// Show a handle on the split between `A` and `B`, which can be dragged across the screen.
+pub fn split(ctx: &egui::Context, split: &mut f32) {
+    const WIDTH: f32 = 8.0;
+
+    let screen = ctx.screen_rect();
+    let x = screen.left() + *split * screen.width();
+
+    egui::Area::new("compare split")
+        .order(egui::Order::Background)
+        .fixed_pos(egui::pos2(x - WIDTH / 2.0, screen.top()))
+        .show(ctx, |ui| {
+            let size = egui::vec2(WIDTH, screen.height());
+            let (rect, response) = ui.allocate_exact_size(size, egui::Sense::drag());
+            let response = response.on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+
+            let stroke = if response.hovered() || response.dragged() {
+                ui.visuals().widgets.active.fg_stroke
+            } else {
+                ui.visuals().widgets.inactive.fg_stroke
+            };
+            ui.painter().vline(rect.center().x, rect.y_range(), stroke);
+
+            if response.dragged() {
+                *split = (*split + response.drag_delta().x / screen.width()).clamp(0.0, 1.0);
+            }
+        });
+}
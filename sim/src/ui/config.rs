@@ -1,4 +1,8 @@
 use common::{
+    camera::{
+        self,
+        Observer,
+    },
     Config,
     Features,
 };
@@ -21,6 +25,17 @@ pub fn show(ui: &mut egui::Ui, cfg: &mut Config) {
             ui.label("Fov: ");
             fov_angle(ui, &mut cfg.camera.fov_mut().0);
         });
+        ui.horizontal(|ui| {
+            ui.label("Observer: ");
+            let observer = cfg.camera.observer_mut();
+            ui.selectable_value(observer, Observer::Static, "Static");
+            ui.selectable_value(observer, Observer::Infalling, "Infalling");
+        });
+        if ui.button("Frame shadow").clicked() {
+            if let Some(framing) = camera::frame_shadow(cfg, 0.1) {
+                framing.apply(&mut cfg.camera);
+            }
+        }
     });
 
     let disk_on =
@@ -42,6 +57,27 @@ pub fn show(ui: &mut egui::Ui, cfg: &mut Config) {
             })
         });
     });
+
+    ui.add_enabled_ui(cfg.features.contains(Features::RIPPLE), |ui| {
+        ui.group(|ui| {
+            ui.strong("Ripple");
+            ui.add(egui::Slider::new(&mut cfg.ripple.amplitude, 0.0..=1.0).text("Amplitude"));
+            ui.add(egui::Slider::new(&mut cfg.ripple.frequency, 0.1..=4.0).text("Frequency"));
+            ui.add(egui::Slider::new(&mut cfg.ripple.time, 0.0..=10.0).text("Time"));
+        });
+    });
+
+    ui.add_enabled_ui(cfg.features.contains(Features::STAR), |ui| {
+        ui.group(|ui| {
+            ui.strong("Star");
+            ui.add(egui::Slider::new(&mut cfg.star.radius, 0.1..=3.0).text("Radius"));
+            ui.add(
+                egui::Slider::new(&mut cfg.star.temperature, 1000.0..=15000.0)
+                    .suffix(" K")
+                    .text("Temperature"),
+            );
+        });
+    });
 }
 
 fn fov_angle(ui: &mut egui::Ui, radians: &mut f32) -> egui::Response {
@@ -0,0 +1,50 @@
+use crate::convergence::Convergence;
+
+/// Show the settings and latest metrics of the convergence measurements.
+pub fn show(ui: &mut egui::Ui, convergence: &mut Convergence) {
+    ui.group(|ui| {
+        ui.strong("Convergence");
+        ui.checkbox(&mut convergence.enabled, "measure");
+
+        ui.add_enabled_ui(convergence.enabled, |ui| {
+            ui.add(
+                egui::Slider::new(&mut convergence.interval, 1..=256)
+                    .logarithmic(true)
+                    .text("Interval"),
+            );
+            ui.add(
+                egui::Slider::new(&mut convergence.tolerance, 1e-4..=0.1)
+                    .logarithmic(true)
+                    .text("Tolerance"),
+            );
+
+            ui.horizontal(|ui| {
+                let mut auto_stop = convergence.auto_stop.is_some();
+                ui.checkbox(&mut auto_stop, "stop at");
+
+                let mut target = convergence.auto_stop.unwrap_or(0.99) * 100.0;
+                ui.add_enabled(
+                    auto_stop,
+                    egui::DragValue::new(&mut target)
+                        .clamp_range(0.0..=100.0)
+                        .speed(0.1)
+                        .suffix("%"),
+                );
+
+                convergence.auto_stop = auto_stop.then_some(target / 100.0);
+            });
+
+            match convergence.metrics {
+                Some(metrics) => {
+                    ui.label(format!("Samples: {}", metrics.samples));
+                    ui.label(format!("Mean change: {:.2e}", metrics.mean));
+                    ui.label(format!("RMS change: {:.2e}", metrics.rms));
+                    ui.label(format!("Converged: {:.2}%", metrics.converged * 100.0));
+                }
+                None => {
+                    ui.label("Not measured");
+                }
+            }
+        });
+    });
+}
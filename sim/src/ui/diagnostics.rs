@@ -0,0 +1,132 @@
+use graphics::wgpu;
+
+/// Show the adapter, the limits of the device, and the gpu memory in use by each label,
+/// with a button to copy it all for a bug report.
+///
+/// The other `adapters` are listed with the index to pass to `--adapter`.
+pub fn show(
+    ctx: &egui::Context,
+    open: &mut bool,
+    adapter: &wgpu::AdapterInfo,
+    adapters: &[wgpu::AdapterInfo],
+    device: &wgpu::Device,
+    surface: &wgpu::SurfaceConfiguration,
+    memory: &graphics::MemoryTracker,
+) {
+    let limits = device.limits();
+
+    // the surface has a texture for each frame in flight, and the one being drawn
+    let frames = surface.desired_maximum_frame_latency as u64 + 1;
+    let surface_bytes = frames * bytes(surface.width, surface.height, surface.format);
+    let total = memory.total();
+
+    let rows = [
+        ("Adapter", adapter.name.clone()),
+        ("Type", format!("{:?}", adapter.device_type)),
+        ("Backend", format!("{:?}", adapter.backend)),
+        (
+            "Driver",
+            format!("{} {}", adapter.driver, adapter.driver_info),
+        ),
+        (
+            "Vendor/device",
+            format!("{:#06x}/{:#06x}", adapter.vendor, adapter.device),
+        ),
+        ("Surface format", format!("{:?}", surface.format)),
+        ("Present mode", format!("{:?}", surface.present_mode)),
+        (
+            "Surface size",
+            format!("{}x{}", surface.width, surface.height),
+        ),
+        (
+            "Max texture size",
+            limits.max_texture_dimension_2d.to_string(),
+        ),
+        (
+            "Max workgroup size",
+            format!(
+                "{}x{}x{}",
+                limits.max_compute_workgroup_size_x,
+                limits.max_compute_workgroup_size_y,
+                limits.max_compute_workgroup_size_z
+            ),
+        ),
+        (
+            "Max invocations",
+            limits.max_compute_invocations_per_workgroup.to_string(),
+        ),
+        (
+            "Max storage textures",
+            limits.max_storage_textures_per_shader_stage.to_string(),
+        ),
+        ("Max buffer size", mebibytes(limits.max_buffer_size)),
+        ("Surface memory", mebibytes(surface_bytes)),
+        ("Texture memory", mebibytes(total.textures)),
+        ("Buffer memory", mebibytes(total.buffers)),
+        ("Peak memory", mebibytes(memory.peak())),
+    ];
+    let labels = memory.by_label();
+
+    egui::Window::new("Diagnostics").open(open).show(ctx, |ui| {
+        egui::Grid::new("diagnostics").striped(true).show(ui, |ui| {
+            for (name, value) in &rows {
+                ui.label(*name);
+                ui.label(value);
+                ui.end_row();
+            }
+        });
+
+        ui.label("Memory is an estimate, added up from the sizes of what's been allocated.");
+
+        ui.collapsing("Memory", |ui| {
+            egui::Grid::new("memory").striped(true).show(ui, |ui| {
+                for (label, usage) in &labels {
+                    ui.label(label);
+                    ui.label(mebibytes(usage.total()));
+                    ui.end_row();
+                }
+            });
+        });
+
+        ui.collapsing("Adapters", |ui| {
+            for (index, info) in adapters.iter().enumerate() {
+                let text = format!(
+                    "[{index}] {} ({:?}, {:?})",
+                    info.name, info.backend, info.device_type
+                );
+
+                if info == adapter {
+                    ui.strong(text);
+                } else {
+                    ui.label(text);
+                }
+            }
+        })
+        .header_response
+        .on_hover_text("Start the sim with --adapter <index> to use another");
+
+        if ui.button("Copy").clicked() {
+            let text = rows
+                .iter()
+                .map(|(name, value)| format!("{name}: {value}"))
+                .chain(
+                    labels
+                        .iter()
+                        .map(|(label, usage)| format!("{label}: {}", mebibytes(usage.total()))),
+                )
+                .collect::<Vec<_>>()
+                .join("\n");
+            ui.output_mut(|o| o.copied_text = text);
+        }
+    });
+}
+
+/// The bytes used by a `width` x `height` texture.
+fn bytes(width: u32, height: u32, format: wgpu::TextureFormat) -> u64 {
+    let texel = format.block_copy_size(None).unwrap_or(4) as u64;
+    width as u64 * height as u64 * texel
+}
+
+fn mebibytes(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
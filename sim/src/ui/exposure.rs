@@ -0,0 +1,43 @@
+use crate::histogram::{
+    Histogram,
+    BINS,
+};
+
+/// Show the `histogram` of the frame, and whether to draw it in false color.
+pub fn show(ctx: &egui::Context, open: &mut bool, histogram: &Histogram, false_color: &mut bool) {
+    egui::Window::new("Exposure")
+        .open(open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let size = egui::vec2(4.0 * BINS as f32, 120.0);
+            let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+            let max = histogram.bins.iter().copied().max().unwrap_or(0).max(1);
+            let width = rect.width() / BINS as f32;
+
+            for (i, &count) in histogram.bins.iter().enumerate() {
+                let height = rect.height() * count as f32 / max as f32;
+                let x = rect.left() + i as f32 * width;
+
+                let bar = egui::Rect::from_min_max(
+                    egui::pos2(x, rect.bottom() - height),
+                    egui::pos2(x + width, rect.bottom()),
+                );
+                painter.rect_filled(bar, 0.0, ui.visuals().text_color());
+            }
+
+            let clipped = histogram.clipped as f32 / histogram.pixels.max(1) as f32;
+            ui.label(format!("Clipped: {:.2}%", clipped * 100.0));
+
+            ui.checkbox(false_color, "false color");
+            if *false_color {
+                ui.label("blue: 6 stops under middle grey");
+                ui.label("green: middle grey");
+                ui.label("red: white");
+                ui.label("magenta: clipped");
+            }
+        });
+}
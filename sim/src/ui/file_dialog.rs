@@ -1,6 +1,10 @@
 use std::{
     fs,
     io::Write,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 
 use anyhow::Context as _;
@@ -12,8 +16,8 @@ use egui_file::{
 };
 
 pub enum Action {
-    Saved,
-    Opened,
+    Saved(PathBuf),
+    Opened(PathBuf),
 }
 
 pub fn show(
@@ -26,18 +30,8 @@ pub fn show(
             match dialog.dialog_type() {
                 DialogType::OpenFile => {
                     if let Some(path) = dialog.path() {
-                        let contents = fs::read_to_string(path).with_context(|| {
-                            format!("failed to read config file at {}", path.display())
-                        })?;
-
-                        if let Ok(cfg) = Config::load(&contents) {
-                            log::info!("loaded new config from {}", path.display());
-
-                            *config = cfg;
-
-                            return Ok(Some(Action::Opened));
-                        } else {
-                            log::error!("failed to load config from {}", path.display());
+                        if open(path, config)? {
+                            return Ok(Some(Action::Opened(path.to_owned())));
                         }
                     }
                 }
@@ -57,7 +51,7 @@ pub fn show(
 
                         log::info!("saved config to {}", path.display());
 
-                        return Ok(Some(Action::Saved));
+                        return Ok(Some(Action::Saved(path.to_owned())));
                     }
                 }
                 _ => unreachable!(),
@@ -67,3 +61,23 @@ pub fn show(
 
     Ok(None)
 }
+
+/// Load the config file at `path` into `config`.
+///
+/// Returns false, leaving the config alone, if the file isn't a valid config.
+pub fn open(path: &Path, config: &mut Config) -> anyhow::Result<bool> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file at {}", path.display()))?;
+
+    if let Ok(cfg) = Config::load(&contents) {
+        log::info!("loaded new config from {}", path.display());
+
+        *config = cfg;
+
+        Ok(true)
+    } else {
+        log::error!("failed to load config from {}", path.display());
+
+        Ok(false)
+    }
+}
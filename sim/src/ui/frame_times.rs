@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+use crate::frame_times::FrameTimes;
+
+/// Show graphs of the recent `times` and frames of the `timer` in the top right corner.
+pub fn show(ctx: &egui::Context, times: &FrameTimes, timer: &event::Timer) {
+    egui::Area::new("Frame Times")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 30.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let ms = |duration: std::time::Duration| duration.as_secs_f32() * 1000.0;
+
+                ui.label(format!("{:.0} fps", timer.fps()));
+                ui.label(format!(
+                    "avg {:.1}ms, p99 {:.1}ms",
+                    ms(timer.average()),
+                    ms(timer.percentile(0.99))
+                ));
+
+                let cpu = timer.history().map(ms).collect();
+                graph(ui, "CPU", &cpu, "ms");
+                if times.gpu.is_empty() {
+                    ui.label("GPU: no timestamps");
+                } else {
+                    graph(ui, "GPU", &times.gpu, "ms");
+                    scopes(ui, &times.scopes);
+                }
+
+                for dispatch in &times.dispatches {
+                    ui.label(format!(
+                        "{}: {} invocations",
+                        dispatch.label, dispatch.compute_invocations
+                    ));
+                }
+
+                let samples = times.samples.iter().map(|&s| s as f32).collect();
+                graph(ui, "Samples", &samples, "");
+            });
+        });
+}
+
+/// List the recent cost of each gpu scope.
+fn scopes(ui: &mut egui::Ui, scopes: &profiler::ScopeStats) {
+    let ms =
+        |duration: Option<std::time::Duration>| duration.unwrap_or_default().as_secs_f32() * 1000.0;
+
+    egui::Grid::new("gpu scopes").show(ui, |ui| {
+        for label in scopes.labels() {
+            ui.label(label);
+            ui.label(format!(
+                "avg {:.2}ms, p95 {:.2}ms",
+                ms(scopes.average(label)),
+                ms(scopes.percentile(label, 0.95))
+            ));
+            ui.end_row();
+        }
+    });
+}
+
+/// Plot the `values` as a line, scaled to the largest value.
+fn graph(ui: &mut egui::Ui, name: &str, values: &VecDeque<f32>, unit: &str) {
+    let latest = values.back().copied().unwrap_or(0.0);
+    ui.label(format!("{name}: {latest:.1}{unit}"));
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 40.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+    let max = values.iter().copied().fold(f32::EPSILON, f32::max);
+    let step = rect.width() / values.len().max(1) as f32;
+
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| {
+            egui::pos2(
+                rect.left() + i as f32 * step,
+                rect.bottom() - rect.height() * v / max,
+            )
+        })
+        .collect();
+
+    let stroke = egui::Stroke::new(1.0, ui.visuals().text_color());
+    painter.add(egui::Shape::line(points, stroke));
+}
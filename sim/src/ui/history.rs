@@ -0,0 +1,55 @@
+use crate::history::History;
+
+/// The number of edits to step through.
+pub enum Action {
+    Undo(usize),
+    Redo(usize),
+}
+
+/// Show the edits in the `history`, clicking one steps back or forward to just after it.
+pub fn show(ctx: &egui::Context, open: &mut bool, history: &History) -> Option<Action> {
+    let mut action = None;
+
+    egui::Window::new("History").open(open).show(ctx, |ui| {
+        let undo = history.undo_stack();
+        let redo = history.redo_stack();
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!undo.is_empty(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                action = Some(Action::Undo(1));
+            }
+            if ui
+                .add_enabled(!redo.is_empty(), egui::Button::new("Redo"))
+                .clicked()
+            {
+                action = Some(Action::Redo(1));
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                if undo.is_empty() && redo.is_empty() {
+                    ui.label("No edits yet");
+                }
+
+                // oldest first, with the undone edits greyed out below
+                for (i, entry) in undo.iter().enumerate() {
+                    if ui.selectable_label(false, &entry.label).clicked() {
+                        action = Some(Action::Undo(undo.len() - 1 - i));
+                    }
+                }
+                for (i, entry) in redo.iter().enumerate().rev() {
+                    let label = egui::RichText::new(&entry.label).weak();
+                    if ui.selectable_label(false, label).clicked() {
+                        action = Some(Action::Redo(redo.len() - i));
+                    }
+                }
+            });
+    });
+
+    action
+}
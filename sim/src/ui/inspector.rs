@@ -0,0 +1,85 @@
+use glam::UVec2;
+use software_renderer::Termination;
+
+use crate::inspector::Pixel;
+
+/// Show everything known about the inspected `pixel`.
+pub fn show(ctx: &egui::Context, open: &mut bool, pixel: Option<&Pixel>) {
+    egui::Window::new("Inspector").open(open).show(ctx, |ui| {
+        let Some(pixel) = pixel else {
+            ui.label("Click on a pixel to inspect it");
+            return;
+        };
+
+        egui::Grid::new("inspected pixel").show(ui, |ui| {
+            ui.label("Pixel");
+            ui.label(format!("({}, {})", pixel.position.x, pixel.position.y));
+            ui.end_row();
+
+            ui.label("Value");
+            let v = pixel.value;
+            ui.label(format!("[{:.4}, {:.4}, {:.4}, {:.4}]", v.x, v.y, v.z, v.w));
+            ui.end_row();
+
+            if let Some(linear) = pixel.linear {
+                ui.label("Linear");
+                ui.label(format!(
+                    "[{:.4}, {:.4}, {:.4}]",
+                    linear.x, linear.y, linear.z
+                ));
+                ui.end_row();
+            }
+
+            ui.label("Samples");
+            ui.label(pixel.samples.to_string());
+            ui.end_row();
+
+            ui.label("Ended at");
+            ui.label(termination(pixel.trace.termination));
+            ui.end_row();
+
+            ui.label("Steps");
+            ui.label(pixel.trace.steps.to_string());
+            ui.end_row();
+
+            ui.label("Bounces");
+            ui.label(pixel.trace.bounces.to_string());
+            ui.end_row();
+        });
+    });
+}
+
+/// The pixel of a `width` x `height` frame that was clicked on,
+/// if the click wasn't on the gui.
+pub fn pick(ctx: &egui::Context, (width, height): (u32, u32)) -> Option<UVec2> {
+    if ctx.is_pointer_over_area() {
+        return None;
+    }
+
+    ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+
+    let pos = ctx.input(|i| {
+        i.pointer
+            .primary_clicked()
+            .then(|| i.pointer.interact_pos())
+            .flatten()
+    })?;
+
+    // egui works in points, the frame is in pixels
+    let pos = pos * ctx.pixels_per_point();
+    let pixel = UVec2::new(pos.x as u32, pos.y as u32);
+
+    (pixel.x < width && pixel.y < height).then_some(pixel)
+}
+
+fn termination(termination: Termination) -> &'static str {
+    match termination {
+        Termination::Sky => "Sky",
+        Termination::Disk => "Disk",
+        Termination::Horizon => "Horizon",
+        Termination::Singularity => "Singularity",
+        Termination::Star => "Star",
+        Termination::Stuck => "Stuck in the disk",
+        Termination::Steps => "Out of steps",
+    }
+}
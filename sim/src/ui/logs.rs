@@ -0,0 +1,49 @@
+use crate::logs::Logs;
+
+/// Show the recent log lines, with the filters above them.
+pub fn show(ui: &mut egui::Ui, logs: &mut Logs) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source("log level")
+            .selected_text(logs.level.as_str())
+            .show_ui(ui, |ui| {
+                for level in log::LevelFilter::iter().skip(1) {
+                    ui.selectable_value(&mut logs.level, level, level.as_str());
+                }
+            });
+
+        ui.label("Target:");
+        ui.text_edit_singleline(&mut logs.target);
+
+        if ui.button("Clear").clicked() {
+            logs.lines.clear();
+        }
+    });
+
+    ui.separator();
+
+    egui::ScrollArea::vertical()
+        .auto_shrink(false)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for line in logs.filtered() {
+                let color = match line.level {
+                    log::Level::Error => ui.visuals().error_fg_color,
+                    log::Level::Warn => ui.visuals().warn_fg_color,
+                    _ => ui.visuals().text_color(),
+                };
+
+                let time = line.time.time();
+                let text = format!(
+                    "{:02}:{:02}:{:02} {:5} {}: {}",
+                    time.hour(),
+                    time.minute(),
+                    time.second(),
+                    line.level,
+                    line.target,
+                    line.message
+                );
+
+                ui.label(egui::RichText::new(text).monospace().color(color));
+            }
+        });
+}
@@ -1,2 +1,18 @@
+pub mod bookmarks;
+pub mod compare;
 pub mod config;
+pub mod convergence;
+pub mod diagnostics;
+pub mod exposure;
 pub mod file_dialog;
+pub mod frame_times;
+pub mod history;
+pub mod inspector;
+pub mod logs;
+pub mod presets;
+pub mod profiler;
+pub mod render_dialog;
+pub mod shadow;
+pub mod sky;
+pub mod theme;
+pub mod timeline;
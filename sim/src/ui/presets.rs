@@ -0,0 +1,31 @@
+use common::Config;
+
+use crate::presets::Gallery;
+
+/// Show the presets of the `gallery` in a dropdown, returning the one picked.
+pub fn show(ui: &mut egui::Ui, gallery: &Gallery) -> Option<Config> {
+    let mut picked = None;
+
+    ui.menu_button("Presets", |ui| {
+        for preset in &gallery.presets {
+            ui.horizontal(|ui| {
+                let size = egui::vec2(64.0, 64.0);
+                match &preset.thumbnail {
+                    Some(thumbnail) => {
+                        ui.add(egui::Image::new((thumbnail.id(), size)));
+                    }
+                    None => {
+                        ui.add_sized(size, egui::Spinner::new());
+                    }
+                }
+
+                if ui.button(preset.name).clicked() {
+                    picked = Some(preset.config.clone());
+                    ui.close_menu();
+                }
+            });
+        }
+    });
+
+    picked
+}
@@ -0,0 +1,34 @@
+use std::sync::{
+    atomic::{
+        AtomicBool,
+        Ordering,
+    },
+    Arc,
+};
+
+/// Show the profiler in a window of its own while `open`, cleared once the window is closed.
+pub fn window(ctx: &egui::Context, open: &Arc<AtomicBool>) {
+    if !open.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let open = Arc::clone(open);
+    let builder = egui::ViewportBuilder::default()
+        .with_title("Profiler")
+        .with_inner_size([800.0, 600.0]);
+
+    ctx.show_viewport_deferred(
+        egui::ViewportId::from_hash_of("Profiler"),
+        builder,
+        move |ctx, _| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                profiling::scope!("profiler");
+                puffin_egui::profiler_ui(ui);
+            });
+
+            if ctx.input(|i| i.viewport().close_requested()) {
+                open.store(false, Ordering::Relaxed);
+            }
+        },
+    );
+}
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use crate::offline::Settings;
+
+pub enum Action {
+    Start,
+    Cancel,
+}
+
+/// Show the settings of an offline render,
+/// or the `progress` of the one that is running.
+///
+/// `max_size` is the largest width or height the device supports.
+pub fn show(
+    ctx: &egui::Context,
+    open: &mut bool,
+    settings: &mut Settings,
+    max_size: u32,
+    progress: Option<f32>,
+) -> Option<Action> {
+    let mut action = None;
+
+    egui::Window::new("Render").open(open).show(ctx, |ui| {
+        ui.add_enabled_ui(progress.is_none(), |ui| {
+            egui::Grid::new("render settings").show(ui, |ui| {
+                ui.label("Resolution");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut settings.width).clamp_range(1..=max_size));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(&mut settings.height).clamp_range(1..=max_size));
+                });
+                ui.end_row();
+
+                ui.label("Samples");
+                ui.add(egui::DragValue::new(&mut settings.samples).clamp_range(1..=65536));
+                ui.end_row();
+
+                ui.label("Turntable");
+                ui.horizontal(|ui| {
+                    let mut turntable = settings.turntable.is_some();
+                    ui.checkbox(&mut turntable, "");
+
+                    let mut frames = settings.turntable.unwrap_or(120);
+                    ui.add_enabled(
+                        turntable,
+                        egui::DragValue::new(&mut frames)
+                            .clamp_range(1..=3600)
+                            .suffix(" frames"),
+                    );
+
+                    settings.turntable = turntable.then_some(frames);
+                });
+                ui.end_row();
+
+                ui.label("Output");
+                let mut path = settings.path.display().to_string();
+                if ui.text_edit_singleline(&mut path).changed() {
+                    settings.path = PathBuf::from(path);
+                }
+                ui.end_row();
+            });
+        });
+
+        match progress {
+            Some(progress) => {
+                ui.add(egui::ProgressBar::new(progress).show_percentage());
+                if ui.button("Cancel").clicked() {
+                    action = Some(Action::Cancel);
+                }
+            }
+            None => {
+                let label = if settings.turntable.is_some() {
+                    "Record turntable"
+                } else {
+                    "Render"
+                };
+                if ui.button(label).clicked() {
+                    action = Some(Action::Start);
+                }
+            }
+        }
+    });
+
+    action
+}
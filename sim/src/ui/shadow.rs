@@ -0,0 +1,26 @@
+use common::shadow::Shadow;
+
+/// Show the last measurement of the shadow.
+///
+/// Returns true if a new measurement was requested.
+pub fn show(ui: &mut egui::Ui, shadow: Option<&Shadow>) -> bool {
+    ui.group(|ui| {
+        ui.strong("Shadow");
+
+        match shadow {
+            Some(shadow) => {
+                ui.label(format!("Diameter: {:.3}°", shadow.diameter));
+                ui.label(format!(
+                    "Centroid: ({:.3}°, {:.3}°)",
+                    shadow.centroid.x, shadow.centroid.y
+                ));
+            }
+            None => {
+                ui.label("Not measured");
+            }
+        }
+
+        ui.button("Measure").clicked()
+    })
+    .inner
+}
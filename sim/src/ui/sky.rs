@@ -0,0 +1,54 @@
+use crate::{
+    gui::THUMBNAIL_SIZE,
+    sky::Sky,
+};
+
+pub enum Action {
+    Open,
+    Reset,
+}
+
+/// Show which starmap the `sky` is using, with buttons to change it.
+pub fn show(
+    ui: &mut egui::Ui,
+    sky: Option<&Sky>,
+    thumbnail: Option<egui::TextureId>,
+) -> Option<Action> {
+    let mut action = None;
+
+    ui.group(|ui| {
+        ui.strong("Sky");
+
+        match sky {
+            Some(sky) => {
+                let name = sky.path.file_name().unwrap_or(sky.path.as_os_str());
+                ui.label(name.to_string_lossy())
+                    .on_hover_text(sky.path.display().to_string());
+
+                if let Some(thumbnail) = thumbnail {
+                    // fit the longest side, the same as the thumbnail
+                    let aspect = sky.image.width() as f32 / sky.image.height() as f32;
+                    let size = egui::vec2(aspect, 1.0) * THUMBNAIL_SIZE as f32 / aspect.max(1.0);
+                    ui.image((thumbnail, size));
+                }
+            }
+            None => {
+                ui.label("Built in starmap");
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Open").clicked() {
+                action = Some(Action::Open);
+            }
+            if ui
+                .add_enabled(sky.is_some(), egui::Button::new("Reset"))
+                .clicked()
+            {
+                action = Some(Action::Reset);
+            }
+        });
+    });
+
+    action
+}
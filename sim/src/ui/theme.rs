@@ -0,0 +1,30 @@
+use crate::gui::{
+    Mode,
+    Theme,
+};
+
+/// Show a dropdown to pick the `theme`, returning true if it changed.
+pub fn show(ui: &mut egui::Ui, theme: &mut Theme) -> bool {
+    let old = *theme;
+
+    ui.menu_button("Theme", |ui| {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut theme.mode, Mode::Dark, "Dark");
+            ui.selectable_value(&mut theme.mode, Mode::Light, "Light");
+        });
+
+        ui.horizontal(|ui| {
+            let mut custom = theme.accent.is_some();
+            ui.checkbox(&mut custom, "Accent");
+
+            let mut accent = theme.accent_color();
+            ui.add_enabled_ui(custom, |ui| {
+                ui.color_edit_button_srgb(&mut accent);
+            });
+
+            theme.accent = custom.then_some(accent);
+        });
+    });
+
+    *theme != old
+}
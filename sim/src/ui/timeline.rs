@@ -0,0 +1,91 @@
+use common::camera::{
+    Bookmark,
+    Easing,
+};
+
+use crate::timeline::Timeline;
+
+pub enum Action {
+    Export,
+}
+
+/// Show the keyframes of the `timeline`, added from the `bookmarks`.
+pub fn show(
+    ctx: &egui::Context,
+    open: &mut bool,
+    timeline: &mut Timeline,
+    bookmarks: &[Bookmark],
+) -> Option<Action> {
+    let mut action = None;
+
+    egui::Window::new("Timeline").open(open).show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.menu_button("Add", |ui| {
+                if bookmarks.is_empty() {
+                    ui.label("Bookmark a camera first");
+                }
+
+                for bookmark in bookmarks {
+                    if ui.button(&bookmark.name).clicked() {
+                        timeline.push(bookmark);
+                        ui.close_menu();
+                    }
+                }
+            });
+
+            let export = egui::Button::new("Export");
+            if ui
+                .add_enabled(!timeline.entries.is_empty(), export)
+                .clicked()
+            {
+                action = Some(Action::Export);
+            }
+        });
+
+        let mut removed = None;
+
+        egui::Grid::new("keyframes").show(ui, |ui| {
+            for (i, entry) in timeline.entries.iter_mut().enumerate() {
+                ui.label(&entry.name);
+                ui.add(
+                    egui::DragValue::new(&mut entry.keyframe.time)
+                        .clamp_range(0.0..=f32::MAX)
+                        .speed(0.05)
+                        .suffix("s"),
+                );
+
+                egui::ComboBox::from_id_source(("easing", i))
+                    .selected_text(easing(entry.keyframe.easing))
+                    .show_ui(ui, |ui| {
+                        for e in [Easing::Linear, Easing::In, Easing::Out, Easing::InOut] {
+                            ui.selectable_value(&mut entry.keyframe.easing, e, easing(e));
+                        }
+                    });
+
+                if ui.small_button("x").clicked() {
+                    removed = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(i) = removed {
+            timeline.entries.remove(i);
+        }
+
+        let duration = timeline.path().duration();
+        ui.add(egui::Slider::new(&mut timeline.time, 0.0..=duration).text("Time"));
+        ui.checkbox(&mut timeline.preview, "preview");
+    });
+
+    action
+}
+
+fn easing(easing: Easing) -> &'static str {
+    match easing {
+        Easing::Linear => "Linear",
+        Easing::In => "Ease in",
+        Easing::Out => "Ease out",
+        Easing::InOut => "Ease in and out",
+    }
+}
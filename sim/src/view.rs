@@ -0,0 +1,112 @@
+//! A renderer shown in the sim, running on the gpu or the cpu.
+
+use common::{
+    Config,
+    Render,
+};
+use graphics::{
+    wgpu,
+    Encoder,
+};
+use kerrbhy::{
+    RendererKind,
+    Simulator,
+};
+use profiler::PipelineStatistics;
+use software_renderer::Heatmap;
+
+use crate::software::SoftwareView;
+
+pub struct View {
+    sim: Simulator,
+    /// The texture the frames are uploaded to, only set for the software renderer.
+    upload: Option<SoftwareView>,
+}
+
+impl View {
+    pub fn new(ctx: &graphics::Context, software: bool) -> Self {
+        if software {
+            Self {
+                sim: Simulator::software(),
+                upload: Some(SoftwareView::new(ctx)),
+            }
+        } else {
+            Self {
+                sim: Simulator::hardware(ctx),
+                upload: None,
+            }
+        }
+    }
+
+    pub fn is_software(&self) -> bool {
+        self.sim.kind() == RendererKind::Software
+    }
+
+    /// The renderer being shown.
+    pub fn renderer(&self) -> &dyn Render {
+        &self.sim
+    }
+
+    /// The texture holding the last sample.
+    pub fn view(&self) -> wgpu::TextureView {
+        match &self.upload {
+            Some(upload) => upload.view(),
+            None => self
+                .sim
+                .as_hardware()
+                .expect("only software frames are uploaded")
+                .view(),
+        }
+    }
+
+    pub fn update(&mut self, width: u32, height: u32, config: Config) {
+        self.sim.update(width, height, config);
+
+        if let Some(upload) = &mut self.upload {
+            upload.resize(width, height);
+        }
+    }
+
+    /// Show a `heatmap` instead of the color, only traced by the software renderer.
+    pub fn set_heatmap(&mut self, heatmap: Option<Heatmap>) {
+        if let Some(renderer) = self.sim.as_software_mut() {
+            renderer.set_heatmap(heatmap);
+        }
+    }
+
+    /// Replace the equirectangular map of the sky, restarting accumulation.
+    pub fn set_stars(&mut self, image: &image::DynamicImage) {
+        self.sim.set_stars(image);
+    }
+
+    /// Rebuild the shader of the hardware renderer from new WGSL `source`.
+    ///
+    /// The software renderer has no shader, so it's left alone.
+    pub fn reload_shader(&mut self, source: &str) -> Result<(), wgpu::Error> {
+        match self.sim.as_hardware_mut() {
+            Some(renderer) => renderer.reload_shader(source),
+            None => Ok(()),
+        }
+    }
+
+    /// The invocations of each dispatch, only counted by the hardware renderer.
+    pub fn pipeline_statistics(&mut self) -> Option<&mut PipelineStatistics> {
+        self.sim.as_hardware_mut()?.pipeline_statistics()
+    }
+
+    /// Compute another sample if `accumulate` is set, or the last one is out of date.
+    pub fn compute(&mut self, encoder: &mut Encoder, accumulate: bool) {
+        if !accumulate && !self.sim.must_render() {
+            return;
+        }
+
+        if let Some(renderer) = self.sim.as_hardware_mut() {
+            // recorded with the rest of the frame
+            renderer.compute(encoder);
+        } else if let (Some(renderer), Some(upload)) = (self.sim.as_software_mut(), &self.upload) {
+            // computed on the renderer itself, the sim marks its own profiling frames
+            renderer.compute_sample();
+            upload.write(&renderer.frame());
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Double precision integration of rays.
+//!
+//! These mirror the single precision integrators in the crate root,
+//! but keep the ray state in `f64` to reduce error close to the horizon.
+//! The step size and the hit tests stay in `f64` too, only the color is narrowed.
+
+use common::Features;
+use glam::{
+    DMat3,
+    DVec2,
+    DVec3,
+    Vec3,
+    Vec3Swizzles as _,
+};
+
+use crate::{
+    RayState,
+    BLACKHOLE_RADIUS,
+};
+
+fn mat2x3(x: DVec3, y: DVec3) -> DMat3 {
+    DMat3::from_cols(x, y, DVec3::ZERO)
+}
+
+fn gravitational_field(p: DVec3) -> DVec3 {
+    let r = p / BLACKHOLE_RADIUS as f64;
+    let rn = r.length();
+    -6.0 * r / (rn * rn * rn * rn * rn)
+}
+
+fn disk_sdf(p: DVec3, h: f64, r: f64) -> f64 {
+    let d = DVec2::new(p.xz().length(), p.y).abs() - DVec2::new(r, h);
+    d.x.max(d.y).min(0.0) + d.max(DVec2::ZERO).length()
+}
+
+/// s: state (position, velocity)
+fn ode(s: DMat3) -> DMat3 {
+    let p = s.x_axis;
+    let v = s.y_axis;
+    let a = gravitational_field(p);
+
+    mat2x3(v, a)
+}
+
+/// Simpler Euler integration
+/// s: state (position, velocity)
+/// h: time step
+/// returns: (delta position, delta velocity)
+fn euler(s: DMat3, h: f64) -> DMat3 {
+    ode(s) * h
+}
+
+/// Runge–Kutta (order 4)
+/// s: state (position, velocity)
+/// h: time step
+/// returns: (delta position, delta velocity)
+fn rk4(s: DMat3, h: f64) -> DMat3 {
+    // calculate coefficients
+    let k1 = ode(s);
+    let k2 = ode(s + 0.5 * h * k1);
+    let k3 = ode(s + 0.5 * h * k2);
+    let k4 = ode(s + h * k3);
+
+    // calculate timestep
+    h / 6.0 * (k1 + 2.0 * (k2 + k3) + k4)
+}
+
+/// Bogacki-Shampine method
+/// https://en.wikipedia.org/wiki/Bogacki%E2%80%93Shampine_method
+fn bogacki_shampine(s: DMat3, h: &mut f64) -> DMat3 {
+    const A: [f64; 3] = [2.0 / 9.0, 1.0 / 3.0, 4.0 / 9.0];
+    const B: [f64; 4] = [7.0 / 24.0, 1.0 / 4.0, 1.0 / 3.0, 1.0 / 8.0];
+
+    const H_MIN: f64 = 1e-8;
+    const H_MAX: f64 = 1e-1;
+    const ERR_TOLERANCE: f64 = 1e-5;
+
+    let h0 = *h;
+
+    // calculate coefficients
+    let k1 = ode(s);
+    let k2 = ode(s + 0.5 * h0 * k1);
+    let k3 = ode(s + 0.75 * h0 * k2);
+
+    // find step
+    let step = A[0] * h0 * k1 + A[1] * h0 * k2 + A[2] * h0 * k3;
+
+    // calculate next state
+    let k4 = ode(s + step);
+
+    // calculate better estimate using k4
+    let better = B[0] * h0 * k1 + B[1] * h0 * k2 + B[2] * h0 * k3 + B[3] * h0 * k4;
+
+    // compute the error
+    let err = better - step; // difference between the two guesses
+    let err = err.x_axis.max(err.y_axis).length(); // get the magnitude of the largest errors
+
+    // find the step change coefficient
+    let x = ERR_TOLERANCE * 0.5 / err;
+    let dstep = x.powf(0.5);
+
+    // update h and clamp within bounds
+    // https://en.wikipedia.org/wiki/Adaptive_step_size
+    (*h) = 0.9 * (h0 * dstep).clamp(H_MIN, H_MAX);
+
+    step
+}
+
+impl RayState for DMat3 {
+    type Real = f64;
+
+    fn new(p: Vec3, v: Vec3) -> Self {
+        mat2x3(p.as_dvec3(), v.as_dvec3())
+    }
+
+    fn position(&self) -> Vec3 {
+        self.x_axis.as_vec3()
+    }
+
+    fn velocity(&self) -> Vec3 {
+        self.y_axis.as_vec3()
+    }
+
+    fn set_velocity(&mut self, v: Vec3) {
+        self.y_axis = v.as_dvec3();
+    }
+
+    fn within(&self, radius: f32) -> bool {
+        let radius = radius as f64;
+        self.x_axis.length_squared() < radius * radius
+    }
+
+    fn in_disk(&self, thickness: f32, radius: f32) -> bool {
+        disk_sdf(self.x_axis, thickness as f64, radius as f64) <= 0.0
+    }
+
+    fn step(&mut self, features: Features, h: &mut f64) {
+        // choose the method of integration
+        let step = if features.contains(Features::ADAPTIVE) {
+            bogacki_shampine(*self, h)
+        } else if features.contains(Features::RK4) {
+            rk4(*self, *h)
+        } else {
+            euler(*self, *h)
+        };
+
+        // update system
+        self.x_axis += step.x_axis;
+        self.y_axis += step.y_axis;
+    }
+}
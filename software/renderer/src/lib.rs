@@ -1,3 +1,6 @@
+mod double;
+mod polarization;
+
 use std::f32::consts::{
     FRAC_1_PI,
     PI,
@@ -5,12 +8,21 @@ use std::f32::consts::{
 };
 
 use common::{
+    camera::Observer,
     Config,
     Features,
+    Precision,
+    Render,
+    Ripple,
+    Star,
+    Tile,
 };
 use glam::{
     mat3,
+    DMat3,
     Mat3,
+    UVec2,
+    UVec3,
     Vec2,
     Vec2Swizzles as _,
     Vec3,
@@ -18,26 +30,83 @@ use glam::{
     Vec4,
     Vec4Swizzles as _,
 };
+use polarization::Polarization;
 use wcpu::{
-    texture::{EdgeMode, Filter},
+    texture::{EdgeMode, Filter, Semantics},
     FrameBuffer,
     Sample,
+    SampleCube,
+    SampleVolume,
     Sampler,
+    Texture3D,
+};
+pub use wcpu::{
     Texture2D,
+    TextureCube,
+    TiledTexture2D,
 };
 
 pub struct Renderer {
     buffer: FrameBuffer,
     config: Config,
 
+    resolution: UVec2,
+    tile: Tile,
+    first_sample: u32,
+    /// The number of samples accumulated in the buffer.
+    sample_no: u32,
+    dirty: bool,
+
+    /// Renders on the global rayon pool when [`None`].
+    pool: Option<rayon::ThreadPool>,
+
     sampler: Sampler,
-    stars: Texture2D,
+    sky: Sky,
+
+    /// Bake the disk into a volume of this size when set.
+    disk_resolution: Option<UVec3>,
+    baked_disk: Option<BakedDisk>,
+
+    /// Shown in place of the color when set.
+    heatmap: Option<Heatmap>,
+}
+
+/// What to show in place of the color of each ray, to find where the time is spent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heatmap {
+    /// The number of integration steps taken.
+    Steps,
+    /// The number of times the ray scattered in the disk.
+    Bounces,
+}
+
+/// The texture of the sky, used unless it's procedural.
+pub enum Sky {
+    /// An equirectangular map, such as the default starmap.
+    Equirectangular(Texture2D),
+    Cube(TextureCube),
+    /// An equirectangular map read from disk as it's sampled, for maps too large to load.
+    Tiled(TiledTexture2D),
+}
+
+impl Sky {
+    /// How many texels of the sky fit in a radian, to choose mip levels.
+    fn texels_per_radian(&self) -> f32 {
+        match self {
+            Sky::Equirectangular(tex) => tex.size().x as f32 * FRAC_1_2PI,
+            Sky::Tiled(tex) => tex.size().x as f32 * FRAC_1_2PI,
+            // each face covers a quarter turn
+            Sky::Cube(tex) => tex.size() as f32 * 2.0 * FRAC_1_PI,
+        }
+    }
 }
 
 const MAX_STEPS: u32 = 128;
 const MAX_BOUNCES: u32 = 4;
 const DELTA: f32 = 0.05;
 const BLACKHOLE_RADIUS: f32 = 0.6;
+const SINGULARITY_RADIUS: f32 = 0.25 * BLACKHOLE_RADIUS;
+const STAR_LIMB_DARKENING: f32 = 0.6;
 const SKYBOX_RADIUS: f32 = 3.6;
 
 const FRAC_1_2PI: f32 = FRAC_1_PI * 0.5;
@@ -65,6 +134,23 @@ fn hash22(p: Vec2) -> Vec2 {
     ((p3.xx() + p3.yz()) * p3.zy()).fract()
 }
 
+/// Seeds the rng for a single pixel and sample.
+///
+/// Every pixel gets its own stream of random numbers,
+/// so renders are reproducible no matter which thread computes each pixel.
+fn seed_rng(id: UVec2, sample: u32) {
+    // https://prng.di.unimi.it/splitmix64.c
+    fn splitmix64(x: u64) -> u64 {
+        let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    let pixel = ((id.x as u64) << 32) | id.y as u64;
+    fastrand::seed(splitmix64(splitmix64(pixel) ^ sample as u64));
+}
+
 fn rand() -> f32 {
     fastrand::f32()
 }
@@ -207,6 +293,13 @@ const XYZ2_SRGB: Mat3 = Mat3::from_cols(
     Vec3::new(0.056, -0.204, 1.057),
 );
 
+/// Blue at `0`, through green, to red at `1`.
+fn heat(t: f32) -> Vec3 {
+    let t = t.clamp(0.0, 1.0);
+    Vec3::new(2.0 * t - 1.0, 2.0 - (4.0 * t - 2.0).abs(), 1.0 - 2.0 * t)
+        .clamp(Vec3::ZERO, Vec3::ONE)
+}
+
 // Convert XYZ to sRGB
 fn xyz2rgb(color_xyz: Vec3) -> Vec3 {
     // Note: glsl uses column-major, not row-major matricies (as they are in glam)
@@ -214,6 +307,14 @@ fn xyz2rgb(color_xyz: Vec3) -> Vec3 {
     XYZ2_SRGB.transpose() * color_xyz
 }
 
+// Encode linear light with the sRGB transfer function
+// https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)
+fn linear_to_srgb(color: Vec3) -> Vec3 {
+    let lower = color * 12.92;
+    let higher = 1.055 * color.powf(1.0 / 2.4) - 0.055;
+    Vec3::select(color.cmplt(Vec3::splat(0.0031308)), lower, higher)
+}
+
 #[allow(clippy::excessive_precision)]
 fn blackbody_xyz(t: f32) -> Vec3 {
     // https://en.wikipedia.org/wiki/Planckian_locus
@@ -232,6 +333,31 @@ fn blackbody_xyz(t: f32) -> Vec3 {
     Vec3::new(xy.x / xy.y, 1.0, (1.0 - xy.x - xy.y) / xy.y)
 }
 
+/// Turn the ray direction `rd` of an observer falling towards the black hole from `ro`
+/// into the direction seen by a static observer.
+///
+/// Returns the direction, and how much brighter the light looks to the falling observer.
+fn infalling_observer(ro: Vec3, rd: Vec3) -> (Vec3, f32) {
+    // falling from rest far away, as a fraction of the speed of light
+    let beta = (BLACKHOLE_RADIUS / ro.length()).sqrt().min(0.999);
+    let gamma = 1.0 / (1.0 - beta * beta).sqrt();
+    let n = -ro.normalize();
+
+    // https://en.wikipedia.org/wiki/Relativistic_aberration
+    let cos_moving = rd.dot(n);
+    let cos_static = (cos_moving - beta) / (1.0 - beta * cos_moving);
+    let sin_static = (1.0 - cos_static * cos_static).max(0.0).sqrt();
+    let side = (rd - cos_moving * n)
+        .try_normalize()
+        .unwrap_or_else(|| n.any_orthonormal_vector());
+
+    // https://en.wikipedia.org/wiki/Relativistic_Doppler_effect
+    // the total intensity scales with the 4th power of the doppler factor
+    let doppler = 1.0 / (gamma * (1.0 - beta * cos_moving));
+
+    ((cos_static * n + sin_static * side).normalize(), doppler.powi(4))
+}
+
 fn aa_filter(coord: Vec2) -> Vec2 {
     const A: f32 = 0.35875;
     const B: f32 = 0.48829;
@@ -255,7 +381,67 @@ struct DiskInfo {
     distance: f32,
 }
 
-fn disk_volume(p: Vec3, radius: f32, thickness: f32) -> DiskInfo {
+fn disk_noise(p: Vec3) -> f32 {
+    let np = 20.0
+        * rotate(p.xz(), (8.0 * p.y) + (4.0 * p.xz().length()))
+            .extend(p.y)
+            .xzy();
+
+    fbm(np, 8)
+}
+
+/// The half size of the box around the disk volume.
+fn disk_extent(radius: f32, thickness: f32) -> Vec3 {
+    Vec3::new(radius.sqrt(), thickness.sqrt(), radius.sqrt())
+}
+
+/// The noise of the disk volume baked into a [`Texture3D`].
+///
+/// Sampling is much faster than evaluating the fbm every step,
+/// at the cost of detail smaller than a texel.
+struct BakedDisk {
+    radius: f32,
+    thickness: f32,
+    noise: Texture3D,
+}
+
+impl BakedDisk {
+    #[profiling::function]
+    fn bake(resolution: UVec3, radius: f32, thickness: f32) -> Self {
+        let extent = disk_extent(radius, thickness);
+
+        let noise = Texture3D::from_fn(resolution, |id| {
+            // bake at the texel centers
+            let uvw = (id.as_vec3() + 0.5) / resolution.as_vec3();
+            let p = (2.0 * uvw - 1.0) * extent;
+
+            Vec4::new(disk_noise(p), 0.0, 0.0, 0.0)
+        });
+
+        Self {
+            radius,
+            thickness,
+            noise,
+        }
+    }
+
+    fn noise(&self, p: Vec3) -> f32 {
+        let sampler = Sampler {
+            filter_mode: Filter::Linear,
+            mipmap_filter: Filter::Nearest,
+            lod_bias: 0.0,
+            edge_mode: EdgeMode::Clamp,
+            semantics: Semantics::Wgsl,
+        };
+
+        let extent = disk_extent(self.radius, self.thickness);
+        let uvw = 0.5 * (p / extent + 1.0);
+
+        sampler.sample_volume(&self.noise, uvw).x
+    }
+}
+
+fn disk_volume(p: Vec3, radius: f32, thickness: f32, baked: Option<&BakedDisk>) -> DiskInfo {
     // define the bounds of the disk volume
     if p.xz().length_squared() > radius || p.y * p.y > thickness {
         return DiskInfo {
@@ -264,11 +450,10 @@ fn disk_volume(p: Vec3, radius: f32, thickness: f32) -> DiskInfo {
         };
     }
 
-    let np = 20.0
-        * rotate(p.xz(), (8.0 * p.y) + (4.0 * p.xz().length()))
-            .extend(p.y)
-            .xzy();
-    let n0 = fbm(np, 8);
+    let n0 = match baked {
+        Some(baked) => baked.noise(p),
+        None => disk_noise(p),
+    };
 
     let d_falloff = (Vec3::new(0.12, 7.50, 0.12) * p).length();
     let e_falloff = (Vec3::new(0.20, 8.00, 0.20) * p).length();
@@ -294,7 +479,11 @@ fn disk_sdf(p: Vec3, h: f32, r: f32) -> f32 {
     d.x.max(d.y).min(0.0) + d.max(Vec2::ZERO).length()
 }
 
-fn sample_sky(sampler: Sampler, stars: &Texture2D, rd: Vec3) -> Vec3 {
+fn sample_sky(sampler: Sampler, sky: &Sky, rd: Vec3, lod: f32) -> Vec3 {
+    if let Sky::Cube(stars) = sky {
+        return sampler.sample_cube_level(stars, rd, lod).xyz();
+    }
+
     // https://en.wikipedia.org/wiki/Azimuth
     let azimuth = f32::atan2(rd.z, rd.x);
     let inclination = f32::asin(-rd.y);
@@ -304,7 +493,13 @@ fn sample_sky(sampler: Sampler, stars: &Texture2D, rd: Vec3) -> Vec3 {
         0.5 - (inclination * FRAC_1_PI),
     );
 
-    sampler.sample(stars, uv).xyz()
+    let color = match sky {
+        Sky::Equirectangular(stars) => sampler.sample_level(stars, uv, lod),
+        Sky::Tiled(stars) => sampler.sample_level(stars, uv, lod),
+        Sky::Cube(_) => unreachable!("cube maps are sampled by direction"),
+    };
+
+    color.xyz()
 }
 
 fn procedural_sky(rd: Vec3) -> Vec3 {
@@ -351,6 +546,36 @@ fn gravitational_field(p: Vec3) -> Vec3 {
     -6.0 * r / (rn * rn * rn * rn * rn)
 }
 
+/// The light leaving the surface of a `star` at `p`, seen by a ray moving with velocity `v`.
+fn star_emission(p: Vec3, v: Vec3, star: &Star) -> Vec3 {
+    // the linear limb darkening law, with a coefficient close to the sun's
+    // https://en.wikipedia.org/wiki/Limb_darkening
+    let mu = p.normalize().dot(-v.normalize()).max(0.0);
+    let darkening = 1.0 - STAR_LIMB_DARKENING * (1.0 - mu);
+
+    // the planckian locus is only accurate within this range
+    let color = xyz2rgb(blackbody_xyz(star.temperature.clamp(1000.0, 15000.0)));
+    let color = color.max(Vec3::ZERO) / color.max_element().max(0.01);
+
+    darkening * color
+}
+
+/// A toy gravitational wave, a rotating quadrupole spreading out from the black hole.
+///
+/// Not a solution of GR, but it lenses the sky in waves that move outward.
+fn ripple_field(p: Vec3, ripple: &Ripple) -> Vec3 {
+    let r = p.length();
+
+    // an outgoing spiral with two arms, like the waves from a binary
+    let phase = TAU * ripple.frequency * (r - ripple.time) - 2.0 * p.z.atan2(p.x);
+
+    // strongest above the poles, half as strong in the plane of the disk
+    let cos_theta = p.y / r;
+    let pattern = 0.5 * (1.0 + cos_theta * cos_theta);
+
+    ripple.amplitude * pattern * phase.sin() * p / (r * r)
+}
+
 /// s: state (position, velocity)
 fn ode(s: Mat3) -> Mat3 {
     let p = s.x_axis;
@@ -424,48 +649,287 @@ fn bogacki_shampine(s: Mat3, h: &mut f32) -> Mat3 {
     step
 }
 
-fn render(ro: Vec3, rd: Vec3, sampler: Sampler, stars: &Texture2D, config: &Config) -> Vec3 {
+/// A scalar that rays are integrated with, `f32` or `f64`.
+trait Real: Copy + std::ops::Mul<Output = Self> + From<f32> {
+    fn as_f32(self) -> f32;
+}
+
+impl Real for f32 {
+    fn as_f32(self) -> f32 {
+        self
+    }
+}
+
+impl Real for f64 {
+    fn as_f32(self) -> f32 {
+        self as f32
+    }
+}
+
+/// The state of a ray (position, velocity) stored at some precision.
+trait RayState: Copy {
+    /// The precision of the state, and of its step size.
+    type Real: Real;
+
+    fn new(p: Vec3, v: Vec3) -> Self;
+
+    /// The position of the ray.
+    fn position(&self) -> Vec3;
+
+    /// The velocity of the ray.
+    fn velocity(&self) -> Vec3;
+
+    fn set_velocity(&mut self, v: Vec3);
+
+    /// Whether the ray is closer than `radius` to the center, at the precision of the state.
+    fn within(&self, radius: f32) -> bool;
+
+    /// Whether the ray is inside the disk, as a cylinder.
+    fn in_disk(&self, thickness: f32, radius: f32) -> bool;
+
+    /// Integrate the state by one step of size `h`.
+    ///
+    /// The method of integration is chosen by the `features`.
+    fn step(&mut self, features: Features, h: &mut Self::Real);
+}
+
+impl RayState for Mat3 {
+    type Real = f32;
+
+    fn new(p: Vec3, v: Vec3) -> Self {
+        mat2x3(p, v)
+    }
+
+    fn position(&self) -> Vec3 {
+        self.x_axis
+    }
+
+    fn velocity(&self) -> Vec3 {
+        self.y_axis
+    }
+
+    fn set_velocity(&mut self, v: Vec3) {
+        self.y_axis = v;
+    }
+
+    fn within(&self, radius: f32) -> bool {
+        self.x_axis.length_squared() < radius * radius
+    }
+
+    fn in_disk(&self, thickness: f32, radius: f32) -> bool {
+        disk_sdf(self.x_axis, thickness, radius) <= 0.0
+    }
+
+    fn step(&mut self, features: Features, h: &mut f32) {
+        // choose the method of integration
+        let step = if features.contains(Features::ADAPTIVE) {
+            bogacki_shampine(*self, h)
+        } else if features.contains(Features::RK4) {
+            rk4(*self, *h)
+        } else {
+            euler(*self, *h)
+        };
+
+        // update system
+        self.x_axis += step.x_axis;
+        self.y_axis += step.y_axis;
+    }
+}
+
+/// The speed of light at a distance `r`, after falling from far away.
+///
+/// Rays start inside the horizon at this speed, so that they can climb back out.
+fn infall_speed(r: f32) -> f32 {
+    // the work done by the field from `r` out to infinity
+    let rn = r / BLACKHOLE_RADIUS;
+    let work = 2.0 * BLACKHOLE_RADIUS / (rn * rn * rn);
+
+    (1.0 + 2.0 * work).sqrt()
+}
+
+/// Turns positions on the screen into rays leaving the camera.
+struct CameraRays {
+    view: glam::Affine3A,
+    origin: Vec3,
+    fov: f32,
+    observer: Observer,
+}
+
+impl CameraRays {
+    fn new(config: &Config) -> Self {
+        let view = config.camera.view();
+
+        // make the view is being transposed, the same as on the gpu
+        let transposed = glam::Affine3A::from_mat3(view.matrix3.transpose().into());
+
+        Self {
+            view: transposed,
+            origin: view.translation.into(),
+            fov: config.camera.fov().as_f32(),
+            observer: config.camera.observer(),
+        }
+    }
+
+    /// The ray through `uv`, in the frame of a static observer,
+    /// and how much brighter the light it carries appears to the camera.
+    fn ray(&self, uv: Vec2) -> (Vec3, Vec3, f32) {
+        // the ray origin
+        let ro = self.view.transform_vector3(self.origin);
+        // the ray direction (multiplied by the fov factor 2 * FOV * 1/PI, which gives us 90 degrees = 1.0 factor)
+        let rd = self
+            .view
+            .transform_vector3((uv * 2.0 * self.fov * FRAC_1_PI).extend(-1.0))
+            .normalize();
+
+        // move into the frame of a static observer
+        let (rd, brightness) = match self.observer {
+            Observer::Static => (rd, 1.0),
+            Observer::Infalling => infalling_observer(ro, rd),
+        };
+
+        (ro, rd, brightness)
+    }
+
+    /// The camera's right axis.
+    fn right(&self) -> Vec3 {
+        self.view.transform_vector3(Vec3::X)
+    }
+}
+
+/// How a ray ended, found by [`Renderer::trace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// Escaped to the sky.
+    Sky,
+    /// Hit the surface of the disk.
+    Disk,
+    /// Fell into the black hole.
+    Horizon,
+    /// Reached the singularity, from a camera inside the horizon.
+    Singularity,
+    /// Came from the surface of the star.
+    Star,
+    /// Scattered too many times in the disk, and was discarded.
+    Stuck,
+    /// Ran out of steps before reaching anything, then looked up the sky.
+    Steps,
+}
+
+/// What happened to a ray along the way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trace {
+    pub termination: Termination,
+    /// The number of integration steps taken.
+    pub steps: u32,
+    /// The number of times the ray scattered in the disk.
+    pub bounces: u32,
+}
+
+/// Everything a ray can look up, shared by every pixel of a sample.
+struct Scene<'a> {
+    config: &'a Config,
+    sampler: Sampler,
+    sky: &'a Sky,
+    sky_lod: f32,
+    disk: Option<&'a BakedDisk>,
+}
+
+/// Trace a ray, returning the color it sees and how it ended.
+///
+/// The light is also gathered into `pol`, when polarization is tracked.
+fn render<S: RayState>(
+    ro: Vec3,
+    rd: Vec3,
+    scene: &Scene,
+    mut pol: Option<&mut Polarization>,
+) -> (Vec3, Trace) {
+    let config = scene.config;
+
     // our timestep, start at a low value
-    let mut h = DELTA;
+    let mut h0 = DELTA;
     if config.features.contains(Features::RK4) {
-        h *= 1.5;
+        h0 *= 1.5;
     }
+    // kept at the precision of the state, it's only narrowed for the color
+    let mut step = S::Real::from(h0);
 
     // color information
     let mut attenuation = Vec3::ONE;
     let mut r = Vec3::ZERO;
 
+    // rays from a camera inside the horizon carry on until the singularity
+    // the field only diverges at the center, so nothing special happens at the horizon
+    let mut inside = config.features.contains(Features::INTERIOR)
+        && !config.features.contains(Features::STAR)
+        && ro.length_squared() < BLACKHOLE_RADIUS * BLACKHOLE_RADIUS;
+    let speed = if inside { infall_speed(ro.length()) } else { 1.0 };
+
     // add variation to our start point along the direction
-    let mut p = ro + (rand() * h * rd);
     // our inital velocity is just ray direction
-    let mut v = rd;
+    let mut s = S::new(ro + (rand() * h0 * rd), speed * rd);
 
     // keep track of the number of bounces the light takes
     // this is useful when integrating volumes
     let mut bounces = 0_u32;
+    let mut steps = 0_u32;
+    let trace = |termination, steps, bounces| Trace {
+        termination,
+        steps,
+        bounces,
+    };
+
+    let mut termination = Termination::Steps;
 
     for _ in 0..MAX_STEPS {
+        let p = s.position();
+        let h = step.as_f32();
+
         if bounces > MAX_BOUNCES {
             // discard sample, light gets stuck
-            return Vec3::splat(-1.0);
+            return (Vec3::splat(-1.0), trace(Termination::Stuck, steps, bounces));
         }
 
-        if p.length_squared() < BLACKHOLE_RADIUS * BLACKHOLE_RADIUS {
+        if inside {
+            if s.within(SINGULARITY_RADIUS) {
+                // light has hit the singularity
+                return (r, trace(Termination::Singularity, steps, bounces));
+            }
+
+            // the ray can leave through the horizon, but not enter it again
+            inside = s.within(BLACKHOLE_RADIUS);
+        } else if config.features.contains(Features::STAR) {
+            if s.within(config.star.radius) {
+                // light has come from the surface of the star
+                let emission = star_emission(p, s.velocity(), &config.star);
+
+                if let Some(pol) = pol {
+                    pol.unpolarized(attenuation * emission);
+                }
+
+                let color = r + attenuation * emission;
+                return (color, trace(Termination::Star, steps, bounces));
+            }
+        } else if s.within(BLACKHOLE_RADIUS) {
             // light has entered the black hole...
             // dont just return black, we might have gone through a volume to get here
-            return r;
+            return (r, trace(Termination::Horizon, steps, bounces));
         }
 
-        if p.length_squared() > SKYBOX_RADIUS * SKYBOX_RADIUS {
+        if !s.within(SKYBOX_RADIUS) {
             // we have hit the skybox
             // no need to integrate anymore
+            termination = Termination::Sky;
             break;
         }
 
         if config.features.contains(Features::DISK_VOL) {
-            let sample = disk_volume(p, config.disk.radius, config.disk.thickness);
+            let sample = disk_volume(p, config.disk.radius, config.disk.thickness, scene.disk);
             r += attenuation * sample.emission * h;
 
+            if let Some(pol) = pol.as_deref_mut() {
+                pol.disk(p, s.velocity(), attenuation * sample.emission * h);
+            }
+
             if sample.distance > 0.0 {
                 // hit the disc
 
@@ -474,7 +938,8 @@ fn render(ro: Vec3, rd: Vec3, sampler: Sampler, stars: &Texture2D, config: &Conf
                 let absorbance = (-1.0 * h * sample.distance).exp();
                 if absorbance < rand() {
                     // change the direction of v but keep its magnitude
-                    v = v.length() * reflect(v.normalize(), udir3());
+                    let v = s.velocity();
+                    s.set_velocity(v.length() * reflect(v.normalize(), udir3()));
 
                     attenuation *= config.disk.color;
 
@@ -485,74 +950,215 @@ fn render(ro: Vec3, rd: Vec3, sampler: Sampler, stars: &Texture2D, config: &Conf
             // represent the disk as a cylinder
             // it's much easier to see the entire volume of the disk this way,
             // without any fancy volume and fbm
-            let dist = disk_sdf(p, config.disk.thickness, config.disk.radius.sqrt());
-
-            if dist <= 0.0 {
+            if s.in_disk(config.disk.thickness, config.disk.radius.sqrt()) {
                 // hit the disc
-                return config.disk.color;
+                if let Some(pol) = pol {
+                    pol.disk(p, s.velocity(), config.disk.color);
+                }
+
+                return (config.disk.color, trace(Termination::Disk, steps, bounces));
             }
         }
 
-        // create state
-        let s = mat2x3(p, v);
+        if config.features.contains(Features::RIPPLE) {
+            // the ripple kicks the ray each step, on top of the field
+            let v = s.velocity() + h * ripple_field(p, &config.ripple);
+            s.set_velocity(v);
+        }
 
         // integrate
-        // choose the method of integration
-        let step = if config.features.contains(Features::ADAPTIVE) {
-            bogacki_shampine(s, &mut h)
-        } else if config.features.contains(Features::RK4) {
-            rk4(s, h)
+        if inside && !config.features.contains(Features::ADAPTIVE) {
+            // the field grows quickly towards the singularity, so take smaller steps
+            let scale = p.length_squared() / (BLACKHOLE_RADIUS * BLACKHOLE_RADIUS);
+            s.step(config.features, &mut (step * scale.max(0.05).into()));
         } else {
-            euler(s, h)
-        };
+            s.step(config.features, &mut step);
+        }
+        steps += 1;
 
-        // update system
-        p += step.x_axis;
-        v += step.y_axis;
+        if let Some(pol) = pol.as_deref_mut() {
+            pol.transport(s.velocity());
+        }
+    }
+
+    if inside {
+        // never made it out of the horizon
+        return (r, trace(Termination::Horizon, steps, bounces));
     }
 
-    if config.features.contains(Features::SKY_PROC) {
+    let v = s.velocity();
+
+    let sky = if config.features.contains(Features::SKY_PROC) {
         // procedurally create the skybox
-        r += attenuation * procedural_sky(v.normalize());
+        procedural_sky(v.normalize())
     } else {
         // sample the sky from a texture
-        r += attenuation * sample_sky(sampler, stars, v.normalize());
+        sample_sky(scene.sampler, scene.sky, v.normalize(), scene.sky_lod)
+    };
+    r += attenuation * sky;
+
+    if let Some(pol) = pol {
+        pol.unpolarized(attenuation * sky);
     }
 
-    r
+    (r, trace(termination, steps, bounces))
 }
 
 impl Renderer {
-    #[profiling::function]
     pub fn new(width: u32, height: u32, config: crate::Config) -> Self {
+        Self::with_tile(width, height, Tile::full(width, height), config)
+    }
+
+    /// Create a [`Renderer`] that only renders a `tile` of the frame.
+    ///
+    /// The buffer is the size of the tile.
+    #[profiling::function]
+    pub fn with_tile(width: u32, height: u32, tile: Tile, config: crate::Config) -> Self {
         let sampler = Sampler {
-            filter_mode: Filter::Nearest,
+            filter_mode: Filter::Linear,
+            mipmap_filter: Filter::Linear,
+            lod_bias: 0.0,
             edge_mode: EdgeMode::Wrap,
+            // sample the same texels as the gpu
+            semantics: Semantics::Wgsl,
         };
         let stars =
             Texture2D::from_bytes(include_bytes!("../../../textures/starmap_2020_4k.exr")).unwrap();
 
         Self {
-            buffer: FrameBuffer::new(width, height),
+            buffer: FrameBuffer::new(tile.width(), tile.height()),
             config,
 
+            resolution: UVec2::new(width, height),
+            tile,
+            first_sample: 0,
+            sample_no: 0,
+            dirty: true,
+
+            pool: None,
+
             sampler,
-            stars,
+            sky: Sky::Equirectangular(stars),
+
+            disk_resolution: None,
+            baked_disk: None,
+
+            heatmap: None,
+        }
+    }
+
+    /// Bake the disk volume into a texture of `resolution`,
+    /// instead of evaluating the noise at every step.
+    ///
+    /// The disk is baked again whenever its size changes.
+    pub fn set_disk_resolution(&mut self, resolution: Option<UVec3>) {
+        self.disk_resolution = resolution;
+        self.sample_no = 0;
+        self.dirty = true;
+    }
+
+    /// Show a `heatmap` of each pixel instead of its color, or the color if [`None`].
+    pub fn set_heatmap(&mut self, heatmap: Option<Heatmap>) {
+        if heatmap != self.heatmap {
+            self.heatmap = heatmap;
+            self.sample_no = 0;
+            self.dirty = true;
         }
     }
 
+    /// Make sure the baked disk matches the config, baking it if it doesn't.
+    fn update_baked_disk(&mut self) {
+        let disk = &self.config.disk;
+
+        let Some(resolution) = self
+            .disk_resolution
+            .filter(|_| self.config.features.contains(Features::DISK_VOL))
+        else {
+            self.baked_disk = None;
+            return;
+        };
+
+        let up_to_date = self.baked_disk.as_ref().is_some_and(|baked| {
+            baked.radius == disk.radius
+                && baked.thickness == disk.thickness
+                && baked.noise.size() == resolution
+        });
+
+        if !up_to_date {
+            self.baked_disk = Some(BakedDisk::bake(resolution, disk.radius, disk.thickness));
+        }
+    }
+
+    /// Replace the texture of the sky, restarting accumulation.
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.sky = sky;
+        self.sample_no = 0;
+        self.dirty = true;
+    }
+
+    /// Render on a dedicated pool of threads rather than the global one.
+    ///
+    /// Uses as many threads as there are cores when `count` is [`None`].
+    /// Low priority threads let long renders run in the background.
+    pub fn set_threads(
+        &mut self,
+        count: Option<usize>,
+        low_priority: bool,
+    ) -> Result<(), rayon::ThreadPoolBuildError> {
+        if count.is_none() && !low_priority {
+            self.pool = None;
+            return Ok(());
+        }
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(count.unwrap_or(0))
+            .thread_name(|i| format!("software-renderer-{i}"))
+            .start_handler(move |_| {
+                if low_priority {
+                    let min = thread_priority::ThreadPriority::Min;
+                    if let Err(e) = thread_priority::set_current_thread_priority(min) {
+                        log::warn!("failed to lower thread priority: {e}");
+                    }
+                }
+            })
+            .build()?;
+
+        self.pool = Some(pool);
+
+        Ok(())
+    }
+
+    /// Seed the samples as if `first` samples had already been computed.
+    ///
+    /// Accumulation still starts from an empty buffer,
+    /// so a range of samples can be rendered independently.
+    pub fn set_first_sample(&mut self, first: u32) {
+        self.first_sample = first;
+    }
+
+    /// Compute the `sample`th sample, accumulating it into the buffer.
     pub fn compute(&mut self, sample: u32) {
-        let view = self.config.camera.view();
-        let fov = self.config.camera.fov().as_f32();
+        self.sample_no = sample + 1;
+        self.update_baked_disk();
+
+        let res = self.resolution.as_vec2();
+        let seed = common::sample_seed(self.config.seed, self.first_sample + sample);
+
+        let scene = Scene {
+            config: &self.config,
+            sampler: self.sampler,
+            sky: &self.sky,
+            sky_lod: self.sky_lod(),
+            disk: self.baked_disk.as_ref(),
+        };
+        let camera = CameraRays::new(&self.config);
 
-        let origin = view.translation.into();
-        let res = Vec2::new(self.buffer.width() as f32, self.buffer.height() as f32);
+        let shade = |id: UVec2, old: Vec4| {
+            // the position of the pixel in the whole frame
+            let id = id + self.tile.min;
 
-        // make the view is being transposed, the same as on the gpu
-        let view = self.config.camera.view().matrix3.transpose();
-        let view = glam::Affine3A::from_mat3(view.into());
+            seed_rng(id, seed);
 
-        self.buffer.par_for_each(|id, old| {
             let coord = id.as_vec2();
 
             let coord = if self.config.features.contains(Features::AA) {
@@ -579,36 +1185,186 @@ impl Renderer {
                 }
             }
 
-            // the ray origin
-            let ro = view.transform_vector3(origin);
-            // the ray direction (multiplied by the fov factor 2 * FOV * 1/PI, which gives us 90 degrees = 1.0 factor)
-            let rd = view
-                .transform_vector3((uv * 2.0 * fov * FRAC_1_PI).extend(-1.0))
-                .normalize();
+            let (ro, rd, brightness) = camera.ray(uv);
+
+            // track polarization against the camera's right axis
+            let mut pol = self
+                .config
+                .features
+                .contains(Features::POLARIZATION)
+                .then(|| Polarization::new(rd, camera.right()));
 
             // render using the ray information
-            let color = render(ro, rd, self.sampler, &self.stars, &self.config);
+            let (color, trace) = match self.config.precision {
+                Precision::Single => render::<Mat3>(ro, rd, &scene, pol.as_mut()),
+                Precision::Double => render::<DMat3>(ro, rd, &scene, pol.as_mut()),
+            };
+            let color = color * brightness;
 
             // remove unused samples
-            let color = if color.cmplt(Vec3::ZERO).any() || !color.is_finite() || color.is_nan() {
-                Vec3::ZERO
+            let unused = color.cmplt(Vec3::ZERO).any() || !color.is_finite() || color.is_nan();
+            let color = if unused { Vec3::ZERO } else { color };
+
+            let color = if let Some(heatmap) = self.heatmap {
+                let t = match heatmap {
+                    Heatmap::Steps => trace.steps as f32 / MAX_STEPS as f32,
+                    Heatmap::Bounces => trace.bounces as f32 / (MAX_BOUNCES + 1) as f32,
+                };
+                heat(t).extend(1.0)
+            } else if let Some(pol) = pol {
+                // output the stokes parameters instead of color
+                let stokes = if unused { Vec4::ZERO } else { pol.stokes() * brightness };
+                common::stokes::encode(stokes)
             } else {
-                color
+                // accumulate linear light, it's encoded when the frame is read
+                color.extend(1.0)
             };
 
-            // gamma correction
-            let color = color.powf(0.45);
-
-            // add alpha (always 1)
-            let color = color.extend(1.0);
-
             // accumulate the color in the buffer
             old.lerp(color, 1.0 / (sample + 1) as f32)
-        });
+        };
+
+        let tile_size = UVec2::splat(16);
+
+        match &self.pool {
+            Some(pool) => pool.install(|| self.buffer.par_for_each_tile(tile_size, shade)),
+            None => self.buffer.par_for_each_tile(tile_size, shade),
+        }
+    }
+
+    /// Trace the ray through the center of a `pixel` of the frame, to find how it ended.
+    ///
+    /// The ray is traced without AA or bloom, using the rng of the first sample.
+    pub fn trace(&self, pixel: UVec2) -> Trace {
+        let res = self.resolution.as_vec2();
+        seed_rng(pixel, common::sample_seed(self.config.seed, self.first_sample));
+
+        let scene = Scene {
+            config: &self.config,
+            sampler: self.sampler,
+            sky: &self.sky,
+            sky_lod: self.sky_lod(),
+            disk: self.baked_disk.as_ref(),
+        };
+        let camera = CameraRays::new(&self.config);
+
+        let uv = 2.0 * (pixel.as_vec2() - 0.5 * res) / f32::max(res.x, res.y);
+        let (ro, rd, _) = camera.ray(uv);
+
+        let (_, trace) = match self.config.precision {
+            Precision::Single => render::<Mat3>(ro, rd, &scene, None),
+            Precision::Double => render::<DMat3>(ro, rd, &scene, None),
+        };
+
+        trace
+    }
+
+    /// The mip level of the sky, chosen from how many texels a pixel covers.
+    fn sky_lod(&self) -> f32 {
+        let res = self.resolution.as_vec2();
+        let fov = self.config.camera.fov().as_f32();
+
+        let pixel_angle = 2.0 / res.max_element() * 2.0 * fov * FRAC_1_PI;
+        (pixel_angle * self.sky.texels_per_radian()).log2()
+    }
+
+    /// Converts the [`Renderer`] into floats `[r, g, b, a]` of linear light,
+    /// see [`Render::frame_linear`].
+    #[profiling::function]
+    pub fn into_frame_linear(self) -> Vec<f32> {
+        self.buffer.into_rgba32f_vec()
+    }
+
+    /// Converts the [`Renderer`] into floats `[r, g, b, a]`, without losing precision.
+    #[profiling::function]
+    pub fn into_frame_hdr(self) -> Vec<f32> {
+        let encoded = self.encodes_srgb();
+        resolve(self.buffer.into_rgba32f_vec(), encoded)
+    }
+
+    /// Returns `true` if the buffer holds linear light, encoded as sRGB when the frame is read.
+    fn encodes_srgb(&self) -> bool {
+        self.heatmap.is_none() && self.config.features.encodes_srgb()
+    }
+}
+
+/// Resolve the accumulated floats `[r, g, b, a]` into the frame output,
+/// encoding the color for display if it's `encoded`.
+fn resolve(mut floats: Vec<f32>, encoded: bool) -> Vec<f32> {
+    if encoded {
+        for pixel in floats.chunks_exact_mut(4) {
+            let color = linear_to_srgb(Vec3::from_slice(&pixel[..3]));
+            color.write_to_slice(&mut pixel[..3]);
+        }
+    }
+
+    floats
+}
+
+/// Quantize floats `[r, g, b, a]` into bytes, the same as the buffer of the hardware renderer.
+fn to_bytes(floats: &[f32]) -> Vec<u8> {
+    floats
+        .iter()
+        .map(|&f| (f.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect()
+}
+
+impl Render for Renderer {
+    /// Resize the buffer to the whole frame, if the size has changed.
+    #[profiling::function]
+    fn update(&mut self, width: u32, height: u32, config: Config) {
+        let resolution = UVec2::new(width, height);
+        let tile = Tile::full(width, height);
+
+        let dimensions_changed = resolution != self.resolution || tile != self.tile;
+        let config_changed = config != self.config;
+
+        if dimensions_changed {
+            self.buffer = FrameBuffer::new(width, height);
+        }
+
+        self.config = config;
+        self.resolution = resolution;
+        self.tile = tile;
+
+        self.dirty = dimensions_changed || config_changed;
+
+        if self.dirty {
+            self.sample_no = 0;
+        }
+    }
+
+    fn must_render(&self) -> bool {
+        self.dirty
+    }
+
+    fn compute_sample(&mut self) {
+        self.compute(self.sample_no);
+    }
+
+    fn sample_count(&self) -> u32 {
+        self.sample_no
+    }
+
+    #[profiling::function]
+    fn frame(&self) -> Vec<u8> {
+        to_bytes(&self.frame_hdr())
+    }
+
+    /// Copies the current frame output into floats, without losing precision.
+    #[profiling::function]
+    fn frame_hdr(&self) -> Vec<f32> {
+        resolve(self.buffer.to_rgba32f_vec(), self.encodes_srgb())
+    }
+
+    /// The buffer always holds linear light, so it's copied as it is.
+    #[profiling::function]
+    fn frame_linear(&self) -> Vec<f32> {
+        self.buffer.to_rgba32f_vec()
     }
 
     #[profiling::function]
-    pub fn into_frame(self) -> Vec<u8> {
-        self.buffer.into_vec()
+    fn into_frame(self) -> Vec<u8> {
+        to_bytes(&self.into_frame_hdr())
     }
 }
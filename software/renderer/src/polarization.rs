@@ -0,0 +1,77 @@
+//! Polarization transported along each ray.
+//!
+//! Rays are integrated in flat space, bent by a force,
+//! so parallel transport keeps the polarization frame perpendicular to the ray
+//! while rotating it as little as possible.
+
+use glam::{
+    Vec3,
+    Vec4,
+};
+
+/// The fraction of the disk's light that is polarized,
+/// close to the limit for synchrotron radiation.
+const DISK_POLARIZATION: f32 = 0.7;
+
+/// The weights of each channel to find the luminance of a color.
+/// https://en.wikipedia.org/wiki/Relative_luminance
+const LUMINANCE: Vec3 = Vec3::new(0.2126, 0.7152, 0.0722);
+
+/// The polarization frame of a ray, and the light it has gathered.
+pub(crate) struct Polarization {
+    /// The camera's right axis, carried along the ray.
+    frame: Vec3,
+    /// `[I, Q, U]`, there is no circular polarization.
+    stokes: Vec3,
+}
+
+impl Polarization {
+    /// Start with the camera's `right` axis, made perpendicular to the ray direction `rd`.
+    pub fn new(rd: Vec3, right: Vec3) -> Self {
+        Self {
+            frame: (right - right.dot(rd) * rd).normalize(),
+            stokes: Vec3::ZERO,
+        }
+    }
+
+    /// Carry the frame along to the new velocity `v` of the ray.
+    pub fn transport(&mut self, v: Vec3) {
+        let v = v.normalize();
+        let frame = self.frame - self.frame.dot(v) * v;
+
+        // keep the old frame if the ray has turned right around
+        self.frame = frame.try_normalize().unwrap_or(self.frame);
+    }
+
+    /// Gather light from the sky or scattering, which isn't polarized.
+    pub fn unpolarized(&mut self, color: Vec3) {
+        self.stokes.x += color.dot(LUMINANCE);
+    }
+
+    /// Gather light emitted by the disk at `p`, for a ray moving with velocity `v`.
+    pub fn disk(&mut self, p: Vec3, v: Vec3, color: Vec3) {
+        let i = color.dot(LUMINANCE);
+        self.stokes.x += i;
+
+        // assume the magnetic field of the disk circles around the black hole
+        let field = Vec3::new(-p.z, 0.0, p.x);
+
+        // synchrotron light is polarized perpendicular to the field and the ray
+        let Some(e) = v.cross(field).try_normalize() else {
+            return;
+        };
+
+        let v = v.normalize();
+        let (c, s) = (e.dot(self.frame), e.dot(v.cross(self.frame)));
+
+        // the angle is doubled, as polarization is the same after half a turn
+        let (cos2, sin2) = (c * c - s * s, 2.0 * c * s);
+        self.stokes.y += DISK_POLARIZATION * i * cos2;
+        self.stokes.z += DISK_POLARIZATION * i * sin2;
+    }
+
+    /// The Stokes parameters `[I, Q, U, V]` of everything gathered.
+    pub fn stokes(&self) -> Vec4 {
+        self.stokes.extend(0.0)
+    }
+}
@@ -0,0 +1,88 @@
+use glam::{
+    Vec2,
+    Vec3,
+    Vec4,
+};
+
+use crate::texture::{
+    EdgeMode,
+    Sample,
+    Sampler,
+    Texture2D,
+};
+
+/// A cubemap made of six square faces, sampled by direction.
+pub struct TextureCube {
+    /// Faces in the order `+x, -x, +y, -y, +z, -z`, the same as wgpu.
+    faces: [Texture2D; 6],
+}
+
+impl TextureCube {
+    /// Loads each face from bytes in memory.
+    ///
+    /// The faces are in the order `+x, -x, +y, -y, +z, -z`.
+    #[profiling::function]
+    pub fn from_faces(faces: [&[u8]; 6]) -> Result<Self, image::ImageError> {
+        let [px, nx, py, ny, pz, nz] = faces.map(Texture2D::from_bytes);
+
+        Ok(Self {
+            faces: [px?, nx?, py?, ny?, pz?, nz?],
+        })
+    }
+
+    /// The width of a single face.
+    pub fn size(&self) -> u32 {
+        self.faces[0].size().x
+    }
+
+    /// Find the face that `dir` points at, and the uv on that face.
+    fn face(dir: Vec3) -> (usize, Vec2) {
+        let abs = dir.abs();
+
+        // https://registry.khronos.org/vulkan/specs/1.3/html/chap16.html#_cube_map_face_selection
+        let (face, ma, sc, tc) = if abs.x >= abs.y && abs.x >= abs.z {
+            if dir.x >= 0.0 {
+                (0, abs.x, -dir.z, -dir.y)
+            } else {
+                (1, abs.x, dir.z, -dir.y)
+            }
+        } else if abs.y >= abs.z {
+            if dir.y >= 0.0 {
+                (2, abs.y, dir.x, dir.z)
+            } else {
+                (3, abs.y, dir.x, -dir.z)
+            }
+        } else if dir.z >= 0.0 {
+            (4, abs.z, dir.x, -dir.y)
+        } else {
+            (5, abs.z, -dir.x, -dir.y)
+        };
+
+        (face, 0.5 * (Vec2::new(sc, tc) / ma + 1.0))
+    }
+}
+
+/// Allows samplers to sample a [`TextureCube`] in a direction.
+pub trait SampleCube {
+    /// Samples a [`TextureCube`] in the direction `dir`, which doesn't need to be normalized.
+    fn sample_cube(&self, tex: &TextureCube, dir: Vec3) -> Vec4 {
+        self.sample_cube_level(tex, dir, 0.0)
+    }
+
+    /// Samples a [`TextureCube`] at a level of detail, where `0.0` is the full size level.
+    fn sample_cube_level(&self, tex: &TextureCube, dir: Vec3, lod: f32) -> Vec4;
+}
+
+impl SampleCube for Sampler {
+    fn sample_cube_level(&self, tex: &TextureCube, dir: Vec3, lod: f32) -> Vec4 {
+        let (face, uv) = TextureCube::face(dir);
+
+        // wrapping would bleed in the opposite edge of the face
+        let sampler = Sampler {
+            edge_mode: EdgeMode::Clamp,
+            ..*self
+        };
+
+        sampler.sample_level(&tex.faces[face], uv, lod)
+    }
+}
@@ -0,0 +1,68 @@
+//! Compute dispatches that mirror WGSL workgroups.
+//!
+//! A [`Kernel`] sees the same builtin ids as a compute shader,
+//! so it can be written in the same style and compared against the GPU.
+
+use glam::{
+    UVec2,
+    UVec3,
+    Vec4,
+};
+
+use crate::FrameBuffer;
+
+/// The builtin ids of an invocation, named after their WGSL counterparts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Invocation {
+    /// `@builtin(global_invocation_id)`
+    pub global_id: UVec3,
+    /// `@builtin(local_invocation_id)`
+    pub local_id: UVec3,
+    /// `@builtin(local_invocation_index)`
+    pub local_index: u32,
+    /// `@builtin(workgroup_id)`
+    pub workgroup_id: UVec3,
+    /// `@builtin(num_workgroups)`
+    pub num_workgroups: UVec3,
+}
+
+/// A compute shader that runs once per invocation.
+pub trait Kernel: Sync {
+    /// Memory shared by the invocations of a workgroup, like `var<workgroup>`.
+    ///
+    /// Created with [`Default`] at the start of each workgroup.
+    type Shared: Default;
+
+    /// The number of stages, with a `workgroupBarrier()` between each one.
+    const STAGES: u32 = 1;
+
+    /// Run a `stage` of an invocation.
+    ///
+    /// `texel` is the pixel at the global id, or [`None`] if it's outside of the buffer.
+    fn run(&self, stage: u32, id: Invocation, shared: &mut Self::Shared, texel: Option<&mut Vec4>);
+}
+
+/// Closures are kernels with a single stage and no shared memory.
+impl<F> Kernel for F
+where
+    F: Fn(Invocation, Option<&mut Vec4>) + Sync,
+{
+    type Shared = ();
+
+    fn run(&self, _stage: u32, id: Invocation, _shared: &mut (), texel: Option<&mut Vec4>) {
+        self(id, texel)
+    }
+}
+
+/// Dispatch enough workgroups of `workgroup_size` to cover `width` by `height`,
+/// returning the texels the kernel wrote, starting from zero.
+pub fn dispatch<K: Kernel>(
+    width: u32,
+    height: u32,
+    workgroup_size: UVec2,
+    kernel: &K,
+) -> FrameBuffer {
+    let mut buffer = FrameBuffer::new(width, height);
+    buffer.dispatch(workgroup_size, kernel);
+    buffer
+}
@@ -1,17 +1,42 @@
+use std::path::Path;
+
+use common::Tile;
 use glam::{
     UVec2,
     Vec4,
 };
 use rayon::prelude::*;
 
+pub mod cube;
+pub mod dispatch;
 pub mod texture;
+pub mod tiled;
+pub mod volume;
 
+pub use cube::{
+    SampleCube,
+    TextureCube,
+};
+pub use dispatch::{
+    dispatch,
+    Invocation,
+    Kernel,
+};
 pub use texture::{
     Sample,
     Sampler,
+    Texels2D,
     Texture1D,
     Texture2D,
 };
+pub use tiled::{
+    TexelFormat,
+    TiledTexture2D,
+};
+pub use volume::{
+    SampleVolume,
+    Texture3D,
+};
 
 pub struct FrameBuffer {
     buffer: image::Rgba32FImage,
@@ -58,6 +83,131 @@ impl FrameBuffer {
             });
     }
 
+    /// Iterates through the [`FrameBuffer`] in parallel, one tile of `tile_size` at a time.
+    ///
+    /// Each tile is visited in row order,
+    /// which is friendlier to the cache than [`Self::par_for_each`].
+    /// For each pixel, it calls a function (id, color) and expects you to return an updated color.
+    #[profiling::function]
+    pub fn par_for_each_tile(
+        &mut self,
+        tile_size: UVec2,
+        f: impl (Fn(UVec2, Vec4) -> Vec4) + Sync,
+    ) {
+        assert!(tile_size.cmpgt(UVec2::ZERO).all(), "tiles can't be empty");
+
+        let tile_size = tile_size.min(UVec2::new(self.width, self.height).max(UVec2::ONE));
+
+        self.par_tiles(tile_size, |min, rows| {
+            for (y, row) in rows.into_iter().enumerate() {
+                for (x, p) in row.chunks_exact_mut(4).enumerate() {
+                    let id = min + UVec2::new(x as u32, y as u32);
+                    let color = f(id, Vec4::from_slice(p));
+
+                    p.copy_from_slice(&color.to_array());
+                }
+            }
+        });
+    }
+
+    /// Runs a compute `kernel` over the [`FrameBuffer`] in parallel,
+    /// with one invocation per pixel, the same as dispatching a compute shader.
+    ///
+    /// Invocations past the edge of the buffer still run, but without a texel.
+    #[profiling::function]
+    pub fn dispatch<K: Kernel>(&mut self, workgroup_size: UVec2, kernel: &K) {
+        assert!(workgroup_size.cmpgt(UVec2::ZERO).all(), "workgroups can't be empty");
+
+        let num_workgroups = UVec2::new(
+            self.width.div_ceil(workgroup_size.x),
+            self.height.div_ceil(workgroup_size.y),
+        );
+
+        self.par_tiles(workgroup_size, |min, mut rows| {
+            let mut shared = K::Shared::default();
+
+            // every invocation finishes a stage before the next starts, like `workgroupBarrier()`
+            for stage in 0..K::STAGES {
+                for local_index in 0..workgroup_size.x * workgroup_size.y {
+                    let local = UVec2::new(
+                        local_index % workgroup_size.x,
+                        local_index / workgroup_size.x,
+                    );
+                    let id = Invocation {
+                        global_id: (min + local).extend(0),
+                        local_id: local.extend(0),
+                        local_index,
+                        workgroup_id: (min / workgroup_size).extend(0),
+                        num_workgroups: num_workgroups.extend(1),
+                    };
+
+                    let (x, y) = (local.x as usize * 4, local.y as usize);
+                    let texel = rows.get_mut(y).and_then(|row| row.get_mut(x..x + 4));
+
+                    let mut color = texel.as_deref().map(Vec4::from_slice);
+                    kernel.run(stage, id, &mut shared, color.as_mut());
+
+                    if let (Some(texel), Some(color)) = (texel, color) {
+                        texel.copy_from_slice(&color.to_array());
+                    }
+                }
+            }
+        });
+    }
+
+    /// Calls `f` in parallel with the position and rows of each tile of `tile_size`.
+    ///
+    /// Tiles on the right and bottom edges are cut short.
+    fn par_tiles(&mut self, tile_size: UVec2, f: impl Fn(UVec2, Vec<&mut [f32]>) + Sync) {
+        let width = self.width as usize;
+        let columns = self.width.div_ceil(tile_size.x) as usize;
+
+        self.buffer
+            .par_chunks_mut(width * 4 * tile_size.y as usize)
+            .enumerate()
+            .for_each(|(band, rows)| {
+                // split the band of rows into the rows of each tile
+                let mut tiles: Vec<Vec<&mut [f32]>> = (0..columns).map(|_| Vec::new()).collect();
+                for row in rows.chunks_mut(width * 4) {
+                    let row_tiles = row.chunks_mut(tile_size.x as usize * 4);
+
+                    for (tile, row) in tiles.iter_mut().zip(row_tiles) {
+                        tile.push(row);
+                    }
+                }
+
+                tiles.into_par_iter().enumerate().for_each(|(column, rows)| {
+                    f(UVec2::new(column as u32, band as u32) * tile_size, rows);
+                });
+            });
+    }
+
+    /// Iterates through the pixels in `region` of the [`FrameBuffer`] in parallel,
+    /// leaving the rest of the buffer untouched.
+    ///
+    /// For each pixel, it calls a function (id, color) and expects you to return an updated color.
+    #[profiling::function]
+    pub fn update_region(&mut self, region: Tile, f: impl (Fn(UVec2, Vec4) -> Vec4) + Sync) {
+        assert!(region.fits(self.width, self.height), "region is outside of the buffer");
+
+        let width = self.width as usize;
+        let columns = region.min.x as usize * 4..region.max.x as usize * 4;
+
+        self.buffer
+            .par_chunks_mut(width * 4)
+            .enumerate()
+            .skip(region.min.y as usize)
+            .take(region.height() as usize)
+            .for_each(|(y, row)| {
+                for (x, p) in row[columns.clone()].chunks_exact_mut(4).enumerate() {
+                    let id = UVec2::new(region.min.x + x as u32, y as u32);
+                    let color = f(id, Vec4::from_slice(p));
+
+                    p.copy_from_slice(&color.to_array());
+                }
+            });
+    }
+
     /// Width of the [`FrameBuffer`].
     pub fn width(&self) -> u32 {
         self.width
@@ -68,11 +218,37 @@ impl FrameBuffer {
         self.height
     }
 
-    /// Converts this [`FrameBuffer`] into an array of bytes `[r, g, b, a]`.
-    pub fn into_vec(self) -> Vec<u8> {
+    /// Copies this [`FrameBuffer`] into an array of bytes `[r, g, b, a]`.
+    pub fn to_vec(&self) -> Vec<u8> {
         use image::buffer::ConvertBuffer;
 
         let buffer: image::RgbaImage = self.buffer.convert();
         buffer.into_vec()
     }
+
+    /// Converts this [`FrameBuffer`] into an array of bytes `[r, g, b, a]`.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    /// The floats `[r, g, b, a]` of this [`FrameBuffer`], without copying.
+    pub fn as_f32_slice(&self) -> &[f32] {
+        self.buffer.as_raw()
+    }
+
+    /// Copies this [`FrameBuffer`] into an array of floats `[r, g, b, a]`.
+    pub fn to_rgba32f_vec(&self) -> Vec<f32> {
+        self.as_f32_slice().to_vec()
+    }
+
+    /// Converts this [`FrameBuffer`] into an array of floats `[r, g, b, a]`.
+    pub fn into_rgba32f_vec(self) -> Vec<f32> {
+        self.buffer.into_raw()
+    }
+
+    /// Saves this [`FrameBuffer`] as an OpenEXR image, without losing any precision.
+    #[profiling::function]
+    pub fn save_exr(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        self.buffer.save_with_format(path, image::ImageFormat::OpenExr)
+    }
 }
@@ -1,5 +1,6 @@
 use glam::{
     UVec2,
+    UVec3,
     Vec2,
     Vec4,
 };
@@ -8,48 +9,113 @@ pub type Texture1D = Texture<1>;
 pub type Texture2D = Texture<2>;
 
 pub struct Texture<const DIM: u32> {
-    img: image::Rgba32FImage,
+    /// The mip chain, starting with the full size image.
+    mips: Vec<image::Rgba32FImage>,
 }
 
 impl<const DIM: u32> Texture<DIM> {
     /// Loads an Rgba texture from bytes in memory.
+    ///
+    /// Generates the full mip chain.
     #[profiling::function]
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, image::ImageError> {
         assert!(DIM > 0 && DIM <= 2, "Incorrect dimensions");
 
         let dyn_img = image::load_from_memory(bytes)?;
 
-        Ok(Self {
-            img: dyn_img.into_rgba32f(),
-        })
+        Ok(Self::from_image(dyn_img.into_rgba32f()))
     }
+
+    /// Creates a texture from an image, generating the full mip chain.
+    pub fn from_image(img: image::Rgba32FImage) -> Self {
+        let mut mips = vec![img];
+
+        while let Some(mip) = mips.last().and_then(downsample) {
+            mips.push(mip);
+        }
+
+        Self { mips }
+    }
+
+    /// The number of levels in the mip chain, including the full size image.
+    pub fn mip_levels(&self) -> u32 {
+        self.mips.len() as u32
+    }
+}
+
+/// Halve the size of an image, averaging each 2x2 block of pixels.
+///
+/// Returns [`None`] once the image is a single pixel.
+#[profiling::function]
+pub(crate) fn downsample(img: &image::Rgba32FImage) -> Option<image::Rgba32FImage> {
+    let (width, height) = img.dimensions();
+    if width == 1 && height == 1 {
+        return None;
+    }
+
+    let mip = image::Rgba32FImage::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+        // odd edges are clamped rather than wrapped
+        let x0 = (2 * x).min(width - 1);
+        let y0 = (2 * y).min(height - 1);
+        let x1 = (x0 + 1).min(width - 1);
+        let y1 = (y0 + 1).min(height - 1);
+
+        let sum = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+            .into_iter()
+            .map(|(x, y)| pixel_to_vec(*img.get_pixel(x, y)))
+            .sum::<Vec4>();
+
+        image::Rgba((sum * 0.25).to_array())
+    });
+
+    Some(mip)
 }
 
 impl Texture<1> {
     pub fn size(&self) -> u32 {
-        self.img.width()
+        self.level_size(0)
+    }
+
+    /// The size of a `level` of the mip chain.
+    pub fn level_size(&self, level: u32) -> u32 {
+        self.mips[level as usize].width()
     }
 
     pub fn get(&self, x: u32) -> Vec4 {
-        pixel_to_vec(*self.img.get_pixel(x, 0))
+        self.get_level(x, 0)
+    }
+
+    /// Get a texel from a `level` of the mip chain.
+    pub fn get_level(&self, x: u32, level: u32) -> Vec4 {
+        pixel_to_vec(*self.mips[level as usize].get_pixel(x, 0))
     }
 
     pub fn get_checked(&self, x: u32) -> Option<Vec4> {
-        self.img.get_pixel_checked(x, 0).copied().map(pixel_to_vec)
+        self.mips[0].get_pixel_checked(x, 0).copied().map(pixel_to_vec)
     }
 }
 
 impl Texture<2> {
     pub fn size(&self) -> UVec2 {
-        self.img.dimensions().into()
+        self.level_size(0)
+    }
+
+    /// The size of a `level` of the mip chain.
+    pub fn level_size(&self, level: u32) -> UVec2 {
+        self.mips[level as usize].dimensions().into()
     }
 
     pub fn get(&self, x: u32, y: u32) -> Vec4 {
-        pixel_to_vec(*self.img.get_pixel(x, y))
+        self.get_level(x, y, 0)
+    }
+
+    /// Get a texel from a `level` of the mip chain.
+    pub fn get_level(&self, x: u32, y: u32, level: u32) -> Vec4 {
+        pixel_to_vec(*self.mips[level as usize].get_pixel(x, y))
     }
 
     pub fn get_checked(&self, x: u32, y: u32) -> Option<Vec4> {
-        self.img.get_pixel_checked(x, y).copied().map(pixel_to_vec)
+        self.mips[0].get_pixel_checked(x, y).copied().map(pixel_to_vec)
     }
 }
 
@@ -57,6 +123,32 @@ fn pixel_to_vec(pixel: image::Rgba<f32>) -> Vec4 {
     Vec4::from_array(pixel.0)
 }
 
+/// A 2D mip chain of texels that a [`Sampler`] can read from.
+pub trait Texels2D {
+    /// The number of levels in the mip chain, including the full size image.
+    fn mip_levels(&self) -> u32;
+
+    /// The size of a `level` of the mip chain.
+    fn level_size(&self, level: u32) -> UVec2;
+
+    /// Get a texel from a `level` of the mip chain.
+    fn get_level(&self, x: u32, y: u32, level: u32) -> Vec4;
+}
+
+impl Texels2D for Texture<2> {
+    fn mip_levels(&self) -> u32 {
+        self.mip_levels()
+    }
+
+    fn level_size(&self, level: u32) -> UVec2 {
+        self.level_size(level)
+    }
+
+    fn get_level(&self, x: u32, y: u32, level: u32) -> Vec4 {
+        self.get_level(x, y, level)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Filter {
     Nearest,
@@ -66,68 +158,119 @@ pub enum Filter {
 #[derive(Clone, Copy)]
 pub enum EdgeMode {
     Wrap,
+    /// Repeat the texels at the edge.
+    Clamp,
 }
 
 impl EdgeMode {
-    pub fn apply2d(&self, tex: &Texture2D, x: u32, y: u32) -> (u32, u32) {
-        let size = tex.size();
+    pub fn apply2d(&self, size: UVec2, x: u32, y: u32) -> (u32, u32) {
         match self {
             EdgeMode::Wrap => (x % size.x, y % size.y),
+            EdgeMode::Clamp => (x.min(size.x - 1), y.min(size.y - 1)),
+        }
+    }
+
+    pub fn apply3d(&self, size: UVec3, x: i64, y: i64, z: i64) -> (u32, u32, u32) {
+        (
+            self.apply1d(size.x, x),
+            self.apply1d(size.y, y),
+            self.apply1d(size.z, z),
+        )
+    }
+
+    pub(crate) fn apply1d(&self, size: u32, x: i64) -> u32 {
+        match self {
+            EdgeMode::Wrap => x.rem_euclid(size as i64) as u32,
+            EdgeMode::Clamp => x.clamp(0, size as i64 - 1) as u32,
         }
     }
 }
 
+/// The rules used to turn coordinates into texels.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Semantics {
+    /// The original behaviour of wcpu.
+    ///
+    /// [`Filter::Nearest`] rounds to the closest texel corner,
+    /// and [`Filter::Linear`] blends the texels at the floor and ceiling of the position.
+    /// The blend is divided by the width between them and multiplied by the height,
+    /// so it's `NaN` or zero where a coordinate lands exactly on a texel corner.
+    /// In 1D, [`Filter::Linear`] blends from the texel at the floor to the next one.
+    #[default]
+    Legacy,
+    /// Matches WGSL texture addressing and filtering, so samples agree with the GPU.
+    ///
+    /// Texel centers are at `(i + 0.5) / size`, nearest filtering floors,
+    /// and the edge mode applies to each texel of a linear filter.
+    /// <https://www.w3.org/TR/webgpu/#texel-coordinates>
+    Wgsl,
+}
+
 #[derive(Clone, Copy)]
 pub struct Sampler {
     /// What filter is applied to each point.
     pub filter_mode: Filter,
+    /// How the sampler blends between levels of the mip chain.
+    ///
+    /// [`Filter::Linear`] with a linear `filter_mode` gives trilinear filtering.
+    pub mipmap_filter: Filter,
+    /// Added to the level of detail of every sample.
+    pub lod_bias: f32,
     /// What the sampler does at the edge of a texture
     pub edge_mode: EdgeMode,
+    /// How coordinates are turned into texels.
+    pub semantics: Semantics,
 }
 
-/// Allows samplers to Sample [`Textures`](Texture) of dimension `D`, using different types of points.
-pub trait Sample<const D: u32> {
-    /// The type of query point.
-    type Point;
-
-    /// Samples a [`Texture`] and returns the color at that point.
-    fn sample(&self, tex: &Texture<D>, uv: Self::Point) -> Vec4;
-}
-
-impl Sample<1> for Sampler {
-    type Point = f32;
-
-    fn sample(&self, tex: &Texture<1>, uv: Self::Point) -> Vec4 {
-        let pos = uv * tex.size() as f32;
+impl Sampler {
+    /// Choose the mip levels to sample at `lod`, and how much of the second level to blend in.
+    fn levels(&self, lod: f32, mip_levels: u32) -> (u32, u32, f32) {
+        let max = (mip_levels - 1) as f32;
+        let lod = (lod + self.lod_bias).clamp(0.0, max);
 
-        match self.filter_mode {
+        match self.mipmap_filter {
             Filter::Nearest => {
-                let x = pos.round();
-                tex.get(x as u32)
+                let level = match self.semantics {
+                    Semantics::Legacy => lod.round(),
+                    // halfway rounds down
+                    Semantics::Wgsl => (lod + 0.5).ceil() - 1.0,
+                } as u32;
+                (level, level, 0.0)
             }
             Filter::Linear => {
-                unimplemented!()
+                let level = lod.floor();
+                (level as u32, lod.ceil() as u32, lod - level)
             }
         }
     }
-}
 
-impl Sample<2> for Sampler {
-    type Point = Vec2;
+    /// Apply the edge mode to a texel position.
+    fn texel(&self, size: UVec2, p: Vec2) -> (u32, u32) {
+        match self.semantics {
+            Semantics::Legacy => self.edge_mode.apply2d(size, p.x as u32, p.y as u32),
+            Semantics::Wgsl => (
+                self.edge_mode.apply1d(size.x, p.x as i64),
+                self.edge_mode.apply1d(size.y, p.y as i64),
+            ),
+        }
+    }
 
-    fn sample(&self, tex: &Texture<2>, uv: Self::Point) -> Vec4 {
-        let size = tex.size();
+    fn sample_2d(&self, tex: &impl Texels2D, uv: Vec2, level: u32) -> Vec4 {
+        let size = tex.level_size(level);
         let pos = uv * size.as_vec2();
 
         match self.filter_mode {
             Filter::Nearest => {
-                let Vec2 { x, y } = pos.round();
+                let pos = match self.semantics {
+                    Semantics::Legacy => pos.round(),
+                    Semantics::Wgsl => pos.floor(),
+                };
 
-                let (x, y) = self.edge_mode.apply2d(tex, x as u32, y as u32);
+                let (x, y) = self.texel(size, pos);
 
-                tex.get(x, y)
+                tex.get_level(x, y, level)
             }
-            Filter::Linear => {
+            Filter::Linear if self.semantics == Semantics::Legacy => {
                 let Vec2 { x, y } = pos;
 
                 let x1 = x.floor();
@@ -136,14 +279,14 @@ impl Sample<2> for Sampler {
                 let y2 = y.ceil();
 
                 let (q11, q12, q21, q22) = {
-                    let (x1, y1) = self.edge_mode.apply2d(tex, x1 as u32, y1 as u32);
-                    let (x2, y2) = self.edge_mode.apply2d(tex, x2 as u32, y2 as u32);
+                    let (x1, y1) = self.texel(size, Vec2::new(x1, y1));
+                    let (x2, y2) = self.texel(size, Vec2::new(x2, y2));
 
                     (
-                        tex.get(x1, y1),
-                        tex.get(x1, y2),
-                        tex.get(x2, y1),
-                        tex.get(x2, y2),
+                        tex.get_level(x1, y1, level),
+                        tex.get_level(x1, y2, level),
+                        tex.get_level(x2, y1, level),
+                        tex.get_level(x2, y2, level),
                     )
                 };
 
@@ -154,6 +297,231 @@ impl Sample<2> for Sampler {
                     / (x2 - x1)
                     * (y2 - y1)
             }
+            Filter::Linear => {
+                // blend between the nearest texel centers
+                let pos = pos - 0.5;
+
+                let p1 = pos.floor();
+                let Vec2 { x: tx, y: ty } = pos - p1;
+
+                let (x1, y1) = self.texel(size, p1);
+                let (x2, y2) = self.texel(size, p1 + 1.0);
+
+                let top = tex.get_level(x1, y1, level).lerp(tex.get_level(x2, y1, level), tx);
+                let bottom = tex.get_level(x1, y2, level).lerp(tex.get_level(x2, y2, level), tx);
+
+                top.lerp(bottom, ty)
+            }
         }
     }
 }
+
+/// Allows samplers to Sample [`Textures`](Texture) of dimension `D`, using different types of points.
+///
+/// `T` is the type of texture, any [`Texels2D`] can be sampled in 2D.
+pub trait Sample<const D: u32, T = Texture<D>> {
+    /// The type of query point.
+    type Point;
+
+    /// Samples a texture and returns the color at that point.
+    ///
+    /// Uses the full size level, offset by the sampler's bias.
+    fn sample(&self, tex: &T, uv: Self::Point) -> Vec4 {
+        self.sample_level(tex, uv, 0.0)
+    }
+
+    /// Samples a texture at a level of detail, where `0.0` is the full size level.
+    fn sample_level(&self, tex: &T, uv: Self::Point, lod: f32) -> Vec4;
+}
+
+impl Sample<1> for Sampler {
+    type Point = f32;
+
+    fn sample_level(&self, tex: &Texture<1>, uv: Self::Point, lod: f32) -> Vec4 {
+        let sample = |level: u32| {
+            let size = tex.level_size(level);
+            let pos = uv * size as f32;
+            let texel = |x: f32| tex.get_level(self.edge_mode.apply1d(size, x as i64), level);
+
+            match self.filter_mode {
+                Filter::Nearest => match self.semantics {
+                    Semantics::Legacy => texel(pos.round()),
+                    Semantics::Wgsl => texel(pos.floor()),
+                },
+                Filter::Linear => {
+                    let pos = match self.semantics {
+                        Semantics::Legacy => pos,
+                        // blend between the nearest texel centers
+                        Semantics::Wgsl => pos - 0.5,
+                    };
+
+                    let x1 = pos.floor();
+                    texel(x1).lerp(texel(x1 + 1.0), pos - x1)
+                }
+            }
+        };
+
+        let (a, b, t) = self.levels(lod, tex.mip_levels());
+        if a == b {
+            sample(a)
+        } else {
+            sample(a).lerp(sample(b), t)
+        }
+    }
+}
+
+impl<T: Texels2D> Sample<2, T> for Sampler {
+    type Point = Vec2;
+
+    fn sample_level(&self, tex: &T, uv: Self::Point, lod: f32) -> Vec4 {
+        let (a, b, t) = self.levels(lod, tex.mip_levels());
+
+        if a == b {
+            self.sample_2d(tex, uv, a)
+        } else {
+            self.sample_2d(tex, uv, a).lerp(self.sample_2d(tex, uv, b), t)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The texels `[0, 1, 2, 3]`, with mips of `[0.5, 2.5]` and `[1.5]`.
+    fn texels() -> image::Rgba32FImage {
+        image::Rgba32FImage::from_fn(4, 1, |x, _| image::Rgba([x as f32; 4]))
+    }
+
+    fn sampler(filter_mode: Filter, edge_mode: EdgeMode) -> Sampler {
+        Sampler {
+            filter_mode,
+            mipmap_filter: Filter::Nearest,
+            lod_bias: 0.0,
+            edge_mode,
+            semantics: Semantics::Wgsl,
+        }
+    }
+
+    /// Check `sampler` against the values WGSL gives at each `(u, lod)`,
+    /// sampling the texels as a 1D texture and a 2D texture one texel high.
+    #[track_caller]
+    fn check(sampler: Sampler, expected: &[(f32, f32, f32)]) {
+        let tex_1d = Texture1D::from_image(texels());
+        let tex_2d = Texture2D::from_image(texels());
+
+        for &(u, lod, value) in expected {
+            let sample_1d = sampler.sample_level(&tex_1d, u, lod).x;
+            let sample_2d = sampler.sample_level(&tex_2d, Vec2::new(u, 0.5), lod).x;
+
+            assert_eq!(sample_1d, value, "1D sample at u = {u}, lod = {lod}");
+            assert_eq!(sample_2d, value, "2D sample at u = {u}, lod = {lod}");
+        }
+    }
+
+    #[test]
+    fn nearest() {
+        // texel centers, and edges which floor into the texel to the right
+        let centers_and_edges = [(0.125, 0.0, 0.0), (0.375, 0.0, 1.0), (0.25, 0.0, 1.0)];
+
+        check(sampler(Filter::Nearest, EdgeMode::Wrap), &centers_and_edges);
+        check(
+            sampler(Filter::Nearest, EdgeMode::Clamp),
+            &centers_and_edges,
+        );
+
+        // either side of the texture
+
+        check(
+            sampler(Filter::Nearest, EdgeMode::Wrap),
+            &[(1.0, 0.0, 0.0), (-0.125, 0.0, 3.0)],
+        );
+        check(
+            sampler(Filter::Nearest, EdgeMode::Clamp),
+            &[(1.0, 0.0, 3.0), (-0.125, 0.0, 0.0)],
+        );
+    }
+
+    #[test]
+    fn linear() {
+        // exact at texel centers, halfway between them at texel edges
+        let centers_and_edges = [(0.125, 0.0, 0.0), (0.25, 0.0, 0.5), (0.5, 0.0, 1.5)];
+
+        check(sampler(Filter::Linear, EdgeMode::Wrap), &centers_and_edges);
+        check(sampler(Filter::Linear, EdgeMode::Clamp), &centers_and_edges);
+
+        // past the outer texel centers, wrapping blends in the opposite edge
+        check(
+            sampler(Filter::Linear, EdgeMode::Wrap),
+            &[(0.0, 0.0, 1.5), (1.0, 0.0, 1.5)],
+        );
+        check(
+            sampler(Filter::Linear, EdgeMode::Clamp),
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 3.0)],
+        );
+    }
+
+    #[test]
+    fn linear_2d_corner() {
+        // `[0, 1; 2, 3]`, the corner blends all four texels when wrapping
+        let tex = Texture2D::from_image(image::Rgba32FImage::from_fn(2, 2, |x, y| {
+            image::Rgba([(x + 2 * y) as f32; 4])
+        }));
+
+        let wrap = sampler(Filter::Linear, EdgeMode::Wrap);
+        let clamp = sampler(Filter::Linear, EdgeMode::Clamp);
+
+        assert_eq!(wrap.sample(&tex, Vec2::ZERO).x, 1.5);
+        assert_eq!(clamp.sample(&tex, Vec2::ZERO).x, 0.0);
+        assert_eq!(clamp.sample(&tex, Vec2::ONE).x, 3.0);
+        assert_eq!(clamp.sample(&tex, Vec2::splat(0.5)).x, 1.5);
+    }
+
+    #[test]
+    fn mip_selection() {
+        // nearest mips round halfway down, and the lod is clamped to the chain
+        check(
+            sampler(Filter::Nearest, EdgeMode::Clamp),
+            &[
+                (0.125, 0.5, 0.0),
+                (0.125, 0.6, 0.5),
+                (0.375, 1.0, 0.5),
+                (0.75, 1.0, 2.5),
+                (0.125, 1.5, 0.5),
+                (0.125, 2.0, 1.5),
+                (0.125, 10.0, 1.5),
+                (0.125, -1.0, 0.0),
+            ],
+        );
+
+        // linear mips blend between the levels either side
+        let trilinear = Sampler {
+            mipmap_filter: Filter::Linear,
+            ..sampler(Filter::Nearest, EdgeMode::Clamp)
+        };
+        check(
+            trilinear,
+            &[(0.125, 0.5, 0.25), (0.125, 1.5, 1.0), (0.75, 0.25, 2.875)],
+        );
+    }
+
+    #[test]
+    fn legacy_edges_stay_in_bounds() {
+        let legacy = Sampler {
+            semantics: Semantics::Legacy,
+            ..sampler(Filter::Nearest, EdgeMode::Wrap)
+        };
+        let tex = Texture1D::from_image(texels());
+
+        assert_eq!(legacy.sample(&tex, 1.0).x, 0.0);
+        assert_eq!(legacy.sample(&tex, 0.3).x, 1.0);
+
+        let legacy_linear = Sampler {
+            filter_mode: Filter::Linear,
+            ..legacy
+        };
+
+        assert_eq!(legacy_linear.sample(&tex, 0.375).x, 1.5);
+        assert_eq!(legacy_linear.sample(&tex, 1.0).x, 0.0);
+    }
+}
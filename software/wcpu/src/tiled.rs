@@ -0,0 +1,303 @@
+//! Textures too large to keep in memory, such as 16k starmaps.
+//!
+//! The mip chain is converted once into a file of square tiles with [`TiledTexture2D::create`],
+//! which is then memory mapped by [`TiledTexture2D::open`].
+//! Only the tiles that are sampled are read from disk, and the OS can drop them again when needed.
+
+use std::{
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+};
+
+use glam::{
+    UVec2,
+    Vec4,
+};
+use half::f16;
+use memmap2::Mmap;
+
+use crate::texture::{
+    downsample,
+    Texels2D,
+};
+
+const MAGIC: &[u8; 8] = b"WCPUTILE";
+const VERSION: u32 = 1;
+/// The magic, followed by the version, format, tile size, width, height and number of levels.
+const HEADER_SIZE: usize = 32;
+
+/// How each texel is stored on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TexelFormat {
+    Rgba32F,
+    /// Half the size of [`TexelFormat::Rgba32F`], with less precision.
+    Rgba16F,
+}
+
+impl TexelFormat {
+    /// The number of bytes in a texel.
+    fn size(self) -> usize {
+        match self {
+            TexelFormat::Rgba32F => 16,
+            TexelFormat::Rgba16F => 8,
+        }
+    }
+
+    fn id(self) -> u32 {
+        match self {
+            TexelFormat::Rgba32F => 0,
+            TexelFormat::Rgba16F => 1,
+        }
+    }
+
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(TexelFormat::Rgba32F),
+            1 => Some(TexelFormat::Rgba16F),
+            _ => None,
+        }
+    }
+
+    fn write(self, out: &mut impl Write, texel: Vec4) -> io::Result<()> {
+        for c in texel.to_array() {
+            match self {
+                TexelFormat::Rgba32F => out.write_all(&c.to_le_bytes())?,
+                TexelFormat::Rgba16F => out.write_all(&f16::from_f32(c).to_le_bytes())?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read(self, bytes: &[u8]) -> Vec4 {
+        let channel = |i: usize| match self {
+            TexelFormat::Rgba32F => {
+                f32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap())
+            }
+            TexelFormat::Rgba16F => {
+                f16::from_le_bytes(bytes[i * 2..i * 2 + 2].try_into().unwrap()).to_f32()
+            }
+        };
+
+        Vec4::new(channel(0), channel(1), channel(2), channel(3))
+    }
+}
+
+/// A level of the mip chain.
+struct Level {
+    size: UVec2,
+    /// The number of tiles in each row.
+    tiles_x: u32,
+    /// Where the first tile starts in the file.
+    offset: usize,
+}
+
+/// A 2D texture read on demand from a memory mapped file of tiles.
+///
+/// Sampled the same as a [`Texture2D`](crate::Texture2D).
+pub struct TiledTexture2D {
+    map: Mmap,
+    format: TexelFormat,
+    tile_size: u32,
+    levels: Vec<Level>,
+}
+
+impl TiledTexture2D {
+    /// Convert an image into a tiled file at `path`, generating the full mip chain.
+    ///
+    /// The image is converted a row of tiles at a time, so it is never held as floats.
+    /// `tile_size` must be a power of two, larger than one.
+    #[profiling::function]
+    pub fn create(
+        path: impl AsRef<Path>,
+        img: &image::DynamicImage,
+        tile_size: u32,
+        format: TexelFormat,
+    ) -> io::Result<()> {
+        assert!(tile_size > 1 && tile_size.is_power_of_two(), "tile size must be a power of two");
+
+        let (width, height) = (img.width(), img.height());
+        let levels = level_sizes(UVec2::new(width, height));
+
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        for value in [VERSION, format.id(), tile_size, width, height, levels.len() as u32] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+
+        // the next level is built as the rows of tiles are written
+        let mut next = image::Rgba32FImage::new((width / 2).max(1), (height / 2).max(1));
+
+        for y0 in (0..height).step_by(tile_size as usize) {
+            let strip = img.crop_imm(0, y0, width, tile_size.min(height - y0)).into_rgba32f();
+            write_tile_row(&mut out, format, tile_size, width, |x, y| {
+                strip.get_pixel_checked(x, y).map(|p| Vec4::from_array(p.0))
+            })?;
+
+            // rows are paired within a strip, as it starts on an even row
+            let rows = y0 / 2..(y0 + strip.height()).div_ceil(2).min(next.height());
+            for y in rows {
+                let sy0 = 2 * y - y0;
+                let sy1 = (sy0 + 1).min(strip.height() - 1);
+
+                for x in 0..next.width() {
+                    let x0 = (2 * x).min(width - 1);
+                    let x1 = (x0 + 1).min(width - 1);
+
+                    let sum = [(x0, sy0), (x1, sy0), (x0, sy1), (x1, sy1)]
+                        .into_iter()
+                        .map(|(x, y)| Vec4::from_array(strip.get_pixel(x, y).0))
+                        .sum::<Vec4>();
+
+                    next.put_pixel(x, y, image::Rgba((sum * 0.25).to_array()));
+                }
+            }
+        }
+
+        let mut level = Some(next).filter(|_| levels.len() > 1);
+        while let Some(img) = level {
+            for y0 in (0..img.height()).step_by(tile_size as usize) {
+                write_tile_row(&mut out, format, tile_size, img.width(), |x, y| {
+                    img.get_pixel_checked(x, y0 + y).map(|p| Vec4::from_array(p.0))
+                })?;
+            }
+
+            level = downsample(&img);
+        }
+
+        out.flush()
+    }
+
+    /// Map a file made by [`TiledTexture2D::create`].
+    ///
+    /// The file must not be modified while the texture is alive.
+    #[profiling::function]
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the file isn't modified while it's mapped, as documented
+        let map = unsafe { Mmap::map(&file)? };
+
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        if map.len() < HEADER_SIZE || &map[..8] != MAGIC {
+            return Err(invalid("not a tiled texture"));
+        }
+
+        let header = |i: usize| u32::from_le_bytes(map[8 + i * 4..12 + i * 4].try_into().unwrap());
+        let [version, format, tile_size, width, height, level_count] =
+            std::array::from_fn(header);
+
+        if version != VERSION {
+            return Err(invalid("unsupported tiled texture version"));
+        }
+        let format = TexelFormat::from_id(format).ok_or_else(|| invalid("unknown texel format"))?;
+
+        let sizes = level_sizes(UVec2::new(width, height));
+        if tile_size == 0 || sizes.len() != level_count as usize {
+            return Err(invalid("corrupt tiled texture header"));
+        }
+
+        // the header can't be trusted, so sizes that don't fit are corrupt
+        let overflow = || invalid("tiled texture is too large");
+        let tile_bytes = (tile_size as usize)
+            .checked_mul(tile_size as usize)
+            .and_then(|texels| texels.checked_mul(format.size()))
+            .ok_or_else(overflow)?;
+
+        let mut offset = HEADER_SIZE;
+        let mut levels = Vec::with_capacity(sizes.len());
+        for size in sizes {
+            let tiles = UVec2::new(size.x.div_ceil(tile_size), size.y.div_ceil(tile_size));
+            levels.push(Level {
+                size,
+                tiles_x: tiles.x,
+                offset,
+            });
+
+            offset = (tiles.x as usize)
+                .checked_mul(tiles.y as usize)
+                .and_then(|count| count.checked_mul(tile_bytes))
+                .and_then(|bytes| bytes.checked_add(offset))
+                .ok_or_else(overflow)?;
+        }
+
+        if map.len() < offset {
+            return Err(invalid("tiled texture is truncated"));
+        }
+
+        Ok(Self {
+            map,
+            format,
+            tile_size,
+            levels,
+        })
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.level_size(0)
+    }
+
+    pub fn format(&self) -> TexelFormat {
+        self.format
+    }
+}
+
+impl Texels2D for TiledTexture2D {
+    fn mip_levels(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    fn level_size(&self, level: u32) -> UVec2 {
+        self.levels[level as usize].size
+    }
+
+    fn get_level(&self, x: u32, y: u32, level: u32) -> Vec4 {
+        let level = &self.levels[level as usize];
+        let ts = self.tile_size as usize;
+        let (x, y) = (x as usize, y as usize);
+
+        let tile = (y / ts) * level.tiles_x as usize + (x / ts);
+        let texel = tile * ts * ts + (y % ts) * ts + (x % ts);
+
+        let start = level.offset + texel * self.format.size();
+        self.format.read(&self.map[start..start + self.format.size()])
+    }
+}
+
+/// The size of each level of a full mip chain.
+fn level_sizes(mut size: UVec2) -> Vec<UVec2> {
+    let mut sizes = vec![size];
+
+    while size != UVec2::ONE {
+        size = (size / 2).max(UVec2::ONE);
+        sizes.push(size);
+    }
+
+    sizes
+}
+
+/// Write a row of tiles, where `texel` returns [`None`] past the edge of the level.
+fn write_tile_row(
+    out: &mut impl Write,
+    format: TexelFormat,
+    tile_size: u32,
+    width: u32,
+    texel: impl Fn(u32, u32) -> Option<Vec4>,
+) -> io::Result<()> {
+    for tile_x in 0..width.div_ceil(tile_size) {
+        for y in 0..tile_size {
+            for x in 0..tile_size {
+                // padding is never sampled, edge modes are applied first
+                let texel = texel(tile_x * tile_size + x, y).unwrap_or(Vec4::ZERO);
+                format.write(out, texel)?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use glam::{
+    UVec3,
+    Vec3,
+    Vec4,
+};
+use rayon::prelude::*;
+
+use crate::texture::{
+    Filter,
+    Sampler,
+};
+
+/// A 3D texture, for volumes that are baked once and sampled many times.
+pub struct Texture3D {
+    size: UVec3,
+    /// Texels in x, then y, then z order.
+    texels: Vec<Vec4>,
+}
+
+impl Texture3D {
+    /// Bakes a texture of `size` by calling `f` for each texel in parallel.
+    #[profiling::function]
+    pub fn from_fn(size: UVec3, f: impl (Fn(UVec3) -> Vec4) + Sync) -> Self {
+        assert!(size.cmpgt(UVec3::ZERO).all(), "Texture3D can't be empty");
+
+        let texels = (0..size.x * size.y * size.z)
+            .into_par_iter()
+            .map(|i| {
+                let x = i % size.x;
+                let y = (i / size.x) % size.y;
+                let z = i / (size.x * size.y);
+
+                f(UVec3::new(x, y, z))
+            })
+            .collect();
+
+        Self { size, texels }
+    }
+
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    pub fn get(&self, x: u32, y: u32, z: u32) -> Vec4 {
+        let index = (z * self.size.y + y) * self.size.x + x;
+        self.texels[index as usize]
+    }
+
+    pub fn get_checked(&self, x: u32, y: u32, z: u32) -> Option<Vec4> {
+        let inside = x < self.size.x && y < self.size.y && z < self.size.z;
+        inside.then(|| self.get(x, y, z))
+    }
+}
+
+/// Allows samplers to sample a [`Texture3D`].
+pub trait SampleVolume {
+    /// Samples a [`Texture3D`] at `uvw`, in the range `[0, 1]`.
+    ///
+    /// Always follows [`Semantics::Wgsl`](crate::texture::Semantics::Wgsl),
+    /// texel centers are at `(i + 0.5) / size`.
+    fn sample_volume(&self, tex: &Texture3D, uvw: Vec3) -> Vec4;
+}
+
+impl SampleVolume for Sampler {
+    fn sample_volume(&self, tex: &Texture3D, uvw: Vec3) -> Vec4 {
+        let size = tex.size();
+        let pos = uvw * size.as_vec3();
+
+        let get = |x: f32, y: f32, z: f32| {
+            let (x, y, z) = self.edge_mode.apply3d(size, x as i64, y as i64, z as i64);
+            tex.get(x, y, z)
+        };
+
+        match self.filter_mode {
+            Filter::Nearest => {
+                let p = pos.floor();
+                get(p.x, p.y, p.z)
+            }
+            Filter::Linear => {
+                // blend between the nearest texel centers
+                let pos = pos - 0.5;
+                let p = pos.floor();
+                let t = pos - p;
+
+                let lerp_x = |y: f32, z: f32| get(p.x, y, z).lerp(get(p.x + 1.0, y, z), t.x);
+                let lerp_y = |z: f32| lerp_x(p.y, z).lerp(lerp_x(p.y + 1.0, z), t.y);
+
+                lerp_y(p.z).lerp(lerp_y(p.z + 1.0), t.z)
+            }
+        }
+    }
+}